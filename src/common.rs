@@ -1,5 +1,8 @@
 use std::time::Duration;
 
+#[cfg(feature = "sync")]
+use std::sync::Arc;
+
 pub(crate) mod builder_utils;
 pub(crate) mod concurrent;
 pub(crate) mod deque;
@@ -7,8 +10,12 @@ pub(crate) mod entry;
 pub(crate) mod error;
 pub(crate) mod frequency_sketch;
 pub(crate) mod iter;
+pub(crate) mod memory_size;
+pub(crate) mod predicate_id;
 pub(crate) mod time;
 pub(crate) mod timer_wheel;
+pub(crate) mod write_behind;
+pub(crate) mod writer;
 
 #[cfg(test)]
 pub(crate) mod test_utils;
@@ -18,6 +25,9 @@ use self::concurrent::constants::{
     DEFAULT_MAX_LOG_SYNC_REPEATS,
 };
 
+#[cfg(feature = "sync")]
+use self::concurrent::housekeeper::ExpirationScheduler;
+
 // Note: `CacheRegion` cannot have more than four enum variants. This is because
 // `crate::{sync,unsync}::DeqNodes` uses a `tagptr::TagNonNull<DeqNode<T>, 2>`
 // pointer, where the 2-bit tag is `CacheRegion`.
@@ -81,6 +91,15 @@ pub(crate) struct HousekeeperConfig {
     /// The batch size of entries to be processed by each internal eviction method.
     /// Default: `EVICTION_BATCH_SIZE`.
     pub(crate) eviction_batch_size: u32,
+    /// The tolerance of the proactive expiration. When set, a background thread
+    /// runs the pending tasks when the next entry expires. Used only by the sync
+    /// caches. Default: `None` (disabled).
+    #[cfg_attr(not(feature = "sync"), allow(dead_code))]
+    pub(crate) proactive_expiration: Option<Duration>,
+    /// The scheduler of the proactive expiration shared by the segments of a
+    /// `SegmentedCache`. When `None`, each cache creates its own scheduler.
+    #[cfg(feature = "sync")]
+    pub(crate) expiration_scheduler: Option<Arc<ExpirationScheduler>>,
 }
 
 impl Default for HousekeeperConfig {
@@ -91,11 +110,23 @@ impl Default for HousekeeperConfig {
             ),
             max_log_sync_repeats: DEFAULT_MAX_LOG_SYNC_REPEATS as u32,
             eviction_batch_size: DEFAULT_EVICTION_BATCH_SIZE,
+            proactive_expiration: None,
+            #[cfg(feature = "sync")]
+            expiration_scheduler: None,
         }
     }
 }
 
 impl HousekeeperConfig {
+    /// Creates the scheduler of the proactive expiration (if enabled) to be shared
+    /// by all the caches built from this config.
+    #[cfg(feature = "sync")]
+    pub(crate) fn share_expiration_scheduler(&mut self) {
+        self.expiration_scheduler = self
+            .proactive_expiration
+            .map(|tolerance| Arc::new(ExpirationScheduler::new(tolerance)));
+    }
+
     #[cfg(test)]
     pub(crate) fn new(
         maintenance_task_timeout: Option<Duration>,
@@ -109,6 +140,9 @@ impl HousekeeperConfig {
             max_log_sync_repeats: max_log_sync_repeats
                 .unwrap_or(DEFAULT_MAX_LOG_SYNC_REPEATS as u32),
             eviction_batch_size: eviction_batch_size.unwrap_or(DEFAULT_EVICTION_BATCH_SIZE),
+            proactive_expiration: None,
+            #[cfg(feature = "sync")]
+            expiration_scheduler: None,
         }
     }
 }
@@ -1,7 +1,10 @@
-use crate::common::{concurrent::arc::MiniArc, deque::DeqNode, time::Instant};
+use crate::{
+    common::{concurrent::arc::MiniArc, deque::DeqNode, time::Instant, writer::CacheWriter},
+    policy::EvictionPolicyConfig,
+};
 
 use parking_lot::Mutex;
-use std::{fmt, ptr::NonNull, sync::Arc};
+use std::{fmt, ptr::NonNull, sync::Arc, time::Duration};
 use tagptr::TagNonNull;
 
 pub(crate) mod arc;
@@ -19,7 +22,36 @@ use self::entry_info::EntryInfo;
 
 use super::timer_wheel::TimerNode;
 
-pub(crate) type Weigher<K, V> = Arc<dyn Fn(&K, &V) -> u32 + Send + Sync + 'static>;
+pub(crate) type Weigher<K, V> = Arc<dyn Fn(&K, &V) -> u64 + Send + Sync + 'static>;
+
+pub(crate) type EvictionVeto<K, V> = Arc<dyn Fn(&K, &V) -> bool + Send + Sync + 'static>;
+
+pub(crate) type Writer<K, V> = Arc<dyn CacheWriter<K, V> + 'static>;
+
+/// Returns the approximate number of bytes used by a single cache entry,
+/// excluding the heap memory owned by its key and value.
+///
+/// It includes the key itself (held by an `Arc`), the `ValueEntry` holding the
+/// value, the `EntryInfo` and the `DeqNodes`, the nodes of the access-order queue,
+/// write-order queue and timer wheel, and the bucket of the concurrent hash table.
+/// The write-order queue and timer wheel nodes are counted even when they are not
+/// used, so it is a slightly conservative estimate.
+pub(crate) fn entry_overhead_bytes<K, V>() -> usize {
+    use crate::cht::map::bucket::Bucket;
+    use std::mem::size_of;
+
+    // An `Arc<K>` allocation has the strong and weak counters besides the key.
+    let key = 2 * size_of::<usize>() + size_of::<K>();
+    let value_entry = MiniArc::<ValueEntry<K, V>>::allocation_size();
+    let entry_info = MiniArc::<EntryInfo<K>>::allocation_size();
+    let deq_nodes = MiniArc::<Mutex<DeqNodes<K>>>::allocation_size();
+    let ao_and_wo_nodes = 2 * size_of::<DeqNode<KeyHashDate<K>>>();
+    let timer_node = size_of::<DeqNode<TimerNode<K>>>();
+    // A bucket is pointed by a slot in the bucket array.
+    let bucket = size_of::<Bucket<Arc<K>, MiniArc<ValueEntry<K, V>>>>() + size_of::<usize>();
+
+    key + value_entry + entry_info + deq_nodes + ao_and_wo_nodes + timer_node + bucket
+}
 
 pub(crate) trait AccessTime {
     fn last_accessed(&self) -> Option<Instant>;
@@ -206,7 +238,7 @@ impl<K, V> ValueEntry<K, V> {
     }
 
     #[inline]
-    pub(crate) fn policy_weight(&self) -> u32 {
+    pub(crate) fn policy_weight(&self) -> u64 {
         self.info.policy_weight()
     }
 
@@ -286,6 +318,31 @@ impl<K, V> AccessTime for MiniArc<ValueEntry<K, V>> {
     }
 }
 
+/// The options of an insert. They are applied to the `EntryInfo` before the entry
+/// is stored in the hash table, so that other threads will never see the entry
+/// without them.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct InsertOptions {
+    /// Pins the entry so that it will never be evicted for size.
+    pub(crate) pin: bool,
+    /// `true` if the value was loaded by `get_with` or a similar method.
+    pub(crate) loaded: bool,
+    /// The per-entry time-to-live. It takes precedence over the `Expiry` of the
+    /// cache.
+    pub(crate) ttl: Option<Duration>,
+}
+
+impl InsertOptions {
+    pub(crate) fn apply<K>(&self, info: &EntryInfo<K>, now: Instant) {
+        if self.pin {
+            info.set_pinned(true);
+        }
+        if let Some(ttl) = self.ttl {
+            info.set_expiration_time(Some(now.saturating_add(ttl)));
+        }
+    }
+}
+
 pub(crate) enum ReadOp<K, V> {
     Hit {
         value_entry: MiniArc<ValueEntry<K, V>>,
@@ -301,8 +358,8 @@ pub(crate) enum WriteOp<K, V> {
         value_entry: MiniArc<ValueEntry<K, V>>,
         /// Entry generation after the operation.
         entry_gen: u16,
-        old_weight: u32,
-        new_weight: u32,
+        old_weight: u64,
+        new_weight: u64,
     },
     Remove {
         kv_entry: KvEntry<K, V>,
@@ -311,6 +368,20 @@ pub(crate) enum WriteOp<K, V> {
     SetCapacity {
         new_capacity: u64,
     },
+    /// Switches the eviction policy of the cache.
+    SetEvictionPolicy {
+        policy: EvictionPolicyConfig,
+    },
+    /// The pinned flag of the entry has been changed by a user thread. Updates the
+    /// pinned weight of the cache.
+    UpdatePinned {
+        value_entry: MiniArc<ValueEntry<K, V>>,
+    },
+    /// The expiration time of the entry has been changed by a user thread.
+    /// Reschedules the entry on the timer wheel.
+    UpdateExpiration {
+        value_entry: MiniArc<ValueEntry<K, V>>,
+    },
 }
 
 /// Cloning a `WriteOp` is safe and cheap because it uses `Arc` and `MiniArc` pointers to
@@ -341,6 +412,13 @@ impl<K, V> Clone for WriteOp<K, V> {
             Self::SetCapacity { new_capacity } => Self::SetCapacity {
                 new_capacity: *new_capacity,
             },
+            Self::SetEvictionPolicy { policy } => Self::SetEvictionPolicy { policy: *policy },
+            Self::UpdatePinned { value_entry } => Self::UpdatePinned {
+                value_entry: MiniArc::clone(value_entry),
+            },
+            Self::UpdateExpiration { value_entry } => Self::UpdateExpiration {
+                value_entry: MiniArc::clone(value_entry),
+            },
         }
     }
 }
@@ -354,6 +432,12 @@ impl<K, V> fmt::Debug for WriteOp<K, V> {
                 .debug_struct("SetCapacity")
                 .field("new_capacity", new_capacity)
                 .finish(),
+            Self::SetEvictionPolicy { policy } => f
+                .debug_struct("SetEvictionPolicy")
+                .field("policy", policy)
+                .finish(),
+            Self::UpdatePinned { .. } => f.debug_struct("UpdatePinned").finish(),
+            Self::UpdateExpiration { .. } => f.debug_struct("UpdateExpiration").finish(),
         }
     }
 }
@@ -364,8 +448,8 @@ impl<K, V> WriteOp<K, V> {
         hash: u64,
         value_entry: &MiniArc<ValueEntry<K, V>>,
         entry_generation: u16,
-        old_weight: u32,
-        new_weight: u32,
+        old_weight: u64,
+        new_weight: u64,
     ) -> Self {
         let key_hash = KeyHash::new(Arc::clone(key), hash);
         let value_entry = MiniArc::clone(value_entry);
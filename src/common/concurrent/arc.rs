@@ -69,6 +69,11 @@ impl<T> MiniArc<T> {
             }))),
         }
     }
+
+    /// Returns the size in bytes of the heap allocation made by `MiniArc::new`.
+    pub(crate) const fn allocation_size() -> usize {
+        std::mem::size_of::<ArcData<T>>()
+    }
 }
 
 impl<T: ?Sized> MiniArc<T> {
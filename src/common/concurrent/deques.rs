@@ -1,10 +1,11 @@
 use super::{arc::MiniArc, KeyHashDate, ValueEntry};
 use crate::common::{
     deque::{DeqNode, Deque},
+    time::Instant,
     CacheRegion,
 };
 
-use std::ptr::NonNull;
+use std::{ptr::NonNull, time::Duration};
 use tagptr::TagNonNull;
 
 pub(crate) struct Deques<K> {
@@ -14,12 +15,11 @@ pub(crate) struct Deques<K> {
     pub(crate) write_order: Deque<KeyHashDate<K>>,
 }
 
-#[cfg(feature = "future")]
 // TODO: https://github.com/moka-rs/moka/issues/54
 #[allow(clippy::non_send_fields_in_send_ty)]
-// Multi-threaded async runtimes require base_cache::Inner to be Send, but it will
-// not be without this `unsafe impl`. This is because DeqNodes have NonNull
-// pointers.
+// Multi-threaded async runtimes and the proactive expiration thread of the sync
+// cache require base_cache::Inner to be Send, but it will not be without this
+// `unsafe impl`. This is because DeqNodes have NonNull pointers.
 unsafe impl<K> Send for Deques<K> {}
 
 impl<K> Default for Deques<K> {
@@ -46,6 +46,30 @@ impl<K> Deques<K> {
         }
     }
 
+    /// Returns the earliest time when the entry at the front of the access order
+    /// deques or the write order deque will expire by the time-to-idle or
+    /// time-to-live policy. Returns `None` if neither policy is set or the deques
+    /// are empty.
+    pub(crate) fn next_expiration_time(
+        &self,
+        ttl: Option<Duration>,
+        tti: Option<Duration>,
+    ) -> Option<Instant> {
+        let ao = tti.into_iter().flat_map(|tti| {
+            [&self.window, &self.probation, &self.protected]
+                .into_iter()
+                .filter_map(move |deq| {
+                    let ts = deq.peek_front()?.element.last_accessed()?;
+                    Some(ts.saturating_add(tti))
+                })
+        });
+        let wo = ttl.and_then(|ttl| {
+            let ts = self.write_order.peek_front()?.element.last_modified()?;
+            Some(ts.saturating_add(ttl))
+        });
+        ao.chain(wo).min()
+    }
+
     pub(crate) fn push_back_ao<V>(
         &mut self,
         region: CacheRegion,
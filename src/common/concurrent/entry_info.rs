@@ -1,4 +1,5 @@
-use std::sync::atomic::{self, AtomicBool, AtomicU16, AtomicU32, Ordering};
+use portable_atomic::AtomicU64;
+use std::sync::atomic::{self, AtomicBool, AtomicU16, Ordering};
 
 use super::{AccessTime, KeyHash};
 use crate::common::time::{AtomicInstant, Instant};
@@ -17,15 +18,22 @@ pub(crate) struct EntryInfo<K> {
     /// is applied to the cache policies including the access-order queue (the LRU
     /// deque).
     policy_gen: AtomicU16,
+    /// `is_pinned` indicates that the entry should never be evicted by the size
+    /// constraint of the cache. It is set by user threads (e.g. `Cache::pin`).
+    is_pinned: AtomicBool,
+    /// `policy_pinned` indicates that the weight of this entry has been added to
+    /// the pinned weight of the cache. It is only updated by the thread that
+    /// applies the `WriteOp`s to the cache policies.
+    policy_pinned: AtomicBool,
     last_accessed: AtomicInstant,
     last_modified: AtomicInstant,
     expiration_time: AtomicInstant,
-    policy_weight: AtomicU32,
+    policy_weight: AtomicU64,
 }
 
 impl<K> EntryInfo<K> {
     #[inline]
-    pub(crate) fn new(key_hash: KeyHash<K>, timestamp: Instant, policy_weight: u32) -> Self {
+    pub(crate) fn new(key_hash: KeyHash<K>, timestamp: Instant, policy_weight: u64) -> Self {
         #[cfg(feature = "unstable-debug-counters")]
         super::debug_counters::InternalGlobalDebugCounters::entry_info_created();
 
@@ -35,10 +43,12 @@ impl<K> EntryInfo<K> {
             // `entry_gen` starts at 1 and `policy_gen` start at 0.
             entry_gen: AtomicU16::new(1),
             policy_gen: AtomicU16::new(0),
+            is_pinned: AtomicBool::default(),
+            policy_pinned: AtomicBool::default(),
             last_accessed: AtomicInstant::new(timestamp),
             last_modified: AtomicInstant::new(timestamp),
             expiration_time: AtomicInstant::default(),
-            policy_weight: AtomicU32::new(policy_weight),
+            policy_weight: AtomicU64::new(policy_weight),
         }
     }
 
@@ -108,11 +118,31 @@ impl<K> EntryInfo<K> {
     }
 
     #[inline]
-    pub(crate) fn policy_weight(&self) -> u32 {
+    pub(crate) fn is_pinned(&self) -> bool {
+        self.is_pinned.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_pinned(&self, value: bool) {
+        self.is_pinned.store(value, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn is_policy_pinned(&self) -> bool {
+        self.policy_pinned.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_policy_pinned(&self, value: bool) {
+        self.policy_pinned.store(value, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn policy_weight(&self) -> u64 {
         self.policy_weight.load(Ordering::Acquire)
     }
 
-    pub(crate) fn set_policy_weight(&self, size: u32) {
+    pub(crate) fn set_policy_weight(&self, size: u64) {
         self.policy_weight.store(size, Ordering::Release);
     }
 
@@ -215,7 +245,7 @@ mod test {
 
         let expected_sizes = match arch {
             Linux64 | Linux32Arm | Linux32Mips => vec![("1.51", 56)],
-            Linux32X86 => vec![("1.51", 48)],
+            Linux32X86 => vec![("1.51", 56)],
             MacOS64 => vec![("1.62", 56)],
         };
 
@@ -4,12 +4,19 @@ use super::constants::{READ_LOG_FLUSH_POINT, WRITE_LOG_FLUSH_POINT};
 use crate::common::time::{AtomicInstant, Instant};
 use crate::common::HousekeeperConfig;
 
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::{Condvar, Mutex, MutexGuard};
 use std::{
-    sync::atomic::{AtomicBool, Ordering},
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    time::{Duration, Instant as StdInstant},
 };
 
+/// The minimum tolerance of the proactive expiration. A smaller tolerance is
+/// rounded up to this value to keep the background thread from spinning.
+const MIN_EXPIRATION_TOLERANCE_MILLIS: u64 = 1;
+
 pub(crate) trait InnerSync {
     /// Runs the pending tasks. Returns `true` if there are more entries to evict in
     /// next run.
@@ -21,8 +28,15 @@ pub(crate) trait InnerSync {
     ) -> bool;
 
     fn now(&self) -> Instant;
+
+    /// Returns the earliest time when a cached entry will expire, or `None` if no
+    /// entry will expire.
+    fn next_expiration_time(&self) -> Option<Instant>;
 }
 
+/// A cache whose pending tasks are run by the expiration scheduler.
+type ExpirationTarget = (Weak<Housekeeper>, Weak<dyn InnerSync + Send + Sync>);
+
 pub(crate) struct Housekeeper {
     run_lock: Mutex<()>,
     run_after: AtomicInstant,
@@ -46,6 +60,17 @@ pub(crate) struct Housekeeper {
     /// Default: `EVICTION_BATCH_SIZE`.
     eviction_batch_size: u32,
     auto_run_enabled: AtomicBool,
+    /// The state shared with the background thread of the proactive expiration.
+    /// `None` if the proactive expiration is disabled.
+    expiration_scheduler: Option<Arc<ExpirationScheduler>>,
+}
+
+impl Drop for Housekeeper {
+    fn drop(&mut self) {
+        if let Some(scheduler) = &self.expiration_scheduler {
+            scheduler.notify_drop();
+        }
+    }
 }
 
 impl Housekeeper {
@@ -71,6 +96,36 @@ impl Housekeeper {
             max_log_sync_repeats: config.max_log_sync_repeats,
             eviction_batch_size: config.eviction_batch_size,
             auto_run_enabled: AtomicBool::new(true),
+            expiration_scheduler: config.expiration_scheduler.clone().or_else(|| {
+                config
+                    .proactive_expiration
+                    .map(|tolerance| Arc::new(ExpirationScheduler::new(tolerance)))
+            }),
+        }
+    }
+
+    /// Registers the cache to the background thread that runs the pending tasks
+    /// when the next entry expires. The thread is started by the first
+    /// registration. Does nothing if the proactive expiration is disabled.
+    ///
+    /// The thread holds only weak references to the housekeepers and the caches,
+    /// and exits when all the registered housekeepers are dropped.
+    pub(crate) fn start_expiration_scheduler<T>(self: &Arc<Self>, cache: &Arc<T>)
+    where
+        T: InnerSync + Send + Sync + 'static,
+    {
+        if let Some(scheduler) = &self.expiration_scheduler {
+            let target: ExpirationTarget = (Arc::downgrade(self), Arc::downgrade(cache) as _);
+            ExpirationScheduler::register(scheduler, target);
+        }
+    }
+
+    /// Notifies the expiration scheduler that a write op has been sent to the write
+    /// op channel, so that the timer wheel will be updated within the tolerance.
+    #[inline]
+    pub(crate) fn notify_write(&self) {
+        if let Some(scheduler) = &self.expiration_scheduler {
+            scheduler.notify_write();
         }
     }
 
@@ -102,7 +157,7 @@ impl Housekeeper {
             && (ch_len >= ch_flush_point || now >= self.run_after.instant().unwrap())
     }
 
-    pub(crate) fn run_pending_tasks<T: InnerSync>(&self, cache: &T) {
+    pub(crate) fn run_pending_tasks<T: InnerSync + ?Sized>(&self, cache: &T) {
         let lock = self.run_lock.lock();
         self.do_run_pending_tasks(cache, lock);
     }
@@ -116,7 +171,7 @@ impl Housekeeper {
         }
     }
 
-    fn do_run_pending_tasks<T: InnerSync>(&self, cache: &T, _lock: MutexGuard<'_, ()>) {
+    fn do_run_pending_tasks<T: InnerSync + ?Sized>(&self, cache: &T, _lock: MutexGuard<'_, ()>) {
         let now = cache.now();
         self.run_after.set_instant(Self::sync_after(now));
         let timeout = self.maintenance_task_timeout;
@@ -132,8 +187,150 @@ impl Housekeeper {
     }
 }
 
+/// The state shared between caches and the background thread of the proactive
+/// expiration. The segments of a `SegmentedCache` share one scheduler, hence one
+/// thread.
+pub(crate) struct ExpirationScheduler {
+    /// The maximum delay of running the pending tasks after a write op was sent.
+    tolerance: Duration,
+    /// Set when a write op was sent after the last run of the pending tasks.
+    is_write_pending: AtomicBool,
+    /// The registered caches. The thread is running while this is not empty.
+    targets: Mutex<Vec<ExpirationTarget>>,
+    condvar: Condvar,
+}
+
+impl std::fmt::Debug for ExpirationScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExpirationScheduler")
+            .field("tolerance", &self.tolerance)
+            .finish()
+    }
+}
+
+impl ExpirationScheduler {
+    pub(crate) fn new(tolerance: Duration) -> Self {
+        let min_tolerance = Duration::from_millis(MIN_EXPIRATION_TOLERANCE_MILLIS);
+        Self {
+            tolerance: tolerance.max(min_tolerance),
+            is_write_pending: AtomicBool::new(false),
+            targets: Mutex::default(),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn register(this: &Arc<Self>, target: ExpirationTarget) {
+        let mut targets = this.targets.lock();
+        let is_first = targets.is_empty();
+        targets.push(target);
+        if is_first {
+            let scheduler = Arc::clone(this);
+            std::thread::Builder::new()
+                .name("moka-expiration-scheduler".into())
+                .spawn(move || scheduler.run())
+                .expect("Failed to spawn the expiration scheduler thread");
+        }
+    }
+
+    fn notify_write(&self) {
+        // Check the flag first to avoid writing to the shared cache line on every
+        // write op.
+        if !self.is_write_pending.load(Ordering::Acquire)
+            && !self.is_write_pending.swap(true, Ordering::AcqRel)
+        {
+            // Acquire the lock so that the notification will not be lost while the
+            // scheduler thread is checking the flag.
+            let _guard = self.targets.lock();
+            self.condvar.notify_one();
+        }
+    }
+
+    /// Wakes up the thread to let it find that a housekeeper has been dropped.
+    fn notify_drop(&self) {
+        let _guard = self.targets.lock();
+        self.condvar.notify_one();
+    }
+
+    fn run(&self) {
+        loop {
+            self.is_write_pending.store(false, Ordering::Release);
+            // Do not hold the lock while running the pending tasks.
+            let targets = self.targets.lock().clone();
+            let mut next: Option<Duration> = None;
+
+            for (hk, cache) in targets {
+                let (Some(hk), Some(cache)) = (hk.upgrade(), cache.upgrade()) else {
+                    continue;
+                };
+                hk.run_pending_tasks(&*cache);
+                let duration = cache
+                    .next_expiration_time()
+                    .map(|time| time.saturating_duration_since(cache.now()));
+                next = match (next, duration) {
+                    (Some(n), Some(d)) => Some(n.min(d)),
+                    (n, d) => n.or(d),
+                };
+                // The housekeeper needs to be dropped before the cache.
+                std::mem::drop(hk);
+            }
+
+            let deadline = next.and_then(|duration| {
+                // If some entries have expired but were not removed by the last run
+                // (e.g. the eviction batch was full), retry after the tolerance.
+                let duration = if duration.is_zero() {
+                    self.tolerance
+                } else {
+                    duration
+                };
+                StdInstant::now().checked_add(duration)
+            });
+
+            if !self.wait(deadline) {
+                break;
+            }
+        }
+    }
+
+    /// Waits until the deadline, or until the tolerance has elapsed after a write
+    /// op was notified. Returns `false` if all the registered housekeepers have
+    /// been dropped.
+    fn wait(&self, deadline: Option<StdInstant>) -> bool {
+        let mut deadline = deadline;
+        let mut targets = self.targets.lock();
+        let mut is_write_seen = false;
+
+        loop {
+            targets.retain(|(hk, _)| hk.strong_count() > 0);
+            if targets.is_empty() {
+                return false;
+            }
+            if !is_write_seen && self.is_write_pending.load(Ordering::Acquire) {
+                is_write_seen = true;
+                if let Some(t) = StdInstant::now().checked_add(self.tolerance) {
+                    deadline = Some(deadline.map_or(t, |d| d.min(t)));
+                }
+            }
+            match deadline {
+                Some(t) => {
+                    if self.condvar.wait_until(&mut targets, t).timed_out() {
+                        return true;
+                    }
+                }
+                None => self.condvar.wait(&mut targets),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 impl Housekeeper {
+    pub(crate) fn shares_expiration_scheduler_with(&self, other: &Housekeeper) -> bool {
+        match (&self.expiration_scheduler, &other.expiration_scheduler) {
+            (Some(s1), Some(s2)) => Arc::ptr_eq(s1, s2),
+            _ => false,
+        }
+    }
+
     pub(crate) fn disable_auto_run(&self) {
         self.auto_run_enabled.store(false, Ordering::Relaxed);
     }
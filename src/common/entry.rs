@@ -1,4 +1,8 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// A snapshot of a single entry in the cache.
 ///
@@ -90,3 +94,92 @@ impl<K, V> Entry<K, V> {
         self.is_old_value_replaced
     }
 }
+
+/// A snapshot of the metadata of a single entry in the cache.
+///
+/// `EntryMetadata` is returned by the `peek_metadata` method of the caches. It
+/// carries the timestamps and the policy related information that the cache keeps
+/// for the entry at the time it was constructed.
+///
+/// Obtaining the metadata is _not_ counted as a read of the entry. So it does not
+/// update the last accessed time, the hit/miss statistics or the estimated access
+/// frequency of the entry.
+///
+/// See the followings for more information about `peek_metadata` method:
+///
+/// - [`sync::Cache::peek_metadata`](./sync/struct.Cache.html#method.peek_metadata)
+/// - [`future::Cache::peek_metadata`](./future/struct.Cache.html#method.peek_metadata)
+///
+#[derive(Clone, Debug)]
+pub struct EntryMetadata {
+    pub(crate) last_accessed: Instant,
+    pub(crate) last_modified: Instant,
+    pub(crate) expiration_time: Option<Instant>,
+    pub(crate) time_to_expiration: Option<Duration>,
+    pub(crate) policy_weight: u64,
+    pub(crate) frequency: u8,
+    pub(crate) is_admitted: bool,
+    pub(crate) is_pinned: bool,
+}
+
+impl EntryMetadata {
+    /// Returns the time when the entry was last read or written.
+    pub fn last_accessed(&self) -> Instant {
+        self.last_accessed
+    }
+
+    /// Returns the time when the entry was last written (inserted or updated).
+    pub fn last_modified(&self) -> Instant {
+        self.last_modified
+    }
+
+    /// Returns the time when the entry will expire, or `None` if the entry will
+    /// never expire.
+    ///
+    /// This is the earliest of the expiration times derived from the cache-wide
+    /// time-to-live and time-to-idle, and the per-entry expiration time set by the
+    /// `Expiry` or the `insert_with_ttl` method.
+    pub fn expiration_time(&self) -> Option<Instant> {
+        self.expiration_time
+    }
+
+    /// Returns the remaining duration until the entry expires, or `None` if the
+    /// entry will never expire.
+    pub fn time_to_expiration(&self) -> Option<Duration> {
+        self.time_to_expiration
+    }
+
+    /// Returns the weight of the entry, calculated by the weigher of the cache when
+    /// the entry was inserted or updated. Returns `1` if the cache does not have a
+    /// weigher.
+    ///
+    /// Note that the weight is updated by the cache's housekeeping task, so it can
+    /// lag behind the latest update of the entry.
+    pub fn policy_weight(&self) -> u64 {
+        self.policy_weight
+    }
+
+    /// Returns the estimated access frequency of the entry, ranging from `0` to
+    /// `15`.
+    ///
+    /// The frequency is estimated by the frequency sketch of the cache (a
+    /// Count-Min Sketch), which is only enabled when the cache is bounded and has
+    /// been filled about half of its capacity. `0` is returned while the sketch is
+    /// disabled.
+    pub fn frequency(&self) -> u8 {
+        self.frequency
+    }
+
+    /// Returns `true` if the entry has been admitted to the cache by the
+    /// housekeeping task. An entry is temporarily admitted until the pending
+    /// writes are applied to the cache policies.
+    pub fn is_admitted(&self) -> bool {
+        self.is_admitted
+    }
+
+    /// Returns `true` if the entry is pinned, and will never be evicted by the
+    /// size constraint of the cache.
+    pub fn is_pinned(&self) -> bool {
+        self.is_pinned
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, sync::Arc};
 
 /// The error type for the functionalities around
 /// [`Cache::invalidate_entries_if`][invalidate-if] method.
@@ -56,3 +56,101 @@ impl Display for CapacityError {
 }
 
 impl Error for CapacityError {}
+
+/// The error type for the insert operations that report a rejection, such as
+/// [`Cache::try_insert`][try-insert] method.
+///
+/// [try-insert]: ./sync/struct.Cache.html#method.try_insert
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InsertError {
+    /// The entry was not stored in the cache because its weight exceeds the
+    /// maximum weight of a single entry.
+    ///
+    /// The maximum weight is configured by
+    /// [`CacheBuilder::max_entry_weight`][max-entry-weight] method.
+    ///
+    /// [max-entry-weight]: ./sync/struct.CacheBuilder.html#method.max_entry_weight
+    EntryTooHeavy {
+        /// The weight of the rejected entry.
+        weight: u64,
+        /// The maximum weight of a single entry configured for the cache.
+        max_entry_weight: u64,
+    },
+    /// The entry was not stored in the cache because the
+    /// [`CacheWriter`][cache-writer] of the cache failed to write it.
+    ///
+    /// [cache-writer]: ./trait.CacheWriter.html
+    WriterFailed(WriteError),
+}
+
+impl Display for InsertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsertError::EntryTooHeavy {
+                weight,
+                max_entry_weight,
+            } => {
+                write!(
+                    f,
+                    "The entry was rejected because its weight {weight} exceeds \
+                    the max entry weight {max_entry_weight} of the cache"
+                )
+            }
+            InsertError::WriterFailed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for InsertError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InsertError::WriterFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The error type for the operations that failed because the
+/// [`CacheWriter`][cache-writer] of the cache returned an error. The cache was not
+/// modified by the operation.
+///
+/// The error returned by the writer is available as the [`source`][error-source]
+/// of this error.
+///
+/// Two `WriteError`s are equal if they hold the same error returned by the
+/// writer, e.g. when one is a clone of the other.
+///
+/// [cache-writer]: ./trait.CacheWriter.html
+/// [error-source]: https://doc.rust-lang.org/std/error/trait.Error.html#method.source
+#[derive(Clone, Debug)]
+pub struct WriteError {
+    source: Arc<dyn Error + Send + Sync + 'static>,
+}
+
+impl WriteError {
+    pub(crate) fn new(source: Box<dyn Error + Send + Sync + 'static>) -> Self {
+        Self {
+            source: source.into(),
+        }
+    }
+}
+
+impl PartialEq for WriteError {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.source, &other.source)
+    }
+}
+
+impl Eq for WriteError {}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The cache writer failed: {}", self.source)
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
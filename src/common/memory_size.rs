@@ -0,0 +1,236 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    mem::{size_of, size_of_val},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// A trait to estimate the heap memory owned by a value.
+///
+/// This trait is used by `max_memory_bytes` method of the cache builders
+/// ([`sync::CacheBuilder::max_memory_bytes`][sync-max-memory-bytes] and
+/// [`future::CacheBuilder::max_memory_bytes`][future-max-memory-bytes]) to weigh
+/// the cache entries by bytes.
+///
+/// This crate implements it for primitive types and common types in the standard
+/// library. You can implement it for your own types by summing up the heap sizes
+/// of their fields.
+///
+/// [sync-max-memory-bytes]: ./sync/struct.CacheBuilder.html#method.max_memory_bytes
+/// [future-max-memory-bytes]: ./future/struct.CacheBuilder.html#method.max_memory_bytes
+///
+/// # Example
+///
+/// ```rust
+/// use moka::MemorySize;
+///
+/// struct User {
+///     id: u64,
+///     name: String,
+///     emails: Vec<String>,
+/// }
+///
+/// impl MemorySize for User {
+///     fn heap_size(&self) -> usize {
+///         self.id.heap_size() + self.name.heap_size() + self.emails.heap_size()
+///     }
+/// }
+///
+/// let user = User {
+///     id: 1,
+///     name: String::from("alice"),
+///     emails: vec![String::from("alice@example.com")],
+/// };
+/// assert!(user.heap_size() >= "alice".len() + "alice@example.com".len());
+/// ```
+pub trait MemorySize {
+    /// Returns the number of bytes of the heap memory owned by this value.
+    ///
+    /// It must not include the size of the value itself (`size_of_val(self)`),
+    /// which is counted by the owner of the value (e.g. the cache).
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! impl_memory_size_without_heap {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl MemorySize for $t {
+                #[inline]
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_memory_size_without_heap!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    str,
+    Duration,
+    Instant,
+    SystemTime,
+);
+
+/// A reference does not own the referent, so its heap size is zero.
+impl<T: ?Sized> MemorySize for &T {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl MemorySize for String {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: MemorySize> MemorySize for [T] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(MemorySize::heap_size).sum()
+    }
+}
+
+impl<T: MemorySize, const N: usize> MemorySize for [T; N] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(MemorySize::heap_size).sum()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.as_slice().heap_size()
+    }
+}
+
+impl<T: MemorySize> MemorySize for VecDeque<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(MemorySize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Option<T> {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, MemorySize::heap_size)
+    }
+}
+
+impl<T: MemorySize + ?Sized> MemorySize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+/// The whole allocation is counted for every `Arc` pointer, so the memory shared
+/// by multiple `Arc`s will be counted more than once.
+impl<T: MemorySize + ?Sized> MemorySize for Arc<T> {
+    fn heap_size(&self) -> usize {
+        // The strong and weak counters.
+        2 * size_of::<usize>() + size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+/// The whole allocation is counted for every `Rc` pointer, so the memory shared
+/// by multiple `Rc`s will be counted more than once.
+impl<T: MemorySize + ?Sized> MemorySize for Rc<T> {
+    fn heap_size(&self) -> usize {
+        // The strong and weak counters.
+        2 * size_of::<usize>() + size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+/// The heap size is estimated from the capacity of the map, as the actual
+/// memory layout of the hash table is an implementation detail.
+impl<K: MemorySize, V: MemorySize, S> MemorySize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        // Each bucket has a one-byte control tag besides the key and value.
+        self.capacity() * (size_of::<(K, V)>() + 1)
+            + self
+                .iter()
+                .map(|(k, v)| k.heap_size() + v.heap_size())
+                .sum::<usize>()
+    }
+}
+
+/// The heap size is estimated from the capacity of the set, as the actual
+/// memory layout of the hash table is an implementation detail.
+impl<T: MemorySize, S> MemorySize for HashSet<T, S> {
+    fn heap_size(&self) -> usize {
+        // Each bucket has a one-byte control tag besides the element.
+        self.capacity() * (size_of::<T>() + 1)
+            + self.iter().map(MemorySize::heap_size).sum::<usize>()
+    }
+}
+
+macro_rules! impl_memory_size_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: MemorySize),+> MemorySize for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn heap_size(&self) -> usize {
+                let ($($name,)+) = self;
+                0 $(+ $name.heap_size())+
+            }
+        }
+    };
+}
+
+impl_memory_size_for_tuple!(A);
+impl_memory_size_for_tuple!(A, B);
+impl_memory_size_for_tuple!(A, B, C);
+impl_memory_size_for_tuple!(A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use super::MemorySize;
+    use std::{mem::size_of, sync::Arc};
+
+    #[test]
+    fn primitives_and_strings() {
+        assert_eq!(42u64.heap_size(), 0);
+        assert_eq!("static".heap_size(), 0);
+
+        let s = String::with_capacity(32);
+        assert_eq!(s.heap_size(), 32);
+
+        let b: Box<str> = "hello".into();
+        assert_eq!(b.heap_size(), 5);
+
+        let a: Arc<str> = "hello".into();
+        assert_eq!(a.heap_size(), 2 * size_of::<usize>() + 5);
+    }
+
+    #[test]
+    fn collections() {
+        let mut v = Vec::with_capacity(4);
+        v.push(String::with_capacity(10));
+        v.push(String::with_capacity(20));
+        assert_eq!(v.heap_size(), 4 * size_of::<String>() + 30);
+
+        let o: Option<Vec<u8>> = Some(Vec::with_capacity(8));
+        assert_eq!(o.heap_size(), 8);
+        assert_eq!((None as Option<Vec<u8>>).heap_size(), 0);
+
+        let t = (1u32, String::with_capacity(3), vec![0u16; 2]);
+        assert_eq!(t.heap_size(), 3 + t.2.capacity() * size_of::<u16>());
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+/// The type of the unique ID to identify a predicate used by the
+/// `invalidate_entries_if` method of the caches.
+///
+/// A `PredicateId` is a UUID (version 4). It is `Copy`, so it can be carried by
+/// [`RemovalCause::InvalidatedByPredicate`][by-predicate]. Use its `Display`
+/// implementation (e.g. `to_string`) to get the hyphenated UUID string.
+///
+/// [by-predicate]: ../notification/enum.RemovalCause.html#variant.InvalidatedByPredicate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PredicateId(Uuid);
+
+impl PredicateId {
+    pub(crate) fn new_v4() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    /// Returns the UUID of this ID as a 128-bit integer.
+    pub fn as_u128(&self) -> u128 {
+        self.0.as_u128()
+    }
+}
+
+impl fmt::Display for PredicateId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0.as_hyphenated(), f)
+    }
+}
+
+impl From<PredicateId> for String {
+    fn from(id: PredicateId) -> Self {
+        id.to_string()
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, SystemTime};
+
 mod atomic_time;
 mod clock;
 mod instant;
@@ -8,3 +10,15 @@ pub(crate) use instant::Instant;
 
 #[cfg(test)]
 pub(crate) use clock::Mock;
+
+/// Converts a wall-clock deadline to the duration from now, measured by the
+/// system clock. Returns zero if the deadline has already passed.
+///
+/// The cache keeps time with a monotonic clock, so the returned duration should be
+/// used right away as a time-to-live. Adjustments to the system clock made after
+/// the conversion will not be reflected.
+pub(crate) fn duration_until(deadline: SystemTime) -> Duration {
+    deadline
+        .duration_since(SystemTime::now())
+        .unwrap_or_default()
+}
@@ -11,53 +11,17 @@
 // For full authorship information, see the version control history of
 // https://github.com/ben-manes/caffeine/
 
-use std::{ptr::NonNull, time::Duration};
+use std::{cell::Cell, ptr::NonNull, time::Duration};
 
 use super::{
     concurrent::{arc::MiniArc, entry_info::EntryInfo, DeqNodes},
     deque::{DeqNode, Deque},
     time::Instant,
 };
+use crate::policy::TimerWheelConfig;
 
 use parking_lot::Mutex;
 
-const BUCKET_COUNTS: &[u64] = &[
-    64, // roughly seconds
-    64, // roughly minutes
-    32, // roughly hours
-    4,  // roughly days
-    1,  // overflow (> ~6.5 days)
-];
-
-const OVERFLOW_QUEUE_INDEX: usize = BUCKET_COUNTS.len() - 1;
-const NUM_LEVELS: usize = OVERFLOW_QUEUE_INDEX - 1;
-
-const DAY: Duration = Duration::from_secs(60 * 60 * 24);
-
-const SPANS: &[u64] = &[
-    aligned_duration(Duration::from_secs(1)),       // 1.07s
-    aligned_duration(Duration::from_secs(60)),      // 1.14m
-    aligned_duration(Duration::from_secs(60 * 60)), // 1.22h
-    aligned_duration(DAY),                          // 1.63d
-    BUCKET_COUNTS[3] * aligned_duration(DAY),       // 6.5d
-    BUCKET_COUNTS[3] * aligned_duration(DAY),       // 6.5d
-];
-
-const SHIFT: &[u64] = &[
-    SPANS[0].trailing_zeros() as u64,
-    SPANS[1].trailing_zeros() as u64,
-    SPANS[2].trailing_zeros() as u64,
-    SPANS[3].trailing_zeros() as u64,
-    SPANS[4].trailing_zeros() as u64,
-];
-
-/// Returns the next power of two of the duration in nanoseconds.
-const fn aligned_duration(duration: Duration) -> u64 {
-    // NOTE: as_nanos() returns u128, so convert it to u64 by using `as`.
-    // We cannot call TryInto::try_into() here because it is not a const fn.
-    (duration.as_nanos() as u64).next_power_of_two()
-}
-
 /// A timer node stored in a bucket of a timer wheel.
 pub(crate) enum TimerNode<K> {
     /// A sentinel node that is used to mark the end of a timer wheel bucket.
@@ -136,6 +100,17 @@ impl<K> TimerNode<K> {
 
 type Bucket<K> = Deque<TimerNode<K>>;
 
+/// The earliest expiration time of the timer events in a bucket, cached for
+/// `TimerWheel::next_expiration_time`.
+#[derive(Clone, Copy)]
+enum BucketMin {
+    /// A timer event was removed from the bucket since it was last scanned, so the
+    /// bucket needs to be scanned again.
+    Unknown,
+    /// The earliest expiration time, or `None` if the bucket has no timer event.
+    Known(Option<Instant>),
+}
+
 #[must_use = "this `ReschedulingResult` may be an `Removed` variant, which should be handled"]
 pub(crate) enum ReschedulingResult<K> {
     /// The timer event was rescheduled.
@@ -152,29 +127,58 @@ pub(crate) enum ReschedulingResult<K> {
 pub(crate) struct TimerWheel<K> {
     /// The hierarchical timer wheels.
     wheels: Box<[Box<[Bucket<K>]>]>,
+    /// The earliest expiration time of each bucket in the `wheels`.
+    bucket_mins: Box<[Box<[Cell<BucketMin>]>]>,
     /// The time when this `TimerWheel` was created.
     origin: Instant,
     /// The time when this `TimerWheel` was last advanced.
     current: Instant,
+    /// The number of buckets in each level. The last level is the overflow queue,
+    /// which has only one bucket.
+    bucket_counts: Box<[u64]>,
+    /// The span of a bucket in each level in nanoseconds. The last one is the span
+    /// of the whole timer wheel.
+    spans: Box<[u64]>,
+    /// The number of bits to shift a time in nanoseconds to get the ticks in each
+    /// level.
+    shifts: Box<[u32]>,
 }
 
-#[cfg(feature = "future")]
 // TODO: https://github.com/moka-rs/moka/issues/54
 #[allow(clippy::non_send_fields_in_send_ty)]
-// Multi-threaded async runtimes require base_cache::Inner to be Send, but it will
-// not be without this `unsafe impl`. This is because DeqNodes have NonNull
-// pointers.
+// Multi-threaded async runtimes and the proactive expiration thread of the sync
+// cache require base_cache::Inner to be Send, but it will not be without this
+// `unsafe impl`. This is because DeqNodes have NonNull pointers.
 unsafe impl<K> Send for TimerWheel<K> {}
 
 impl<K> TimerWheel<K> {
-    pub(crate) fn new(now: Instant) -> Self {
+    pub(crate) fn new(now: Instant, config: &TimerWheelConfig) -> Self {
+        let bucket_counts = config
+            .bucket_counts()
+            .iter()
+            .map(|c| *c as u64)
+            .chain(std::iter::once(1)) // The overflow queue.
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+        let spans = config.level_spans().into_boxed_slice();
+        let shifts = spans.iter().map(|s| s.trailing_zeros()).collect();
+
         Self {
             wheels: Box::default(), // Empty.
+            bucket_mins: Box::default(),
             origin: now,
             current: now,
+            bucket_counts,
+            spans,
+            shifts,
         }
     }
 
+    /// Returns the index of the overflow queue, which is the last level.
+    fn overflow_queue_index(&self) -> usize {
+        self.bucket_counts.len() - 1
+    }
+
     pub(crate) fn is_enabled(&self) -> bool {
         !self.wheels.is_empty()
     }
@@ -183,7 +187,8 @@ impl<K> TimerWheel<K> {
         assert!(!self.is_enabled());
 
         // Populate each bucket with a queue having a sentinel node.
-        self.wheels = BUCKET_COUNTS
+        self.wheels = self
+            .bucket_counts
             .iter()
             .map(|b| {
                 (0..*b)
@@ -197,6 +202,17 @@ impl<K> TimerWheel<K> {
             })
             .collect::<Vec<_>>()
             .into_boxed_slice();
+        self.bucket_mins = self
+            .bucket_counts
+            .iter()
+            .map(|b| {
+                (0..*b)
+                    .map(|_| Cell::new(BucketMin::Known(None)))
+                    .collect::<Vec<_>>()
+                    .into_boxed_slice()
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
     }
 
     /// Schedules a timer event for the node.
@@ -213,6 +229,7 @@ impl<K> TimerWheel<K> {
                 entry_info, deq_nodes, level, index,
             )));
             let node = self.wheels[level][index].push_back(node);
+            self.record_push(level, index, t);
             Some(node)
         } else {
             None
@@ -237,6 +254,7 @@ impl<K> TimerWheel<K> {
                 entry.set_position(level, index);
                 let node = unsafe { Box::from_raw(node.as_ptr()) };
                 self.wheels[level][index].push_back(node);
+                self.record_push(level, index, t);
                 ReschedulingResult::Rescheduled
             } else {
                 entry.unset_position();
@@ -277,6 +295,7 @@ impl<K> TimerWheel<K> {
         if let entry @ TimerNode::Entry { .. } = &mut p.element {
             if let Some((level, index)) = entry.position() {
                 self.wheels[level][index].unlink(node);
+                self.bucket_mins[level][index].set(BucketMin::Unknown);
                 entry.unset_position();
             }
         } else {
@@ -301,6 +320,86 @@ impl<K> TimerWheel<K> {
         TimerEventsIter::new(self, previous_time, current_time)
     }
 
+    /// Returns the time when this timer wheel will fire the earliest timer event,
+    /// or `None` if there is no timer event.
+    ///
+    /// An event is fired when the timer wheel is advanced into the level 0 tick
+    /// containing its expiration time, at or after the expiration time. If the
+    /// timer wheel has already been advanced into that tick, the event will not be
+    /// fired until the next tick, so the returned time will be the end of the tick.
+    ///
+    /// At each level, the buckets are visited in time order from the current tick
+    /// until a bucket starts after the earliest expiration time found so far. The
+    /// overflow queue is visited only when no other bucket has a timer event, so an
+    /// event in the overflow queue can be reported later than it actually is. The
+    /// earliest expiration time of each bucket is cached, and a bucket is scanned
+    /// only when a timer event was removed from it since the last scan.
+    pub(crate) fn next_expiration_time(&self) -> Option<Instant> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let current_nanos = self.time_nanos(self.current);
+        let mut earliest: Option<Instant> = None;
+
+        for level in 0..self.overflow_queue_index() {
+            let current_ticks = current_nanos >> self.shifts[level];
+            let bucket_count = self.bucket_counts[level];
+
+            for ticks in current_ticks..(current_ticks + bucket_count) {
+                let nanos = ticks.saturating_mul(self.spans[level]);
+                let bucket_start = self.origin.saturating_add(Duration::from_nanos(nanos));
+                if earliest.is_some_and(|t| bucket_start > t) {
+                    break;
+                }
+                let index = (ticks & (bucket_count - 1)) as usize;
+                earliest = self
+                    .earliest_in_bucket(level, index)
+                    .into_iter()
+                    .chain(earliest)
+                    .min();
+            }
+        }
+
+        if earliest.is_none() {
+            earliest = self.earliest_in_bucket(self.overflow_queue_index(), 0);
+        }
+
+        earliest.map(|time| {
+            let ticks = self.time_nanos(time) >> self.shifts[0];
+            if ticks > current_nanos >> self.shifts[0] {
+                time
+            } else {
+                let nanos = (ticks + 1).saturating_mul(self.spans[0]);
+                self.origin.saturating_add(Duration::from_nanos(nanos))
+            }
+        })
+    }
+
+    /// Returns the earliest expiration time of the timer events in the bucket at
+    /// the given level and index. Scans the bucket if its cached time is unknown.
+    fn earliest_in_bucket(&self, level: usize, index: usize) -> Option<Instant> {
+        let bucket_min = &self.bucket_mins[level][index];
+        match bucket_min.get() {
+            BucketMin::Known(time) => time,
+            BucketMin::Unknown => {
+                let time = scan_earliest_in_bucket(&self.wheels[level][index]);
+                bucket_min.set(BucketMin::Known(time));
+                time
+            }
+        }
+    }
+
+    /// Updates the cached earliest expiration time of the bucket at the given level
+    /// and index for a timer event with the given time pushed to it.
+    fn record_push(&self, level: usize, index: usize, time: Instant) {
+        let bucket_min = &self.bucket_mins[level][index];
+        if let BucketMin::Known(earliest) = bucket_min.get() {
+            let earliest = earliest.map_or(time, |t| t.min(time));
+            bucket_min.set(BucketMin::Known(Some(earliest)));
+        }
+    }
+
     /// Returns a pointer to the timer event (cache entry) at the front of the queue.
     /// Returns `None` if the front node is a sentinel.
     fn pop_timer_node(&mut self, level: usize, index: usize) -> Option<Box<DeqNode<TimerNode<K>>>> {
@@ -311,7 +410,9 @@ impl<K> TimerWheel<K> {
             }
         }
 
-        deque.pop_front()
+        let node = deque.pop_front();
+        self.bucket_mins[level][index].set(BucketMin::Unknown);
+        node
     }
 
     /// Reset the positions of the nodes in the queue at the given level and index.
@@ -335,14 +436,15 @@ impl<K> TimerWheel<K> {
     fn bucket_indices(&self, time: Instant) -> (usize, usize) {
         let duration_nanos = self.duration_nanos_since_last_advanced(time);
         let time_nanos = self.time_nanos(time);
-        for level in 0..=NUM_LEVELS {
-            if duration_nanos < SPANS[level + 1] {
-                let ticks = time_nanos >> SHIFT[level];
-                let index = ticks & (BUCKET_COUNTS[level] - 1);
+        let overflow_queue_index = self.overflow_queue_index();
+        for level in 0..overflow_queue_index {
+            if duration_nanos < self.spans[level + 1] {
+                let ticks = time_nanos >> self.shifts[level];
+                let index = ticks & (self.bucket_counts[level] - 1);
                 return (level, index as usize);
             }
         }
-        (OVERFLOW_QUEUE_INDEX, 0)
+        (overflow_queue_index, 0)
     }
 
     // Returns nano-seconds between the given `time` and the time when this timer
@@ -376,6 +478,24 @@ impl<K> TimerWheel<K> {
     }
 }
 
+/// Scans the bucket and returns the earliest expiration time of its timer events.
+fn scan_earliest_in_bucket<K>(bucket: &Bucket<K>) -> Option<Instant> {
+    let mut earliest = None;
+    let mut next = bucket.peek_front_ptr();
+    while let Some(node) = next {
+        // SAFETY: The caller holds a shared reference to the timer wheel, so the
+        // nodes in the bucket cannot be unlinked or dropped while we are reading
+        // them.
+        let element = &unsafe { node.as_ref() }.element;
+        if let TimerNode::Entry { .. } = element {
+            let t = element.entry_info().expiration_time();
+            earliest = t.into_iter().chain(earliest).min();
+        }
+        next = DeqNode::next_node_ptr(node);
+    }
+    earliest
+}
+
 /// A timer event, which is either an expired/rescheduled cache entry, or a
 /// descheduled timer. `TimerWheel::advance` method returns an iterator over timer
 /// events.
@@ -402,8 +522,8 @@ pub(crate) struct TimerEventsIter<'iter, K> {
     current_time: Instant,
     is_done: bool,
     level: usize,
-    index: u8,
-    end_index: u8,
+    index: u64,
+    end_index: u64,
     index_mask: u64,
     is_new_level: bool,
     is_new_index: bool,
@@ -455,18 +575,19 @@ impl<K> Iterator for TimerEventsIter<'_, K> {
             if self.is_new_level {
                 let previous_time_nanos = self.timer_wheel.time_nanos(self.previous_time);
                 let current_time_nanos = self.timer_wheel.time_nanos(self.current_time);
-                let previous_ticks = previous_time_nanos >> SHIFT[self.level];
-                let current_ticks = current_time_nanos >> SHIFT[self.level];
+                let shift = self.timer_wheel.shifts[self.level];
+                let previous_ticks = previous_time_nanos >> shift;
+                let current_ticks = current_time_nanos >> shift;
 
                 if current_ticks <= previous_ticks {
                     self.is_done = true;
                     return None;
                 }
 
-                self.index_mask = BUCKET_COUNTS[self.level] - 1;
-                self.index = (previous_ticks & self.index_mask) as u8;
-                let steps =
-                    (current_ticks - previous_ticks + 1).min(BUCKET_COUNTS[self.level]) as u8;
+                let bucket_count = self.timer_wheel.bucket_counts[self.level];
+                self.index_mask = bucket_count - 1;
+                self.index = previous_ticks & self.index_mask;
+                let steps = (current_ticks - previous_ticks + 1).min(bucket_count);
                 self.end_index = self.index + steps;
 
                 self.is_new_level = false;
@@ -475,7 +596,7 @@ impl<K> Iterator for TimerEventsIter<'_, K> {
                 // dbg!(self.level, self.index, self.end_index);
             }
 
-            let i = self.index & self.index_mask as u8;
+            let i = self.index & self.index_mask;
 
             if self.is_new_index {
                 // Move the sentinel to the back of the queue.
@@ -533,7 +654,7 @@ impl<K> Iterator for TimerEventsIter<'_, K> {
                 if self.index >= self.end_index {
                     self.level += 1;
                     // No more levels to process. We are done.
-                    if self.level >= BUCKET_COUNTS.len() {
+                    if self.level >= self.timer_wheel.bucket_counts.len() {
                         self.is_done = true;
                         return None;
                     }
@@ -548,12 +669,33 @@ impl<K> Iterator for TimerEventsIter<'_, K> {
 mod tests {
     use std::{sync::Arc, time::Duration};
 
-    use super::{TimerEvent, TimerWheel, SPANS};
-    use crate::common::{
-        concurrent::{arc::MiniArc, entry_info::EntryInfo, KeyHash},
-        time::{Clock, Instant, Mock},
+    use super::{TimerEvent, TimerWheel};
+    use crate::{
+        common::{
+            concurrent::{arc::MiniArc, entry_info::EntryInfo, KeyHash},
+            time::{Clock, Instant, Mock},
+        },
+        policy::TimerWheelConfig,
     };
 
+    const DAY: Duration = Duration::from_secs(60 * 60 * 24);
+
+    // The spans of the levels of the default timer wheel.
+    const SPANS: &[u64] = &[
+        aligned_duration(Duration::from_secs(1)),       // 1.07s
+        aligned_duration(Duration::from_secs(60)),      // 1.14m
+        aligned_duration(Duration::from_secs(60 * 60)), // 1.22h
+        aligned_duration(DAY),                          // 1.63d
+        4 * aligned_duration(DAY),                      // 6.5d
+    ];
+
+    /// Returns the next power of two of the duration in nanoseconds.
+    const fn aligned_duration(duration: Duration) -> u64 {
+        // NOTE: as_nanos() returns u128, so convert it to u64 by using `as`.
+        // We cannot call TryInto::try_into() here because it is not a const fn.
+        (duration.as_nanos() as u64).next_power_of_two()
+    }
+
     #[test]
     fn test_bucket_indices() {
         fn bi(timer: &TimerWheel<()>, now: Instant, dur: Duration) -> (usize, usize) {
@@ -564,7 +706,7 @@ mod tests {
         let (clock, mock) = Clock::mock();
         let now = clock.now();
 
-        let mut timer = TimerWheel::<()>::new(now);
+        let mut timer = TimerWheel::<()>::new(now, &TimerWheelConfig::default());
         timer.enable();
 
         assert_eq!(timer.bucket_indices(now), (0, 0));
@@ -638,17 +780,6 @@ mod tests {
 
     #[test]
     fn test_advance() {
-        fn schedule_timer(timer: &mut TimerWheel<u32>, key: u32, now: Instant, ttl: Duration) {
-            let hash = key as u64;
-            let key_hash = KeyHash::new(Arc::new(key), hash);
-            let policy_weight = 0;
-            let entry_info = MiniArc::new(EntryInfo::new(key_hash, now, policy_weight));
-            entry_info.set_expiration_time(Some(now.saturating_add(ttl)));
-            let deq_nodes = Default::default();
-            let timer_node = timer.schedule(entry_info, MiniArc::clone(&deq_nodes));
-            deq_nodes.lock().set_timer_node(timer_node);
-        }
-
         fn expired_key(maybe_entry: Option<TimerEvent<u32>>) -> u32 {
             let entry = maybe_entry.expect("entry is none");
             match entry {
@@ -668,7 +799,7 @@ mod tests {
         let (clock, mock) = Clock::mock();
         let now = advance_clock(&clock, &mock, s2d(10));
 
-        let mut timer = TimerWheel::<u32>::new(now);
+        let mut timer = TimerWheel::<u32>::new(now, &TimerWheelConfig::default());
         timer.enable();
 
         // Add timers that will expire in some seconds.
@@ -781,10 +912,152 @@ mod tests {
         drop(expired_entries);
     }
 
+    #[test]
+    fn test_custom_layout() {
+        fn bi(timer: &TimerWheel<u32>, now: Instant, dur: Duration) -> (usize, usize) {
+            let t = now.saturating_add(dur);
+            timer.bucket_indices(t)
+        }
+
+        // A tick of ~134ms, and two levels of 16 and 4 buckets, spanning ~8.6s.
+        let config = TimerWheelConfig::new(Duration::from_millis(100), &[16, 4]);
+        let tick = config.tick().as_nanos() as u64;
+        assert_eq!(tick, 1 << 27);
+        assert_eq!(config.span(), n2d(tick * 16 * 4));
+
+        let (clock, mock) = Clock::mock();
+        let now = clock.now();
+
+        let mut timer = TimerWheel::<u32>::new(now, &config);
+        timer.enable();
+
+        assert_eq!(bi(&timer, now, n2d(tick - 1)), (0, 0));
+        assert_eq!(bi(&timer, now, n2d(tick * 15)), (0, 15));
+        assert_eq!(bi(&timer, now, n2d(tick * 16)), (1, 1));
+        assert_eq!(bi(&timer, now, n2d(tick * 16 * 3)), (1, 3));
+        assert_eq!(bi(&timer, now, n2d(tick * 16 * 4)), (2, 0)); // overflow
+
+        schedule_timer(&mut timer, 1, now, Duration::from_millis(300));
+        schedule_timer(&mut timer, 2, now, Duration::from_secs(5));
+        schedule_timer(&mut timer, 3, now, Duration::from_secs(60));
+
+        let mut expired_keys = |duration| {
+            let now = advance_clock(&clock, &mock, duration);
+            timer
+                .advance(now)
+                .filter_map(|e| match e {
+                    TimerEvent::Expired(node) => Some(*node.element.entry_info().key_hash().key),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(expired_keys(Duration::from_millis(250)).is_empty());
+        assert_eq!(expired_keys(Duration::from_millis(100)), vec![1]); // 350ms.
+        assert_eq!(expired_keys(Duration::from_secs(5)), vec![2]); // 5.35s.
+        assert!(expired_keys(Duration::from_secs(50)).is_empty()); // 55.35s.
+        assert_eq!(expired_keys(Duration::from_secs(5)), vec![3]); // 60.35s.
+    }
+
+    #[test]
+    fn test_next_expiration_time() {
+        const MINUTES: u64 = 60;
+        const DAYS: u64 = 24 * 60 * 60;
+
+        let (clock, mock) = Clock::mock();
+        let start = advance_clock(&clock, &mock, s2d(10));
+
+        let mut timer = TimerWheel::<u32>::new(start, &TimerWheelConfig::default());
+        assert!(timer.next_expiration_time().is_none());
+        timer.enable();
+        assert!(timer.next_expiration_time().is_none());
+
+        schedule_timer(&mut timer, 1, start, s2d(5 * MINUTES));
+        schedule_timer(&mut timer, 2, start, s2d(3 * DAYS));
+        schedule_timer(&mut timer, 3, start, s2d(30));
+        schedule_timer(&mut timer, 4, start, s2d(10 * DAYS)); // overflow
+
+        let at = |secs| start.saturating_add(s2d(secs));
+        // Returns the end of the level 0 tick containing the given time.
+        let end_of_tick = |time: Instant| {
+            let ticks = time.saturating_duration_since(start).as_nanos() as u64 / SPANS[0];
+            start.saturating_add(n2d((ticks + 1) * SPANS[0]))
+        };
+        let advance_to = |timer: &mut TimerWheel<u32>, time: Instant| {
+            mock.increment(time.saturating_duration_since(clock.now()));
+            timer
+                .advance(clock.now())
+                .filter(|e| matches!(e, TimerEvent::Expired(_)))
+                .count()
+        };
+
+        assert_eq!(timer.next_expiration_time(), Some(at(30)));
+        assert_eq!(advance_to(&mut timer, at(30)), 1);
+
+        assert_eq!(timer.next_expiration_time(), Some(at(5 * MINUTES)));
+        // Advance into the tick of "1" right before it expires. "1" will not be
+        // fired until the end of the tick.
+        let just_before = Instant::from_nanos(at(5 * MINUTES).as_nanos() - 1);
+        assert_eq!(advance_to(&mut timer, just_before), 0);
+        let fire_time = end_of_tick(at(5 * MINUTES));
+        assert_eq!(timer.next_expiration_time(), Some(fire_time));
+        assert_eq!(advance_to(&mut timer, at(5 * MINUTES)), 0);
+        assert_eq!(advance_to(&mut timer, fire_time), 1);
+
+        assert_eq!(timer.next_expiration_time(), Some(at(3 * DAYS)));
+        assert_eq!(advance_to(&mut timer, at(3 * DAYS)), 1);
+
+        assert_eq!(timer.next_expiration_time(), Some(at(10 * DAYS)));
+        assert_eq!(advance_to(&mut timer, at(10 * DAYS)), 1);
+        assert!(timer.next_expiration_time().is_none());
+    }
+
+    #[test]
+    fn test_next_expiration_time_after_deschedule() {
+        let (clock, mock) = Clock::mock();
+        let start = advance_clock(&clock, &mock, s2d(10));
+
+        let mut timer = TimerWheel::<u32>::new(start, &TimerWheelConfig::default());
+        timer.enable();
+
+        // Schedule two timers in the same level 1 bucket, and remove the earlier one.
+        let schedule = |timer: &mut TimerWheel<u32>, key: u32, secs: u64| {
+            let key_hash = KeyHash::new(Arc::new(key), key as u64);
+            let entry_info = MiniArc::new(EntryInfo::new(key_hash, start, 0));
+            entry_info.set_expiration_time(Some(start.saturating_add(s2d(secs))));
+            timer
+                .schedule(entry_info, Default::default())
+                .expect("Failed to schedule")
+        };
+        let at = |secs| start.saturating_add(s2d(secs));
+        let node1 = schedule(&mut timer, 1, 120);
+        schedule(&mut timer, 2, 130);
+        assert_eq!(timer.bucket_indices(at(120)), timer.bucket_indices(at(130)));
+        assert_eq!(timer.next_expiration_time(), Some(at(120)));
+
+        timer.deschedule(node1);
+        assert_eq!(timer.next_expiration_time(), Some(at(130)));
+
+        // An earlier timer pushed to the same bucket is reflected too.
+        schedule(&mut timer, 3, 125);
+        assert_eq!(timer.next_expiration_time(), Some(at(125)));
+    }
+
     //
     // Utility functions
     //
 
+    fn schedule_timer(timer: &mut TimerWheel<u32>, key: u32, now: Instant, ttl: Duration) {
+        let hash = key as u64;
+        let key_hash = KeyHash::new(Arc::new(key), hash);
+        let policy_weight = 0;
+        let entry_info = MiniArc::new(EntryInfo::new(key_hash, now, policy_weight));
+        entry_info.set_expiration_time(Some(now.saturating_add(ttl)));
+        let deq_nodes = Default::default();
+        let timer_node = timer.schedule(entry_info, MiniArc::clone(&deq_nodes));
+        deq_nodes.lock().set_timer_node(timer_node);
+    }
+
     fn advance_clock(clock: &Clock, mock: &Arc<Mock>, duration: Duration) -> Instant {
         mock.increment(duration);
         clock.now()
@@ -0,0 +1,417 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+#[cfg(feature = "future")]
+use event_listener::{Event, EventListener};
+use parking_lot::{Condvar, Mutex};
+
+use super::time::{Clock, Instant};
+use crate::notification::RemovalCause;
+
+/// The entries passed to the flush function of the write-behind buffer.
+pub(crate) type DirtyEntries<K, V> = Vec<(Arc<K>, V)>;
+
+pub(crate) type WriteBehindFlush<K, V> = Arc<dyn Fn(DirtyEntries<K, V>) + Send + Sync + 'static>;
+
+/// How the write-behind buffer flushes the dirty entries. Set by the cache builder.
+pub(crate) struct WriteBehindConfig<K, V> {
+    pub(crate) flush: WriteBehindFlush<K, V>,
+    pub(crate) max_batch_size: usize,
+    pub(crate) max_delay: Duration,
+    /// The flush thread shared by the segments of a `SegmentedCache`. If `None`,
+    /// the buffer starts its own thread.
+    pub(crate) flush_thread: Option<Arc<FlushThread>>,
+}
+
+impl<K, V> Clone for WriteBehindConfig<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            flush: Arc::clone(&self.flush),
+            max_batch_size: self.max_batch_size,
+            max_delay: self.max_delay,
+            flush_thread: self.flush_thread.clone(),
+        }
+    }
+}
+
+impl<K, V> WriteBehindConfig<K, V> {
+    /// Starts the flush thread to be shared by all the buffers built from this
+    /// config.
+    #[cfg(feature = "sync")]
+    pub(crate) fn share_flush_thread(&mut self, cache_name: Option<&str>) {
+        self.flush_thread = Some(Arc::new(FlushThread::new(cache_name)));
+    }
+}
+
+/// A batch of dirty entries to pass to the flush function.
+type Job = Box<dyn FnOnce() + Send>;
+
+enum Message {
+    Flush(Job),
+    /// A new buffer to flush when its oldest dirty entry has waited for the max
+    /// delay.
+    Register(Weak<dyn Flushable>),
+    /// A buffer got its oldest dirty entry, so the thread has to recompute when to
+    /// wake up.
+    Dirty,
+}
+
+/// The thread calling the flush function of the write-behind buffers. The segments
+/// of a `SegmentedCache` share one thread. The thread also flushes the buffers
+/// whose oldest dirty entry has waited for the max delay, so an idle cache does not
+/// keep its dirty entries forever. The thread exits when all the buffers using it
+/// are dropped and the remaining batches are flushed.
+pub(crate) struct FlushThread {
+    sender: Sender<Message>,
+    thread_id: ThreadId,
+}
+
+impl FlushThread {
+    fn new(cache_name: Option<&str>) -> Self {
+        // The channel is unbounded so that handing a batch to the thread never
+        // blocks the caller, which may hold the key lock or the deques lock.
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let handle = thread::Builder::new()
+            .name(thread_name(cache_name))
+            .spawn(move || Self::run(&receiver))
+            .expect("Failed to spawn the write-behind thread");
+
+        Self {
+            sender,
+            thread_id: handle.thread().id(),
+        }
+    }
+
+    fn run(receiver: &Receiver<Message>) {
+        let mut buffers: Vec<Weak<dyn Flushable>> = Vec::new();
+        // The time until the oldest dirty entry of the buffers waits for the max
+        // delay, or `None` if the buffers are empty.
+        let mut timeout: Option<Duration> = None;
+        loop {
+            let message = match timeout {
+                Some(timeout) => receiver.recv_timeout(timeout),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                Ok(Message::Flush(job)) => job(),
+                Ok(Message::Register(buffer)) => buffers.push(buffer),
+                Ok(Message::Dirty) | Err(RecvTimeoutError::Timeout) => (),
+                // All the buffers have been dropped, and the remaining batches have
+                // been flushed.
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            buffers.retain(|buffer| buffer.strong_count() > 0);
+            timeout = buffers
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter_map(|buffer| buffer.flush_if_due())
+                .min();
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        thread::current().id() == self.thread_id
+    }
+}
+
+/// Collects the entries written to the cache and passes them to the flush function
+/// in batches. Repeated writes to the same key are coalesced, so only the latest
+/// value of a key is flushed.
+///
+/// The flush function is called on the flush thread, so it never runs while the
+/// cache holds a lock. The batches are taken from the buffer and queued to the
+/// thread under the buffer lock, so they are flushed in the order they were taken.
+/// When the buffer is dropped, the remaining dirty entries are handed to the thread,
+/// which flushes them before it exits.
+pub(crate) struct WriteBehind<K, V> {
+    buffer: Arc<Buffer<K, V>>,
+    /// The same buffer as `buffer`, to flush it on drop without the trait bounds.
+    flushable: Arc<dyn Flushable>,
+}
+
+struct Buffer<K, V> {
+    config: WriteBehindConfig<K, V>,
+    dirty: Mutex<DirtyBuffer<K, V>>,
+    thread: Arc<FlushThread>,
+    progress: Arc<Progress>,
+    clock: Clock,
+}
+
+struct DirtyBuffer<K, V> {
+    entries: HashMap<Arc<K>, V>,
+    /// The time when the oldest entry in the buffer was written.
+    oldest: Option<Instant>,
+}
+
+impl<K, V> WriteBehind<K, V>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Send + 'static,
+{
+    pub(crate) fn new(
+        config: WriteBehindConfig<K, V>,
+        cache_name: Option<&str>,
+        clock: Clock,
+    ) -> Self {
+        let thread = config
+            .flush_thread
+            .clone()
+            .unwrap_or_else(|| Arc::new(FlushThread::new(cache_name)));
+        let buffer = Arc::new(Buffer {
+            config,
+            dirty: Mutex::new(DirtyBuffer {
+                entries: HashMap::new(),
+                oldest: None,
+            }),
+            thread,
+            progress: Arc::default(),
+            clock,
+        });
+
+        let flushable: Arc<dyn Flushable> = buffer.clone();
+        let weak = Arc::downgrade(&flushable);
+        let _ = buffer.thread.sender.send(Message::Register(weak));
+        Self { buffer, flushable }
+    }
+
+    /// Marks the entry as dirty, replacing the value of a pending write for the same
+    /// key (if any). Hands the buffer to the flush thread if it has reached the max
+    /// batch size.
+    pub(crate) fn mark_dirty(&self, key: Arc<K>, value: V, now: Instant) {
+        let buffer = &self.buffer;
+        let mut dirty = buffer.dirty.lock();
+        dirty.entries.insert(key, value);
+        if dirty.entries.len() >= buffer.config.max_batch_size {
+            buffer.submit(&mut dirty);
+        } else if dirty.oldest.is_none() {
+            dirty.oldest = Some(now);
+            // Let the thread flush the buffer after the max delay.
+            let _ = buffer.thread.sender.send(Message::Dirty);
+        }
+    }
+
+    /// Hands the buffer to the flush thread if the oldest dirty entry has waited for
+    /// the max delay.
+    pub(crate) fn flush_if_due(&self, now: Instant) {
+        self.buffer.flush_if_due_at(now);
+    }
+
+    /// Called before notifying the removal of the key. If the key has a pending
+    /// write, hands the buffer to the flush thread, so that the latest value of the
+    /// key has been flushed when the listener is called. Or, if the key was
+    /// invalidated by the user, discards the pending write so that the deleted value
+    /// will not be written back. A replaced value needs neither, as the new value
+    /// replaces it in the buffer.
+    ///
+    /// Returns the flush to wait for before the notification, or `None` if no batch
+    /// is in flight.
+    pub(crate) fn prepare_removal(&self, key: &K, cause: RemovalCause) -> Option<PendingFlush> {
+        let mut dirty = self.buffer.dirty.lock();
+        match cause {
+            RemovalCause::Replaced => return None,
+            RemovalCause::Explicit | RemovalCause::InvalidatedByPredicate(_) => {
+                if dirty.entries.remove(key).is_some() && dirty.entries.is_empty() {
+                    dirty.oldest = None;
+                }
+            }
+            _ => {
+                if dirty.entries.contains_key(key) {
+                    self.buffer.submit(&mut dirty);
+                }
+            }
+        }
+        // The key may be in a batch that was taken earlier and is still in flight.
+        let pending = self.pending();
+        (!pending.is_done()).then_some(pending)
+    }
+
+    /// Flushes all the dirty entries and waits for the flush thread to finish them.
+    #[cfg(feature = "sync")]
+    pub(crate) fn flush(&self) {
+        self.buffer.submit(&mut self.buffer.dirty.lock());
+        self.pending().wait();
+    }
+
+    /// Flushes all the dirty entries and waits for the flush thread to finish them,
+    /// without blocking the async runtime.
+    #[cfg(feature = "future")]
+    pub(crate) async fn flush_async(&self) {
+        self.buffer.submit(&mut self.buffer.dirty.lock());
+        self.pending().wait_async().await;
+    }
+
+    /// Returns the flush of all the batches handed to the flush thread so far.
+    pub(crate) fn pending(&self) -> PendingFlush {
+        let buffer = &self.buffer;
+        PendingFlush {
+            ticket: buffer.progress.submitted.load(Ordering::Acquire),
+            progress: Arc::clone(&buffer.progress),
+            thread: Arc::clone(&buffer.thread),
+        }
+    }
+}
+
+impl<K, V> Drop for WriteBehind<K, V> {
+    fn drop(&mut self) {
+        // Hand the remaining dirty entries to the flush thread. Do not wait for the
+        // flush, as the cache may be dropped by an async task or by the flush
+        // function itself.
+        self.flushable.flush_all();
+    }
+}
+
+impl<K, V> Buffer<K, V>
+where
+    K: Send + Sync + 'static,
+    V: Send + 'static,
+{
+    /// Hands the buffer to the flush thread if the oldest dirty entry has waited for
+    /// the max delay. Otherwise, returns the time until it will, or `None` if the
+    /// buffer is empty.
+    fn flush_if_due_at(&self, now: Instant) -> Option<Duration> {
+        let mut dirty = self.dirty.lock();
+        let waited = now.saturating_duration_since(dirty.oldest?);
+        if waited >= self.config.max_delay {
+            self.submit(&mut dirty);
+            None
+        } else {
+            Some(self.config.max_delay - waited)
+        }
+    }
+
+    /// Takes all the dirty entries from the buffer and queues them to the flush
+    /// thread. Must be called with the buffer lock held.
+    fn submit(&self, dirty: &mut DirtyBuffer<K, V>) {
+        dirty.oldest = None;
+        let entries = std::mem::take(&mut dirty.entries);
+        if entries.is_empty() {
+            return;
+        }
+
+        self.progress.submitted.fetch_add(1, Ordering::AcqRel);
+        let flush = Arc::clone(&self.config.flush);
+        let progress = Arc::clone(&self.progress);
+        let job = Box::new(move || {
+            // Ignore a panic in the flush function so that the thread can keep
+            // flushing the other batches and the waiters will not block forever.
+            let _ = catch_unwind(AssertUnwindSafe(|| flush(entries.into_iter().collect())));
+            progress.incr_flushed();
+        });
+        if self.thread.sender.send(Message::Flush(job)).is_err() {
+            // The thread has gone. Count it as flushed so that the waiters will not
+            // block forever.
+            self.progress.incr_flushed();
+        }
+    }
+}
+
+/// A write-behind buffer with its key and value types erased.
+trait Flushable: Send + Sync {
+    /// Hands the buffer to the flush thread if the oldest dirty entry has waited for
+    /// the max delay. Otherwise, returns the time until it will, or `None` if the
+    /// buffer is empty.
+    fn flush_if_due(&self) -> Option<Duration>;
+
+    /// Hands all the dirty entries to the flush thread.
+    fn flush_all(&self);
+}
+
+impl<K, V> Flushable for Buffer<K, V>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Send + 'static,
+{
+    fn flush_if_due(&self) -> Option<Duration> {
+        self.flush_if_due_at(self.clock.now())
+    }
+
+    fn flush_all(&self) {
+        self.submit(&mut self.dirty.lock());
+    }
+}
+
+/// A batch handed to the flush thread, and all the batches of the same buffer
+/// before it, to wait for.
+pub(crate) struct PendingFlush {
+    /// The number of the batches of the buffer to be flushed. The batches are
+    /// numbered in the order they were taken from the buffer.
+    ticket: u64,
+    progress: Arc<Progress>,
+    thread: Arc<FlushThread>,
+}
+
+impl PendingFlush {
+    fn is_done(&self) -> bool {
+        *self.progress.flushed.lock() >= self.ticket
+    }
+
+    /// Blocks the current thread until the batches have been flushed. Returns
+    /// immediately when called by the flush function running on the flush thread,
+    /// which would otherwise wait for itself.
+    #[cfg(feature = "sync")]
+    pub(crate) fn wait(self) {
+        if self.thread.is_current() {
+            return;
+        }
+        let mut flushed = self.progress.flushed.lock();
+        while *flushed < self.ticket {
+            self.progress.cond.wait(&mut flushed);
+        }
+    }
+
+    /// Waits until the batches have been flushed, without blocking the async
+    /// runtime.
+    #[cfg(feature = "future")]
+    pub(crate) async fn wait_async(self) {
+        if self.thread.is_current() {
+            return;
+        }
+        let mut listener: Option<EventListener> = None;
+        while !self.is_done() {
+            match listener.take() {
+                // Start listening, and then check the progress again so that we will
+                // not miss a flush finished before the listener was created.
+                None => listener = Some(self.progress.flushed_event.listen()),
+                Some(l) => l.await,
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Progress {
+    /// The number of the batches handed to the flush thread. Only updated under the
+    /// buffer lock.
+    submitted: AtomicU64,
+    flushed: Mutex<u64>,
+    cond: Condvar,
+    #[cfg(feature = "future")]
+    flushed_event: Event,
+}
+
+impl Progress {
+    fn incr_flushed(&self) {
+        *self.flushed.lock() += 1;
+        self.cond.notify_all();
+        #[cfg(feature = "future")]
+        self.flushed_event.notify(usize::MAX);
+    }
+}
+
+fn thread_name(cache_name: Option<&str>) -> String {
+    match cache_name {
+        Some(name) => format!("moka-write-behind-{name}"),
+        None => "moka-write-behind".to_string(),
+    }
+}
@@ -0,0 +1,91 @@
+use std::error::Error;
+
+use crate::notification::RemovalCause;
+
+/// A trait to write the changes made to the cache through to an external storage
+/// such as a database.
+///
+/// The writer is set to a cache by the `writer` method of the cache builders
+/// ([`sync::CacheBuilder::writer`][sync-writer] and
+/// [`future::CacheBuilder::writer`][future-writer]). The cache calls the writer
+/// while it holds the lock for the key, so the writes for the same key are
+/// serialized and applied to the storage in the same order as the cache.
+///
+/// If the writer returns an error, the cache will not be modified. The `try_*`
+/// methods of the cache such as `try_insert` and `try_remove` return the error as
+/// a [`WriteError`][write-error]. The other methods such as `insert` and
+/// `invalidate` ignore it.
+///
+/// The writer is called for the following operations:
+///
+/// - `write`: Inserting or updating an entry by the methods such as `insert` and
+///   `and_compute_with`. The values loaded by the methods such as `get_with` are
+///   not written, as they are expected to come from the storage.
+/// - `delete`: Removing an entry by the methods such as `invalidate` and
+///   `remove`, with [`RemovalCause::Explicit`][removal-cause]. It is not called
+///   when the cache does not have the entry for the key.
+///
+/// The writer is not called for the entries removed by the housekeeping tasks,
+/// e.g. evicted, expired, or invalidated by `invalidate_all` and
+/// `invalidate_entries_if` methods.
+///
+/// The compute methods of the entry selectors also call the writer for `Op::Put`,
+/// `Op::PutWithTtl` and `Op::Remove`. If the writer fails, `and_compute_with`
+/// leaves the entry unchanged and returns [`CompResult::Unchanged`][comp-unchanged]
+/// (or `CompResult::StillNone` if the entry did not exist), and `and_upsert_with`
+/// returns an entry with the new value that was not stored. Use
+/// `and_compute_with_checked` and `and_upsert_with_checked` to get the error as a
+/// `WriteError`.
+///
+/// [sync-writer]: ./sync/struct.CacheBuilder.html#method.writer
+/// [future-writer]: ./future/struct.CacheBuilder.html#method.writer
+/// [write-error]: ./struct.WriteError.html
+/// [removal-cause]: ./notification/enum.RemovalCause.html#variant.Explicit
+/// [comp-unchanged]: ./ops/compute/enum.CompResult.html#variant.Unchanged
+///
+/// # Example
+///
+/// ```rust
+/// use moka::{notification::RemovalCause, sync::Cache, CacheWriter};
+/// use std::{collections::HashMap, error::Error, sync::Mutex};
+///
+/// // A stand-in for a database table.
+/// #[derive(Default)]
+/// struct Table(Mutex<HashMap<u32, String>>);
+///
+/// type BoxError = Box<dyn Error + Send + Sync>;
+///
+/// impl CacheWriter<u32, String> for Table {
+///     fn write(&self, key: &u32, value: &String) -> Result<(), BoxError> {
+///         if value.is_empty() {
+///             return Err("empty value".into());
+///         }
+///         self.0.lock().unwrap().insert(*key, value.clone());
+///         Ok(())
+///     }
+///
+///     fn delete(&self, key: &u32, _cause: RemovalCause) -> Result<(), BoxError> {
+///         self.0.lock().unwrap().remove(key);
+///         Ok(())
+///     }
+/// }
+///
+/// let cache = Cache::builder()
+///     .max_capacity(100)
+///     .writer(Table::default())
+///     .build();
+///
+/// assert!(cache.try_insert(1, "alice".to_string()).is_ok());
+/// // The writer rejects an empty value, so the cache is not modified.
+/// assert!(cache.try_insert(1, String::new()).is_err());
+/// assert_eq!(cache.get(&1), Some("alice".to_string()));
+///
+/// assert_eq!(cache.try_remove(&1).unwrap(), Some("alice".to_string()));
+/// ```
+pub trait CacheWriter<K, V>: Send + Sync {
+    /// Writes the value of the entry inserted or updated in the cache.
+    fn write(&self, key: &K, value: &V) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Deletes the entry removed from the cache.
+    fn delete(&self, key: &K, cause: RemovalCause) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
@@ -25,15 +25,7 @@ pub use {
     entry_selector::{OwnedKeyEntrySelector, RefKeyEntrySelector},
 };
 
-/// The type of the unique ID to identify a predicate used by
-/// [`Cache::invalidate_entries_if`][invalidate-if] method.
-///
-/// A `PredicateId` is a `String` of UUID (version 4).
-///
-/// [invalidate-if]: ./struct.Cache.html#method.invalidate_entries_if
-pub type PredicateId = String;
-
-pub(crate) type PredicateIdStr<'a> = &'a str;
+pub use crate::common::predicate_id::PredicateId;
 
 // Empty struct to be used in `InitResult::InitErr` to represent the Option None.
 pub(crate) struct OptionallyNone;
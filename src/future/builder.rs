@@ -1,12 +1,22 @@
 use super::{Cache, FutureExt};
 use crate::{
-    common::{builder_utils, concurrent::Weigher, time::Clock, HousekeeperConfig},
-    notification::{AsyncEvictionListener, ListenerFuture, RemovalCause},
-    policy::{EvictionPolicy, ExpirationPolicy},
-    Expiry,
+    common::{
+        builder_utils,
+        concurrent::{self, EvictionVeto, Weigher, Writer},
+        time::Clock,
+        write_behind::WriteBehindConfig,
+        HousekeeperConfig,
+    },
+    notification::{
+        AsyncEvictionListener, DeliveryMode, ListenerConfig, ListenerFuture, ListenerPanicPolicy,
+        RemovalCause, RemovalSender,
+    },
+    policy::{AsyncExpiry, EvictionPolicy, ExpirationPolicy, TimerWheelConfig},
+    CacheWriter, Expiry, MemorySize,
 };
 
 use std::{
+    any::Any,
     collections::hash_map::RandomState,
     hash::{BuildHasher, Hash},
     marker::PhantomData,
@@ -60,8 +70,13 @@ pub struct CacheBuilder<K, V, C> {
     max_capacity: Option<u64>,
     initial_capacity: Option<usize>,
     weigher: Option<Weigher<K, V>>,
+    max_entry_weight: Option<u64>,
+    can_evict: Option<EvictionVeto<K, V>>,
+    writer: Option<Writer<K, V>>,
+    write_behind: Option<WriteBehindConfig<K, V>>,
     eviction_policy: EvictionPolicy,
     eviction_listener: Option<AsyncEvictionListener<K, V>>,
+    listener_config: ListenerConfig<K>,
     expiration_policy: ExpirationPolicy<K, V>,
     housekeeper_config: HousekeeperConfig,
     invalidator_enabled: bool,
@@ -80,8 +95,13 @@ where
             max_capacity: None,
             initial_capacity: None,
             weigher: None,
+            max_entry_weight: None,
+            can_evict: None,
+            writer: None,
+            write_behind: None,
             eviction_policy: EvictionPolicy::default(),
             eviction_listener: None,
+            listener_config: ListenerConfig::default(),
             expiration_policy: ExpirationPolicy::default(),
             housekeeper_config: HousekeeperConfig::default(),
             invalidator_enabled: false,
@@ -122,8 +142,13 @@ where
             self.initial_capacity,
             build_hasher,
             self.weigher,
+            self.max_entry_weight,
+            self.can_evict,
+            self.writer,
+            self.write_behind,
             self.eviction_policy,
             self.eviction_listener,
+            self.listener_config,
             self.expiration_policy,
             self.housekeeper_config,
             self.invalidator_enabled,
@@ -221,8 +246,13 @@ where
             self.initial_capacity,
             hasher,
             self.weigher,
+            self.max_entry_weight,
+            self.can_evict,
+            self.writer,
+            self.write_behind,
             self.eviction_policy,
             self.eviction_listener,
+            self.listener_config,
             self.expiration_policy,
             self.housekeeper_config,
             self.invalidator_enabled,
@@ -274,13 +304,239 @@ impl<K, V, C> CacheBuilder<K, V, C> {
     ///
     /// The closure should take `&K` and `&V` as the arguments and returns a `u32`
     /// representing the relative size of the entry.
+    ///
+    /// If a single entry can weigh more than `u32::MAX`, use
+    /// [`weigher_u64`](#method.weigher_u64) instead.
     pub fn weigher(self, weigher: impl Fn(&K, &V) -> u32 + Send + Sync + 'static) -> Self {
+        Self {
+            weigher: Some(Arc::new(move |k: &K, v: &V| weigher(k, v) as u64)),
+            ..self
+        }
+    }
+
+    /// Sets the weigher closure returning a `u64` weight to the cache.
+    ///
+    /// The closure should take `&K` and `&V` as the arguments and returns a `u64`
+    /// representing the relative size of the entry. This is useful when the
+    /// weight of a single entry can exceed `u32::MAX`, e.g. when weighing large
+    /// blobs by bytes.
+    pub fn weigher_u64(self, weigher: impl Fn(&K, &V) -> u64 + Send + Sync + 'static) -> Self {
         Self {
             weigher: Some(Arc::new(weigher)),
             ..self
         }
     }
 
+    /// Bounds the cache by the approximate memory usage in bytes.
+    ///
+    /// This sets the max capacity of the cache to `max_memory_bytes` and sets a
+    /// weigher that weighs each entry by the heap memory owned by its key and
+    /// value (calculated by the [`MemorySize`][memory-size-trait] trait) plus the
+    /// memory used by the cache to hold the entry, such as the internal nodes and
+    /// pointers. This overrides the max capacity and the weigher set by other
+    /// methods.
+    ///
+    /// Note that the memory used by the cache that is not tied to a single entry
+    /// (e.g. the frequency sketch and the internal channels) is not counted, and
+    /// the actual memory usage can exceed `max_memory_bytes` until the pending
+    /// evictions are processed.
+    ///
+    /// [memory-size-trait]: ../trait.MemorySize.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::future::Cache;
+    ///
+    /// // Create a cache that uses up to about 32 MiB of memory.
+    /// let cache: Cache<String, Vec<u8>> = Cache::builder()
+    ///     .max_memory_bytes(32 * 1024 * 1024)
+    ///     .build();
+    /// ```
+    pub fn max_memory_bytes(self, max_memory_bytes: u64) -> Self
+    where
+        K: MemorySize + 'static,
+        V: MemorySize + 'static,
+    {
+        let weigher = |key: &K, value: &V| {
+            let overhead = concurrent::entry_overhead_bytes::<K, V>();
+            (overhead + key.heap_size() + value.heap_size()) as u64
+        };
+        Self {
+            max_capacity: Some(max_memory_bytes),
+            weigher: Some(Arc::new(weigher)),
+            ..self
+        }
+    }
+
+    /// Sets the maximum weight of a single entry.
+    ///
+    /// An entry whose weight (calculated by the weigher, or `1` if no weigher is
+    /// set) exceeds `max_entry_weight` will be rejected at the insert time without
+    /// being stored in the cache. The eviction listener will be notified with
+    /// [`RemovalCause::Rejected`][removal-cause-rejected] for the rejected entry,
+    /// and [`try_insert`][try-insert-method] method will return an error for it.
+    ///
+    /// If the cache already has an entry for the key, it will be removed so that a
+    /// stale value will not be returned. The eviction listener will be notified
+    /// with `RemovalCause::Replaced` for the old value before the rejected one.
+    /// For a rejected value, the `and_compute_with` method of the entry API returns
+    /// `CompResult::Removed` (or `CompResult::StillNone` if there was no entry), and
+    /// the `and_upsert_with` method returns an entry with the rejected value whose
+    /// `is_fresh` is `false`.
+    ///
+    /// [removal-cause-rejected]: ../notification/enum.RemovalCause.html#variant.Rejected
+    /// [try-insert-method]: ./struct.Cache.html#method.try_insert
+    pub fn max_entry_weight(self, max_entry_weight: u64) -> Self {
+        Self {
+            max_entry_weight: Some(max_entry_weight),
+            ..self
+        }
+    }
+
+    /// Sets a closure that can veto the eviction of an entry due to size
+    /// constraints.
+    ///
+    /// The closure takes `&K` and `&V` of an entry chosen to be evicted, either the
+    /// least recently used entry or a victim for admitting a new entry, and
+    /// returns `false` to keep the entry in the cache, e.g. while the value is
+    /// still in use elsewhere. The vetoed entry is skipped and the next candidate
+    /// is tried.
+    ///
+    /// The number of entries checked by a single maintenance run is bounded, so
+    /// the cache may temporarily hold more entries than its max capacity while the
+    /// closure keeps vetoing. The vetoed entries will be checked again by later
+    /// maintenance runs. The closure does not affect the expirations or the
+    /// invalidations.
+    ///
+    /// The closure is called while the cache holds its internal locks, so it
+    /// should be cheap and must not call the methods of the cache.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::future::Cache;
+    /// use std::sync::Arc;
+    ///
+    /// // Do not evict a value while it is shared outside of the cache.
+    /// let cache: Cache<u32, Arc<Vec<u8>>> = Cache::builder()
+    ///     .max_capacity(100)
+    ///     .can_evict(|_key, value| Arc::strong_count(value) == 1)
+    ///     .build();
+    /// ```
+    pub fn can_evict(self, can_evict: impl Fn(&K, &V) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            can_evict: Some(Arc::new(can_evict)),
+            ..self
+        }
+    }
+
+    /// Sets the [`CacheWriter`][cache-writer] to the cache, which writes the
+    /// entries inserted, updated and removed by the user through to an external
+    /// storage.
+    ///
+    /// The writer is called while the cache holds the lock for the key, before the
+    /// cache is modified. If the writer fails, the cache will not be modified, and
+    /// the `try_*` methods such as `try_insert` and `try_remove` return the error.
+    /// See the [`CacheWriter`][cache-writer] for the operations calling the writer.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    pub fn writer(self, writer: impl CacheWriter<K, V> + 'static) -> Self {
+        Self {
+            writer: Some(Arc::new(writer)),
+            ..self
+        }
+    }
+
+    /// Sets the write-behind buffer to the cache, which persists the entries
+    /// written to the cache through the `flush` closure asynchronously from the
+    /// writes.
+    ///
+    /// The entries inserted or updated by the user (but not the values loaded by
+    /// the methods such as `get_with`) are collected in the buffer as dirty
+    /// entries. Repeated writes to the same key are coalesced, so only the latest
+    /// value of the key is flushed. The `flush` closure is called with all the dirty
+    /// entries when either of the following happens:
+    ///
+    /// - The buffer has `max_batch_size` keys.
+    /// - The oldest dirty entry has waited for `max_delay`. The flush thread checks
+    ///   it by itself, so the entries are flushed even when the cache is idle.
+    /// - The cache is closed by [`close`](./struct.Cache.html#method.close) method.
+    /// - The cache is dropped. The remaining dirty entries are handed to the flush
+    ///   thread, which flushes them in background. Dropping the cache does not wait
+    ///   for the flush, so call `close` before dropping the cache (e.g. at the
+    ///   process exit) to make sure that all the entries are flushed.
+    ///
+    /// When a dirty entry is evicted or expired, the buffer is flushed before the
+    /// eviction listener is called for the entry. When it is invalidated by
+    /// `invalidate`, `remove` or `invalidate_entries_if`, its pending write is
+    /// discarded instead, so that the deleted value will not be written back. A
+    /// pending write is kept when the value of the entry is replaced; the new value
+    /// replaces it in the buffer.
+    ///
+    /// The closure is called on a dedicated thread named `moka-write-behind`, in the
+    /// order the batches were taken from the buffer. It is never called
+    /// concurrently, and never while the cache holds a lock. A panic in the closure
+    /// is ignored, and the entries of the batch are not flushed again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::future::Cache;
+    /// use std::{
+    ///     sync::{Arc, Mutex},
+    ///     time::Duration,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = Arc::new(Mutex::new(Vec::new()));
+    ///     let store2 = Arc::clone(&store);
+    ///
+    ///     let cache = Cache::builder()
+    ///         .max_capacity(100)
+    ///         .write_behind(2, Duration::from_secs(1), move |entries| {
+    ///             let mut store = store2.lock().unwrap();
+    ///             store.extend(entries.into_iter().map(|(k, v)| (*k, v)));
+    ///         })
+    ///         .build();
+    ///
+    ///     // The second write to "a" replaces the first one in the buffer.
+    ///     cache.insert("a", 1).await;
+    ///     cache.insert("a", 2).await;
+    ///     assert!(store.lock().unwrap().is_empty());
+    ///
+    ///     // The buffer reached the max batch size and was handed to the flush
+    ///     // thread. `close` waits for the thread to flush it.
+    ///     cache.insert("b", 1).await;
+    ///     cache.close().await;
+    ///     let mut flushed = store.lock().unwrap().clone();
+    ///     flushed.sort_unstable();
+    ///     assert_eq!(flushed, vec![("a", 2), ("b", 1)]);
+    /// }
+    /// ```
+    pub fn write_behind(
+        self,
+        max_batch_size: usize,
+        max_delay: Duration,
+        flush: impl Fn(Vec<(Arc<K>, V)>) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            write_behind: Some(WriteBehindConfig {
+                flush: Arc::new(flush),
+                max_batch_size: max_batch_size.max(1),
+                max_delay,
+                flush_thread: None,
+            }),
+            ..self
+        }
+    }
+
     /// Sets the eviction listener closure to the cache. The closure should take
     /// `Arc<K>`, `V` and [`RemovalCause`][removal-cause] as the arguments.
     ///
@@ -303,6 +559,9 @@ impl<K, V, C> CacheBuilder<K, V, C> {
     /// the cache will stop calling the listener after a panic. This is an intended
     /// behavior because the cache cannot know whether it is memory safe or not to
     /// call the panicked listener again.
+    /// You can change this behavior by
+    /// [`eviction_listener_panic_policy`](#method.eviction_listener_panic_policy)
+    /// method.
     ///
     /// [removal-cause]: ../notification/enum.RemovalCause.html
     /// [example]: ./struct.Cache.html#per-entry-expiration-policy
@@ -343,6 +602,9 @@ impl<K, V, C> CacheBuilder<K, V, C> {
     /// the cache will stop calling the listener after a panic. This is an intended
     /// behavior because the cache cannot know whether it is memory safe or not to
     /// call the panicked listener again.
+    /// You can change this behavior by
+    /// [`eviction_listener_panic_policy`](#method.eviction_listener_panic_policy)
+    /// method.
     ///
     /// [removal-cause]: ../notification/enum.RemovalCause.html
     /// [listener-future]: ../notification/type.ListenerFuture.html
@@ -352,16 +614,213 @@ impl<K, V, C> CacheBuilder<K, V, C> {
         F: Fn(Arc<K>, V, RemovalCause) -> ListenerFuture + Send + Sync + 'static,
     {
         Self {
-            eviction_listener: Some(Box::new(listener)),
+            eviction_listener: Some(AsyncEvictionListener::Single(Box::new(listener))),
+            ..self
+        }
+    }
+
+    /// Sets the sending half of a removal channel as the eviction listener of the
+    /// cache. This replaces the listener set by other `*eviction_listener` methods.
+    ///
+    /// The cache sends the removal notifications to the channel, and you can
+    /// receive them from the [`RemovalStream`][removal-stream] on your own task.
+    /// Create the channel by the [`removal_channel`][removal-channel] function.
+    ///
+    /// See [`removal_channel`][removal-channel] for the backpressure, the
+    /// cancellation semantics and an example.
+    ///
+    /// [removal-stream]: ../notification/struct.RemovalStream.html
+    /// [removal-channel]: ../notification/fn.removal_channel.html
+    pub fn eviction_listener_channel(self, sender: RemovalSender<K, V>) -> Self
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        let sender = Arc::new(sender);
+        let async_listener = move |k, v, c| {
+            let sender = Arc::clone(&sender);
+            async move { sender.send((k, v, c)).await }.boxed()
+        };
+
+        self.async_eviction_listener(async_listener)
+    }
+
+    /// Sets the batch eviction listener closure to the cache. This replaces the
+    /// listener set by other `*eviction_listener` methods.
+    ///
+    /// The closure takes a `Vec` of `(Arc<K>, V, RemovalCause)` tuples. When the
+    /// cache runs its pending maintenance tasks, it calls the closure once with all
+    /// the entries evicted, expired or invalidated by the maintenance (e.g. by
+    /// `invalidate_all`). The entries removed by other cache operations such as
+    /// `invalidate` and `insert` (replacing a value) are passed one at a time.
+    ///
+    /// If you need to `.await` something in the listener, use
+    /// [`async_batch_eviction_listener`](#method.async_batch_eviction_listener)
+    /// method instead.
+    ///
+    /// See [`async_batch_eviction_listener`](#method.async_batch_eviction_listener)
+    /// for the delivery order and panics.
+    pub fn batch_eviction_listener<F>(self, listener: F) -> Self
+    where
+        F: Fn(Vec<(Arc<K>, V, RemovalCause)>) + Send + Sync + 'static,
+    {
+        let async_listener = move |entries| {
+            {
+                listener(entries);
+                std::future::ready(())
+            }
+            .boxed()
+        };
+
+        self.async_batch_eviction_listener(async_listener)
+    }
+
+    /// Sets the asynchronous batch eviction listener closure to the cache. This
+    /// replaces the listener set by other `*eviction_listener` methods.
+    ///
+    /// The closure takes a `Vec` of `(Arc<K>, V, RemovalCause)` tuples and returns
+    /// a [`ListenerFuture`][listener-future]. When the cache runs its pending
+    /// maintenance tasks, it calls the closure once with all the entries evicted,
+    /// expired or invalidated by the maintenance (e.g. by `invalidate_all`). This
+    /// will reduce the per-entry overhead of a listener that writes the entries to
+    /// an external storage. The entries removed by other cache operations such as
+    /// `invalidate` and `insert` (replacing a value) are passed one at a time.
+    ///
+    /// Unlike the single entry listener, the notifications collected by a
+    /// maintenance pass are delivered at the end of the pass, so they may be
+    /// delivered after the notifications of later operations on the same keys.
+    ///
+    /// # Panics
+    ///
+    /// The panics are handled in the same way as the single entry listener. When a
+    /// batch panicked, the panic hook set by
+    /// [`eviction_listener_panic_hook`](#method.eviction_listener_panic_hook)
+    /// method is called with the keys and causes of all the entries in the batch.
+    ///
+    /// [listener-future]: ../notification/type.ListenerFuture.html
+    pub fn async_batch_eviction_listener<F>(self, listener: F) -> Self
+    where
+        F: Fn(Vec<(Arc<K>, V, RemovalCause)>) -> ListenerFuture + Send + Sync + 'static,
+    {
+        Self {
+            eviction_listener: Some(AsyncEvictionListener::Batch(Box::new(listener))),
             ..self
         }
     }
 
+    /// Delivers the notifications to the eviction listener through a bounded
+    /// queue ([`DeliveryMode::Queued`][queued]).
+    ///
+    /// By default ([`DeliveryMode::Immediate`][immediate]), the listener is called
+    /// and its future is awaited by the task performing the cache operation that
+    /// caused the removal. With this method, the notifications are pushed to a
+    /// bounded queue and the listener is called by a dedicated async task, so a slow
+    /// listener will not slow down the cache operations unless the queue gets full.
+    ///
+    /// When the cache is built, the `spawn` closure is called once with the future
+    /// of the delivery task. Spawn it on your async runtime, so that the listener
+    /// futures can use the features of the runtime such as timers and I/O. The task
+    /// ends when the cache is dropped.
+    ///
+    /// Use [`Cache::wait_for_notifications`][wait-for-notifications] to wait until
+    /// the queued notifications are delivered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros", "time" ] }
+    /// use moka::{future::Cache, notification::ListenerFuture};
+    /// // FutureExt trait provides the boxed method.
+    /// use moka::future::FutureExt;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let listener = |k, v: String, _cause| -> ListenerFuture {
+    ///         async move {
+    ///             // The listener future can use the timers of the runtime.
+    ///             tokio::time::sleep(Duration::from_millis(1)).await;
+    ///             println!("Removed {k}: {v}");
+    ///         }
+    ///         .boxed()
+    ///     };
+    ///
+    ///     let cache = Cache::builder()
+    ///         .async_eviction_listener(listener)
+    ///         .queued_delivery(|task| {
+    ///             tokio::spawn(task);
+    ///         })
+    ///         .build();
+    ///
+    ///     cache.insert(1, "one".to_string()).await;
+    ///     cache.invalidate(&1).await;
+    ///     cache.wait_for_notifications().await;
+    /// }
+    /// ```
+    ///
+    /// [immediate]: ../notification/enum.DeliveryMode.html#variant.Immediate
+    /// [queued]: ../notification/enum.DeliveryMode.html#variant.Queued
+    /// [wait-for-notifications]: ./struct.Cache.html#method.wait_for_notifications
+    pub fn queued_delivery(self, spawn: impl Fn(ListenerFuture) + Send + Sync + 'static) -> Self {
+        let mut builder = self;
+        builder.listener_config.delivery_mode = DeliveryMode::Queued;
+        builder.listener_config.delivery_spawner = Some(Arc::new(spawn));
+        builder
+    }
+
+    /// Sets what happens to the eviction listener after it panicked. The default
+    /// is [`ListenerPanicPolicy::Disable`][disable].
+    ///
+    /// See [`ListenerPanicPolicy`][panic-policy] for details.
+    ///
+    /// [disable]: ../notification/enum.ListenerPanicPolicy.html#variant.Disable
+    /// [panic-policy]: ../notification/enum.ListenerPanicPolicy.html
+    pub fn eviction_listener_panic_policy(self, policy: ListenerPanicPolicy) -> Self {
+        let mut builder = self;
+        builder.listener_config.panic_policy = policy;
+        builder
+    }
+
+    /// Sets a hook closure that will be called when the eviction listener panicked.
+    ///
+    /// The closure takes the panic payload, and the keys and the
+    /// [`RemovalCause`][removal-cause]s of the notifications that the listener was
+    /// processing: one entry for a listener set by `eviction_listener`, and all the
+    /// entries of the batch for a listener set by `batch_eviction_listener`. The
+    /// values are not available because they were moved into the listener. If the
+    /// hook itself panics, the panic is ignored.
+    ///
+    /// Setting a hook does not change the
+    /// [panic policy](#method.eviction_listener_panic_policy); set it to
+    /// `ListenerPanicPolicy::Continue` to keep calling the listener after a panic.
+    ///
+    /// [removal-cause]: ../notification/enum.RemovalCause.html
+    pub fn eviction_listener_panic_hook(
+        self,
+        hook: impl Fn(Box<dyn Any + Send>, &[(Arc<K>, RemovalCause)]) + Send + Sync + 'static,
+    ) -> Self {
+        let mut builder = self;
+        builder.listener_config.panic_hook = Some(Arc::new(hook));
+        builder
+    }
+
     /// Sets the time to live of the cache.
     ///
     /// A cached entry will be expired after the specified duration past from
     /// `insert`.
     ///
+    /// Expired entries are removed (and the eviction listener is notified) only
+    /// when the pending tasks run. Unlike the `sync` caches, `future::Cache` has no
+    /// proactive expiration, as it does not start a background thread or task. To
+    /// remove expired entries from an idle cache, drive the pending tasks from your
+    /// own task using [`Cache::next_expiration_time`][next-expiration-time].
+    ///
+    /// [next-expiration-time]: ./struct.Cache.html#method.next_expiration_time
+    ///
     /// # Panics
     ///
     /// `CacheBuilder::build*` methods will panic if the given `duration` is longer
@@ -376,7 +835,8 @@ impl<K, V, C> CacheBuilder<K, V, C> {
     /// Sets the time to idle of the cache.
     ///
     /// A cached entry will be expired after the specified duration past from `get`
-    /// or `insert`.
+    /// or `insert`. See [`time_to_live`](#method.time_to_live) for when the expired
+    /// entries are removed.
     ///
     /// # Panics
     ///
@@ -402,6 +862,43 @@ impl<K, V, C> CacheBuilder<K, V, C> {
         builder
     }
 
+    /// Sets the given [`AsyncExpiry`][async-expiry] to the cache. Use it instead of
+    /// [`expire_after`](#method.expire_after) when the expiration time of an entry
+    /// needs to be calculated asynchronously.
+    ///
+    /// Only one of `Expiry` and `AsyncExpiry` can be set to the cache. Calling this
+    /// method will replace the `Expiry` set by `expire_after`, and vice versa.
+    ///
+    /// [async-expiry]: ../policy/trait.AsyncExpiry.html
+    pub fn async_expire_after(
+        self,
+        expiry: impl AsyncExpiry<K, V> + Send + Sync + 'static,
+    ) -> Self {
+        let mut builder = self;
+        builder.expiration_policy.set_async_expiry(Arc::new(expiry));
+        builder
+    }
+
+    /// Sets the layout of the timer wheel, which is used to expire the entries
+    /// having per-entry expiration times (e.g. set by
+    /// [`expire_after`](#method.expire_after)).
+    ///
+    /// The tick of the timer wheel is the precision of the per-entry expiration. Use
+    /// a shorter tick when you need better precision, or more levels or buckets when
+    /// many entries expire far in the future. The effective tick is available from
+    /// [`Policy::timer_wheel_precision`][policy-precision].
+    ///
+    /// The default layout has a tick of ~1.07 seconds and spans ~6.5 days. See
+    /// [`TimerWheelConfig`][timer-wheel-config] for details.
+    ///
+    /// [policy-precision]: ../struct.Policy.html#method.timer_wheel_precision
+    /// [timer-wheel-config]: ../policy/struct.TimerWheelConfig.html
+    pub fn timer_wheel(self, config: TimerWheelConfig) -> Self {
+        let mut builder = self;
+        builder.expiration_policy.set_timer_wheel(config);
+        builder
+    }
+
     #[cfg(test)]
     pub(crate) fn housekeeper_config(self, conf: HousekeeperConfig) -> Self {
         Self {
@@ -433,6 +930,10 @@ impl<K, V, C> CacheBuilder<K, V, C> {
 #[cfg(test)]
 mod tests {
     use super::CacheBuilder;
+    use crate::{
+        future::Cache,
+        policy::{EvictionPolicy, TimerWheelConfig},
+    };
 
     use std::time::Duration;
 
@@ -465,6 +966,50 @@ mod tests {
         assert_eq!(cache.get(&'a').await, Some("Alice"));
     }
 
+    #[tokio::test]
+    async fn build_cache_with_timer_wheel() {
+        let config = TimerWheelConfig::new(Duration::from_millis(10), &[64, 64, 64, 16]);
+        let cache = CacheBuilder::new(100).timer_wheel(config.clone()).build();
+        assert_eq!(cache.policy().timer_wheel_precision(), config.tick());
+        assert_eq!(config.tick(), Duration::from_nanos(1 << 24));
+
+        cache.insert('a', "Alice").await;
+        assert_eq!(cache.get(&'a').await, Some("Alice"));
+    }
+
+    #[tokio::test]
+    async fn build_cache_with_reported_policy() {
+        let cache: Cache<char, &str> = CacheBuilder::new(100).build();
+        let policy = cache.policy();
+
+        assert_eq!(policy.initial_capacity(), None);
+        assert_eq!(policy.eviction_policy(), &EvictionPolicy::tiny_lfu());
+        assert!(!policy.has_weigher());
+        assert!(!policy.has_expiry());
+        assert!(!policy.has_eviction_listener());
+        assert!(!policy.supports_invalidation_closures());
+        assert_eq!(policy.maintenance_task_timeout(), None);
+
+        let cache = CacheBuilder::new(100)
+            .initial_capacity(10)
+            .eviction_policy(EvictionPolicy::lru())
+            .weigher(|_k, v: &&str| v.len() as u32)
+            .eviction_listener(|_k, _v, _cause| {})
+            .support_invalidation_closures()
+            .build();
+        cache.insert('a', "Alice").await;
+        let policy = cache.policy();
+
+        assert_eq!(policy.initial_capacity(), Some(10));
+        assert_eq!(policy.eviction_policy(), &EvictionPolicy::lru());
+        assert!(policy.has_weigher());
+        assert!(!policy.has_expiry());
+        assert!(policy.has_eviction_listener());
+        assert!(policy.supports_invalidation_closures());
+        assert!(policy.maintenance_task_timeout().is_some());
+        assert_eq!(policy.proactive_expiration(), None);
+    }
+
     #[tokio::test]
     #[should_panic(expected = "time_to_live is longer than 1000 years")]
     async fn build_cache_too_long_ttl() {
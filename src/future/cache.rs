@@ -2,16 +2,24 @@ use equivalent::Equivalent;
 
 use super::{
     base_cache::BaseCache,
-    value_initializer::{InitResult, ValueInitializer},
+    value_initializer::{FailedOp, InitResult, ValueInitializer},
     CacheBuilder, CancelGuard, Iter, OwnedKeyEntrySelector, PredicateId, RefKeyEntrySelector,
     WriteOp,
 };
 use crate::{
-    common::{concurrent::Weigher, time::Clock, HousekeeperConfig},
-    notification::AsyncEvictionListener,
+    common::{
+        builder_utils,
+        concurrent::{EvictionVeto, InsertOptions, Weigher, Writer},
+        time::{self, Clock, Instant},
+        write_behind::WriteBehindConfig,
+        HousekeeperConfig,
+    },
+    notification::{
+        AsyncEvictionListener, Backpressure, EventStream, ListenerConfig, RemovalCause, Subscriber,
+    },
     ops::compute::{self, CompResult},
     policy::{EvictionPolicy, ExpirationPolicy},
-    Entry, Policy, PredicateError,
+    Entry, EntryMetadata, InsertError, Policy, PredicateError, WriteError,
 };
 
 #[cfg(feature = "unstable-debug-counters")]
@@ -24,6 +32,7 @@ use std::{
     hash::{BuildHasher, Hash},
     pin::Pin,
     sync::Arc,
+    time::{Duration, Instant as StdInstant, SystemTime},
 };
 
 #[cfg(test)]
@@ -697,10 +706,23 @@ impl<K, V, S> Cache<K, V, S> {
         self.base.name()
     }
 
+    /// Logs the error from the [`CacheWriter`][crate::CacheWriter] that is not
+    /// returned to the caller, e.g. by `insert` and `invalidate`.
+    pub(crate) fn log_writer_error(&self, _error: &WriteError) {
+        #[cfg(feature = "logging")]
+        {
+            let cn = self
+                .name()
+                .map(|name| format!("[{name}] "))
+                .unwrap_or_default();
+            log::error!("{cn}{_error}");
+        }
+    }
+
     /// Returns a read-only cache policy of this cache.
     ///
-    /// At this time, cache policy cannot be modified after cache creation.
-    /// A future version may support to modify it.
+    /// Some of the policy can be modified after cache creation by methods such as
+    /// `set_time_to_live` and `set_time_to_idle`.
     pub fn policy(&self) -> Policy {
         self.base.policy()
     }
@@ -761,6 +783,25 @@ impl<K, V, S> Cache<K, V, S> {
         self.base.weighted_size()
     }
 
+    /// Returns an approximate total weighted size of the pinned entries in this
+    /// cache. The pinned entries are also counted in the
+    /// [`weighted_size`](#method.weighted_size).
+    ///
+    /// Like `weighted_size`, the value returned is _an estimate_. See
+    /// [`pin`](#method.pin) for more details about pinned entries.
+    pub fn pinned_weighted_size(&self) -> u64 {
+        self.base.pinned_weighted_size()
+    }
+
+    /// Returns the number of times the eviction listener of this cache panicked.
+    ///
+    /// The panics are caught by the cache. What happens to the listener after a
+    /// panic is configured by the `eviction_listener_panic_policy` method of the
+    /// cache builder.
+    pub fn eviction_listener_panic_count(&self) -> u64 {
+        self.base.eviction_listener_panic_count()
+    }
+
     #[cfg(feature = "unstable-debug-counters")]
     #[cfg_attr(docsrs, doc(cfg(feature = "unstable-debug-counters")))]
     pub async fn debug_stats(&self) -> CacheDebugStats {
@@ -787,8 +828,13 @@ where
             None,
             build_hasher,
             None,
+            None,
+            None,
+            None,
+            None,
             EvictionPolicy::default(),
             None,
+            ListenerConfig::default(),
             ExpirationPolicy::default(),
             HousekeeperConfig::default(),
             false,
@@ -819,8 +865,13 @@ where
         initial_capacity: Option<usize>,
         build_hasher: S,
         weigher: Option<Weigher<K, V>>,
+        max_entry_weight: Option<u64>,
+        can_evict: Option<EvictionVeto<K, V>>,
+        writer: Option<Writer<K, V>>,
+        write_behind: Option<WriteBehindConfig<K, V>>,
         eviction_policy: EvictionPolicy,
         eviction_listener: Option<AsyncEvictionListener<K, V>>,
+        listener_config: ListenerConfig<K>,
         expiration_policy: ExpirationPolicy<K, V>,
         housekeeper_config: HousekeeperConfig,
         invalidator_enabled: bool,
@@ -833,8 +884,13 @@ where
                 initial_capacity,
                 build_hasher.clone(),
                 weigher,
+                max_entry_weight,
+                can_evict,
+                writer,
+                write_behind,
                 eviction_policy,
                 eviction_listener,
+                listener_config,
                 expiration_policy,
                 housekeeper_config,
                 invalidator_enabled,
@@ -884,6 +940,100 @@ where
             .map(Entry::into_value)
     }
 
+    /// Returns a _clone_ of the value corresponding to the key, without any side
+    /// effects on the cache.
+    ///
+    /// Unlike the `get` method, this method is not considered a cache read
+    /// operation. So it does not update the historic popularity estimator, the
+    /// access order (LRU) of the entry, or reset the idle timer for the key. It is
+    /// useful for debugging or scanning the cache in background.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// Note that this method is not `async`, as it never waits for the pending
+    /// operations on the cache.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::future::Cache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::builder()
+    ///         .time_to_idle(Duration::from_secs(60))
+    ///         .build();
+    ///     cache.insert("key1", "value1").await;
+    ///
+    ///     // `peek` does not reset the idle timer of "key1".
+    ///     assert_eq!(cache.peek(&"key1"), Some("value1"));
+    ///     assert_eq!(cache.peek(&"key2"), None);
+    /// }
+    /// ```
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.peek_with_hash(key, self.base.hash(key))
+    }
+
+    /// Returns the [`EntryMetadata`] of the entry for the key, such as its last
+    /// accessed and modified times, its expiration time, its weight and its
+    /// estimated access frequency.
+    ///
+    /// Like the `contains_key` method, this method is not considered a cache read
+    /// operation, so it does not update the historic popularity estimator or reset
+    /// the idle timer for the key.
+    ///
+    /// Returns `None` if the cache does not contain a value for the key.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [`EntryMetadata`]: ../struct.EntryMetadata.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::future::Cache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::builder()
+    ///         .time_to_live(Duration::from_secs(60))
+    ///         .build();
+    ///     cache.insert("key1", "value1").await;
+    ///
+    ///     let metadata = cache.peek_metadata(&"key1").await.unwrap();
+    ///     assert!(metadata.last_modified() <= metadata.last_accessed());
+    ///     assert!(metadata.time_to_expiration().unwrap() <= Duration::from_secs(60));
+    ///     assert_eq!(metadata.policy_weight(), 1);
+    ///
+    ///     assert!(cache.peek_metadata(&"key2").await.is_none());
+    /// }
+    /// ```
+    pub async fn peek_metadata<Q>(&self, key: &Q) -> Option<EntryMetadata>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base
+            .peek_metadata_with_hash(key, self.base.hash(key))
+            .await
+    }
+
     /// Takes a key `K` and returns an [`OwnedKeyEntrySelector`] that can be used to
     /// select or insert an entry.
     ///
@@ -1334,19 +1484,339 @@ where
     /// Inserts a key-value pair into the cache.
     ///
     /// If the cache has this key present, the value is updated.
+    ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to write the entry,
+    /// the cache is not modified and the error is only logged (when the `logging`
+    /// feature is enabled). Use [`try_insert`](#method.try_insert) to get the error.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub async fn insert(&self, key: K, value: V) {
         let hash = self.base.hash(&key);
         let key = Arc::new(key);
         self.insert_with_hash(key, hash, value).await;
     }
 
+    /// Inserts a key-value pair into the cache, and returns an error if the entry
+    /// was rejected by the cache.
+    ///
+    /// This method works like [`insert`](#method.insert) but returns
+    /// [`InsertError::EntryTooHeavy`][entry-too-heavy] when the weight of the entry
+    /// exceeds the max entry weight configured by
+    /// [`CacheBuilder::max_entry_weight`][max-entry-weight]. In that case, the
+    /// entry is not stored, and the existing entry for the key (if any) is
+    /// removed.
+    ///
+    /// It also returns [`InsertError::WriterFailed`][writer-failed] when the
+    /// [`CacheWriter`][cache-writer] of the cache failed to write the entry. In that
+    /// case, the cache is not modified.
+    ///
+    /// [entry-too-heavy]: ../enum.InsertError.html#variant.EntryTooHeavy
+    /// [max-entry-weight]: ./struct.CacheBuilder.html#method.max_entry_weight
+    /// [writer-failed]: ../enum.InsertError.html#variant.WriterFailed
+    /// [cache-writer]: ../trait.CacheWriter.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::{future::Cache, InsertError};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::builder()
+    ///         .max_capacity(100)
+    ///         .weigher(|_k, v: &String| v.len() as u32)
+    ///         .max_entry_weight(10)
+    ///         .build();
+    ///
+    ///     assert!(cache.try_insert(1, "short".to_string()).await.is_ok());
+    ///     assert_eq!(
+    ///         cache.try_insert(2, "a little too long".to_string()).await,
+    ///         Err(InsertError::EntryTooHeavy { weight: 17, max_entry_weight: 10 })
+    ///     );
+    ///     assert!(!cache.contains_key(&2));
+    /// }
+    /// ```
+    pub async fn try_insert(&self, key: K, value: V) -> Result<(), InsertError> {
+        let hash = self.base.hash(&key);
+        let key = Arc::new(key);
+        self.do_insert_with_hash(key, hash, value, InsertOptions::default())
+            .await
+    }
+
+    /// Inserts a key-value pair into the cache with the time-to-live for this
+    /// entry.
+    ///
+    /// The entry will expire after `ttl` elapsed from this insert, regardless of
+    /// the [`Expiry`][expiry-trait] set to the cache; the `Expiry` is not called
+    /// for this insert. The cache-wide
+    /// `time_to_live` and `time_to_idle` still apply, so the entry will expire at
+    /// the earliest of them.
+    ///
+    /// If the cache has this key present, the value is updated and the
+    /// time-to-live of the entry is replaced with `ttl`. Note that updating the
+    /// entry by [`insert`](#method.insert) will clear the time-to-live of the
+    /// entry set by this method, unless an `Expiry` is set to the cache.
+    ///
+    /// [expiry-trait]: ../policy/trait.Expiry.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros", "time" ] }
+    /// use moka::future::Cache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::new(100);
+    ///
+    ///     cache
+    ///         .insert_with_ttl("a", "alice", Duration::from_millis(50))
+    ///         .await;
+    ///     cache.insert("b", "bob").await;
+    ///     assert_eq!(cache.get(&"a").await, Some("alice"));
+    ///
+    ///     tokio::time::sleep(Duration::from_millis(100)).await;
+    ///     assert_eq!(cache.get(&"a").await, None);
+    ///     assert_eq!(cache.get(&"b").await, Some("bob"));
+    /// }
+    /// ```
+    pub async fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        let hash = self.base.hash(&key);
+        let key = Arc::new(key);
+        // The rejection has been already notified to the eviction listener.
+        let result = self
+            .insert_with_hash_and_ttl(key, hash, value, Some(ttl))
+            .await;
+        if let Err(InsertError::WriterFailed(e)) = result {
+            self.log_writer_error(&e);
+        }
+    }
+
+    /// Inserts a key-value pair into the cache with the wall-clock time when this
+    /// entry expires.
+    ///
+    /// This is useful when the upstream gives the expiration as an absolute time,
+    /// such as the `Expires` header of HTTP or the `exp` claim of JWT. If
+    /// `expire_at` has already passed, the entry is inserted as expired, so it will
+    /// never be returned and will be removed by a housekeeping task later.
+    ///
+    /// Other than that, this method works in the same way as
+    /// [`insert_with_ttl`](#method.insert_with_ttl).
+    ///
+    /// # Clock adjustments
+    ///
+    /// The cache keeps time with a monotonic clock, which is not affected by the
+    /// adjustments to the system clock. So `expire_at` is converted to the
+    /// time-to-live of the entry (`expire_at` minus `SystemTime::now()`) when this
+    /// method is called, and the adjustments to the system clock made _after_ the
+    /// insert (e.g. by NTP) will not change when the entry expires.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros", "time" ] }
+    /// use moka::future::Cache;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::new(100);
+    ///
+    ///     // e.g. The expiration time of a token given by an authorization server.
+    ///     let expire_at = SystemTime::now() + Duration::from_millis(50);
+    ///     cache.insert_with_expire_at("token", "secret", expire_at).await;
+    ///     assert_eq!(cache.get(&"token").await, Some("secret"));
+    ///
+    ///     tokio::time::sleep(Duration::from_millis(100)).await;
+    ///     assert_eq!(cache.get(&"token").await, None);
+    /// }
+    /// ```
+    pub async fn insert_with_expire_at(&self, key: K, value: V, expire_at: SystemTime) {
+        self.insert_with_ttl(key, value, time::duration_until(expire_at))
+            .await;
+    }
+
+    /// Inserts a key-value pair into the cache and pins the entry.
+    ///
+    /// If the cache has this key present, the value is updated and the entry is
+    /// pinned. See [`pin`](#method.pin) for more details about pinned entries.
+    pub async fn insert_pinned(&self, key: K, value: V) {
+        let hash = self.base.hash(&key);
+        let key = Arc::new(key);
+        let opts = InsertOptions {
+            pin: true,
+            ..Default::default()
+        };
+        // The rejection has been already notified to the eviction listener.
+        let result = self.do_insert_with_hash(key, hash, value, opts).await;
+        if let Err(InsertError::WriterFailed(e)) = result {
+            self.log_writer_error(&e);
+        }
+    }
+
+    /// Pins the cached entry for the key so that it will never be evicted by the
+    /// max capacity of the cache. Returns `true` if the entry exists.
+    ///
+    /// A pinned entry still counts toward the max capacity, so the cache will evict
+    /// the unpinned entries to make room for it. A pinned entry can still be
+    /// removed by [`invalidate`](#method.invalidate) and other invalidation
+    /// methods, or expire by the expiration policies. Updating the value of a
+    /// pinned entry does not unpin it.
+    ///
+    /// If the total weight of the pinned entries exceeds the max capacity, the cache
+    /// evicts all unpinned entries and keeps the pinned ones, so its
+    /// [`weighted_size`](#method.weighted_size) will exceed the max capacity until
+    /// some of the pinned entries are unpinned or removed. Check
+    /// [`pinned_weighted_size`](#method.pinned_weighted_size) to monitor it.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::future::Cache;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::new(2);
+    ///     cache.insert_pinned("config", 0).await;
+    ///     cache.insert("a", 1).await;
+    ///     assert!(cache.pin(&"a").await);
+    ///     assert!(!cache.pin(&"b").await);
+    ///
+    ///     for i in 0..10 {
+    ///         cache.insert("other", i).await;
+    ///         cache.run_pending_tasks().await;
+    ///     }
+    ///
+    ///     assert!(cache.contains_key(&"config"));
+    ///     assert!(cache.contains_key(&"a"));
+    ///     assert_eq!(cache.pinned_weighted_size(), 2);
+    /// }
+    /// ```
+    pub async fn pin<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.set_pinned(key, true).await
+    }
+
+    /// Unpins the cached entry for the key so that it can be evicted again. Returns
+    /// `true` if the entry exists.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub async fn unpin<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.set_pinned(key, false).await
+    }
+
+    /// Sets the expiration of the cached entry for the key to `ttl` from now, without
+    /// changing its value. Returns `true` if the entry exists.
+    ///
+    /// When `ttl` is `None`, the per-entry expiration time of the entry is unset, so
+    /// the entry will only be expired by the cache-wide `time_to_live` and
+    /// `time_to_idle`. Like the per-entry TTL given by `insert_with_ttl`, the
+    /// expiration time set by this method can be overridden by the
+    /// [`Expiry`][crate::Expiry] of the cache on the next read or update of the
+    /// entry.
+    ///
+    /// This method does not trigger a `Replaced` notification to the eviction
+    /// listener as the value is not changed.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros", "time" ] }
+    /// use moka::future::Cache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::builder()
+    ///         .time_to_live(Duration::from_secs(60))
+    ///         .build();
+    ///     cache.insert("a", 1).await;
+    ///
+    ///     // Expire "a" earlier than the cache-wide TTL.
+    ///     assert!(cache.set_expiration(&"a", Some(Duration::from_millis(50))).await);
+    ///     assert!(!cache.set_expiration(&"b", None).await);
+    ///
+    ///     tokio::time::sleep(Duration::from_millis(100)).await;
+    ///     assert_eq!(cache.get(&"a").await, None);
+    /// }
+    /// ```
+    pub async fn set_expiration<Q>(&self, key: &Q, ttl: Option<Duration>) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        let Some((op, ts)) = self.base.set_expiration_with_hash(key, hash, ttl) else {
+            return false;
+        };
+        self.schedule_entry_update_op(op, ts).await;
+        true
+    }
+
+    /// Marks the cached entry for the key as accessed, without cloning its value.
+    /// Returns `true` if the entry exists.
+    ///
+    /// Like the `get` method, this method resets the idle timer for the key, calls
+    /// the `expire_after_read` method of the [`Expiry`][crate::Expiry] if any, and
+    /// updates the historic popularity estimator.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub async fn touch<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.touch_with_hash(key, self.base.hash(key)).await
+    }
+
     /// Discards any cached value for the key.
     ///
     /// If you need to get the value that has been discarded, use the
     /// [`remove`](#method.remove) method instead.
     ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to delete the entry,
+    /// the cached value is not discarded and the error is only logged (when the
+    /// `logging` feature is enabled). Use [`try_invalidate`](#method.try_invalidate)
+    /// to get the error.
+    ///
     /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
     /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub async fn invalidate<Q>(&self, key: &Q)
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -1360,8 +1830,15 @@ where
     /// If you do not need to get the value that has been discarded, use the
     /// [`invalidate`](#method.invalidate) method instead.
     ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to delete the entry,
+    /// the cached value is not discarded, `None` is returned and the error is only
+    /// logged (when the `logging` feature is enabled). Use
+    /// [`try_remove`](#method.try_remove) to get the error.
+    ///
     /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
     /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub async fn remove<Q>(&self, key: &Q) -> Option<V>
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -1370,6 +1847,39 @@ where
         self.invalidate_with_hash(key, hash, true).await
     }
 
+    /// Discards any cached value for the key, and returns an error if the
+    /// [`CacheWriter`][cache-writer] of the cache failed to delete it.
+    ///
+    /// This method works like [`invalidate`](#method.invalidate). If the writer
+    /// fails, the cached value is not discarded and the error is returned.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    pub async fn try_invalidate<Q>(&self, key: &Q) -> Result<(), WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        self.try_invalidate_with_hash(key, hash, false, true)
+            .await
+            .map(|_| ())
+    }
+
+    /// Discards any cached value for the key and returns a _clone_ of the value,
+    /// or returns an error if the [`CacheWriter`][cache-writer] of the cache failed
+    /// to delete it.
+    ///
+    /// This method works like [`remove`](#method.remove). If the writer fails, the
+    /// cached value is not discarded and the error is returned.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    pub async fn try_remove<Q>(&self, key: &Q) -> Result<Option<V>, WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        self.try_invalidate_with_hash(key, hash, true, true).await
+    }
+
     /// Discards all cached values.
     ///
     /// This method returns immediately by just setting the current time as the
@@ -1497,17 +2007,338 @@ where
             hk.run_pending_tasks(Arc::clone(&self.base.inner)).await;
         }
     }
-}
-
-impl<'a, K, V, S> IntoIterator for &'a Cache<K, V, S>
-where
-    K: Hash + Eq + Send + Sync + 'static,
-    V: Clone + Send + Sync + 'static,
-    S: BuildHasher + Clone + Send + Sync + 'static,
-{
-    type Item = (Arc<K>, V);
 
-    type IntoIter = Iter<'a, K, V>;
+    /// Returns the earliest time when a cached entry will expire, or `None` if no
+    /// entry will expire.
+    ///
+    /// The expired entries are removed by [`run_pending_tasks`][run-pending-tasks].
+    /// As this crate does not depend on any async runtime, it does not run the
+    /// pending tasks in background. To get the expired entries removed (and the
+    /// eviction listener notified) promptly, spawn a task that sleeps until the
+    /// returned time and then calls `run_pending_tasks`.
+    ///
+    /// The returned time reflects only the writes that have been applied by the
+    /// pending tasks, and it can be earlier than the actual expiration time, e.g.
+    /// when the entry has been read or updated since then. So the task should also
+    /// wake up periodically to pick up new entries.
+    ///
+    /// [run-pending-tasks]: #method.run_pending_tasks
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros", "time" ] }
+    /// use moka::future::Cache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: Cache<u32, &str> = Cache::builder()
+    ///         .time_to_live(Duration::from_secs(60))
+    ///         .build();
+    ///
+    ///     // Drive the expiration in background. Wake up at least every second
+    ///     // to pick up new entries.
+    ///     let c = cache.clone();
+    ///     tokio::spawn(async move {
+    ///         loop {
+    ///             c.run_pending_tasks().await;
+    ///             let max_wait = tokio::time::Instant::now() + Duration::from_secs(1);
+    ///             let deadline = match c.next_expiration_time().await {
+    ///                 Some(t) => max_wait.min(t.into()),
+    ///                 None => max_wait,
+    ///             };
+    ///             tokio::time::sleep_until(deadline).await;
+    ///         }
+    ///     });
+    ///
+    ///     cache.insert(1, "one").await;
+    /// }
+    /// ```
+    pub async fn next_expiration_time(&self) -> Option<StdInstant> {
+        self.base.next_expiration_time().await
+    }
+
+    /// Sets the `time_to_live` of this cache. `None` disables the expiration by
+    /// the time-to-live.
+    ///
+    /// The new value also applies to the existing entries. They will not be
+    /// returned by the read methods once they are expired by the new value, and
+    /// they will be removed from the cache at the next run of the pending
+    /// maintenance tasks.
+    ///
+    /// Enabling the time-to-live on a cache that was built without it (and without
+    /// [`support_invalidation_closures`][support-invalidation-closures]) takes a
+    /// time proportional to the number of the entries in the cache.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is longer than 1000 years.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros", "time" ] }
+    /// use moka::future::Cache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::new(100);
+    ///     cache.insert(1, "one").await;
+    ///
+    ///     cache.set_time_to_live(Some(Duration::from_millis(100))).await;
+    ///     let ttl = cache.policy().time_to_live();
+    ///     assert_eq!(ttl, Some(Duration::from_millis(100)));
+    ///
+    ///     tokio::time::sleep(Duration::from_millis(150)).await;
+    ///     assert_eq!(cache.get(&1).await, None);
+    /// }
+    /// ```
+    ///
+    /// [support-invalidation-closures]:
+    ///     ./struct.CacheBuilder.html#method.support_invalidation_closures
+    pub async fn set_time_to_live(&self, duration: Option<Duration>) {
+        builder_utils::ensure_expirations_or_panic(duration, None);
+        self.base.set_time_to_live(duration).await;
+    }
+
+    /// Sets the `time_to_idle` of this cache. `None` disables the expiration by
+    /// the time-to-idle.
+    ///
+    /// The new value also applies to the existing entries. They will not be
+    /// returned by the read methods once they are expired by the new value, and
+    /// they will be removed from the cache at the next run of the pending
+    /// maintenance tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is longer than 1000 years.
+    pub fn set_time_to_idle(&self, duration: Option<Duration>) {
+        builder_utils::ensure_expirations_or_panic(None, duration);
+        self.base.set_time_to_idle(duration);
+    }
+
+    /// Switches the [`EvictionPolicy`] of this cache without losing the cached
+    /// entries.
+    ///
+    /// The new policy is sent to the cache as a write operation, so it takes effect
+    /// (and is reported by [`policy`](#method.policy)) once the pending tasks are
+    /// processed, e.g. by calling [`run_pending_tasks`](#method.run_pending_tasks).
+    ///
+    /// Switching to the LRU policy releases the frequency sketch used by TinyLFU.
+    /// Switching back to TinyLFU allocates a new one, so the historical popularity
+    /// of the keys is rebuilt from scratch.
+    pub async fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        let op = WriteOp::SetEvictionPolicy {
+            policy: policy.config,
+        };
+        self.schedule_entry_update_op(op, self.base.current_time())
+            .await;
+    }
+
+    /// Subscribes to the mutations of this cache, and returns an
+    /// [`EventStream`][event-stream] of the [`CacheEvent`][cache-event]s.
+    ///
+    /// The events are sent to a bounded channel with the given `capacity`
+    /// (at least one). When the channel is full, the given
+    /// [`Backpressure`][backpressure] determines whether the new event waits for
+    /// room, or an event is discarded and counted. `Backpressure::Block` makes the
+    /// cache operation wait asynchronously without blocking the thread.
+    ///
+    /// Unlike the eviction listener, the subscriber also receives the inserts,
+    /// updates and loads, so it can be used to replicate the contents of this cache
+    /// to a secondary store. When an existing value is replaced, the `Removed`
+    /// event of the old value is sent before the `Updated` event of the new value.
+    ///
+    /// The events of the same key are sent in the order the mutations were applied,
+    /// even when the key is updated by multiple tasks concurrently. (The first
+    /// subscription makes the cache serialize the mutations of the same key by a
+    /// per-key lock.) Only the mutations made after subscribing are sent, and
+    /// those in progress while subscribing may be sent out of order. Dropping the
+    /// stream unsubscribes it.
+    ///
+    /// [event-stream]: ../notification/struct.EventStream.html
+    /// [cache-event]: ../notification/enum.CacheEvent.html
+    /// [backpressure]: ../notification/enum.Backpressure.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// // futures-util = "0.3"
+    ///
+    /// use futures_util::StreamExt;
+    /// use moka::{
+    ///     future::Cache,
+    ///     notification::{Backpressure, CacheEvent},
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = Cache::new(100);
+    ///     let mut events = cache.subscribe(16, Backpressure::CountDrops);
+    ///
+    ///     cache.insert(1, "one").await;
+    ///     cache.invalidate(&1).await;
+    ///
+    ///     assert!(matches!(events.next().await, Some(CacheEvent::Inserted { .. })));
+    ///     assert!(matches!(events.next().await, Some(CacheEvent::Removed { .. })));
+    /// }
+    /// ```
+    pub fn subscribe(&self, capacity: usize, backpressure: Backpressure) -> EventStream<K, V> {
+        let subscriber = Subscriber::new(capacity, backpressure);
+        let stream = EventStream::new(&subscriber);
+        self.base.subscribe(subscriber);
+        stream
+    }
+
+    /// Waits until the eviction listener has been called for all the notifications
+    /// queued so far.
+    ///
+    /// This method is useful only when the cache was built with
+    /// [`DeliveryMode::Queued`][queued] by the `queued_delivery` method of the
+    /// builder. With the default `DeliveryMode::Immediate`, the listener has already
+    /// been called when a cache operation returns, so this method returns
+    /// immediately.
+    ///
+    /// Note that this method does not run the pending tasks. Call
+    /// [`run_pending_tasks`](#method.run_pending_tasks) first if you want the
+    /// notifications for the evicted or expired entries to be queued.
+    ///
+    /// When called from the eviction listener itself (which runs on the delivery
+    /// task), this method returns immediately instead of waiting for the listener to
+    /// return.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::future::Cache;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let removed = Arc::new(Mutex::new(Vec::new()));
+    ///     let removed2 = Arc::clone(&removed);
+    ///
+    ///     let cache = Cache::builder()
+    ///         .eviction_listener(move |k, v, _cause| removed2.lock().unwrap().push((*k, v)))
+    ///         .queued_delivery(|task| {
+    ///             tokio::spawn(task);
+    ///         })
+    ///         .build();
+    ///
+    ///     cache.insert(1, "one").await;
+    ///     cache.invalidate(&1).await;
+    ///
+    ///     cache.wait_for_notifications().await;
+    ///     assert_eq!(*removed.lock().unwrap(), vec![(1, "one")]);
+    /// }
+    /// ```
+    ///
+    /// [queued]: ../notification/enum.DeliveryMode.html#variant.Queued
+    pub async fn wait_for_notifications(&self) {
+        self.base.wait_for_notifications().await;
+    }
+
+    /// Closes the cache for a shutdown. Invalidates all entries, runs the pending
+    /// tasks until the invalidated entries are removed, flushes the write-behind
+    /// buffer (if any) and waits for the flush, and then waits until the eviction
+    /// listener has been called for all of them.
+    ///
+    /// The eviction listener will be notified with
+    /// [`RemovalCause::InvalidatedAll`][invalidated-all] for the removed entries.
+    /// Note that dropping a cache drops its entries without notifying the listener,
+    /// so call this method before dropping the cache if the listener needs to see
+    /// every entry (e.g. to flush them to a backing store).
+    ///
+    /// This method does not prevent other tasks from using the cache. Entries
+    /// inserted while this method is running may be left in the cache, so stop the
+    /// writers before calling it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Cargo.toml
+    /// //
+    /// // [dependencies]
+    /// // moka = { version = "0.12", features = ["future"] }
+    /// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+    /// use moka::{future::Cache, notification::RemovalCause};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let flushed = Arc::new(Mutex::new(Vec::new()));
+    ///     let flushed2 = Arc::clone(&flushed);
+    ///
+    ///     let cache = Cache::builder()
+    ///         .eviction_listener(move |k, v, cause| {
+    ///             assert_eq!(cause, RemovalCause::InvalidatedAll);
+    ///             flushed2.lock().unwrap().push((*k, v));
+    ///         })
+    ///         .build();
+    ///
+    ///     cache.insert(1, "one").await;
+    ///     cache.insert(2, "two").await;
+    ///
+    ///     cache.close().await;
+    ///     assert_eq!(cache.entry_count(), 0);
+    ///
+    ///     let mut flushed = flushed.lock().unwrap();
+    ///     flushed.sort_unstable();
+    ///     assert_eq!(*flushed, vec![(1, "one"), (2, "two")]);
+    /// }
+    /// ```
+    ///
+    /// [invalidated-all]: ../notification/enum.RemovalCause.html#variant.InvalidatedAll
+    pub async fn close(&self) {
+        self.base.invalidate_all_until_now();
+
+        // `run_pending_tasks` may not remove all the invalidated entries at once
+        // (e.g. when it timed out because of a slow eviction listener), so repeat it
+        // while it makes progress.
+        let mut last_count = u64::MAX;
+        loop {
+            self.run_pending_tasks().await;
+            let count = self.entry_count();
+            if count == 0 || count >= last_count {
+                break;
+            }
+            last_count = count;
+        }
+
+        self.base.flush_write_behind().await;
+        self.wait_for_notifications().await;
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a Cache<K, V, S>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    type Item = (Arc<K>, V);
+
+    type IntoIter = Iter<'a, K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -1603,6 +2434,7 @@ where
         key: Arc<K>,
         hash: u64,
         init: impl FnOnce() -> V,
+        ttl: Option<Duration>,
     ) -> Entry<K, V> {
         match self
             .base
@@ -1612,7 +2444,7 @@ where
             Some(entry) => entry,
             None => {
                 let value = init();
-                self.insert_with_hash(Arc::clone(&key), hash, value.clone())
+                self.load_with_hash_and_ttl(Arc::clone(&key), hash, value.clone(), ttl)
                     .await;
                 Entry::new(Some(key), value, true, false)
             }
@@ -1624,6 +2456,7 @@ where
         key: &Q,
         hash: u64,
         init: impl FnOnce() -> V,
+        ttl: Option<Duration>,
     ) -> Entry<K, V>
     where
         Q: Equivalent<K> + ToOwned<Owned = K> + Hash + ?Sized,
@@ -1637,7 +2470,7 @@ where
             None => {
                 let key = Arc::new(key.to_owned());
                 let value = init();
-                self.insert_with_hash(Arc::clone(&key), hash, value.clone())
+                self.load_with_hash_and_ttl(Arc::clone(&key), hash, value.clone(), ttl)
                     .await;
                 Entry::new(Some(key), value, true, false)
             }
@@ -1808,11 +2641,74 @@ where
     }
 
     pub(crate) async fn insert_with_hash(&self, key: Arc<K>, hash: u64, value: V) {
+        // The rejection has been already notified to the eviction listener. The
+        // error from the writer (if any) is returned only by `try_insert`.
+        let result = self
+            .do_insert_with_hash(key, hash, value, InsertOptions::default())
+            .await;
+        if let Err(InsertError::WriterFailed(e)) = result {
+            self.log_writer_error(&e);
+        }
+    }
+
+    /// Inserts the entry with the given per-entry time-to-live. If `ttl` is
+    /// `None`, this is the same as `try_insert`.
+    pub(crate) async fn insert_with_hash_and_ttl(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<(), InsertError> {
+        let opts = InsertOptions {
+            ttl,
+            ..Default::default()
+        };
+        self.do_insert_with_hash(key, hash, value, opts).await
+    }
+
+    /// Inserts the value loaded by `get_with` or a similar method. The subscribers
+    /// will receive it as a `Loaded` event.
+    pub(crate) async fn load_with_hash_and_ttl(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        value: V,
+        ttl: Option<Duration>,
+    ) {
+        let opts = InsertOptions {
+            loaded: true,
+            ttl,
+            ..Default::default()
+        };
+        // The rejection has been already notified to the eviction listener.
+        let _ = self.do_insert_with_hash(key, hash, value, opts).await;
+    }
+
+    async fn do_insert_with_hash(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        value: V,
+        opts: InsertOptions,
+    ) -> Result<(), InsertError> {
         if self.base.is_map_disabled() {
-            return;
+            return Ok(());
         }
 
-        let (op, ts) = self.base.do_insert_with_hash(key, hash, value).await;
+        let weight = match self.base.check_entry_weight(&key, &value) {
+            Ok(weight) => weight,
+            Err(e) => {
+                self.reject_with_hash(key, hash, value).await;
+                return Err(e);
+            }
+        };
+
+        let (op, ts) = self
+            .base
+            .do_insert_with_hash(key, hash, value, weight, opts)
+            .await
+            .map_err(InsertError::WriterFailed)?;
         let mut cancel_guard = CancelGuard::new(&self.base.interrupted_op_ch_snd, ts);
         cancel_guard.set_op(op.clone());
 
@@ -1841,6 +2737,53 @@ where
         .await
         .expect("Failed to schedule write op for insert");
         cancel_guard.clear();
+        Ok(())
+    }
+
+    async fn set_pinned<Q>(&self, key: &Q, pinned: bool) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        let Some((op, ts)) = self.base.set_pinned_with_hash(key, hash, pinned) else {
+            return false;
+        };
+        self.schedule_entry_update_op(op, ts).await;
+        true
+    }
+
+    /// Schedules a `WriteOp` that updates the policy related states of an existing
+    /// entry (e.g. the pinned flag or the expiration time) or of the cache (e.g. the
+    /// eviction policy).
+    async fn schedule_entry_update_op(&self, op: WriteOp<K, V>, ts: Instant) {
+        let mut cancel_guard = CancelGuard::new(&self.base.interrupted_op_ch_snd, ts);
+        cancel_guard.set_op(op.clone());
+
+        let should_block;
+        #[cfg(not(test))]
+        {
+            should_block = false;
+        }
+        #[cfg(test)]
+        {
+            should_block = self.schedule_write_op_should_block.load(Ordering::Acquire);
+        }
+
+        let hk = self.base.housekeeper.as_ref();
+        let event = self.base.write_op_ch_ready_event();
+
+        BaseCache::<K, V, S>::schedule_write_op(
+            &self.base.inner,
+            &self.base.write_op_ch,
+            event,
+            op,
+            ts,
+            hk,
+            should_block,
+        )
+        .await
+        .expect("Failed to schedule write op for entry update");
+        cancel_guard.clear();
     }
 
     pub(crate) async fn compute_with_hash_and_fun<F, Fut>(
@@ -1859,7 +2802,29 @@ where
             .try_compute(key, hash, self, f, post_init, true)
             .await
         {
-            Ok(result) => result,
+            Ok((result, _)) => result,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    pub(crate) async fn checked_compute_with_hash_and_fun<F, Fut>(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        f: F,
+    ) -> Result<compute::CompResult<K, V>, WriteError>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> Fut,
+        Fut: Future<Output = compute::Op<V>>,
+    {
+        let post_init = ValueInitializer::<K, V, S>::post_init_for_compute_with;
+        match self
+            .value_initializer
+            .try_compute(key, hash, self, f, post_init, true)
+            .await
+        {
+            Ok((_, Some(FailedOp { error: Some(e), .. }))) => Err(e),
+            Ok((result, _)) => Ok(result),
             Err(_) => unreachable!(),
         }
     }
@@ -1879,6 +2844,7 @@ where
         self.value_initializer
             .try_compute(key, hash, self, f, post_init, true)
             .await
+            .map(|(result, _)| result)
     }
 
     pub(crate) async fn try_compute_if_nobody_else_with_hash_and_fun<F, Fut, E>(
@@ -1896,6 +2862,7 @@ where
         self.value_initializer
             .try_compute_if_nobody_else(key, hash, self, f, post_init, true)
             .await
+            .map(|(result, _)| result)
     }
 
     pub(crate) async fn upsert_with_hash_and_fun<F, Fut>(
@@ -1911,37 +2878,172 @@ where
         let post_init = ValueInitializer::<K, V, S>::post_init_for_upsert_with;
         match self
             .value_initializer
-            .try_compute(key, hash, self, f, post_init, false)
+            .try_compute(Arc::clone(&key), hash, self, f, post_init, false)
             .await
         {
-            Ok(CompResult::Inserted(entry) | CompResult::ReplacedWith(entry)) => entry,
+            Ok((CompResult::Inserted(entry) | CompResult::ReplacedWith(entry), None)) => entry,
+            // The value was not stored in the cache.
+            Ok((_, Some(FailedOp { value: Some(v), .. }))) => {
+                Entry::new(Some(key), v, false, false)
+            }
             _ => unreachable!(),
         }
     }
 
-    pub(crate) async fn invalidate_with_hash<Q>(
+    pub(crate) async fn checked_upsert_with_hash_and_fun<F, Fut>(
         &self,
-        key: &Q,
+        key: Arc<K>,
         hash: u64,
-        need_value: bool,
-    ) -> Option<V>
+        f: F,
+    ) -> Result<Entry<K, V>, WriteError>
     where
-        Q: Equivalent<K> + Hash + ?Sized,
+        F: FnOnce(Option<Entry<K, V>>) -> Fut,
+        Fut: Future<Output = V>,
     {
+        let post_init = ValueInitializer::<K, V, S>::post_init_for_upsert_with;
+        match self
+            .value_initializer
+            .try_compute(Arc::clone(&key), hash, self, f, post_init, false)
+            .await
+        {
+            Ok((CompResult::Inserted(entry) | CompResult::ReplacedWith(entry), None)) => Ok(entry),
+            Ok((_, Some(FailedOp { error: Some(e), .. }))) => Err(e),
+            // The value was rejected for its weight.
+            Ok((_, Some(FailedOp { value: Some(v), .. }))) => {
+                Ok(Entry::new(Some(key), v, false, false))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Rejects the value that exceeds the max entry weight. The existing entry (if
+    /// any) is removed so that its stale value will not be returned after this
+    /// insert, and it is notified as replaced by the rejected value. Both
+    /// notifications are made under the same key lock. The old value is not
+    /// deleted through the writer as the rejected value was not written either.
+    async fn reject_with_hash(&self, key: Arc<K>, hash: u64, value: V) {
         use futures_util::FutureExt;
 
         self.base.retry_interrupted_ops().await;
 
-        // Lock the key for removal if blocking removal notification is enabled.
-        let mut kl = None;
-        let mut klg = None;
-        if self.base.is_removal_notifier_enabled() {
-            // To lock the key, we have to get Arc<K> for key (&Q).
-            //
-            // TODO: Enhance this if possible. This is rather hack now because
-            // it cannot prevent race conditions like this:
-            //
-            // 1. We miss the key because it does not exist. So we do not lock
+        let kl = self.base.maybe_key_lock(&key);
+        let klg = if let Some(lock) = &kl {
+            Some(lock.lock().await)
+        } else {
+            None
+        };
+
+        let now = self.base.current_time();
+        // Async Cancellation Safety: The cancel guard saves the op to remove the
+        // old entry (and the notification future) to the interrupted_op_ch channel
+        // if our caller is cancelled, so that we can resume/retry later.
+        let mut cancel_guard = CancelGuard::new(&self.base.interrupted_op_ch_snd, now);
+
+        let mut op = None;
+        if let Some(kv) = self.base.remove_entry(&*key, hash) {
+            let entry_gen = kv.entry.entry_info().incr_entry_gen();
+            let remove_op: WriteOp<K, V> = WriteOp::Remove {
+                kv_entry: kv.clone(),
+                entry_gen,
+            };
+            if self.base.is_removal_notifier_enabled() {
+                let future = self
+                    .base
+                    .notify_replaced(&kv.key, &kv.entry)
+                    .boxed()
+                    .shared();
+                cancel_guard.set_future_and_op(future.clone(), remove_op.clone());
+                future.await;
+                cancel_guard.unset_future();
+            } else {
+                cancel_guard.set_op(remove_op.clone());
+            }
+            op = Some(remove_op);
+        }
+        self.base.notify_rejected(key, value).await;
+
+        // Drop the locks before scheduling write op to avoid a potential dead lock.
+        std::mem::drop(klg);
+        std::mem::drop(kl);
+
+        if let Some(op) = op {
+            let should_block;
+            #[cfg(not(test))]
+            {
+                should_block = false;
+            }
+            #[cfg(test)]
+            {
+                should_block = self.schedule_write_op_should_block.load(Ordering::Acquire);
+            }
+
+            let event = self.base.write_op_ch_ready_event();
+            let hk = self.base.housekeeper.as_ref();
+
+            BaseCache::<K, V, S>::schedule_write_op(
+                &self.base.inner,
+                &self.base.write_op_ch,
+                event,
+                op,
+                now,
+                hk,
+                should_block,
+            )
+            .await
+            .expect("Failed to schedule write op for remove");
+            cancel_guard.clear();
+
+            crossbeam_epoch::pin().flush();
+        }
+    }
+
+    pub(crate) async fn invalidate_with_hash<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        need_value: bool,
+    ) -> Option<V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // The error from the writer (if any) is returned only by `try_invalidate`
+        // and `try_remove`.
+        self.try_invalidate_with_hash(key, hash, need_value, true)
+            .await
+            .unwrap_or_else(|e| {
+                self.log_writer_error(&e);
+                None
+            })
+    }
+
+    /// Removes the entry for the key. If `write_through` is `true`, deletes it
+    /// through the writer (if any) first, and returns an error without removing
+    /// the entry if the writer fails.
+    pub(crate) async fn try_invalidate_with_hash<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        need_value: bool,
+        write_through: bool,
+    ) -> Result<Option<V>, WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        use futures_util::FutureExt;
+
+        self.base.retry_interrupted_ops().await;
+
+        // Lock the key for removal if blocking removal notification or the writer
+        // is enabled.
+        let mut kl = None;
+        let mut klg = None;
+        if self.base.is_removal_notifier_enabled() || self.base.is_writer_enabled() {
+            // To lock the key, we have to get Arc<K> for key (&Q).
+            //
+            // TODO: Enhance this if possible. This is rather hack now because
+            // it cannot prevent race conditions like this:
+            //
+            // 1. We miss the key because it does not exist. So we do not lock
             //    the key.
             // 2. Somebody else (other thread) inserts the key.
             // 3. We remove the entry for the key, but without the key lock!
@@ -1953,10 +3055,16 @@ where
                 } else {
                     None
                 };
+
+                // Delete the entry through the writer before removing it from the
+                // cache, so that the cache will not be modified if the writer fails.
+                if write_through {
+                    self.base.delete_through(&arc_key, RemovalCause::Explicit)?;
+                }
             }
         }
 
-        match self.base.remove_entry(key, hash) {
+        let removed = match self.base.remove_entry(key, hash) {
             None => None,
             Some(kv) => {
                 let now = self.base.current_time();
@@ -2033,7 +3141,8 @@ where
                 crossbeam_epoch::pin().flush();
                 maybe_v
             }
-        }
+        };
+        Ok(removed)
     }
 }
 
@@ -2114,7 +3223,7 @@ mod tests {
             atomic::{AtomicU32, AtomicU8, Ordering},
             Arc,
         },
-        time::{Duration, Instant as StdInstant},
+        time::{Duration, Instant as StdInstant, SystemTime},
         vec,
     };
     use tokio::time::sleep;
@@ -2132,13 +3241,23 @@ mod tests {
         #[allow(deprecated)]
         is_send(cache.get_with_if((), async {}, |_| false));
         is_send(cache.insert((), ()));
+        is_send(cache.insert_pinned((), ()));
+        is_send(cache.try_insert((), ()));
+        is_send(cache.insert_with_ttl((), (), Duration::default()));
+        is_send(cache.insert_with_expire_at((), (), SystemTime::now()));
+        is_send(cache.peek_metadata(&()));
+        is_send(cache.set_expiration(&(), None));
+        is_send(cache.touch(&()));
         is_send(cache.invalidate(&()));
+        is_send(cache.next_expiration_time());
         is_send(cache.optionally_get_with((), async { None }));
         is_send(cache.optionally_get_with_by_ref(&(), async { None }));
+        is_send(cache.pin(&()));
         is_send(cache.remove(&()));
         is_send(cache.run_pending_tasks());
         is_send(cache.try_get_with((), async { Err(()) }));
         is_send(cache.try_get_with_by_ref(&(), async { Err(()) }));
+        is_send(cache.unpin(&()));
 
         // entry fns
         is_send(
@@ -2259,13 +3378,13 @@ mod tests {
 
         // "d" should not be admitted because its frequency is too low.
         cache.insert("d", "david").await; //   count: d -> 0
-        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks().await;
         assert_eq!(cache.get(&"d").await, None); //   d -> 1
         assert!(!cache.contains_key(&"d"));
 
         cache.insert("d", "david").await;
-        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks().await;
         assert!(!cache.contains_key(&"d"));
         assert_eq!(cache.get(&"d").await, None); //   d -> 2
@@ -2403,16 +3522,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn size_aware_eviction() {
-        let weigher = |_k: &&str, v: &(&str, u32)| v.1;
-
-        let alice = ("alice", 10);
-        let bob = ("bob", 15);
-        let bill = ("bill", 20);
-        let cindy = ("cindy", 5);
-        let david = ("david", 15);
-        let dennis = ("dennis", 15);
-
+    async fn switch_eviction_policy() {
         // The following `Vec`s will hold actual and expected notifications.
         let actual = Arc::new(Mutex::new(Vec::new()));
         let mut expected = Vec::new();
@@ -2427,10 +3537,9 @@ mod tests {
             .boxed()
         };
 
-        // Create a cache with the eviction listener.
+        // Create a cache with the TinyLFU policy and the eviction listener.
         let mut cache = Cache::builder()
-            .max_capacity(31)
-            .weigher(weigher)
+            .max_capacity(3)
             .async_eviction_listener(listener)
             .build();
         cache.reconfigure_for_testing().await;
@@ -2438,227 +3547,1436 @@ mod tests {
         // Make the cache exterior immutable.
         let cache = cache;
 
-        cache.insert("a", alice).await;
-        cache.insert("b", bob).await;
-        assert_eq!(cache.get(&"a").await, Some(alice));
-        assert!(cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert_eq!(cache.get(&"b").await, Some(bob));
-        cache.run_pending_tasks().await;
-        // order (LRU -> MRU) and counts: a -> 1, b -> 1
-
-        cache.insert("c", cindy).await;
-        assert_eq!(cache.get(&"c").await, Some(cindy));
-        assert!(cache.contains_key(&"c"));
-        // order and counts: a -> 1, b -> 1, c -> 1
+        cache.insert("a", "alice").await;
+        cache.insert("b", "bob").await;
+        cache.insert("c", "cindy").await;
         cache.run_pending_tasks().await;
-
-        assert!(cache.contains_key(&"a"));
-        assert_eq!(cache.get(&"a").await, Some(alice));
-        assert_eq!(cache.get(&"b").await, Some(bob));
-        assert!(cache.contains_key(&"b"));
+        assert_eq!(cache.get(&"a").await, Some("alice"));
+        assert_eq!(cache.get(&"b").await, Some("bob"));
+        assert_eq!(cache.get(&"c").await, Some("cindy"));
         cache.run_pending_tasks().await;
-        // order and counts: c -> 1, a -> 2, b -> 2
 
-        // To enter "d" (weight: 15), it needs to evict "c" (w: 5) and "a" (w: 10).
-        // "d" must have higher count than 3, which is the aggregated count
-        // of "a" and "c".
-        cache.insert("d", david).await; //   count: d -> 0
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // "d" should not be admitted because its frequency is too low.
+        cache.insert("d", "david").await;
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks().await;
-        assert_eq!(cache.get(&"d").await, None); //   d -> 1
         assert!(!cache.contains_key(&"d"));
 
-        cache.insert("d", david).await;
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // Switch to LRU. The cached entries should be kept.
+        cache.set_eviction_policy(EvictionPolicy::lru()).await;
         cache.run_pending_tasks().await;
-        assert!(!cache.contains_key(&"d"));
-        assert_eq!(cache.get(&"d").await, None); //   d -> 2
+        assert_eq!(cache.policy().eviction_policy(), &EvictionPolicy::lru());
+        assert_eq!(cache.entry_count(), 3);
 
-        cache.insert("d", david).await;
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // "e" should be admitted because the cache uses the LRU strategy now.
+        // "a" is the LRU and should be evicted.
+        cache.insert("e", "emily").await;
+        expected.push((Arc::new("a"), "alice", RemovalCause::Size));
         cache.run_pending_tasks().await;
-        assert_eq!(cache.get(&"d").await, None); //   d -> 3
-        assert!(!cache.contains_key(&"d"));
+        assert!(!cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"e"));
 
-        cache.insert("d", david).await;
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
-        cache.run_pending_tasks().await;
-        assert!(!cache.contains_key(&"d"));
-        assert_eq!(cache.get(&"d").await, None); //   d -> 4
+        verify_notification_vec(&cache, actual, &expected).await;
+    }
 
-        // Finally "d" should be admitted by evicting "c" and "a".
-        cache.insert("d", dennis).await;
-        expected.push((Arc::new("c"), cindy, RemovalCause::Size));
-        expected.push((Arc::new("a"), alice, RemovalCause::Size));
-        cache.run_pending_tasks().await;
-        assert_eq!(cache.get(&"a").await, None);
-        assert_eq!(cache.get(&"b").await, Some(bob));
-        assert_eq!(cache.get(&"c").await, None);
-        assert_eq!(cache.get(&"d").await, Some(dennis));
-        assert!(!cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"c"));
-        assert!(cache.contains_key(&"d"));
+    #[tokio::test]
+    async fn subscribe_to_events() {
+        use crate::notification::{Backpressure, CacheEvent};
+        use futures_util::StreamExt;
 
-        // Update "b" with "bill" (w: 15 -> 20). This should evict "d" (w: 15).
-        cache.insert("b", bill).await;
-        expected.push((Arc::new("b"), bob, RemovalCause::Replaced));
-        expected.push((Arc::new("d"), dennis, RemovalCause::Size));
-        cache.run_pending_tasks().await;
-        assert_eq!(cache.get(&"b").await, Some(bill));
-        assert_eq!(cache.get(&"d").await, None);
-        assert!(cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"d"));
+        // Create a cache with the LRU policy so that the eviction is deterministic.
+        let mut cache = Cache::builder()
+            .max_capacity(2)
+            .eviction_policy(EvictionPolicy::lru())
+            .build();
+        cache.reconfigure_for_testing().await;
 
-        // Re-add "a" (w: 10) and update "b" with "bob" (w: 20 -> 15).
-        cache.insert("a", alice).await;
-        cache.insert("b", bob).await;
-        expected.push((Arc::new("b"), bill, RemovalCause::Replaced));
-        cache.run_pending_tasks().await;
-        assert_eq!(cache.get(&"a").await, Some(alice));
-        assert_eq!(cache.get(&"b").await, Some(bob));
-        assert_eq!(cache.get(&"d").await, None);
-        assert!(cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"d"));
+        // Make the cache exterior immutable.
+        let cache = cache;
 
-        // Verify the sizes.
-        assert_eq!(cache.entry_count(), 2);
-        assert_eq!(cache.weighted_size(), 25);
+        // Receive the events in another task, so that it will be woken up by the
+        // events.
+        let mut events = cache.subscribe(16, Backpressure::Block);
+        let receiver = tokio::spawn(async move {
+            let mut actual = Vec::new();
+            while let Some(event) = events.next().await {
+                actual.push(match event {
+                    CacheEvent::Inserted { key, value } => ("inserted", *key, value, None),
+                    CacheEvent::Updated { key, value } => ("updated", *key, value, None),
+                    CacheEvent::Removed { key, value, cause } => {
+                        ("removed", *key, value, Some(cause))
+                    }
+                    CacheEvent::Loaded { key, value } => ("loaded", *key, value, None),
+                });
+            }
+            actual
+        });
 
-        verify_notification_vec(&cache, actual, &expected).await;
-        assert!(cache.key_locks_map_is_empty());
+        cache.insert("a", "alice").await;
+        cache.insert("a", "alex").await;
+        assert_eq!(cache.get_with("b", async { "bob" }).await, "bob");
+        cache.invalidate(&"a").await;
+        cache.insert("c", "cindy").await;
+        cache.insert("d", "david").await;
+        // "b" is the LRU and should be evicted.
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"b"));
+
+        // The stream should end after the cache is dropped.
+        drop(cache);
+        let actual = receiver.await.unwrap();
+
+        let expected = vec![
+            ("inserted", "a", "alice", None),
+            ("removed", "a", "alice", Some(RemovalCause::Replaced)),
+            ("updated", "a", "alex", None),
+            ("loaded", "b", "bob", None),
+            ("removed", "a", "alex", Some(RemovalCause::Explicit)),
+            ("inserted", "c", "cindy", None),
+            ("inserted", "d", "david", None),
+            ("removed", "b", "bob", Some(RemovalCause::Size)),
+        ];
+        assert_eq!(actual, expected);
     }
 
+    // The default `tokio::test` runtime has only one thread. A blocking send would
+    // prevent the receiver task from running and deadlock.
     #[tokio::test]
-    async fn basic_multi_async_tasks() {
-        let num_tasks = 2;
-        let num_threads = 2;
+    async fn subscribe_with_block_on_current_thread() {
+        use crate::notification::{Backpressure, CacheEvent};
+        use futures_util::StreamExt;
 
         let cache = Cache::new(100);
-        let barrier = Arc::new(Barrier::new(num_tasks + num_threads as usize));
 
-        let tasks = (0..num_tasks)
-            .map(|id| {
-                let cache = cache.clone();
-                let barrier = Arc::clone(&barrier);
+        let mut events = cache.subscribe(1, Backpressure::Block);
+        let receiver = tokio::spawn(async move {
+            let mut keys = Vec::new();
+            while let Some(event) = events.next().await {
+                assert!(matches!(event, CacheEvent::Inserted { .. }));
+                keys.push(**event.key());
+            }
+            keys
+        });
 
-                tokio::spawn(async move {
-                    barrier.wait().await;
+        for i in 0..5 {
+            cache.insert(i, i).await;
+        }
 
-                    cache.insert(10, format!("{id}-100")).await;
-                    cache.get(&10).await;
-                    cache.insert(20, format!("{id}-200")).await;
-                    cache.invalidate(&10).await;
-                })
-            })
-            .collect::<Vec<_>>();
+        drop(cache);
+        assert_eq!(receiver.await.unwrap(), vec![0, 1, 2, 3, 4]);
+    }
 
-        let threads = (0..num_threads)
-            .map(|id| {
-                let cache = cache.clone();
-                let barrier = Arc::clone(&barrier);
-                let rt = tokio::runtime::Handle::current();
+    #[tokio::test]
+    async fn cancel_insert_blocked_on_subscriber() {
+        use crate::notification::Backpressure;
+        use futures_util::{future::poll_immediate, StreamExt};
 
-                std::thread::spawn(move || {
-                    rt.block_on(barrier.wait());
+        let mut cache = Cache::new(100);
+        cache.reconfigure_for_testing().await;
 
-                    rt.block_on(cache.insert(10, format!("{id}-100")));
-                    rt.block_on(cache.get(&10));
-                    rt.block_on(cache.insert(20, format!("{id}-200")));
-                    rt.block_on(cache.invalidate(&10));
-                })
-            })
-            .collect::<Vec<_>>();
+        // Make the cache exterior immutable.
+        let cache = cache;
 
-        let _ = futures_util::future::join_all(tasks).await;
-        threads.into_iter().for_each(|t| t.join().unwrap());
+        let mut events = cache.subscribe(1, Backpressure::Block);
+        // This fills the subscription.
+        cache.insert("a", 1).await;
 
-        assert!(cache.get(&10).await.is_none());
-        assert!(cache.get(&20).await.is_some());
-        assert!(!cache.contains_key(&10));
-        assert!(cache.contains_key(&20));
+        // The insert waits for a room in the subscription, so the futures should
+        // not be completed. Drop (cancel) them after one poll.
+        assert!(poll_immediate(cache.insert("b", 2)).await.is_none());
+        assert!(poll_immediate(cache.insert("a", 3)).await.is_none());
+        assert!(events.next().await.is_some());
+
+        // The interrupted write ops are resumed, so the entries are admitted and
+        // counted.
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"a").await, Some(3));
+        assert_eq!(cache.get(&"b").await, Some(2));
+        assert_eq!(cache.entry_count(), 2);
     }
 
     #[tokio::test]
-    async fn invalidate_all() {
-        // The following `Vec`s will hold actual and expected notifications.
-        let actual = Arc::new(Mutex::new(Vec::new()));
-        let mut expected = Vec::new();
+    async fn eviction_listener_channel() {
+        use crate::notification::{removal_channel, Backpressure};
+        use futures_util::{future::poll_immediate, StreamExt};
 
-        // Create an eviction listener.
-        let a1 = Arc::clone(&actual);
-        let listener = move |k, v, cause| -> ListenerFuture {
-            let a2 = Arc::clone(&a1);
-            async move {
-                a2.lock().await.push((k, v, cause));
-            }
-            .boxed()
-        };
+        // ------------------------------------------------------------
+        // Block: A cancelled operation should not lose the notification.
+        // ------------------------------------------------------------
 
-        // Create a cache with the eviction listener.
+        let (sender, mut removals) = removal_channel(1, Backpressure::Block);
         let mut cache = Cache::builder()
             .max_capacity(100)
-            .async_eviction_listener(listener)
+            .eviction_listener_channel(sender)
             .build();
         cache.reconfigure_for_testing().await;
 
         // Make the cache exterior immutable.
         let cache = cache;
 
-        cache.insert("a", "alice").await;
-        cache.insert("b", "bob").await;
-        cache.insert("c", "cindy").await;
-        assert_eq!(cache.get(&"a").await, Some("alice"));
-        assert_eq!(cache.get(&"b").await, Some("bob"));
-        assert_eq!(cache.get(&"c").await, Some("cindy"));
-        assert!(cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert!(cache.contains_key(&"c"));
-
-        // `cache.run_pending_tasks().await` is no longer needed here before invalidating. The last
-        // modified timestamp of the entries were updated when they were inserted.
-        // https://github.com/moka-rs/moka/issues/155
+        cache.insert("a", 1).await;
+        // This fills the channel.
+        cache.insert("a", 2).await;
 
-        cache.invalidate_all();
-        expected.push((Arc::new("a"), "alice", RemovalCause::Explicit));
-        expected.push((Arc::new("b"), "bob", RemovalCause::Explicit));
-        expected.push((Arc::new("c"), "cindy", RemovalCause::Explicit));
-        cache.run_pending_tasks().await;
+        // The listener waits for a room in the channel, so the fut should not be
+        // completed. Drop (cancel) it after one poll.
+        let fut = cache.invalidate(&"a");
+        assert!(poll_immediate(fut).await.is_none());
+        assert!(!cache.contains_key(&"a"));
+
+        let removal = removals.next().await.unwrap();
+        assert_eq!(
+            (*removal.0, removal.1, removal.2),
+            ("a", 1, RemovalCause::Replaced)
+        );
+
+        // This will resume the interrupted notification.
+        cache.run_pending_tasks().await;
+        let removal = removals.next().await.unwrap();
+        assert_eq!(
+            (*removal.0, removal.1, removal.2),
+            ("a", 2, RemovalCause::Explicit)
+        );
+
+        // The stream should end after the cache is dropped.
+        drop(cache);
+        assert!(removals.next().await.is_none());
+        assert_eq!(removals.dropped_count(), 0);
+
+        // ------------------------------------------------------------
+        // Block: A cancelled maintenance should not lose the notifications.
+        // ------------------------------------------------------------
+
+        let (sender, mut removals) = removal_channel(1, Backpressure::Block);
+        let mut cache = Cache::builder()
+            .max_capacity(2)
+            .eviction_listener_channel(sender)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for key in ["a", "b", "c", "d"] {
+            cache.insert(key, 1).await;
+        }
+
+        // Two of the entries are evicted, and the maintenance waits for a room in
+        // the channel for the second notification. Drop (cancel) it after one poll.
+        assert!(poll_immediate(cache.run_pending_tasks()).await.is_none());
+        let removal = removals.next().await.unwrap();
+        assert_eq!(removal.2, RemovalCause::Rejected);
+
+        // This will resume the interrupted maintenance.
+        cache.run_pending_tasks().await;
+        let removal = removals.next().await.unwrap();
+        assert_eq!(removal.2, RemovalCause::Rejected);
+        assert_eq!(cache.entry_count(), 2);
+
+        drop(cache);
+        assert!(removals.next().await.is_none());
+        assert_eq!(removals.dropped_count(), 0);
+
+        // ------------------------------------------------------------
+        // Block: Dropping the stream should not block the cache.
+        // ------------------------------------------------------------
+
+        let (sender, removals) = removal_channel(1, Backpressure::Block);
+        let cache = Cache::builder()
+            .max_capacity(100)
+            .eviction_listener_channel(sender)
+            .build();
+
+        drop(removals);
+        for i in 0..4 {
+            assert!(poll_immediate(cache.insert("b", i)).await.is_some());
+        }
+        assert!(poll_immediate(cache.invalidate(&"b")).await.is_some());
+
+        // ------------------------------------------------------------
+        // CountDrops: The notifications are discarded when the channel is full.
+        // ------------------------------------------------------------
+
+        let (sender, removals) = removal_channel(2, Backpressure::CountDrops);
+        let cache = Cache::builder()
+            .max_capacity(100)
+            .eviction_listener_channel(sender)
+            .build();
+
+        for i in 0..5 {
+            assert!(poll_immediate(cache.insert("c", i)).await.is_some());
+        }
+        assert_eq!(removals.dropped_count(), 2);
+
+        drop(cache);
+        let actual = removals
+            .map(|(k, v, c)| (*k, v, c))
+            .collect::<Vec<_>>()
+            .await;
+        let expected = vec![
+            ("c", 0, RemovalCause::Replaced),
+            ("c", 1, RemovalCause::Replaced),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn pinned_entries() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .async_eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert_pinned("a", "alice").await;
+        cache.insert("b", "bob").await;
+        assert!(cache.pin(&"b").await);
+        assert!(!cache.pin(&"z").await);
+        cache.insert("c", "cindy").await;
+        cache.run_pending_tasks().await;
+        // a (pinned) -> b (pinned) -> c
+        assert_eq!(cache.entry_count(), 3);
+        assert_eq!(cache.pinned_weighted_size(), 2);
+
+        // "c" should be evicted because "a" and "b" are pinned.
+        cache.insert("d", "david").await;
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"c"));
+        assert!(cache.contains_key(&"d"));
+
+        // The pinned weight exceeds the max capacity. All unpinned entries are
+        // evicted and the pinned entries are kept.
+        cache.insert_pinned("e", "emily").await;
+        cache.insert_pinned("f", "frank").await;
+        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 4);
+        assert_eq!(cache.weighted_size(), 4);
+        assert_eq!(cache.pinned_weighted_size(), 4);
+        assert_eq!(cache.policy().pinned_weighted_size(), 4);
+
+        // Unpinning an entry makes it evictable again.
+        assert!(cache.unpin(&"a").await);
+        expected.push((Arc::new("a"), "alice", RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"a"));
+        assert_eq!(cache.entry_count(), 3);
+        assert_eq!(cache.pinned_weighted_size(), 3);
+
+        // A pinned entry can be invalidated.
+        cache.invalidate(&"b").await;
+        expected.push((Arc::new("b"), "bob", RemovalCause::Explicit));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"b"));
+        assert_eq!(cache.pinned_weighted_size(), 2);
+
+        verify_notification_vec(&cache, actual, &expected).await;
+        assert!(cache.key_locks_map_is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_evict() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a `can_evict` closure that vetoes the eviction of the keys in
+        // `in_use`.
+        let in_use = Arc::new(std::sync::Mutex::new(vec!["a"]));
+        let in_use1 = Arc::clone(&in_use);
+        let can_evict = move |k: &&str, _v: &&str| !in_use1.lock().unwrap().contains(k);
+
+        // Create a cache with the eviction listener and the `can_evict` closure.
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .async_eviction_listener(listener)
+            .can_evict(can_evict)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice").await;
+        cache.insert("b", "bob").await;
+        cache.insert("c", "cindy").await;
+        cache.run_pending_tasks().await;
+        // a -> b -> c
+
+        // "a" is the LRU but in use, so "b" should be evicted instead.
+        cache.insert("d", "david").await;
+        expected.push((Arc::new("b"), "bob", RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert!(cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"b"));
+        assert_eq!(cache.entry_count(), 3);
+
+        // When all entries are in use, nothing can be evicted and the cache
+        // temporarily exceeds its max capacity.
+        in_use.lock().unwrap().extend(["c", "d", "e"]);
+        cache.insert("e", "emily").await;
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 4);
+
+        // Once "c" is no longer in use, it can be evicted.
+        in_use.lock().unwrap().retain(|k| k != &"c");
+        cache.run_pending_tasks().await;
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Size));
+        assert!(!cache.contains_key(&"c"));
+        assert_eq!(cache.entry_count(), 3);
+
+        verify_notification_vec(&cache, actual, &expected).await;
+    }
+
+    #[tokio::test]
+    async fn writer() {
+        use crate::{
+            ops::compute::{CompResult, Op},
+            CacheWriter, InsertError,
+        };
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        type BoxError = Box<dyn std::error::Error + Send + Sync>;
+        type Log = Vec<(&'static str, Option<&'static str>, Option<RemovalCause>)>;
+
+        #[derive(Default)]
+        struct TestWriter {
+            // The writes and deletes the writer has received.
+            log: std::sync::Mutex<Log>,
+            fail: AtomicBool,
+        }
+
+        impl TestWriter {
+            fn check_failure(&self) -> Result<(), BoxError> {
+                if self.fail.load(Ordering::Relaxed) {
+                    Err("storage unavailable".into())
+                } else {
+                    Ok(())
+                }
+            }
+
+            fn take_log(&self) -> Log {
+                std::mem::take(&mut *self.log.lock().unwrap())
+            }
+        }
+
+        impl CacheWriter<&'static str, &'static str> for Arc<TestWriter> {
+            fn write(&self, key: &&'static str, value: &&'static str) -> Result<(), BoxError> {
+                self.check_failure()?;
+                self.log.lock().unwrap().push((key, Some(value), None));
+                Ok(())
+            }
+
+            fn delete(&self, key: &&'static str, cause: RemovalCause) -> Result<(), BoxError> {
+                self.check_failure()?;
+                self.log.lock().unwrap().push((key, None, Some(cause)));
+                Ok(())
+            }
+        }
+
+        let writer = Arc::new(TestWriter::default());
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .writer(Arc::clone(&writer))
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        // Inserts and updates are written through.
+        cache.insert("a", "alice").await;
+        assert_eq!(cache.try_insert("b", "bob").await, Ok(()));
+        cache
+            .entry("b")
+            .and_compute_with(|_| async { Op::Put("bill") })
+            .await;
+        // Loaded values are not written.
+        cache.get_with("c", async { "cindy" }).await;
+        cache.run_pending_tasks().await;
+        assert_eq!(
+            writer.take_log(),
+            vec![
+                ("a", Some("alice"), None),
+                ("b", Some("bob"), None),
+                ("b", Some("bill"), None),
+            ]
+        );
+
+        // Removals of existing entries are deleted through.
+        cache.invalidate(&"a").await;
+        assert_eq!(cache.try_remove(&"c").await, Ok(Some("cindy")));
+        assert_eq!(cache.try_remove(&"z").await, Ok(None));
+        assert_eq!(
+            writer.take_log(),
+            vec![
+                ("a", None, Some(RemovalCause::Explicit)),
+                ("c", None, Some(RemovalCause::Explicit)),
+            ]
+        );
+
+        // When the writer fails, the cache is not modified.
+        writer.fail.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            cache.try_insert("b", "bob").await,
+            Err(InsertError::WriterFailed(_))
+        ));
+        assert!(cache.try_insert("d", "david").await.is_err());
+        assert!(cache.try_invalidate(&"b").await.is_err());
+        assert!(cache.try_remove(&"b").await.is_err());
+        let result = cache
+            .entry("b")
+            .and_compute_with(|_| async { Op::Put("bob") })
+            .await;
+        assert!(matches!(result, CompResult::Unchanged(_)));
+        let result = cache
+            .entry("b")
+            .and_compute_with(|_| async { Op::Remove })
+            .await;
+        assert!(matches!(result, CompResult::Unchanged(_)));
+        let result = cache
+            .entry("d")
+            .and_compute_with(|_| async { Op::Put("david") })
+            .await;
+        assert!(matches!(result, CompResult::StillNone(_)));
+        // The upsert returns the value that was not stored.
+        let entry = cache.entry("b").and_upsert_with(|_| async { "bob" }).await;
+        assert_eq!(entry.into_value(), "bob");
+        // The checked methods return the error from the writer.
+        let result = cache
+            .entry("b")
+            .and_compute_with_checked(|_| async { Op::Put("bob") })
+            .await;
+        assert!(result.is_err());
+        let result = cache
+            .entry_by_ref(&"b")
+            .and_compute_with_checked(|_| async { Op::Remove })
+            .await;
+        assert!(result.is_err());
+        let result = cache
+            .entry("d")
+            .and_upsert_with_checked(|_| async { "david" })
+            .await;
+        assert!(result.is_err());
+        let result = cache
+            .entry_by_ref(&"d")
+            .and_upsert_with_checked(|_| async { "david" })
+            .await;
+        assert!(result.is_err());
+        // A `Nop` does not call the writer.
+        let result = cache
+            .entry("b")
+            .and_compute_with_checked(|_| async { Op::Nop })
+            .await;
+        assert!(matches!(result, Ok(CompResult::Unchanged(_))));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"b").await, Some("bill"));
+        assert!(!cache.contains_key(&"d"));
+        assert!(writer.take_log().is_empty());
+
+        // The housekeeping tasks do not call the writer.
+        writer.fail.store(false, Ordering::Relaxed);
+        cache.invalidate_all();
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 0);
+        assert!(writer.take_log().is_empty());
+    }
+
+    #[tokio::test]
+    async fn write_behind() {
+        use std::sync::Mutex;
+
+        // The flushed entries and the removal notifications in the order they
+        // happened.
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        // Create a flush closure and an eviction listener recording to the log.
+        let log1 = Arc::clone(&log);
+        let flush = move |mut entries: Vec<(Arc<&'static str>, u32)>| {
+            entries.sort_unstable();
+            let mut log = log1.lock().unwrap();
+            log.extend(entries.into_iter().map(|(k, v)| ("flushed", *k, v)));
+        };
+        let log2 = Arc::clone(&log);
+        let listener = move |k: Arc<&'static str>, v, _cause| {
+            log2.lock().unwrap().push(("removed", *k, v));
+        };
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the write-behind buffer and the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .write_behind(3, Duration::from_secs(1), flush)
+            .eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let take_log = || std::mem::take(&mut *log.lock().unwrap());
+
+        // Repeated writes to the same key are coalesced.
+        cache.insert("a", 1).await;
+        cache.insert("a", 2).await;
+        cache.insert("b", 1).await;
+        // Loaded values are not flushed.
+        cache.get_with("c", async { 1 }).await;
+        cache.run_pending_tasks().await;
+        // The replaced value is not flushed.
+        assert_eq!(take_log(), vec![("removed", "a", 1)]);
+
+        // The buffer is handed to the flush thread when it reaches the max batch
+        // size.
+        cache.insert("d", 1).await;
+        cache.base.wait_for_write_behind_flushes().await;
+        assert_eq!(
+            take_log(),
+            vec![
+                ("flushed", "a", 2),
+                ("flushed", "b", 1),
+                ("flushed", "d", 1)
+            ]
+        );
+
+        // The buffer is flushed when the oldest entry has waited for the max delay.
+        cache.insert("e", 1).await;
+        mock.increment(Duration::from_millis(500));
+        cache.run_pending_tasks().await;
+        cache.base.wait_for_write_behind_flushes().await;
+        assert!(take_log().is_empty());
+        mock.increment(Duration::from_millis(500));
+        cache.run_pending_tasks().await;
+        cache.base.wait_for_write_behind_flushes().await;
+        assert_eq!(take_log(), vec![("flushed", "e", 1)]);
+
+        // The pending write of an invalidated entry is discarded, so the deleted
+        // value will not be written back.
+        cache.insert("f", 1).await;
+        cache.invalidate(&"f").await;
+        assert_eq!(take_log(), vec![("removed", "f", 1)]);
+
+        // The remaining dirty entries are flushed by `close`.
+        cache.insert("g", 1).await;
+        mock.increment(Duration::from_millis(100));
+        cache.close().await;
+        let log = take_log();
+        // "g" is flushed right before its removal is notified, and the other
+        // entries are not flushed again.
+        let pos = log.iter().position(|e| e == &("removed", "g", 1)).unwrap();
+        assert_eq!(log[pos - 1], ("flushed", "g", 1));
+        assert_eq!(log.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn max_entry_weight() {
+        use crate::InsertError;
+
+        let weigher = |_k: &&str, v: &(&str, u32)| v.1;
+
+        let alice = ("alice", 10);
+        let bob = ("bob", 15);
+        let bill = ("bill", 20);
+        let cindy = ("cindy", 5);
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(31)
+            .weigher(weigher)
+            .max_entry_weight(15)
+            .async_eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        assert_eq!(cache.try_insert("a", alice).await, Ok(()));
+        cache.insert("b", bob).await;
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"a").await, Some(alice));
+        assert_eq!(cache.get(&"b").await, Some(bob));
+        assert_eq!(cache.weighted_size(), 25);
+
+        // "c" is heavier than the max entry weight, so it is rejected.
+        assert_eq!(
+            cache.try_insert("c", bill).await,
+            Err(InsertError::EntryTooHeavy {
+                weight: 20,
+                max_entry_weight: 15
+            })
+        );
+        expected.push((Arc::new("c"), bill, RemovalCause::Rejected));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"c"));
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.weighted_size(), 25);
+
+        // Updating "b" with a heavier value is rejected, and the stale value of "b"
+        // is removed as replaced.
+        cache.insert("b", bill).await;
+        expected.push((Arc::new("b"), bob, RemovalCause::Replaced));
+        expected.push((Arc::new("b"), bill, RemovalCause::Rejected));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"b"));
+        assert_eq!(cache.entry_count(), 1);
+        assert_eq!(cache.weighted_size(), 10);
+
+        // A light entry can be inserted as usual.
+        assert_eq!(cache.try_insert("c", cindy).await, Ok(()));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"c").await, Some(cindy));
+        assert_eq!(cache.weighted_size(), 15);
+
+        // Computing a heavier value for "a" is rejected too, and "a" is reported as
+        // removed.
+        let result = cache
+            .entry("a")
+            .and_compute_with(|_| async { compute::Op::Put(bill) })
+            .await;
+        assert!(matches!(result, compute::CompResult::Removed(e) if e.value() == &alice));
+        expected.push((Arc::new("a"), alice, RemovalCause::Replaced));
+        expected.push((Arc::new("a"), bill, RemovalCause::Rejected));
+
+        // Upserting a heavier value returns the value that was not stored.
+        let entry = cache.entry("d").and_upsert_with(|_| async { bill }).await;
+        assert!(!entry.is_fresh());
+        assert_eq!(entry.into_value(), bill);
+        expected.push((Arc::new("d"), bill, RemovalCause::Rejected));
+
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.weighted_size(), 5);
+
+        verify_notification_vec(&cache, actual, &expected).await;
+        assert!(cache.key_locks_map_is_empty());
+    }
+
+    #[tokio::test]
+    async fn size_aware_eviction() {
+        let weigher = |_k: &&str, v: &(&str, u32)| v.1;
+
+        let alice = ("alice", 10);
+        let bob = ("bob", 15);
+        let bill = ("bill", 20);
+        let cindy = ("cindy", 5);
+        let david = ("david", 15);
+        let dennis = ("dennis", 15);
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(31)
+            .weigher(weigher)
+            .async_eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", alice).await;
+        cache.insert("b", bob).await;
+        assert_eq!(cache.get(&"a").await, Some(alice));
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert_eq!(cache.get(&"b").await, Some(bob));
+        cache.run_pending_tasks().await;
+        // order (LRU -> MRU) and counts: a -> 1, b -> 1
+
+        cache.insert("c", cindy).await;
+        assert_eq!(cache.get(&"c").await, Some(cindy));
+        assert!(cache.contains_key(&"c"));
+        // order and counts: a -> 1, b -> 1, c -> 1
+        cache.run_pending_tasks().await;
+
+        assert!(cache.contains_key(&"a"));
+        assert_eq!(cache.get(&"a").await, Some(alice));
+        assert_eq!(cache.get(&"b").await, Some(bob));
+        assert!(cache.contains_key(&"b"));
+        cache.run_pending_tasks().await;
+        // order and counts: c -> 1, a -> 2, b -> 2
+
+        // To enter "d" (weight: 15), it needs to evict "c" (w: 5) and "a" (w: 10).
+        // "d" must have higher count than 3, which is the aggregated count
+        // of "a" and "c".
+        cache.insert("d", david).await; //   count: d -> 0
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"d").await, None); //   d -> 1
+        assert!(!cache.contains_key(&"d"));
+
+        cache.insert("d", david).await;
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.get(&"d").await, None); //   d -> 2
+
+        cache.insert("d", david).await;
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"d").await, None); //   d -> 3
+        assert!(!cache.contains_key(&"d"));
+
+        cache.insert("d", david).await;
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.get(&"d").await, None); //   d -> 4
+
+        // Finally "d" should be admitted by evicting "c" and "a".
+        cache.insert("d", dennis).await;
+        expected.push((Arc::new("c"), cindy, RemovalCause::Size));
+        expected.push((Arc::new("a"), alice, RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"a").await, None);
+        assert_eq!(cache.get(&"b").await, Some(bob));
+        assert_eq!(cache.get(&"c").await, None);
+        assert_eq!(cache.get(&"d").await, Some(dennis));
+        assert!(!cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"c"));
+        assert!(cache.contains_key(&"d"));
+
+        // Update "b" with "bill" (w: 15 -> 20). This should evict "d" (w: 15).
+        cache.insert("b", bill).await;
+        expected.push((Arc::new("b"), bob, RemovalCause::Replaced));
+        expected.push((Arc::new("d"), dennis, RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"b").await, Some(bill));
+        assert_eq!(cache.get(&"d").await, None);
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"d"));
+
+        // Re-add "a" (w: 10) and update "b" with "bob" (w: 20 -> 15).
+        cache.insert("a", alice).await;
+        cache.insert("b", bob).await;
+        expected.push((Arc::new("b"), bill, RemovalCause::Replaced));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"a").await, Some(alice));
+        assert_eq!(cache.get(&"b").await, Some(bob));
+        assert_eq!(cache.get(&"d").await, None);
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"d"));
+
+        // Verify the sizes.
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.weighted_size(), 25);
+
+        verify_notification_vec(&cache, actual, &expected).await;
+        assert!(cache.key_locks_map_is_empty());
+    }
+
+    #[tokio::test]
+    async fn u64_weigher() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+
+        // The weights of the values are in GiB, which exceed `u32::MAX` bytes.
+        let weigher = |_k: &&str, v: &(&str, u64)| v.1 * GIB;
+
+        let alice = ("alice", 5);
+        let bob = ("bob", 6);
+        let cindy = ("cindy", 3);
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a cache with the eviction listener. Do not call
+        // `reconfigure_for_testing` here, as it would size the frequency sketch by
+        // the max capacity (12 GiB) rather than by the number of entries.
+        let cache = Cache::builder()
+            .max_capacity(12 * GIB)
+            .eviction_policy(EvictionPolicy::lru())
+            .weigher_u64(weigher)
+            .async_eviction_listener(listener)
+            .build();
+
+        cache.insert("a", alice).await;
+        cache.insert("b", bob).await;
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.weighted_size(), 11 * GIB);
+
+        // "a" should be evicted to make room for "c".
+        cache.insert("c", cindy).await;
+        expected.push((Arc::new("a"), alice, RemovalCause::Size));
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(cache.contains_key(&"c"));
+        assert_eq!(cache.weighted_size(), 9 * GIB);
+
+        verify_notification_vec(&cache, actual, &expected).await;
+    }
+
+    #[tokio::test]
+    async fn basic_multi_async_tasks() {
+        let num_tasks = 2;
+        let num_threads = 2;
+
+        let cache = Cache::new(100);
+        let barrier = Arc::new(Barrier::new(num_tasks + num_threads as usize));
+
+        let tasks = (0..num_tasks)
+            .map(|id| {
+                let cache = cache.clone();
+                let barrier = Arc::clone(&barrier);
+
+                tokio::spawn(async move {
+                    barrier.wait().await;
+
+                    cache.insert(10, format!("{id}-100")).await;
+                    cache.get(&10).await;
+                    cache.insert(20, format!("{id}-200")).await;
+                    cache.invalidate(&10).await;
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let threads = (0..num_threads)
+            .map(|id| {
+                let cache = cache.clone();
+                let barrier = Arc::clone(&barrier);
+                let rt = tokio::runtime::Handle::current();
+
+                std::thread::spawn(move || {
+                    rt.block_on(barrier.wait());
+
+                    rt.block_on(cache.insert(10, format!("{id}-100")));
+                    rt.block_on(cache.get(&10));
+                    rt.block_on(cache.insert(20, format!("{id}-200")));
+                    rt.block_on(cache.invalidate(&10));
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let _ = futures_util::future::join_all(tasks).await;
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        assert!(cache.get(&10).await.is_none());
+        assert!(cache.get(&20).await.is_some());
+        assert!(!cache.contains_key(&10));
+        assert!(cache.contains_key(&20));
+    }
+
+    #[tokio::test]
+    async fn invalidate_all() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .async_eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice").await;
+        cache.insert("b", "bob").await;
+        cache.insert("c", "cindy").await;
+        assert_eq!(cache.get(&"a").await, Some("alice"));
+        assert_eq!(cache.get(&"b").await, Some("bob"));
+        assert_eq!(cache.get(&"c").await, Some("cindy"));
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(cache.contains_key(&"c"));
+
+        // `cache.run_pending_tasks().await` is no longer needed here before invalidating. The last
+        // modified timestamp of the entries were updated when they were inserted.
+        // https://github.com/moka-rs/moka/issues/155
+
+        cache.invalidate_all();
+        expected.push((Arc::new("a"), "alice", RemovalCause::InvalidatedAll));
+        expected.push((Arc::new("b"), "bob", RemovalCause::InvalidatedAll));
+        expected.push((Arc::new("c"), "cindy", RemovalCause::InvalidatedAll));
+        cache.run_pending_tasks().await;
 
         cache.insert("d", "david").await;
         cache.run_pending_tasks().await;
 
-        assert!(cache.get(&"a").await.is_none());
-        assert!(cache.get(&"b").await.is_none());
-        assert!(cache.get(&"c").await.is_none());
-        assert_eq!(cache.get(&"d").await, Some("david"));
-        assert!(!cache.contains_key(&"a"));
-        assert!(!cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"c"));
-        assert!(cache.contains_key(&"d"));
+        assert!(cache.get(&"a").await.is_none());
+        assert!(cache.get(&"b").await.is_none());
+        assert!(cache.get(&"c").await.is_none());
+        assert_eq!(cache.get(&"d").await, Some("david"));
+        assert!(!cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"c"));
+        assert!(cache.contains_key(&"d"));
+
+        verify_notification_vec(&cache, actual, &expected).await;
+    }
+
+    // This test is for https://github.com/moka-rs/moka/issues/155
+    #[tokio::test]
+    async fn invalidate_all_without_running_pending_tasks() {
+        let cache = Cache::new(1024);
+
+        assert_eq!(cache.get(&0).await, None);
+        cache.insert(0, 1).await;
+        assert_eq!(cache.get(&0).await, Some(1));
+
+        cache.invalidate_all();
+        assert_eq!(cache.get(&0).await, None);
+    }
+
+    #[tokio::test]
+    async fn invalidate_entries_if() -> Result<(), Box<dyn std::error::Error>> {
+        use std::collections::HashSet;
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .support_invalidation_closures()
+            .async_eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert(0, "alice").await;
+        cache.insert(1, "bob").await;
+        cache.insert(2, "alex").await;
+        cache.run_pending_tasks().await;
+
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        cache.run_pending_tasks().await;
+
+        assert_eq!(cache.get(&0).await, Some("alice"));
+        assert_eq!(cache.get(&1).await, Some("bob"));
+        assert_eq!(cache.get(&2).await, Some("alex"));
+        assert!(cache.contains_key(&0));
+        assert!(cache.contains_key(&1));
+        assert!(cache.contains_key(&2));
+
+        let by_predicate = RemovalCause::InvalidatedByPredicate;
+
+        let names = ["alice", "alex"].iter().cloned().collect::<HashSet<_>>();
+        let id = cache.invalidate_entries_if(move |_k, &v| names.contains(v))?;
+        assert_eq!(cache.invalidation_predicate_count(), 1);
+        expected.push((Arc::new(0), "alice", by_predicate(id)));
+        expected.push((Arc::new(2), "alex", by_predicate(id)));
+
+        mock.increment(Duration::from_secs(5)); // 10 secs from the start.
+
+        cache.insert(3, "alice").await;
+
+        // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
+        cache.run_pending_tasks().await; // To submit the invalidation task.
+        std::thread::sleep(Duration::from_millis(200));
+        cache.run_pending_tasks().await; // To process the task result.
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert!(cache.get(&0).await.is_none());
+        assert!(cache.get(&2).await.is_none());
+        assert_eq!(cache.get(&1).await, Some("bob"));
+        // This should survive as it was inserted after calling invalidate_entries_if.
+        assert_eq!(cache.get(&3).await, Some("alice"));
+
+        assert!(!cache.contains_key(&0));
+        assert!(cache.contains_key(&1));
+        assert!(!cache.contains_key(&2));
+        assert!(cache.contains_key(&3));
+
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.invalidation_predicate_count(), 0);
+
+        mock.increment(Duration::from_secs(5)); // 15 secs from the start.
+
+        let alice_id = cache.invalidate_entries_if(|_k, &v| v == "alice")?;
+        let bob_id = cache.invalidate_entries_if(|_k, &v| v == "bob")?;
+        assert_eq!(cache.invalidation_predicate_count(), 2);
+        // key 1 was inserted before key 3.
+        expected.push((Arc::new(1), "bob", by_predicate(bob_id)));
+        expected.push((Arc::new(3), "alice", by_predicate(alice_id)));
+
+        // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
+        cache.run_pending_tasks().await; // To submit the invalidation task.
+        std::thread::sleep(Duration::from_millis(200));
+        cache.run_pending_tasks().await; // To process the task result.
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert!(cache.get(&1).await.is_none());
+        assert!(cache.get(&3).await.is_none());
+
+        assert!(!cache.contains_key(&1));
+        assert!(!cache.contains_key(&3));
+
+        assert_eq!(cache.entry_count(), 0);
+        assert_eq!(cache.invalidation_predicate_count(), 0);
+
+        verify_notification_vec(&cache, actual, &expected).await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn insert_with_ttl() {
+        use crate::ops::compute::Op;
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(30))
+            .async_eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache
+            .insert_with_ttl("a", "alice", Duration::from_secs(5))
+            .await;
+        cache.insert("b", "bob").await;
+        cache
+            .entry("c")
+            .or_insert_with_ttl("cindy", Duration::from_secs(10))
+            .await;
+        cache
+            .entry("d")
+            .and_compute_with(|_| async { Op::PutWithTtl("david", Duration::from_secs(15)) })
+            .await;
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 4);
+
+        mock.increment(Duration::from_secs(6)); // 6 secs from the start.
+        assert_eq!(cache.get(&"a").await, None);
+        expected.push((Arc::new("a"), "alice", RemovalCause::Expired));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 3);
+
+        // Replace the TTL of "c" with a new one. ("c" will expire at 26 secs)
+        cache
+            .insert_with_ttl("c", "carl", Duration::from_secs(20))
+            .await;
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Replaced));
+        // Updating "d" without a TTL clears its TTL, so only the cache-wide TTL
+        // applies to it. ("d" will expire at 36 secs)
+        cache.insert("d", "dennis").await;
+        expected.push((Arc::new("d"), "david", RemovalCause::Replaced));
+        cache.run_pending_tasks().await;
+
+        mock.increment(Duration::from_secs(10)); // 16 secs.
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"b").await, Some("bob"));
+        assert_eq!(cache.get(&"c").await, Some("carl"));
+        assert_eq!(cache.get(&"d").await, Some("dennis"));
+        assert_eq!(cache.entry_count(), 3);
+
+        mock.increment(Duration::from_secs(15)); // 31 secs.
+        assert_eq!(cache.get(&"b").await, None);
+        assert_eq!(cache.get(&"c").await, None);
+        assert_eq!(cache.get(&"d").await, Some("dennis"));
+        expected.push((Arc::new("c"), "carl", RemovalCause::Expired));
+        expected.push((Arc::new("b"), "bob", RemovalCause::Expired));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 1);
 
         verify_notification_vec(&cache, actual, &expected).await;
     }
 
-    // This test is for https://github.com/moka-rs/moka/issues/155
     #[tokio::test]
-    async fn invalidate_all_without_running_pending_tasks() {
-        let cache = Cache::new(1024);
+    async fn peek() {
+        let (clock, mock) = Clock::mock();
 
-        assert_eq!(cache.get(&0).await, None);
-        cache.insert(0, 1).await;
-        assert_eq!(cache.get(&0).await, Some(1));
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_idle(Duration::from_secs(10))
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
 
-        cache.invalidate_all();
-        assert_eq!(cache.get(&0).await, None);
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice").await;
+        cache.insert("b", "bob").await;
+        cache.run_pending_tasks().await;
+        let md = cache.peek_metadata(&"a").await.expect("Entry not found");
+        let freq = md.frequency();
+
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        assert_eq!(cache.peek(&"a"), Some("alice"));
+        assert_eq!(cache.peek(&"b"), Some("bob"));
+        assert_eq!(cache.peek(&"c"), None);
+        assert_eq!(cache.get(&"b").await, Some("bob"));
+        cache.run_pending_tasks().await;
+
+        // `peek` did not update the frequency of "a".
+        let md = cache.peek_metadata(&"a").await.expect("Entry not found");
+        assert_eq!(md.frequency(), freq);
+
+        // `peek` did not reset the idle timer of "a", while `get` did for "b".
+        mock.increment(Duration::from_secs(5)); // 10 secs.
+        assert_eq!(cache.peek(&"a"), None);
+        assert_eq!(cache.peek(&"b"), Some("bob"));
     }
 
     #[tokio::test]
-    async fn invalidate_entries_if() -> Result<(), Box<dyn std::error::Error>> {
-        use std::collections::HashSet;
+    async fn peek_metadata() {
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .weigher(|_k, v: &&str| v.len() as u32)
+            .time_to_idle(Duration::from_secs(10))
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice").await;
+        cache.run_pending_tasks().await;
+
+        let md = cache.peek_metadata(&"a").await.expect("Entry not found");
+        let start = md.last_modified();
+        assert_eq!(md.last_accessed(), start);
+        assert_eq!(md.expiration_time(), Some(start + Duration::from_secs(10)));
+        assert_eq!(md.time_to_expiration(), Some(Duration::from_secs(10)));
+        assert_eq!(md.policy_weight(), 5);
+        assert!(md.is_admitted());
+        assert!(cache.peek_metadata(&"b").await.is_none());
+
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        for _ in 0..3 {
+            assert_eq!(cache.get(&"a").await, Some("alice"));
+        }
+        cache.run_pending_tasks().await;
+
+        let md = cache.peek_metadata(&"a").await.expect("Entry not found");
+        let freq = md.frequency();
+        assert!(freq > 0);
+        assert_eq!(md.last_accessed(), start + Duration::from_secs(5));
+
+        // Peeking the metadata does not count as a read. It does neither reset the
+        // idle timer nor update the frequency.
+        mock.increment(Duration::from_secs(9)); // 14 secs.
+        let md = cache.peek_metadata(&"a").await.expect("Entry not found");
+        assert_eq!(md.time_to_expiration(), Some(Duration::from_secs(1)));
+        cache.run_pending_tasks().await;
+        let md = cache.peek_metadata(&"a").await.expect("Entry not found");
+        assert_eq!(md.frequency(), freq);
+
+        mock.increment(Duration::from_secs(1)); // 15 secs.
+        assert!(cache.peek_metadata(&"a").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn insert_with_expire_at() {
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder().max_capacity(100).clock(clock).build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let now = SystemTime::now();
+        cache
+            .insert_with_expire_at("a", "alice", now + Duration::from_secs(5))
+            .await;
+        // "b" has already expired.
+        cache
+            .insert_with_expire_at("b", "bob", now - Duration::from_secs(1))
+            .await;
+        assert_eq!(cache.get(&"a").await, Some("alice"));
+        assert_eq!(cache.get(&"b").await, None);
+
+        mock.increment(Duration::from_secs(4)); // 4 secs from the start.
+        assert_eq!(cache.get(&"a").await, Some("alice"));
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 1);
+
+        mock.increment(Duration::from_secs(2)); // 6 secs.
+        assert_eq!(cache.get(&"a").await, None);
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn next_expiration_time() {
+        async fn exp_time(
+            cache: &Cache<&'static str, &'static str>,
+            key: &'static str,
+        ) -> Option<StdInstant> {
+            cache.peek_metadata(&key).await?.expiration_time()
+        }
+
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(60))
+            .time_to_idle(Duration::from_secs(30))
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        assert_eq!(cache.next_expiration_time().await, None);
+
+        cache.insert("a", "alice").await;
+        mock.increment(Duration::from_secs(1)); // 1 sec from the start.
+        cache.insert("b", "bob").await;
+        cache.run_pending_tasks().await;
+        let next = cache.next_expiration_time().await;
+        assert_eq!(next, exp_time(&cache, "a").await);
+
+        // The per-entry expiration of "c" is earlier than the others.
+        cache
+            .insert_with_ttl("c", "cindy", Duration::from_secs(5))
+            .await;
+        cache.run_pending_tasks().await;
+        let next = cache.next_expiration_time().await;
+        assert_eq!(next, exp_time(&cache, "c").await);
+
+        mock.increment(Duration::from_secs(5)); // 6 secs.
+        cache.run_pending_tasks().await;
+        assert!(!cache.contains_key(&"c"));
+        let next = cache.next_expiration_time().await;
+        assert_eq!(next, exp_time(&cache, "a").await);
+
+        // Reading "a" extends its time-to-idle, so "b" will expire first.
+        assert_eq!(cache.get(&"a").await, Some("alice"));
+        cache.run_pending_tasks().await;
+        let next = cache.next_expiration_time().await;
+        assert_eq!(next, exp_time(&cache, "b").await);
+
+        cache.invalidate_all();
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.next_expiration_time().await, None);
+    }
 
+    #[tokio::test]
+    async fn set_expiration_and_touch() {
         // The following `Vec`s will hold actual and expected notifications.
         let actual = Arc::new(Mutex::new(Vec::new()));
         let mut expected = Vec::new();
@@ -2678,7 +4996,7 @@ mod tests {
         // Create a cache with the eviction listener.
         let mut cache = Cache::builder()
             .max_capacity(100)
-            .support_invalidation_closures()
+            .time_to_idle(Duration::from_secs(10))
             .async_eviction_listener(listener)
             .clock(clock)
             .build();
@@ -2687,78 +5005,140 @@ mod tests {
         // Make the cache exterior immutable.
         let cache = cache;
 
-        cache.insert(0, "alice").await;
-        cache.insert(1, "bob").await;
-        cache.insert(2, "alex").await;
+        cache.insert("a", "alice").await;
+        cache.insert("b", "bob").await;
+        cache.insert("c", "cindy").await;
         cache.run_pending_tasks().await;
 
-        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        // Shorten the expiration of "a".
+        let ttl = Some(Duration::from_secs(5));
+        assert!(cache.set_expiration(&"a", ttl).await);
+        assert!(!cache.set_expiration(&"x", ttl).await);
         cache.run_pending_tasks().await;
 
-        assert_eq!(cache.get(&0).await, Some("alice"));
-        assert_eq!(cache.get(&1).await, Some("bob"));
-        assert_eq!(cache.get(&2).await, Some("alex"));
-        assert!(cache.contains_key(&0));
-        assert!(cache.contains_key(&1));
-        assert!(cache.contains_key(&2));
+        mock.increment(Duration::from_secs(6)); // 6 secs from the start.
 
-        let names = ["alice", "alex"].iter().cloned().collect::<HashSet<_>>();
-        cache.invalidate_entries_if(move |_k, &v| names.contains(v))?;
-        assert_eq!(cache.invalidation_predicate_count(), 1);
-        expected.push((Arc::new(0), "alice", RemovalCause::Explicit));
-        expected.push((Arc::new(2), "alex", RemovalCause::Explicit));
+        // The timer wheel should expire "a" without reading it.
+        cache.run_pending_tasks().await;
+        expected.push((Arc::new("a"), "alice", RemovalCause::Expired));
+        assert_eq!(cache.entry_count(), 2);
 
-        mock.increment(Duration::from_secs(5)); // 10 secs from the start.
+        // Touching "b" resets its idle timer. ("b" will expire at 16 secs)
+        assert!(cache.touch(&"b").await);
+        assert!(!cache.touch(&"a").await);
+        // Set a per-entry expiration on "c" and then unset it.
+        let ttl = Some(Duration::from_secs(1));
+        assert!(cache.set_expiration(&"c", ttl).await);
+        assert!(cache.set_expiration(&"c", None).await);
+        cache.run_pending_tasks().await;
 
-        cache.insert(3, "alice").await;
+        mock.increment(Duration::from_secs(3)); // 9 secs.
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.peek(&"c"), Some("cindy"));
 
-        // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
-        cache.run_pending_tasks().await; // To submit the invalidation task.
-        std::thread::sleep(Duration::from_millis(200));
-        cache.run_pending_tasks().await; // To process the task result.
-        std::thread::sleep(Duration::from_millis(200));
+        mock.increment(Duration::from_secs(3)); // 12 secs.
+        cache.run_pending_tasks().await;
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Expired));
+        assert_eq!(cache.get(&"b").await, Some("bob"));
+        assert_eq!(cache.entry_count(), 1);
 
-        assert!(cache.get(&0).await.is_none());
-        assert!(cache.get(&2).await.is_none());
-        assert_eq!(cache.get(&1).await, Some("bob"));
-        // This should survive as it was inserted after calling invalidate_entries_if.
-        assert_eq!(cache.get(&3).await, Some("alice"));
+        // Neither `set_expiration` nor `touch` should trigger a `Replaced`
+        // notification.
+        verify_notification_vec(&cache, actual, &expected).await;
+    }
 
-        assert!(!cache.contains_key(&0));
-        assert!(cache.contains_key(&1));
-        assert!(!cache.contains_key(&2));
-        assert!(cache.contains_key(&3));
+    #[tokio::test]
+    async fn async_expiry() {
+        use crate::policy::{AsyncExpiry, ExpiryFuture};
+        use std::time::Instant as StdInstant;
+
+        struct MyExpiry;
+
+        impl AsyncExpiry<&'static str, &'static str> for MyExpiry {
+            fn expire_after_create<'a>(
+                &'a self,
+                _key: &'a &'static str,
+                _value: &'a &'static str,
+                _created_at: StdInstant,
+            ) -> ExpiryFuture<'a> {
+                Box::pin(async {
+                    tokio::task::yield_now().await;
+                    Some(Duration::from_secs(10))
+                })
+            }
 
-        assert_eq!(cache.entry_count(), 2);
-        assert_eq!(cache.invalidation_predicate_count(), 0);
+            fn expire_after_read<'a>(
+                &'a self,
+                _key: &'a &'static str,
+                _value: &'a &'static str,
+                _read_at: StdInstant,
+                _duration_until_expiry: Option<Duration>,
+                _last_modified_at: StdInstant,
+            ) -> ExpiryFuture<'a> {
+                Box::pin(async {
+                    tokio::task::yield_now().await;
+                    Some(Duration::from_secs(5))
+                })
+            }
 
-        mock.increment(Duration::from_secs(5)); // 15 secs from the start.
+            fn expire_after_update<'a>(
+                &'a self,
+                _key: &'a &'static str,
+                _value: &'a &'static str,
+                _updated_at: StdInstant,
+                _duration_until_expiry: Option<Duration>,
+            ) -> ExpiryFuture<'a> {
+                Box::pin(async {
+                    tokio::task::yield_now().await;
+                    Some(Duration::from_secs(20))
+                })
+            }
+        }
 
-        cache.invalidate_entries_if(|_k, &v| v == "alice")?;
-        cache.invalidate_entries_if(|_k, &v| v == "bob")?;
-        assert_eq!(cache.invalidation_predicate_count(), 2);
-        // key 1 was inserted before key 3.
-        expected.push((Arc::new(1), "bob", RemovalCause::Explicit));
-        expected.push((Arc::new(3), "alice", RemovalCause::Explicit));
+        let (clock, mock) = Clock::mock();
 
-        // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
-        cache.run_pending_tasks().await; // To submit the invalidation task.
-        std::thread::sleep(Duration::from_millis(200));
-        cache.run_pending_tasks().await; // To process the task result.
-        std::thread::sleep(Duration::from_millis(200));
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .async_expire_after(MyExpiry)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        // Returns the time to expiration of "a".
+        async fn tte(cache: &Cache<&'static str, &'static str>) -> Option<Duration> {
+            let md = cache.peek_metadata(&"a").await;
+            md.and_then(|md| md.time_to_expiration())
+        }
 
-        assert!(cache.get(&1).await.is_none());
-        assert!(cache.get(&3).await.is_none());
+        // `expire_after_create` is awaited by `insert`. ("a" will expire at 10 secs)
+        cache.insert("a", "alice").await;
+        assert_eq!(tte(&cache).await, Some(Duration::from_secs(10)));
+        cache.run_pending_tasks().await;
 
-        assert!(!cache.contains_key(&1));
-        assert!(!cache.contains_key(&3));
+        mock.increment(Duration::from_secs(2)); // 2 secs from the start.
+        assert_eq!(cache.get(&"a").await, Some("alice"));
+        // `expire_after_read` is not awaited by `get`.
+        assert_eq!(tte(&cache).await, Some(Duration::from_secs(8)));
+        // It is lazily applied by the housekeeping. ("a" will expire at 7 secs)
+        cache.run_pending_tasks().await;
+        assert_eq!(tte(&cache).await, Some(Duration::from_secs(5)));
 
-        assert_eq!(cache.entry_count(), 0);
-        assert_eq!(cache.invalidation_predicate_count(), 0);
+        // `expire_after_update` is awaited by `insert`. ("a" will expire at 23 secs)
+        mock.increment(Duration::from_secs(1)); // 3 secs.
+        cache.insert("a", "anna").await;
+        assert_eq!(tte(&cache).await, Some(Duration::from_secs(20)));
+        cache.run_pending_tasks().await;
 
-        verify_notification_vec(&cache, actual, &expected).await;
+        mock.increment(Duration::from_secs(19)); // 22 secs.
+        assert_eq!(cache.peek(&"a"), Some("anna"));
 
-        Ok(())
+        mock.increment(Duration::from_secs(2)); // 24 secs.
+        assert_eq!(cache.peek(&"a"), None);
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 0);
     }
 
     #[tokio::test]
@@ -2849,6 +5229,78 @@ mod tests {
         verify_notification_vec(&cache, actual, &expected).await;
     }
 
+    #[tokio::test]
+    async fn set_time_to_live_and_time_to_idle() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache without the time-to-live and time-to-idle.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .async_eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice").await;
+        cache.run_pending_tasks().await;
+
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        cache.insert("b", "bob").await;
+        cache.run_pending_tasks().await;
+
+        // Enable the time-to-live. It applies to the existing entries.
+        cache.set_time_to_live(Some(Duration::from_secs(8))).await;
+        assert_eq!(cache.policy().time_to_live(), Some(Duration::from_secs(8)));
+
+        mock.increment(Duration::from_secs(4)); // 9 secs.
+        expected.push((Arc::new("a"), "alice", RemovalCause::Expired));
+        assert_eq!(cache.get(&"a").await, None);
+        assert_eq!(cache.get(&"b").await, Some("bob"));
+
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), 1);
+
+        mock.increment(Duration::from_secs(4)); // 13 secs.
+        expected.push((Arc::new("b"), "bob", RemovalCause::Expired));
+        cache.run_pending_tasks().await;
+        assert!(cache.is_table_empty());
+
+        // Disable the time-to-live and enable the time-to-idle.
+        cache.set_time_to_live(None).await;
+        cache.set_time_to_idle(Some(Duration::from_secs(5)));
+        assert_eq!(cache.policy().time_to_live(), None);
+        assert_eq!(cache.policy().time_to_idle(), Some(Duration::from_secs(5)));
+
+        cache.insert("c", "cindy").await;
+        cache.run_pending_tasks().await;
+
+        mock.increment(Duration::from_secs(6)); // 19 secs.
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Expired));
+        assert_eq!(cache.get(&"c").await, None);
+
+        cache.run_pending_tasks().await;
+        assert!(cache.is_table_empty());
+
+        verify_notification_vec(&cache, actual, &expected).await;
+    }
+
     #[tokio::test]
     async fn time_to_idle() {
         // The following `Vec`s will hold actual and expected notifications.
@@ -4965,7 +7417,7 @@ mod tests {
 
         // This will be rejected due to the size constraint.
         cache.insert('e', "emily").await;
-        expected.push((Arc::new('e'), "emily", RemovalCause::Size));
+        expected.push((Arc::new('e'), "emily", RemovalCause::Rejected));
         cache.run_pending_tasks().await;
         assert_eq!(cache.entry_count(), 3);
 
@@ -5241,6 +7693,238 @@ mod tests {
         assert_eq!(cache.entry_count(), MAX_CAPACITY);
     }
 
+    #[tokio::test]
+    async fn close() {
+        const NUM_ENTRIES: u64 = 20;
+
+        // Let `run_pending_tasks` time out after removing a few entries, so that
+        // `close` has to call it repeatedly.
+        const EVICTION_TIMEOUT: Duration = Duration::from_millis(30);
+        const LISTENER_DELAY: Duration = Duration::from_millis(11);
+
+        let hk_conf = HousekeeperConfig::new(Some(EVICTION_TIMEOUT), Some(1), Some(1));
+        let (clock, mock) = Clock::mock();
+
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let a1 = Arc::clone(&actual);
+        let mock1 = Arc::clone(&mock);
+        let listener = move |k: Arc<u64>, _v, cause| -> ListenerFuture {
+            mock1.increment(LISTENER_DELAY);
+            let a2 = Arc::clone(&a1);
+            async move {
+                a2.lock().await.push((*k, cause));
+            }
+            .boxed()
+        };
+
+        let mut cache = Cache::builder()
+            .async_eviction_listener(listener)
+            .housekeeper_config(hk_conf)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for i in 0..NUM_ENTRIES {
+            cache.insert(i, format!("v{i}")).await;
+        }
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.entry_count(), NUM_ENTRIES);
+
+        // Insert one more entry without running the pending tasks. Do not advance
+        // the clock, so the entry is written at the time of `close`.
+        cache.insert(NUM_ENTRIES, format!("v{NUM_ENTRIES}")).await;
+
+        cache.close().await;
+        assert_eq!(cache.entry_count(), 0);
+        assert!(cache.iter().next().is_none());
+
+        let mut actual = actual.lock().await;
+        actual.sort_unstable_by_key(|(k, _)| *k);
+        let expected = (0..=NUM_ENTRIES)
+            .map(|i| (i, RemovalCause::InvalidatedAll))
+            .collect::<Vec<_>>();
+        assert_eq!(*actual, expected);
+    }
+
+    #[tokio::test]
+    async fn queued_eviction_listener() {
+        const NUM_KEYS: u32 = 10;
+        const NUM_ROUNDS: u32 = 300; // More than the queue size in total.
+
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let a1 = Arc::clone(&actual);
+        let listener = move |k: Arc<u32>, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                // The listener future runs on the runtime, so it can use the timers.
+                if v % 100 == 0 {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                a2.lock().await.push((*k, v, cause));
+            }
+            .boxed()
+        };
+
+        let mut cache = Cache::builder()
+            .async_eviction_listener(listener)
+            .queued_delivery(|task| {
+                tokio::spawn(task);
+            })
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for round in 0..NUM_ROUNDS {
+            for key in 0..NUM_KEYS {
+                cache.insert(key, round).await;
+            }
+            cache.run_pending_tasks().await;
+        }
+        for key in 0..NUM_KEYS {
+            cache.invalidate(&key).await;
+        }
+
+        cache.wait_for_notifications().await;
+
+        let actual = actual.lock().await;
+        assert_eq!(actual.len() as u32, NUM_KEYS * NUM_ROUNDS);
+
+        // The notifications for the same key are delivered in order.
+        for key in 0..NUM_KEYS {
+            let values = actual
+                .iter()
+                .filter(|(k, ..)| *k == key)
+                .map(|(_, v, cause)| {
+                    let expected_cause = if *v == NUM_ROUNDS - 1 {
+                        RemovalCause::Explicit
+                    } else {
+                        RemovalCause::Replaced
+                    };
+                    assert_eq!(cause, &expected_cause);
+                    *v
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(values, (0..NUM_ROUNDS).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_for_notifications_in_queued_listener() {
+        let cache: Arc<Mutex<Option<Cache<u32, u32>>>> = Arc::default();
+        let delivered = Arc::new(AtomicU32::new(0));
+
+        let c1 = Arc::clone(&cache);
+        let d1 = Arc::clone(&delivered);
+        let listener = move |_k, _v, _cause| -> ListenerFuture {
+            let c2 = Arc::clone(&c1);
+            let d2 = Arc::clone(&d1);
+            async move {
+                // Waiting for the notifications from the listener should not wait
+                // for the listener itself.
+                if let Some(cache) = &*c2.lock().await {
+                    cache.wait_for_notifications().await;
+                }
+                d2.fetch_add(1, Ordering::AcqRel);
+            }
+            .boxed()
+        };
+
+        let new_cache = Cache::builder()
+            .async_eviction_listener(listener)
+            .queued_delivery(|task| {
+                tokio::spawn(task);
+            })
+            .build();
+        new_cache.insert(1, 1).await;
+        *cache.lock().await = Some(new_cache.clone());
+
+        new_cache.invalidate(&1).await;
+        let waited =
+            tokio::time::timeout(Duration::from_secs(5), new_cache.wait_for_notifications());
+        assert!(waited.await.is_ok());
+        assert_eq!(delivered.load(Ordering::Acquire), 1);
+
+        // Break the reference cycle.
+        *cache.lock().await = None;
+    }
+
+    #[tokio::test]
+    async fn cancel_notification_blocked_on_full_queue() {
+        use crate::notification::NOTIFICATION_QUEUE_SIZE;
+        use futures_util::future::poll_immediate;
+        use std::sync::atomic::AtomicBool;
+
+        let num_keys = NOTIFICATION_QUEUE_SIZE as u32 + 2;
+
+        // The listener waits for the gate to open.
+        let gate = Arc::new(tokio::sync::RwLock::new(()));
+        let closed_gate = gate.write().await;
+        let started = Arc::new(AtomicBool::new(false));
+        let delivered = Arc::new(AtomicU32::new(0));
+
+        let g1 = Arc::clone(&gate);
+        let s1 = Arc::clone(&started);
+        let d1 = Arc::clone(&delivered);
+        let listener = move |_k, _v, _cause| -> ListenerFuture {
+            let g2 = Arc::clone(&g1);
+            let s2 = Arc::clone(&s1);
+            let d2 = Arc::clone(&d1);
+            async move {
+                s2.store(true, Ordering::Release);
+                let _open = g2.read().await;
+                d2.fetch_add(1, Ordering::AcqRel);
+            }
+            .boxed()
+        };
+
+        let mut cache = Cache::builder()
+            .async_eviction_listener(listener)
+            .queued_delivery(|task| {
+                tokio::spawn(task);
+            })
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for key in 0..num_keys {
+            cache.insert(key, key).await;
+            if key % 100 == 0 {
+                cache.run_pending_tasks().await;
+            }
+        }
+
+        // Let the listener take the first notification and wait for the gate.
+        cache.invalidate(&0).await;
+        while !started.load(Ordering::Acquire) {
+            tokio::task::yield_now().await;
+        }
+        // This fills the queue.
+        for key in 1..(num_keys - 1) {
+            cache.invalidate(&key).await;
+            if key % 100 == 0 {
+                cache.run_pending_tasks().await;
+            }
+        }
+        // The invalidation waits for a room in the queue, so the future should not
+        // be completed. Drop (cancel) it after one poll.
+        assert!(poll_immediate(cache.invalidate(&(num_keys - 1)))
+            .await
+            .is_none());
+
+        // The cancelled notification should not be waited for.
+        drop(closed_gate);
+        let waited = tokio::time::timeout(Duration::from_secs(5), cache.wait_for_notifications());
+        assert!(waited.await.is_ok());
+        assert_eq!(delivered.load(Ordering::Acquire), num_keys - 1);
+    }
+
     // NOTE: To enable the panic logging, run the following command:
     //
     // RUST_LOG=moka=info cargo test --features 'future, logging' -- \
@@ -5299,6 +7983,144 @@ mod tests {
         cache.run_pending_tasks().await;
 
         verify_notification_vec(&cache, actual, &expected).await;
+        assert_eq!(cache.eviction_listener_panic_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn batch_eviction_listener() {
+        let (clock, mock) = Clock::mock();
+
+        // Record each batch as a sorted vec of keys and causes.
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let b1 = Arc::clone(&batches);
+        let listener = move |entries: Vec<(Arc<u64>, String, RemovalCause)>| -> ListenerFuture {
+            let b2 = Arc::clone(&b1);
+            async move {
+                let mut batch = entries
+                    .into_iter()
+                    .map(|(k, _v, cause)| (*k, cause))
+                    .collect::<Vec<_>>();
+                batch.sort_unstable_by_key(|(k, _)| *k);
+                b2.lock().await.push(batch);
+            }
+            .boxed()
+        };
+
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .async_batch_eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for i in 0..3 {
+            cache.insert(i, format!("v{i}")).await;
+        }
+        cache.run_pending_tasks().await;
+        // Nothing has been removed yet, so the listener should not be called.
+        assert!(batches.lock().await.is_empty());
+
+        // The two least recently used entries will be evicted by one maintenance
+        // pass.
+        cache.insert(3, "v3".into()).await;
+        cache.insert(4, "v4".into()).await;
+        cache.run_pending_tasks().await;
+        assert_eq!(
+            batches.lock().await.pop(),
+            Some(vec![(0, RemovalCause::Size), (1, RemovalCause::Size)])
+        );
+
+        // An explicit invalidation is notified by itself.
+        cache.invalidate(&2).await;
+        assert_eq!(
+            batches.lock().await.pop(),
+            Some(vec![(2, RemovalCause::Explicit)])
+        );
+
+        mock.increment(Duration::from_secs(1));
+        cache.invalidate_all();
+        cache.run_pending_tasks().await;
+        assert_eq!(
+            *batches.lock().await,
+            vec![vec![
+                (3, RemovalCause::InvalidatedAll),
+                (4, RemovalCause::InvalidatedAll)
+            ]]
+        );
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn continue_after_panicking_eviction_listener() {
+        use crate::notification::ListenerPanicPolicy;
+
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+        let panics = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        // Create an eviction listener that panics when it see
+        // a value "panic now!".
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| -> ListenerFuture {
+            let a2 = Arc::clone(&a1);
+            async move {
+                if v == "panic now!" {
+                    panic!("Panic now!");
+                }
+                a2.lock().await.push((k, v, cause));
+            }
+            .boxed()
+        };
+
+        // Create a panic hook that records the payload, keys and causes.
+        let p1 = Arc::clone(&panics);
+        let hook = move |payload: Box<dyn std::any::Any + Send>,
+                         entries: &[(Arc<&'static str>, RemovalCause)]| {
+            let message = payload.downcast_ref::<&str>().map(ToString::to_string);
+            let entries: Vec<_> = entries.iter().map(|(k, cause)| (**k, *cause)).collect();
+            p1.lock().unwrap().push((message, entries));
+        };
+
+        // Create a cache with the eviction listener that stays enabled after a
+        // panic.
+        let mut cache = Cache::builder()
+            .async_eviction_listener(listener)
+            .eviction_listener_panic_policy(ListenerPanicPolicy::Continue)
+            .eviction_listener_panic_hook(hook)
+            .build();
+        cache.reconfigure_for_testing().await;
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("alice", "panic now!").await;
+        cache.insert("alice", "a1").await;
+        cache.insert("bob", "panic now!").await;
+        cache.invalidate(&"bob").await;
+        cache.invalidate(&"alice").await;
+        expected.push((Arc::new("alice"), "a1", RemovalCause::Explicit));
+        cache.run_pending_tasks().await;
+
+        // The listener was still called after the panics.
+        verify_notification_vec(&cache, actual, &expected).await;
+        assert_eq!(cache.eviction_listener_panic_count(), 2);
+        assert_eq!(
+            *panics.lock().unwrap(),
+            vec![
+                (
+                    Some("Panic now!".into()),
+                    vec![("alice", RemovalCause::Replaced)]
+                ),
+                (
+                    Some("Panic now!".into()),
+                    vec![("bob", RemovalCause::Explicit)]
+                ),
+            ]
+        );
     }
 
     #[tokio::test]
@@ -5607,7 +8429,7 @@ mod tests {
         let counters1 = Arc::clone(&counters);
 
         let listener = move |_k, _v, cause| match cause {
-            RemovalCause::Size => counters1.incl_evicted(),
+            RemovalCause::Size | RemovalCause::Rejected => counters1.incl_evicted(),
             RemovalCause::Explicit => counters1.incl_invalidated(),
             _ => (),
         };
@@ -1,63 +1,404 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    any::Any,
+    cell::Cell,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
 };
 
+use crossbeam_channel::{Receiver, Sender, TryRecvError, TrySendError};
 use futures_util::FutureExt;
 
-use crate::notification::{AsyncEvictionListener, RemovalCause};
+use crate::notification::{
+    AsyncEvictionListener, DeliverySpawner, ListenerConfig, ListenerFuture, ListenerPanicHook,
+    ListenerPanicPolicy, RemovalCause, RemovedEntries, NOTIFICATION_QUEUE_SIZE,
+};
 
 pub(crate) struct RemovalNotifier<K, V> {
+    listener: Arc<ListenerCaller<K, V>>,
+    queue: Option<NotificationQueue<K, V>>,
+}
+
+impl<K, V> RemovalNotifier<K, V> {
+    pub(crate) fn new(
+        listener: AsyncEvictionListener<K, V>,
+        config: ListenerConfig<K>,
+        cache_name: Option<String>,
+    ) -> Self
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        // The spawner is set only for `DeliveryMode::Queued`.
+        let spawner = config.delivery_spawner.clone();
+        let listener = Arc::new(ListenerCaller::new(listener, config, cache_name));
+        let queue = spawner.map(|spawn| NotificationQueue::new(Arc::clone(&listener), &spawn));
+        Self { listener, queue }
+    }
+
+    pub(crate) async fn notify(&self, key: Arc<K>, value: V, cause: RemovalCause) {
+        if let Some(queue) = &self.queue {
+            queue.push(QueueItem::One((key, value, cause))).await;
+        } else {
+            self.listener.call(key, value, cause).await;
+        }
+    }
+
+    /// Notifies the removed entries at once. A batch listener will be called once
+    /// with all the entries, and a single entry listener will be called for each
+    /// entry.
+    pub(crate) async fn notify_batch(&self, entries: RemovedEntries<K, V>) {
+        if entries.is_empty() {
+            return;
+        }
+        if let Some(queue) = &self.queue {
+            queue.push(QueueItem::Batch(entries)).await;
+        } else {
+            self.listener.call_batch(entries).await;
+        }
+    }
+
+    /// Returns `true` if the listener is a batch listener.
+    pub(crate) fn is_batch(&self) -> bool {
+        matches!(self.listener.listener, AsyncEvictionListener::Batch(_))
+    }
+
+    /// Waits until the listener has been called for all the notifications queued
+    /// so far. Returns immediately on `DeliveryMode::Immediate`, or when called by a
+    /// listener running on the delivery task, which would otherwise wait for itself.
+    pub(crate) async fn wait_for_notifications(&self) {
+        if let Some(queue) = &self.queue {
+            queue.wait().await;
+        }
+    }
+
+    /// Returns the number of times the listener panicked.
+    pub(crate) fn panic_count(&self) -> u64 {
+        self.listener.panic_count.load(Ordering::Acquire)
+    }
+}
+
+/// The keys and causes of the notifications passed to the panic hook.
+type HookEntries<K> = Vec<(Arc<K>, RemovalCause)>;
+
+struct ListenerCaller<K, V> {
     listener: AsyncEvictionListener<K, V>,
     is_enabled: AtomicBool,
+    panic_policy: ListenerPanicPolicy,
+    panic_hook: Option<ListenerPanicHook<K>>,
+    panic_count: AtomicU64,
     #[cfg(feature = "logging")]
     cache_name: Option<String>,
 }
 
-impl<K, V> RemovalNotifier<K, V> {
-    pub(crate) fn new(listener: AsyncEvictionListener<K, V>, _cache_name: Option<String>) -> Self {
+impl<K, V> ListenerCaller<K, V> {
+    fn new(
+        listener: AsyncEvictionListener<K, V>,
+        config: ListenerConfig<K>,
+        _cache_name: Option<String>,
+    ) -> Self {
         Self {
             listener,
             is_enabled: AtomicBool::new(true),
+            panic_policy: config.panic_policy,
+            panic_hook: config.panic_hook,
+            panic_count: AtomicU64::default(),
             #[cfg(feature = "logging")]
             cache_name: _cache_name,
         }
     }
 
-    pub(crate) async fn notify(&self, key: Arc<K>, value: V, cause: RemovalCause) {
-        use std::panic::{catch_unwind, AssertUnwindSafe};
-
+    async fn call(&self, key: Arc<K>, value: V, cause: RemovalCause) {
         if !self.is_enabled.load(Ordering::Acquire) {
             return;
         }
 
+        let for_hook = self.for_hook(&key, &cause);
+        match &self.listener {
+            AsyncEvictionListener::Single(listener) => {
+                self.call_guarded(|| listener(key, value, cause), for_hook)
+                    .await
+            }
+            AsyncEvictionListener::Batch(listener) => {
+                self.call_guarded(|| listener(vec![(key, value, cause)]), for_hook)
+                    .await
+            }
+        }
+    }
+
+    async fn call_batch(&self, entries: RemovedEntries<K, V>) {
+        match &self.listener {
+            AsyncEvictionListener::Single(_) => {
+                for (key, value, cause) in entries {
+                    self.call(key, value, cause).await;
+                }
+            }
+            AsyncEvictionListener::Batch(listener) => {
+                if !self.is_enabled.load(Ordering::Acquire) {
+                    return;
+                }
+                if entries.is_empty() {
+                    return;
+                }
+                // The panic hook takes the keys and causes of all the entries.
+                let for_hook = self.panic_hook.as_ref().map(|_| {
+                    entries
+                        .iter()
+                        .map(|(key, _, cause)| (Arc::clone(key), *cause))
+                        .collect()
+                });
+                self.call_guarded(|| listener(entries), for_hook).await;
+            }
+        }
+    }
+
+    /// Clones the key and cause for the panic hook, as they will be moved into the
+    /// listener.
+    fn for_hook(&self, key: &Arc<K>, cause: &RemovalCause) -> Option<HookEntries<K>> {
+        self.panic_hook
+            .as_ref()
+            .map(|_| vec![(Arc::clone(key), *cause)])
+    }
+
+    async fn call_guarded(
+        &self,
+        listener_clo: impl FnOnce() -> ListenerFuture,
+        for_hook: Option<HookEntries<K>>,
+    ) {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
         // This macro unwraps the result of the catch_unwind call if it is Ok. And
-        // disable the notifier and do early return if the listener panicked.
-        macro_rules! try_or_disable {
+        // handle the panic and do early return if the listener panicked.
+        macro_rules! try_or_handle_panic {
             ($match_expr:expr) => {
                 match $match_expr {
                     Ok(v) => v,
-                    Err(_payload) => {
-                        self.is_enabled.store(false, Ordering::Release);
-                        #[cfg(feature = "logging")]
-                        log_panic(&*_payload, self.cache_name.as_deref());
+                    Err(payload) => {
+                        self.handle_panic(payload, for_hook);
                         return;
                     }
                 }
             };
         }
 
-        let listener_clo = || (self.listener)(key, value, cause);
-
         // Safety: It is safe to assert unwind safety here because we will not
-        // call the listener again if it has been panicked.
-        let fut = try_or_disable!(catch_unwind(AssertUnwindSafe(listener_clo)));
-        try_or_disable!(AssertUnwindSafe(fut).catch_unwind().await);
+        // call the listener again if it has been panicked, unless the user opted in
+        // by `ListenerPanicPolicy::Continue`.
+        let fut = try_or_handle_panic!(catch_unwind(AssertUnwindSafe(listener_clo)));
+        try_or_handle_panic!(AssertUnwindSafe(fut).catch_unwind().await);
+    }
+
+    fn handle_panic(&self, payload: Box<dyn Any + Send>, for_hook: Option<HookEntries<K>>) {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        self.panic_count.fetch_add(1, Ordering::AcqRel);
+        let disabled = self.panic_policy == ListenerPanicPolicy::Disable;
+        if disabled {
+            self.is_enabled.store(false, Ordering::Release);
+        }
+
+        #[cfg(feature = "logging")]
+        log_panic(&*payload, self.cache_name.as_deref(), disabled);
+
+        if let (Some(hook), Some(entries)) = (&self.panic_hook, for_hook) {
+            let _ = catch_unwind(AssertUnwindSafe(|| hook(payload, &entries)));
+        }
+    }
+}
+
+enum QueueItem<K, V> {
+    One((Arc<K>, V, RemovalCause)),
+    Batch(RemovedEntries<K, V>),
+}
+
+/// A bounded FIFO queue of notifications for `DeliveryMode::Queued`. A single
+/// task, spawned on the user's async runtime, calls the listener and awaits the
+/// returned future, one notification at a time, so the notifications for the same
+/// key are delivered in order.
+struct NotificationQueue<K, V> {
+    // `None` after the queue is dropped.
+    sender: Option<Sender<QueueItem<K, V>>>,
+    progress: Arc<Progress>,
+}
+
+impl<K, V> NotificationQueue<K, V> {
+    fn new(listener: Arc<ListenerCaller<K, V>>, spawn: &DeliverySpawner) -> Self
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        let (sender, receiver) = crossbeam_channel::bounded(NOTIFICATION_QUEUE_SIZE);
+        let progress = Arc::new(Progress::default());
+        spawn(deliver(listener, receiver, Arc::clone(&progress)).boxed());
+
+        Self {
+            sender: Some(sender),
+            progress,
+        }
+    }
+
+    async fn push(&self, mut item: QueueItem<K, V>) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        loop {
+            // Start listening before trying to send, so that we will not miss a
+            // delivery made in between.
+            let listener = self.progress.delivered_event.listen();
+            if self.progress.is_closed() {
+                return;
+            }
+            match sender.try_send(item) {
+                Ok(()) => {
+                    // Count the notification only after it is in the queue. If the
+                    // caller is cancelled while waiting for a free slot below, the
+                    // notification is dropped and the waiters must not wait for it.
+                    self.progress.enqueued.fetch_add(1, Ordering::AcqRel);
+                    self.progress.queued_event.notify(1);
+                    return;
+                }
+                Err(TrySendError::Full(i)) => {
+                    // Wait for the task to deliver a notification.
+                    item = i;
+                    listener.await;
+                }
+                // The task has gone.
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+        }
+    }
+
+    async fn wait(&self) {
+        // A listener waiting on the delivery task would wait for itself.
+        if self.progress.is_delivering() {
+            return;
+        }
+        let target = self.progress.enqueued.load(Ordering::Acquire);
+        loop {
+            let listener = self.progress.delivered_event.listen();
+            if self.progress.is_closed()
+                || self.progress.delivered.load(Ordering::Acquire) >= target
+            {
+                return;
+            }
+            listener.await;
+        }
+    }
+}
+
+impl<K, V> Drop for NotificationQueue<K, V> {
+    fn drop(&mut self) {
+        // Close the queue, and wake up the task to deliver the remaining
+        // notifications and end.
+        self.sender = None;
+        self.progress.queued_event.notify(1);
+    }
+}
+
+/// The delivery task. Ends when the queue is dropped and all the remaining
+/// notifications are delivered.
+async fn deliver<K, V>(
+    listener: Arc<ListenerCaller<K, V>>,
+    receiver: Receiver<QueueItem<K, V>>,
+    progress: Arc<Progress>,
+) {
+    // Mark the queue closed when the task ends or is dropped (e.g. by a runtime
+    // shutdown), so that the pushers and waiters will not wait forever.
+    let _closer = CloseOnDrop(Arc::clone(&progress));
+    let mut queued = None;
+    loop {
+        match receiver.try_recv() {
+            Ok(item) => {
+                let delivery = async {
+                    match item {
+                        QueueItem::One((key, value, cause)) => {
+                            listener.call(key, value, cause).await
+                        }
+                        QueueItem::Batch(entries) => listener.call_batch(entries).await,
+                    }
+                };
+                let mut delivery = std::pin::pin!(delivery);
+                std::future::poll_fn(|cx| {
+                    let _delivering = DeliveringGuard::enter(&progress);
+                    delivery.as_mut().poll(cx)
+                })
+                .await;
+                progress.incr_delivered();
+            }
+            Err(TryRecvError::Empty) => match queued.take() {
+                // Start listening, and then try again so that we will not miss a
+                // notification queued before the listener was created.
+                None => queued = Some(progress.queued_event.listen()),
+                Some(l) => l.await,
+            },
+            Err(TryRecvError::Disconnected) => break,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Progress {
+    enqueued: AtomicU64,
+    delivered: AtomicU64,
+    is_closed: AtomicBool,
+    queued_event: event_listener::Event,
+    delivered_event: event_listener::Event,
+}
+
+impl Progress {
+    fn incr_delivered(&self) {
+        self.delivered.fetch_add(1, Ordering::AcqRel);
+        self.delivered_event.notify(usize::MAX);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.is_closed.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if called by a listener of this queue being polled by the
+    /// delivery task.
+    fn is_delivering(&self) -> bool {
+        DELIVERING.with(|d| d.get() == self as *const Self as usize)
+    }
+}
+
+struct CloseOnDrop(Arc<Progress>);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0.is_closed.store(true, Ordering::Release);
+        self.0.delivered_event.notify(usize::MAX);
+    }
+}
+
+thread_local! {
+    /// The address of the `Progress` of the queue whose listener future is being
+    /// polled on this thread, or zero.
+    static DELIVERING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Sets `DELIVERING` while the delivery task polls a listener future, and restores
+/// the previous value when dropped.
+struct DeliveringGuard(usize);
+
+impl DeliveringGuard {
+    fn enter(progress: &Progress) -> Self {
+        let addr = progress as *const Progress as usize;
+        Self(DELIVERING.with(|d| d.replace(addr)))
+    }
+}
+
+impl Drop for DeliveringGuard {
+    fn drop(&mut self) {
+        DELIVERING.with(|d| d.set(self.0));
     }
 }
 
 #[cfg(feature = "logging")]
-fn log_panic(payload: &(dyn std::any::Any + Send + 'static), cache_name: Option<&str>) {
+fn log_panic(payload: &(dyn Any + Send + 'static), cache_name: Option<&str>, disabled: bool) {
     // Try to downcast the payload into &str or String.
     //
     // NOTE: Clippy will complain if we use `if let Some(_)` here.
@@ -70,9 +411,15 @@ fn log_panic(payload: &(dyn std::any::Any + Send + 'static), cache_name: Option<
         .map(|name| format!("[{name}] "))
         .unwrap_or_default();
 
+    let action = if disabled {
+        "Disabled the eviction listener because it panicked"
+    } else {
+        "The eviction listener panicked"
+    };
+
     if let Some(m) = message {
-        log::error!("{cn}Disabled the eviction listener because it panicked at '{m}'");
+        log::error!("{cn}{action} at '{m}'");
     } else {
-        log::error!("{cn}Disabled the eviction listener because it panicked");
+        log::error!("{cn}{action}");
     }
 }
@@ -12,7 +12,7 @@ use std::{
 use crate::{
     common::concurrent::arc::MiniArc,
     ops::compute::{CompResult, Op},
-    Entry,
+    Entry, InsertError, WriteError,
 };
 
 use super::{Cache, ComputeNone, OptionallyNone};
@@ -21,6 +21,19 @@ const WAITER_MAP_NUM_SEGMENTS: usize = 64;
 
 type ErrorObject = Arc<dyn Any + Send + Sync + 'static>;
 
+/// The `Op` returned by a compute closure that could not be applied to the cache.
+/// The `CompResult` paired with it reports the actual state of the entry.
+pub(crate) struct FailedOp<V> {
+    /// The value of `Op::Put`, or `None` for `Op::Remove`.
+    pub(crate) value: Option<V>,
+    /// The error from the cache writer, or `None` if the op was rejected for
+    /// another reason, e.g. the weight of the value.
+    pub(crate) error: Option<WriteError>,
+}
+
+/// The result of `try_compute`.
+pub(crate) type Computed<K, V> = (CompResult<K, V>, Option<FailedOp<V>>);
+
 pub(crate) enum InitResult<V, E> {
     Initialized(V),
     ReadExisting(V),
@@ -241,7 +254,7 @@ where
             Ok(value) => match post_init(value) {
                 Ok(value) => {
                     cache
-                        .insert_with_hash(Arc::clone(c_key), c_hash, value.clone())
+                        .load_with_hash_and_ttl(Arc::clone(c_key), c_hash, value.clone(), None)
                         .await;
                     waiter_guard.set_waiter_value(WaiterValue::Ready(Ok(value.clone())));
                     Initialized(value)
@@ -271,7 +284,7 @@ where
         f: F,
         post_init: fn(O) -> Result<Op<V>, E>,
         allow_nop: bool,
-    ) -> Result<CompResult<K, V>, E>
+    ) -> Result<Computed<K, V>, E>
     where
         F: FnOnce(Option<Entry<K, V>>) -> Fut,
         Fut: Future<Output = O> + 'a,
@@ -323,11 +336,15 @@ where
             .base
             .get_with_hash(&*c_key, c_hash, ignore_if, true, true)
             .await;
-        let maybe_value = if allow_nop {
-            maybe_entry.as_ref().map(|ent| ent.value().clone())
-        } else {
-            None
-        };
+        // Keep the current value for `Op::Nop`, for the case that the writer fails
+        // and the entry is left unchanged, and for the case that the new value is
+        // rejected for its weight and the entry is invalidated.
+        let maybe_value =
+            if allow_nop || cache.base.is_writer_enabled() || cache.base.has_max_entry_weight() {
+                maybe_entry.as_ref().map(|ent| ent.value().clone())
+            } else {
+                None
+            };
         let entry_existed = maybe_entry.is_some();
 
         // Evaluate the `f` closure and get a future. Catching panic is safe here as
@@ -357,43 +374,82 @@ where
             }
         };
 
-        match post_init(output)? {
-            Op::Nop => {
-                if let Some(value) = maybe_value {
-                    Ok(CompResult::Unchanged(Entry::new(
-                        Some(c_key),
-                        value,
-                        false,
-                        false,
-                    )))
-                } else {
-                    Ok(CompResult::StillNone(c_key))
-                }
+        let op = post_init(output)?;
+        let ttl = match &op {
+            Op::PutWithTtl(_, ttl) => Some(*ttl),
+            _ => None,
+        };
+
+        let unchanged = |c_key, maybe_value| {
+            if let Some(value) = maybe_value {
+                CompResult::Unchanged(Entry::new(Some(c_key), value, false, false))
+            } else {
+                CompResult::StillNone(c_key)
             }
-            Op::Put(value) => {
-                cache
-                    .insert_with_hash(Arc::clone(&c_key), c_hash, value.clone())
+        };
+
+        let result = match op {
+            Op::Nop => (unchanged(c_key, maybe_value), None),
+            Op::Put(value) | Op::PutWithTtl(value, _) => {
+                let r = cache
+                    .insert_with_hash_and_ttl(Arc::clone(&c_key), c_hash, value.clone(), ttl)
                     .await;
-                if entry_existed {
-                    crossbeam_epoch::pin().flush();
-                    let entry = Entry::new(Some(c_key), value, true, true);
-                    Ok(CompResult::ReplacedWith(entry))
-                } else {
-                    let entry = Entry::new(Some(c_key), value, true, false);
-                    Ok(CompResult::Inserted(entry))
+                match r {
+                    Ok(()) if entry_existed => {
+                        crossbeam_epoch::pin().flush();
+                        let entry = Entry::new(Some(c_key), value, true, true);
+                        (CompResult::ReplacedWith(entry), None)
+                    }
+                    Ok(()) => {
+                        let entry = Entry::new(Some(c_key), value, true, false);
+                        (CompResult::Inserted(entry), None)
+                    }
+                    // The writer failed, so the entry was not modified.
+                    Err(InsertError::WriterFailed(e)) => {
+                        let failed = FailedOp {
+                            value: Some(value),
+                            error: Some(e),
+                        };
+                        (unchanged(c_key, maybe_value), Some(failed))
+                    }
+                    // The value was rejected for its weight, and the existing entry (if
+                    // any) was invalidated.
+                    Err(InsertError::EntryTooHeavy { .. }) => {
+                        let result = match maybe_value {
+                            Some(prev_v) => {
+                                CompResult::Removed(Entry::new(Some(c_key), prev_v, false, false))
+                            }
+                            None => CompResult::StillNone(c_key),
+                        };
+                        let failed = FailedOp {
+                            value: Some(value),
+                            error: None,
+                        };
+                        (result, Some(failed))
+                    }
                 }
             }
-            Op::Remove => {
-                let maybe_prev_v = cache.invalidate_with_hash(&*c_key, c_hash, true).await;
-                if let Some(prev_v) = maybe_prev_v {
+            Op::Remove => match cache
+                .try_invalidate_with_hash(&*c_key, c_hash, true, true)
+                .await
+            {
+                Ok(Some(prev_v)) => {
                     crossbeam_epoch::pin().flush();
                     let entry = Entry::new(Some(c_key), prev_v, false, false);
-                    Ok(CompResult::Removed(entry))
-                } else {
-                    Ok(CompResult::StillNone(c_key))
+                    (CompResult::Removed(entry), None)
                 }
-            }
-        }
+                Ok(None) => (CompResult::StillNone(c_key), None),
+                // The writer failed, so the entry was not removed.
+                Err(e) => {
+                    let failed = FailedOp {
+                        value: None,
+                        error: Some(e),
+                    };
+                    (unchanged(c_key, maybe_value), Some(failed))
+                }
+            },
+        };
+        Ok(result)
 
         // The lock will be unlocked here.
     }
@@ -406,7 +462,7 @@ where
         f: F,
         post_init: fn(O) -> Result<Op<V>, E>,
         allow_nop: bool,
-    ) -> Result<CompResult<K, V>, E>
+    ) -> Result<Computed<K, V>, E>
     where
         F: FnOnce(Option<Entry<K, V>>) -> Fut,
         Fut: Future<Output = O> + 'a,
@@ -435,14 +491,12 @@ where
             let maybe_value = maybe_entry.as_ref().map(|ent| ent.value().clone());
 
             return if let Some(value) = maybe_value {
-                Ok(CompResult::Unchanged(Entry::new(
-                    Some(c_key),
-                    value,
-                    false,
-                    false,
-                )))
+                Ok((
+                    CompResult::Unchanged(Entry::new(Some(c_key), value, false, false)),
+                    None,
+                ))
             } else {
-                Ok(CompResult::StillNone(c_key))
+                Ok((CompResult::StillNone(c_key), None))
             };
             // The lock will be unlocked here.
         } else {
@@ -462,11 +516,15 @@ where
             .base
             .get_with_hash(&*c_key, c_hash, ignore_if, true, true)
             .await;
-        let maybe_value = if allow_nop {
-            maybe_entry.as_ref().map(|ent| ent.value().clone())
-        } else {
-            None
-        };
+        // Keep the current value for `Op::Nop`, for the case that the writer fails
+        // and the entry is left unchanged, and for the case that the new value is
+        // rejected for its weight and the entry is invalidated.
+        let maybe_value =
+            if allow_nop || cache.base.is_writer_enabled() || cache.base.has_max_entry_weight() {
+                maybe_entry.as_ref().map(|ent| ent.value().clone())
+            } else {
+                None
+            };
         let entry_existed = maybe_entry.is_some();
 
         // Evaluate the `f` closure and get a future. Catching panic is safe here as
@@ -495,43 +553,82 @@ where
             }
         };
 
-        match post_init(output)? {
-            Op::Nop => {
-                if let Some(value) = maybe_value {
-                    Ok(CompResult::Unchanged(Entry::new(
-                        Some(c_key),
-                        value,
-                        false,
-                        false,
-                    )))
-                } else {
-                    Ok(CompResult::StillNone(c_key))
-                }
+        let op = post_init(output)?;
+        let ttl = match &op {
+            Op::PutWithTtl(_, ttl) => Some(*ttl),
+            _ => None,
+        };
+
+        let unchanged = |c_key, maybe_value| {
+            if let Some(value) = maybe_value {
+                CompResult::Unchanged(Entry::new(Some(c_key), value, false, false))
+            } else {
+                CompResult::StillNone(c_key)
             }
-            Op::Put(value) => {
-                cache
-                    .insert_with_hash(Arc::clone(&c_key), c_hash, value.clone())
+        };
+
+        let result = match op {
+            Op::Nop => (unchanged(c_key, maybe_value), None),
+            Op::Put(value) | Op::PutWithTtl(value, _) => {
+                let r = cache
+                    .insert_with_hash_and_ttl(Arc::clone(&c_key), c_hash, value.clone(), ttl)
                     .await;
-                if entry_existed {
-                    crossbeam_epoch::pin().flush();
-                    let entry = Entry::new(Some(c_key), value, true, true);
-                    Ok(CompResult::ReplacedWith(entry))
-                } else {
-                    let entry = Entry::new(Some(c_key), value, true, false);
-                    Ok(CompResult::Inserted(entry))
+                match r {
+                    Ok(()) if entry_existed => {
+                        crossbeam_epoch::pin().flush();
+                        let entry = Entry::new(Some(c_key), value, true, true);
+                        (CompResult::ReplacedWith(entry), None)
+                    }
+                    Ok(()) => {
+                        let entry = Entry::new(Some(c_key), value, true, false);
+                        (CompResult::Inserted(entry), None)
+                    }
+                    // The writer failed, so the entry was not modified.
+                    Err(InsertError::WriterFailed(e)) => {
+                        let failed = FailedOp {
+                            value: Some(value),
+                            error: Some(e),
+                        };
+                        (unchanged(c_key, maybe_value), Some(failed))
+                    }
+                    // The value was rejected for its weight, and the existing entry (if
+                    // any) was invalidated.
+                    Err(InsertError::EntryTooHeavy { .. }) => {
+                        let result = match maybe_value {
+                            Some(prev_v) => {
+                                CompResult::Removed(Entry::new(Some(c_key), prev_v, false, false))
+                            }
+                            None => CompResult::StillNone(c_key),
+                        };
+                        let failed = FailedOp {
+                            value: Some(value),
+                            error: None,
+                        };
+                        (result, Some(failed))
+                    }
                 }
             }
-            Op::Remove => {
-                let maybe_prev_v = cache.invalidate_with_hash(&*c_key, c_hash, true).await;
-                if let Some(prev_v) = maybe_prev_v {
+            Op::Remove => match cache
+                .try_invalidate_with_hash(&*c_key, c_hash, true, true)
+                .await
+            {
+                Ok(Some(prev_v)) => {
                     crossbeam_epoch::pin().flush();
                     let entry = Entry::new(Some(c_key), prev_v, false, false);
-                    Ok(CompResult::Removed(entry))
-                } else {
-                    Ok(CompResult::StillNone(c_key))
+                    (CompResult::Removed(entry), None)
                 }
-            }
-        }
+                Ok(None) => (CompResult::StillNone(c_key), None),
+                // The writer failed, so the entry was not removed.
+                Err(e) => {
+                    let failed = FailedOp {
+                        value: None,
+                        error: Some(e),
+                    };
+                    (unchanged(c_key, maybe_value), Some(failed))
+                }
+            },
+        };
+        Ok(result)
 
         // The lock will be unlocked here.
     }
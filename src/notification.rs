@@ -1,9 +1,24 @@
 //! Common data types for notifications.
 
+#[cfg(feature = "future")]
+mod channel;
+mod event;
 #[cfg(feature = "sync")]
 pub(crate) mod notifier;
 
-use std::{future::Future, pin::Pin, sync::Arc};
+#[cfg(feature = "future")]
+pub use channel::{removal_channel, RemovalSender, RemovalStream};
+
+#[cfg(feature = "sync")]
+pub use event::EventReceiver;
+#[cfg(feature = "future")]
+pub use event::EventStream;
+pub use event::{Backpressure, CacheEvent};
+pub(crate) use event::{EventPublisher, Subscriber};
+
+use std::{any::Any, future::Future, pin::Pin, sync::Arc};
+
+use crate::common::predicate_id::PredicateId;
 
 /// A future returned by an eviction listener.
 ///
@@ -13,21 +28,163 @@ use std::{future::Future, pin::Pin, sync::Arc};
 /// [boxed-method]: ../future/trait.FutureExt.html#method.boxed
 pub type ListenerFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
 
+/// The removal notifications passed to a batch eviction listener.
+pub(crate) type RemovedEntries<K, V> = Vec<(Arc<K>, V, RemovalCause)>;
+
+#[cfg(feature = "sync")]
+pub(crate) enum EvictionListener<K, V> {
+    /// Called once for each removed entry.
+    Single(Arc<dyn Fn(Arc<K>, V, RemovalCause) + Send + Sync + 'static>),
+    /// Called once for each maintenance pass with all the entries removed by the
+    /// pass, or with a single entry removed by other cache operations.
+    Batch(Arc<dyn Fn(RemovedEntries<K, V>) + Send + Sync + 'static>),
+}
+
 #[cfg(feature = "sync")]
-pub(crate) type EvictionListener<K, V> =
-    Arc<dyn Fn(Arc<K>, V, RemovalCause) + Send + Sync + 'static>;
+impl<K, V> Clone for EvictionListener<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Single(listener) => Self::Single(Arc::clone(listener)),
+            Self::Batch(listener) => Self::Batch(Arc::clone(listener)),
+        }
+    }
+}
+
+#[cfg(feature = "future")]
+pub(crate) enum AsyncEvictionListener<K, V> {
+    /// Called once for each removed entry.
+    Single(Box<dyn Fn(Arc<K>, V, RemovalCause) -> ListenerFuture + Send + Sync + 'static>),
+    /// Called once for each maintenance pass with all the entries removed by the
+    /// pass, or with a single entry removed by other cache operations.
+    Batch(Box<dyn Fn(RemovedEntries<K, V>) -> ListenerFuture + Send + Sync + 'static>),
+}
+
+/// The capacity of the notification queue used by [`DeliveryMode::Queued`].
+pub(crate) const NOTIFICATION_QUEUE_SIZE: usize = 1024;
+
+/// Specifies how the eviction listener of a cache is called.
+///
+/// The delivery mode can be set by the `delivery_mode` method of the `sync` cache
+/// builders. The `future` cache builder has the `queued_delivery` method instead,
+/// which takes a function to spawn the delivery task on your async runtime.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeliveryMode {
+    /// The listener is called immediately by the thread (or async task) that
+    /// removed the entry. When an entry is evicted or expired, it is the thread
+    /// running the pending maintenance tasks of the cache, so a slow listener will
+    /// delay the maintenance.
+    ///
+    /// This is the default mode.
+    #[default]
+    Immediate,
+    /// The notifications are sent to a bounded queue, and the listener is called
+    /// by a dedicated thread of the cache (a `SegmentedCache` has one thread shared
+    /// by all the segments), or by an async task for a `future::Cache`. The
+    /// notifications are delivered in the order they were queued, so the
+    /// notifications for the same key are never reordered.
+    ///
+    /// When the queue is full, the thread removing an entry waits until the
+    /// listener catches up. The `wait_for_notifications` method of the cache waits
+    /// until all the queued notifications are delivered.
+    Queued,
+}
+
+/// Specifies what happens to the eviction listener of a cache after it panicked.
+///
+/// The panic policy can be set by the `eviction_listener_panic_policy` method of
+/// the cache builder. Either way, the panic is caught by the cache and counted in
+/// the `eviction_listener_panic_count` of the cache, and the panic payload is passed
+/// to the hook set by the `eviction_listener_panic_hook` method of the builder (if
+/// any).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ListenerPanicPolicy {
+    /// The listener is disabled and will not be called again.
+    ///
+    /// This is the default policy because the cache cannot know whether it is
+    /// memory safe or not to call the panicked listener again.
+    #[default]
+    Disable,
+    /// The listener stays enabled and will be called for the next notifications.
+    ///
+    /// Use this policy only when the listener remains in a consistent state after a
+    /// panic.
+    Continue,
+}
 
+pub(crate) type ListenerPanicHook<K> =
+    Arc<dyn Fn(Box<dyn Any + Send>, &[(Arc<K>, RemovalCause)]) + Send + Sync + 'static>;
+
+/// Spawns the task delivering the queued notifications to an async eviction
+/// listener.
 #[cfg(feature = "future")]
-pub(crate) type AsyncEvictionListener<K, V> =
-    Box<dyn Fn(Arc<K>, V, RemovalCause) -> ListenerFuture + Send + Sync + 'static>;
+pub(crate) type DeliverySpawner = Arc<dyn Fn(ListenerFuture) + Send + Sync + 'static>;
+
+/// How the eviction listener is called. Set by the cache builder.
+pub(crate) struct ListenerConfig<K> {
+    pub(crate) delivery_mode: DeliveryMode,
+    pub(crate) panic_policy: ListenerPanicPolicy,
+    pub(crate) panic_hook: Option<ListenerPanicHook<K>>,
+    /// The delivery thread shared by the segments of a `SegmentedCache`. When
+    /// `None`, each cache starts its own thread for `DeliveryMode::Queued`.
+    #[cfg(feature = "sync")]
+    pub(crate) delivery_thread: Option<Arc<notifier::DeliveryThread>>,
+    /// Spawns the delivery task of an async listener for `DeliveryMode::Queued`.
+    /// Always set together with the mode by the `future` cache builder.
+    #[cfg(feature = "future")]
+    pub(crate) delivery_spawner: Option<DeliverySpawner>,
+}
+
+impl<K> Default for ListenerConfig<K> {
+    fn default() -> Self {
+        Self {
+            delivery_mode: DeliveryMode::default(),
+            panic_policy: ListenerPanicPolicy::default(),
+            panic_hook: None,
+            #[cfg(feature = "sync")]
+            delivery_thread: None,
+            #[cfg(feature = "future")]
+            delivery_spawner: None,
+        }
+    }
+}
 
-// NOTE: Currently, dropping the cache will drop all entries without sending
-// notifications. Calling `invalidate_all` method of the cache will trigger
-// the notifications, but currently there is no way to know when all entries
-// have been invalidated and their notifications have been sent.
+impl<K> Clone for ListenerConfig<K> {
+    fn clone(&self) -> Self {
+        Self {
+            delivery_mode: self.delivery_mode,
+            panic_policy: self.panic_policy,
+            panic_hook: self.panic_hook.clone(),
+            #[cfg(feature = "sync")]
+            delivery_thread: self.delivery_thread.clone(),
+            #[cfg(feature = "future")]
+            delivery_spawner: self.delivery_spawner.clone(),
+        }
+    }
+}
+
+impl<K> ListenerConfig<K> {
+    /// Starts the delivery thread (if `DeliveryMode::Queued`) to be shared by all
+    /// the caches built from this config.
+    #[cfg(feature = "sync")]
+    pub(crate) fn share_delivery_thread(&mut self, cache_name: Option<&str>) {
+        if self.delivery_mode == DeliveryMode::Queued {
+            let thread = notifier::DeliveryThread::new(cache_name);
+            self.delivery_thread = Some(Arc::new(thread));
+        }
+    }
+}
+
+// NOTE: Dropping the cache will drop all entries without sending notifications.
+// Call `close` method of the cache before dropping it to invalidate all entries
+// and wait until their notifications have been delivered.
 
 /// Indicates the reason why a cached entry was removed.
+///
+/// New variants may be added in future releases, so a `match` on this type needs
+/// a wildcard arm.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum RemovalCause {
     /// The entry's expiration timestamp has passed.
     Expired,
@@ -38,10 +195,26 @@ pub enum RemovalCause {
     Replaced,
     /// The entry was evicted due to size constraints.
     Size,
+    /// The entry was rejected at the insert time and was not kept in the cache.
+    /// This happens when its weight exceeds the maximum weight of a single
+    /// entry, or when the admission policy (TinyLFU) did not admit it because it was
+    /// accessed less frequently than the entries that would be evicted for it.
+    Rejected,
+    /// The entry was removed by the `invalidate_all` method of the cache.
+    InvalidatedAll,
+    /// The entry was removed by the `invalidate_entries_if` method of the cache.
+    /// Holds the [`PredicateId`] of the predicate that matched the entry.
+    InvalidatedByPredicate(PredicateId),
+    /// The entry was evicted to shrink the cache after its max capacity was
+    /// reduced by the `set_max_capacity_*` methods.
+    CapacityReduced,
 }
 
 impl RemovalCause {
     pub fn was_evicted(&self) -> bool {
-        matches!(self, Self::Expired | Self::Size)
+        matches!(
+            self,
+            Self::Expired | Self::Size | Self::Rejected | Self::CapacityReduced
+        )
     }
 }
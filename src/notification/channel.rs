@@ -0,0 +1,295 @@
+use super::{Backpressure, RemovalCause};
+
+use crossbeam_channel::{Receiver, Sender, TryRecvError, TrySendError};
+use event_listener::{Event, EventListener};
+use futures_util::Stream;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+/// A removal notification sent through a removal channel.
+type Removal<K, V> = (Arc<K>, V, RemovalCause);
+
+/// Creates a bounded channel to forward the removal notifications of a
+/// `future::Cache` to another async task. Returns the [`RemovalSender`] and the
+/// [`RemovalStream`] of the channel.
+///
+/// Set the sender to a cache by the
+/// [`eviction_listener_channel`][listener-channel] method of the cache builder, and
+/// receive the notifications from the stream on your own task. The eviction
+/// listener of the cache will only send a notification to the channel, so a slow
+/// consumer will not run inside the maintenance tasks of the cache.
+///
+/// The channel holds up to `capacity` (at least one) notifications. When the
+/// channel is full, the given [`Backpressure`] determines what happens:
+///
+/// - `Backpressure::Block`: The cache waits _asynchronously_ until the consumer
+///   receives a notification. It does not block the thread. While it waits, the
+///   cache operation that removed the entry (or the `run_pending_tasks` call
+///   evicting it) does not complete.
+/// - `Backpressure::DropOldest` and `Backpressure::CountDrops`: A notification is
+///   discarded and counted in the [`dropped_count`][dropped-count] of the stream.
+///   The cache never waits for the consumer.
+///
+/// # Cancellation
+///
+/// - The removal notification is never lost by cancelling the cache operation that
+///   removed the entry. If the operation is cancelled while waiting for a room in
+///   the channel, the cache keeps the pending notification and sends it when it
+///   resumes the interrupted operation on a later operation such as `insert` or
+///   `run_pending_tasks`. Such a notification can be received after the
+///   notifications of the entries removed later.
+/// - The same goes for the entries evicted or expired by `run_pending_tasks`. If
+///   the call is cancelled, the cache keeps the interrupted maintenance, including
+///   the notifications not sent yet, and resumes it on the next `run_pending_tasks`
+///   call (or the next maintenance run by other cache operations). The pending
+///   notifications are lost only when the cache is dropped before that.
+/// - Receiving from the stream is cancel safe. Dropping a pending `next()` future
+///   of the stream does not lose a notification.
+/// - Dropping the stream closes the channel. The notifications sent after that are
+///   discarded, and a cache waiting for a room in the channel stops waiting.
+/// - The stream ends when all the senders are dropped, i.e. the cache having the
+///   sender (and all its clones) is dropped, and all the remaining notifications
+///   are received. Call the `close` method of the cache before dropping it to
+///   receive the notifications of all the cached entries.
+///
+/// [listener-channel]: ../future/struct.CacheBuilder.html#method.eviction_listener_channel
+/// [dropped-count]: ./struct.RemovalStream.html#method.dropped_count
+///
+/// # Example
+///
+/// ```rust
+/// // Cargo.toml
+/// //
+/// // [dependencies]
+/// // moka = { version = "0.12", features = ["future"] }
+/// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+/// // futures-util = "0.3"
+///
+/// use futures_util::StreamExt;
+/// use moka::{
+///     future::Cache,
+///     notification::{self, Backpressure, RemovalCause},
+/// };
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let (sender, mut removals) = notification::removal_channel(16, Backpressure::Block);
+///
+///     // Process the removal notifications on a separate task.
+///     let consumer = tokio::spawn(async move {
+///         let mut removed = Vec::new();
+///         while let Some((key, value, cause)) = removals.next().await {
+///             removed.push((*key, value, cause));
+///         }
+///         removed
+///     });
+///
+///     let cache = Cache::builder()
+///         .max_capacity(100)
+///         .eviction_listener_channel(sender)
+///         .build();
+///
+///     cache.insert(1, "one").await;
+///     cache.invalidate(&1).await;
+///
+///     // Dropping the cache ends the stream.
+///     drop(cache);
+///     let removed = consumer.await.unwrap();
+///     assert_eq!(removed, vec![(1, "one", RemovalCause::Explicit)]);
+/// }
+/// ```
+pub fn removal_channel<K, V>(
+    capacity: usize,
+    backpressure: Backpressure,
+) -> (RemovalSender<K, V>, RemovalStream<K, V>) {
+    let (sender, receiver) = crossbeam_channel::bounded(capacity.max(1));
+    let state = Arc::new(ChannelState::default());
+    let stream = RemovalStream {
+        receiver: receiver.clone(),
+        state: Arc::clone(&state),
+        listener: None,
+    };
+    let sender = RemovalSender {
+        sender: Some(sender),
+        receiver,
+        backpressure,
+        state,
+    };
+    (sender, stream)
+}
+
+/// The state shared between the senders and the stream of a removal channel.
+#[derive(Default)]
+struct ChannelState {
+    dropped_count: AtomicU64,
+    /// `true` if the stream has been dropped.
+    is_closed: AtomicBool,
+    /// Notified when a notification is sent or a sender is dropped.
+    ready: Event,
+    /// Notified when a notification is received or the stream is dropped.
+    room: Event,
+}
+
+impl ChannelState {
+    fn is_closed(&self) -> bool {
+        self.is_closed.load(Ordering::Acquire)
+    }
+}
+
+/// The sending half of a removal channel, created by the [`removal_channel`]
+/// function.
+///
+/// It can be cloned to send the removal notifications of multiple caches to the
+/// same stream.
+pub struct RemovalSender<K, V> {
+    // This is `None` only while dropping.
+    sender: Option<Sender<Removal<K, V>>>,
+    // Used to discard the oldest notification on `Backpressure::DropOldest`.
+    receiver: Receiver<Removal<K, V>>,
+    backpressure: Backpressure,
+    state: Arc<ChannelState>,
+}
+
+impl<K, V> Clone for RemovalSender<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            backpressure: self.backpressure,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<K, V> Drop for RemovalSender<K, V> {
+    fn drop(&mut self) {
+        // Drop the sender first, then wake up the stream so that it can find that
+        // the channel has been disconnected.
+        drop(self.sender.take());
+        self.state.ready.notify(usize::MAX);
+    }
+}
+
+impl<K, V> RemovalSender<K, V> {
+    /// Sends the removal notification to the channel. Returns immediately if the
+    /// stream has been dropped.
+    pub(crate) async fn send(&self, mut removal: Removal<K, V>) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if self.state.is_closed() {
+            return;
+        }
+
+        match self.backpressure {
+            Backpressure::DropOldest => loop {
+                match sender.try_send(removal) {
+                    Ok(()) => break,
+                    Err(TrySendError::Full(r)) => {
+                        if self.receiver.try_recv().is_ok() {
+                            self.state.dropped_count.fetch_add(1, Ordering::AcqRel);
+                        }
+                        removal = r;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            },
+            Backpressure::Block => {
+                let mut listener: Option<EventListener> = None;
+                loop {
+                    match sender.try_send(removal) {
+                        Ok(()) => break,
+                        Err(TrySendError::Full(r)) => removal = r,
+                        Err(TrySendError::Disconnected(_)) => return,
+                    }
+                    if self.state.is_closed() {
+                        return;
+                    }
+                    match listener.take() {
+                        // Start listening, and then try again so that we will not
+                        // miss a room made before the listener was created.
+                        None => listener = Some(self.state.room.listen()),
+                        Some(l) => l.await,
+                    }
+                }
+            }
+            Backpressure::CountDrops => match sender.try_send(removal) {
+                Ok(()) => (),
+                Err(TrySendError::Full(_)) => {
+                    self.state.dropped_count.fetch_add(1, Ordering::AcqRel);
+                }
+                Err(TrySendError::Disconnected(_)) => return,
+            },
+        }
+
+        self.state.ready.notify(usize::MAX);
+    }
+}
+
+/// A [`Stream`][stream] of the removal notifications of a cache, created by the
+/// [`removal_channel`] function.
+///
+/// Each item is a tuple of the key, the value and the [`RemovalCause`] of the
+/// removed entry. See [`removal_channel`] for the cancellation semantics.
+///
+/// [stream]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+pub struct RemovalStream<K, V> {
+    receiver: Receiver<Removal<K, V>>,
+    state: Arc<ChannelState>,
+    listener: Option<EventListener>,
+}
+
+// The stream does not pin any of its fields.
+impl<K, V> Unpin for RemovalStream<K, V> {}
+
+impl<K, V> Drop for RemovalStream<K, V> {
+    fn drop(&mut self) {
+        self.state.is_closed.store(true, Ordering::Release);
+        // Wake up the senders waiting for a room.
+        self.state.room.notify(usize::MAX);
+    }
+}
+
+impl<K, V> RemovalStream<K, V> {
+    /// Returns the number of the notifications discarded by the [`Backpressure`]
+    /// of this channel.
+    pub fn dropped_count(&self) -> u64 {
+        self.state.dropped_count.load(Ordering::Acquire)
+    }
+}
+
+impl<K, V> Stream for RemovalStream<K, V> {
+    type Item = (Arc<K>, V, RemovalCause);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.receiver.try_recv() {
+                Ok(removal) => {
+                    this.listener = None;
+                    this.state.room.notify(usize::MAX);
+                    return Poll::Ready(Some(removal));
+                }
+                Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+                Err(TryRecvError::Empty) => (),
+            }
+
+            match &mut this.listener {
+                // Start listening, and then check the channel again so that we will
+                // not miss a notification sent before the listener was created.
+                None => this.listener = Some(this.state.ready.listen()),
+                Some(listener) => match Pin::new(listener).poll(cx) {
+                    Poll::Ready(()) => this.listener = None,
+                    Poll::Pending => return Poll::Pending,
+                },
+            }
+        }
+    }
+}
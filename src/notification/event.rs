@@ -0,0 +1,540 @@
+use super::RemovalCause;
+
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender, TrySendError};
+use parking_lot::RwLock;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// The interval to check whether the subscriber has gone while a writer is blocked
+/// by [`Backpressure::Block`].
+const BLOCK_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A mutation of a cache, delivered to the subscribers registered by the
+/// `subscribe` method of the cache.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CacheEvent<K, V> {
+    /// A new entry was inserted by an insert method, an `and_upsert_with` or
+    /// `and_compute_with` method of the entry API, etc.
+    Inserted { key: Arc<K>, value: V },
+    /// The value of an existing entry was replaced with a new value.
+    Updated { key: Arc<K>, value: V },
+    /// An entry was removed from the cache. `value` is the removed value.
+    Removed {
+        key: Arc<K>,
+        value: V,
+        cause: RemovalCause,
+    },
+    /// A value was loaded by `get_with`, `try_get_with`, `optionally_get_with` or
+    /// the similar methods of the entry API, and it was stored in the cache.
+    Loaded { key: Arc<K>, value: V },
+}
+
+impl<K, V: Clone> Clone for CacheEvent<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Inserted { key, value } => Self::Inserted {
+                key: Arc::clone(key),
+                value: value.clone(),
+            },
+            Self::Updated { key, value } => Self::Updated {
+                key: Arc::clone(key),
+                value: value.clone(),
+            },
+            Self::Removed { key, value, cause } => Self::Removed {
+                key: Arc::clone(key),
+                value: value.clone(),
+                cause: *cause,
+            },
+            Self::Loaded { key, value } => Self::Loaded {
+                key: Arc::clone(key),
+                value: value.clone(),
+            },
+        }
+    }
+}
+
+impl<K, V> CacheEvent<K, V> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Arc<K> {
+        match self {
+            Self::Inserted { key, .. }
+            | Self::Updated { key, .. }
+            | Self::Removed { key, .. }
+            | Self::Loaded { key, .. } => key,
+        }
+    }
+
+    /// Returns the value of the entry. For a `Removed` event, this is the removed
+    /// value.
+    pub fn value(&self) -> &V {
+        match self {
+            Self::Inserted { value, .. }
+            | Self::Updated { value, .. }
+            | Self::Removed { value, .. }
+            | Self::Loaded { value, .. } => value,
+        }
+    }
+}
+
+/// Determines what happens when an event is published to a subscriber whose
+/// channel is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Discards the oldest event in the channel to make room for the new event.
+    /// The discarded events are counted.
+    DropOldest,
+    /// Makes the cache operation wait until the subscriber receives an event or
+    /// drops the receiver. A `sync` cache blocks the thread writing to the cache,
+    /// and a `future::Cache` waits _asynchronously_ without blocking the thread.
+    ///
+    /// Note that removals by the eviction and expiration are published while
+    /// running the pending tasks of the cache, so a slow subscriber will also delay
+    /// the maintenance of the cache.
+    Block,
+    /// Discards the new event. The discarded events are counted.
+    #[default]
+    CountDrops,
+}
+
+/// The state shared between a subscriber and its receiver.
+#[derive(Default)]
+struct SubscriptionState {
+    dropped_count: AtomicU64,
+    is_closed: AtomicBool,
+    /// Notified when an event is sent.
+    #[cfg(feature = "future")]
+    ready: event_listener::Event,
+    /// Notified when an event is received by an `EventStream` or the stream is
+    /// dropped.
+    #[cfg(feature = "future")]
+    room: event_listener::Event,
+}
+
+impl SubscriptionState {
+    fn is_closed(&self) -> bool {
+        self.is_closed.load(Ordering::Acquire)
+    }
+
+    fn close(&self) {
+        self.is_closed.store(true, Ordering::Release);
+    }
+
+    fn incr_dropped_count(&self) {
+        self.dropped_count.fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn wake(&self) {
+        #[cfg(feature = "future")]
+        self.ready.notify(usize::MAX);
+    }
+}
+
+/// The sending half of a subscription. A `SegmentedCache` registers clones of it
+/// to all of its segments.
+pub(crate) struct Subscriber<K, V> {
+    sender: Sender<CacheEvent<K, V>>,
+    // Used to discard the oldest event on `Backpressure::DropOldest`.
+    receiver: Receiver<CacheEvent<K, V>>,
+    backpressure: Backpressure,
+    state: Arc<SubscriptionState>,
+}
+
+impl<K, V> Clone for Subscriber<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            backpressure: self.backpressure,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<K, V> Subscriber<K, V> {
+    /// Creates a subscriber with a channel of the given capacity. The capacity
+    /// will be at least one.
+    pub(crate) fn new(capacity: usize, backpressure: Backpressure) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(capacity.max(1));
+        Self {
+            sender,
+            receiver,
+            backpressure,
+            state: Arc::default(),
+        }
+    }
+
+    /// Sends the event to the subscriber. With `Backpressure::Block`, blocks the
+    /// current thread while the channel is full.
+    fn send(&self, mut event: CacheEvent<K, V>) {
+        if self.state.is_closed() {
+            return;
+        }
+
+        match self.backpressure {
+            Backpressure::DropOldest => loop {
+                match self.sender.try_send(event) {
+                    Ok(()) => break,
+                    Err(TrySendError::Full(ev)) => {
+                        if self.receiver.try_recv().is_ok() {
+                            self.state.incr_dropped_count();
+                        }
+                        event = ev;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            },
+            Backpressure::Block => loop {
+                match self.sender.send_timeout(event, BLOCK_CHECK_INTERVAL) {
+                    Ok(()) => break,
+                    Err(SendTimeoutError::Timeout(ev)) => {
+                        if self.state.is_closed() {
+                            return;
+                        }
+                        event = ev;
+                    }
+                    Err(SendTimeoutError::Disconnected(_)) => return,
+                }
+            },
+            Backpressure::CountDrops => match self.sender.try_send(event) {
+                Ok(()) => (),
+                Err(TrySendError::Full(_)) => self.state.incr_dropped_count(),
+                Err(TrySendError::Disconnected(_)) => return,
+            },
+        }
+
+        self.state.wake();
+    }
+
+    /// Sends the event to the subscriber. With `Backpressure::Block`, waits
+    /// asynchronously while the channel is full.
+    #[cfg(feature = "future")]
+    async fn send_async(&self, mut event: CacheEvent<K, V>) {
+        if self.backpressure != Backpressure::Block {
+            // Never waits.
+            self.send(event);
+            return;
+        }
+
+        let mut listener: Option<event_listener::EventListener> = None;
+        loop {
+            if self.state.is_closed() {
+                return;
+            }
+            match self.sender.try_send(event) {
+                Ok(()) => break,
+                Err(TrySendError::Full(ev)) => event = ev,
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+            match listener.take() {
+                // Start listening, and then try again so that we will not miss a
+                // room made before the listener was created.
+                None => listener = Some(self.state.room.listen()),
+                Some(l) => l.await,
+            }
+        }
+
+        self.state.wake();
+    }
+}
+
+/// An event taken out of the [`EventPublisher`] together with its subscribers, so
+/// that the lock of the subscribers is not held while sending the event.
+pub(crate) struct Publication<K, V> {
+    event: CacheEvent<K, V>,
+    subscribers: Vec<Subscriber<K, V>>,
+}
+
+impl<K, V: Clone> Publication<K, V> {
+    /// Sends the event to the subscribers, blocking the current thread while a
+    /// channel with `Backpressure::Block` is full.
+    #[cfg(feature = "sync")]
+    pub(crate) fn send(self) {
+        for subscriber in &self.subscribers {
+            subscriber.send(self.event.clone());
+        }
+    }
+
+    /// Sends the event to the subscribers, waiting asynchronously while a channel
+    /// with `Backpressure::Block` is full.
+    #[cfg(feature = "future")]
+    pub(crate) async fn send_async(self) {
+        for subscriber in &self.subscribers {
+            subscriber.send_async(self.event.clone()).await;
+        }
+    }
+}
+
+/// Publishes the events of a cache to its subscribers.
+pub(crate) struct EventPublisher<K, V> {
+    subscribers: RwLock<Vec<Subscriber<K, V>>>,
+    has_subscribers: AtomicBool,
+}
+
+impl<K, V> Default for EventPublisher<K, V> {
+    fn default() -> Self {
+        Self {
+            subscribers: RwLock::default(),
+            has_subscribers: AtomicBool::default(),
+        }
+    }
+}
+
+impl<K, V> Drop for EventPublisher<K, V> {
+    fn drop(&mut self) {
+        // Drop the senders first, then wake up the async receivers so that they
+        // can find that the channels have been disconnected.
+        let subscribers = std::mem::take(self.subscribers.get_mut());
+        let states = subscribers
+            .iter()
+            .map(|s| Arc::clone(&s.state))
+            .collect::<Vec<_>>();
+        drop(subscribers);
+        for state in states {
+            state.wake();
+        }
+    }
+}
+
+impl<K, V> EventPublisher<K, V> {
+    #[inline]
+    pub(crate) fn has_subscribers(&self) -> bool {
+        self.has_subscribers.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn subscribe(&self, subscriber: Subscriber<K, V>) {
+        self.subscribers.write().push(subscriber);
+        self.has_subscribers.store(true, Ordering::Release);
+    }
+
+    /// Publishes the event created by `make_event` to the subscribers, blocking
+    /// the current thread while a channel with `Backpressure::Block` is full. The
+    /// event will not be created if there is no subscriber.
+    #[cfg(feature = "sync")]
+    pub(crate) fn publish(&self, make_event: impl FnOnce() -> CacheEvent<K, V>)
+    where
+        V: Clone,
+    {
+        if let Some(publication) = self.prepare(make_event) {
+            publication.send();
+        }
+    }
+
+    /// Publishes the event created by `make_event` to the subscribers, waiting
+    /// asynchronously while a channel with `Backpressure::Block` is full. The
+    /// event will not be created if there is no subscriber.
+    #[cfg(feature = "future")]
+    pub(crate) async fn publish_async(&self, make_event: impl FnOnce() -> CacheEvent<K, V>)
+    where
+        V: Clone,
+    {
+        if let Some(publication) = self.prepare(make_event) {
+            publication.send_async().await;
+        }
+    }
+
+    /// Creates the event with `make_event` and returns it with the current
+    /// subscribers, or returns `None` if there is no subscriber. The subscribers
+    /// whose receivers have been dropped are unsubscribed here.
+    pub(crate) fn prepare(
+        &self,
+        make_event: impl FnOnce() -> CacheEvent<K, V>,
+    ) -> Option<Publication<K, V>> {
+        if !self.has_subscribers() {
+            return None;
+        }
+
+        let subscribers = {
+            let subscribers = self.subscribers.read();
+            if subscribers.iter().all(|s| !s.state.is_closed()) {
+                subscribers.clone()
+            } else {
+                drop(subscribers);
+                let mut subscribers = self.subscribers.write();
+                subscribers.retain(|s| !s.state.is_closed());
+                self.has_subscribers
+                    .store(!subscribers.is_empty(), Ordering::Release);
+                subscribers.clone()
+            }
+        };
+
+        if subscribers.is_empty() {
+            return None;
+        }
+        Some(Publication {
+            event: make_event(),
+            subscribers,
+        })
+    }
+}
+
+//
+// for testing
+//
+#[cfg(all(test, feature = "sync"))]
+impl<K, V> EventPublisher<K, V> {
+    pub(crate) fn subscriber_count(&self) -> usize {
+        self.subscribers.read().len()
+    }
+}
+
+#[cfg(feature = "sync")]
+/// A receiver of the [`CacheEvent`]s of a cache, returned by the `subscribe`
+/// method of `sync::Cache` and `sync::SegmentedCache`.
+///
+/// The receiver will be disconnected when the cache is dropped. Dropping the
+/// receiver unsubscribes it from the cache.
+pub struct EventReceiver<K, V> {
+    receiver: Receiver<CacheEvent<K, V>>,
+    state: Arc<SubscriptionState>,
+}
+
+#[cfg(feature = "sync")]
+impl<K, V> Drop for EventReceiver<K, V> {
+    fn drop(&mut self) {
+        self.state.close();
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<K, V> EventReceiver<K, V> {
+    pub(crate) fn new(subscriber: &Subscriber<K, V>) -> Self {
+        Self {
+            receiver: subscriber.receiver.clone(),
+            state: Arc::clone(&subscriber.state),
+        }
+    }
+
+    /// Blocks the current thread until an event is received. Returns `None` if the
+    /// cache has been dropped and all events have been received.
+    pub fn recv(&self) -> Option<CacheEvent<K, V>> {
+        self.receiver.recv().ok()
+    }
+
+    /// Blocks the current thread until an event is received or the timeout
+    /// elapses. Returns `None` on timeout, or if the cache has been dropped and all
+    /// events have been received.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<CacheEvent<K, V>> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Receives an event without blocking. Returns `None` if there is no event in
+    /// the channel.
+    pub fn try_recv(&self) -> Option<CacheEvent<K, V>> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Returns an iterator that receives the events in the channel without
+    /// blocking.
+    pub fn try_iter(&self) -> impl Iterator<Item = CacheEvent<K, V>> + '_ {
+        self.receiver.try_iter()
+    }
+
+    /// Returns the number of the events in the channel.
+    pub fn len(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Returns `true` if there is no event in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.receiver.is_empty()
+    }
+
+    /// Returns the number of the events discarded by the [`Backpressure`] of this
+    /// subscription.
+    pub fn dropped_count(&self) -> u64 {
+        self.state.dropped_count.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(feature = "future")]
+pub use stream::EventStream;
+
+#[cfg(feature = "future")]
+mod stream {
+    use super::{CacheEvent, Subscriber, SubscriptionState};
+
+    use crossbeam_channel::{Receiver, TryRecvError};
+    use event_listener::EventListener;
+    use futures_util::Stream;
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{atomic::Ordering, Arc},
+        task::{Context, Poll},
+    };
+
+    /// A [`Stream`][stream] of the [`CacheEvent`]s of a cache, returned by the
+    /// `subscribe` method of `future::Cache`.
+    ///
+    /// The stream will end when the cache is dropped. Dropping the stream
+    /// unsubscribes it from the cache.
+    ///
+    /// [stream]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+    pub struct EventStream<K, V> {
+        receiver: Receiver<CacheEvent<K, V>>,
+        state: Arc<SubscriptionState>,
+        listener: Option<EventListener>,
+    }
+
+    // The stream does not pin any of its fields.
+    impl<K, V> Unpin for EventStream<K, V> {}
+
+    impl<K, V> Drop for EventStream<K, V> {
+        fn drop(&mut self) {
+            self.state.close();
+            // Wake up the cache operations waiting for a room.
+            self.state.room.notify(usize::MAX);
+        }
+    }
+
+    impl<K, V> EventStream<K, V> {
+        pub(crate) fn new(subscriber: &Subscriber<K, V>) -> Self {
+            Self {
+                receiver: subscriber.receiver.clone(),
+                state: Arc::clone(&subscriber.state),
+                listener: None,
+            }
+        }
+
+        /// Returns the number of the events discarded by the
+        /// [`Backpressure`](super::Backpressure) of this subscription.
+        pub fn dropped_count(&self) -> u64 {
+            self.state.dropped_count.load(Ordering::Acquire)
+        }
+    }
+
+    impl<K, V> Stream for EventStream<K, V> {
+        type Item = CacheEvent<K, V>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.get_mut();
+            loop {
+                match this.receiver.try_recv() {
+                    Ok(event) => {
+                        this.listener = None;
+                        this.state.room.notify(usize::MAX);
+                        return Poll::Ready(Some(event));
+                    }
+                    Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+                    Err(TryRecvError::Empty) => (),
+                }
+
+                match &mut this.listener {
+                    // Start listening, and then check the channel again so that we
+                    // will not miss an event sent before the listener was created.
+                    None => this.listener = Some(this.state.ready.listen()),
+                    Some(listener) => match Pin::new(listener).poll(cx) {
+                        Poll::Ready(()) => this.listener = None,
+                        Poll::Pending => return Poll::Pending,
+                    },
+                }
+            }
+        }
+    }
+}
@@ -1,49 +1,321 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, ThreadId},
 };
 
-use crate::notification::{EvictionListener, RemovalCause};
+use crossbeam_channel::Sender;
+use parking_lot::{Condvar, Mutex};
+
+use crate::notification::{
+    DeliveryMode, EvictionListener, ListenerConfig, ListenerPanicHook, ListenerPanicPolicy,
+    RemovalCause, RemovedEntries, NOTIFICATION_QUEUE_SIZE,
+};
 
 pub(crate) struct RemovalNotifier<K, V> {
+    listener: Arc<ListenerCaller<K, V>>,
+    queue: Option<NotificationQueue>,
+}
+
+impl<K, V> RemovalNotifier<K, V> {
+    pub(crate) fn new(
+        listener: EvictionListener<K, V>,
+        config: ListenerConfig<K>,
+        cache_name: Option<String>,
+    ) -> Self
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        let queue = match config.delivery_mode {
+            DeliveryMode::Immediate => None,
+            DeliveryMode::Queued => {
+                let thread = config
+                    .delivery_thread
+                    .clone()
+                    .unwrap_or_else(|| Arc::new(DeliveryThread::new(cache_name.as_deref())));
+                Some(NotificationQueue::new(thread))
+            }
+        };
+        let listener = Arc::new(ListenerCaller::new(listener, config, cache_name));
+        Self { listener, queue }
+    }
+
+    pub(crate) fn notify(&self, key: Arc<K>, value: V, cause: RemovalCause)
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        if let Some(queue) = &self.queue {
+            let listener = Arc::clone(&self.listener);
+            queue.push(Box::new(move || listener.call(key, value, cause)));
+        } else {
+            self.listener.call(key, value, cause);
+        }
+    }
+
+    /// Notifies the removed entries at once. A batch listener will be called once
+    /// with all the entries, and a single entry listener will be called for each
+    /// entry.
+    pub(crate) fn notify_batch(&self, entries: RemovedEntries<K, V>)
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        if entries.is_empty() {
+            return;
+        }
+        if let Some(queue) = &self.queue {
+            let listener = Arc::clone(&self.listener);
+            queue.push(Box::new(move || listener.call_batch(entries)));
+        } else {
+            self.listener.call_batch(entries);
+        }
+    }
+
+    /// Returns `true` if the listener is a batch listener.
+    pub(crate) fn is_batch(&self) -> bool {
+        matches!(self.listener.listener, EvictionListener::Batch(_))
+    }
+
+    /// Blocks the current thread until the listener has been called for all the
+    /// notifications queued so far. Returns immediately on `DeliveryMode::Immediate`,
+    /// or when called by a listener running on the delivery thread, which would
+    /// otherwise wait for itself.
+    pub(crate) fn wait_for_notifications(&self) {
+        if let Some(queue) = &self.queue {
+            queue.wait();
+        }
+    }
+
+    /// Returns the number of times the listener panicked.
+    pub(crate) fn panic_count(&self) -> u64 {
+        self.listener.panic_count.load(Ordering::Acquire)
+    }
+}
+
+/// The keys and causes of the notifications passed to the panic hook.
+type HookEntries<K> = Vec<(Arc<K>, RemovalCause)>;
+
+struct ListenerCaller<K, V> {
     listener: EvictionListener<K, V>,
     is_enabled: AtomicBool,
+    panic_policy: ListenerPanicPolicy,
+    panic_hook: Option<ListenerPanicHook<K>>,
+    panic_count: AtomicU64,
     #[cfg(feature = "logging")]
     cache_name: Option<String>,
 }
 
-impl<K, V> RemovalNotifier<K, V> {
-    pub(crate) fn new(listener: EvictionListener<K, V>, _cache_name: Option<String>) -> Self {
+impl<K, V> ListenerCaller<K, V> {
+    fn new(
+        listener: EvictionListener<K, V>,
+        config: ListenerConfig<K>,
+        _cache_name: Option<String>,
+    ) -> Self {
         Self {
             listener,
             is_enabled: AtomicBool::new(true),
+            panic_policy: config.panic_policy,
+            panic_hook: config.panic_hook,
+            panic_count: AtomicU64::default(),
             #[cfg(feature = "logging")]
             cache_name: _cache_name,
         }
     }
 
-    pub(crate) fn notify(&self, key: Arc<K>, value: V, cause: RemovalCause) {
-        use std::panic::{catch_unwind, AssertUnwindSafe};
+    fn call(&self, key: Arc<K>, value: V, cause: RemovalCause) {
+        if !self.is_enabled.load(Ordering::Acquire) {
+            return;
+        }
 
+        match &self.listener {
+            EvictionListener::Single(listener) => {
+                let for_hook = self.for_hook(&key, &cause);
+                self.call_guarded(|| listener(key, value, cause), for_hook);
+            }
+            EvictionListener::Batch(_) => self.call_batch(vec![(key, value, cause)]),
+        }
+    }
+
+    fn call_batch(&self, entries: RemovedEntries<K, V>) {
         if !self.is_enabled.load(Ordering::Acquire) {
             return;
         }
 
-        let listener_clo = || (self.listener)(key, value, cause);
+        match &self.listener {
+            EvictionListener::Single(_) => {
+                for (key, value, cause) in entries {
+                    self.call(key, value, cause);
+                }
+            }
+            EvictionListener::Batch(listener) => {
+                if entries.is_empty() {
+                    return;
+                }
+                // The panic hook takes the keys and causes of all the entries.
+                let for_hook = self.panic_hook.as_ref().map(|_| {
+                    entries
+                        .iter()
+                        .map(|(key, _, cause)| (Arc::clone(key), *cause))
+                        .collect()
+                });
+                self.call_guarded(|| listener(entries), for_hook);
+            }
+        }
+    }
+
+    /// Clones the key and cause for the panic hook, as they will be moved into the
+    /// listener.
+    fn for_hook(&self, key: &Arc<K>, cause: &RemovalCause) -> Option<HookEntries<K>> {
+        self.panic_hook
+            .as_ref()
+            .map(|_| vec![(Arc::clone(key), *cause)])
+    }
+
+    fn call_guarded(&self, listener_clo: impl FnOnce(), for_hook: Option<HookEntries<K>>) {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
 
         // Safety: It is safe to assert unwind safety here because we will not
-        // call the listener again if it has been panicked.
+        // call the listener again if it has been panicked, unless the user opted in
+        // by `ListenerPanicPolicy::Continue`.
         let result = catch_unwind(AssertUnwindSafe(listener_clo));
-        if let Err(_payload) = result {
+        if let Err(payload) = result {
+            self.handle_panic(payload, for_hook);
+        }
+    }
+
+    fn handle_panic(&self, payload: Box<dyn Any + Send>, for_hook: Option<HookEntries<K>>) {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        self.panic_count.fetch_add(1, Ordering::AcqRel);
+        let disabled = self.panic_policy == ListenerPanicPolicy::Disable;
+        if disabled {
             self.is_enabled.store(false, Ordering::Release);
-            #[cfg(feature = "logging")]
-            log_panic(&*_payload, self.cache_name.as_deref());
         }
+
+        #[cfg(feature = "logging")]
+        log_panic(&*payload, self.cache_name.as_deref(), disabled);
+
+        if let (Some(hook), Some(entries)) = (&self.panic_hook, for_hook) {
+            let _ = catch_unwind(AssertUnwindSafe(|| hook(payload, &entries)));
+        }
+    }
+}
+
+/// A notification to deliver, i.e. a call to the listener of a cache.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The thread calling the eviction listeners for `DeliveryMode::Queued`. The
+/// segments of a `SegmentedCache` share one thread. The thread exits when all the
+/// queues using it are dropped and the remaining notifications are delivered.
+pub(crate) struct DeliveryThread {
+    sender: Sender<Job>,
+    thread_id: ThreadId,
+}
+
+impl DeliveryThread {
+    pub(crate) fn new(cache_name: Option<&str>) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded::<Job>(NOTIFICATION_QUEUE_SIZE);
+        let handle = thread::Builder::new()
+            .name(thread_name(cache_name))
+            .spawn(move || {
+                for job in receiver {
+                    job();
+                }
+            })
+            .expect("Failed to spawn the notification thread");
+
+        Self {
+            sender,
+            thread_id: handle.thread().id(),
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        thread::current().id() == self.thread_id
+    }
+}
+
+/// A bounded FIFO queue of notifications for `DeliveryMode::Queued`. A single
+/// thread calls the listener in the order of the notifications, so the
+/// notifications for the same key are delivered in order.
+struct NotificationQueue {
+    thread: Arc<DeliveryThread>,
+    progress: Arc<Progress>,
+}
+
+impl NotificationQueue {
+    fn new(thread: Arc<DeliveryThread>) -> Self {
+        Self {
+            thread,
+            progress: Arc::default(),
+        }
+    }
+
+    fn push(&self, job: Job) {
+        self.progress.enqueued.fetch_add(1, Ordering::AcqRel);
+        let progress = Arc::clone(&self.progress);
+        let job = Box::new(move || {
+            job();
+            progress.incr_delivered();
+        });
+        // This will block when the queue is full.
+        if self.thread.sender.send(job).is_err() {
+            // The thread has gone. Count it as delivered so that the waiters will
+            // not block forever.
+            self.progress.incr_delivered();
+        }
+    }
+
+    fn wait(&self) {
+        // A listener waiting on the delivery thread would wait for itself.
+        if self.thread.is_current() {
+            return;
+        }
+        let target = self.progress.enqueued.load(Ordering::Acquire);
+        let mut delivered = self.progress.delivered.lock();
+        while *delivered < target {
+            self.progress.cond.wait(&mut delivered);
+        }
+    }
+}
+
+impl Drop for NotificationQueue {
+    fn drop(&mut self) {
+        // Wait for the thread to deliver the remaining notifications. Do not wait
+        // if the cache is being dropped by the listener running on the thread
+        // itself.
+        self.wait();
+    }
+}
+
+#[derive(Default)]
+struct Progress {
+    enqueued: AtomicU64,
+    delivered: Mutex<u64>,
+    cond: Condvar,
+}
+
+impl Progress {
+    fn incr_delivered(&self) {
+        *self.delivered.lock() += 1;
+        self.cond.notify_all();
+    }
+}
+
+fn thread_name(cache_name: Option<&str>) -> String {
+    match cache_name {
+        Some(name) => format!("moka-notifier-{name}"),
+        None => "moka-notifier".to_string(),
     }
 }
 
 #[cfg(feature = "logging")]
-fn log_panic(payload: &(dyn std::any::Any + Send + 'static), cache_name: Option<&str>) {
+fn log_panic(payload: &(dyn Any + Send + 'static), cache_name: Option<&str>, disabled: bool) {
     // Try to downcast the payload into &str or String.
     //
     // NOTE: Clippy will complain if we use `if let Some(_)` here.
@@ -56,9 +328,15 @@ fn log_panic(payload: &(dyn std::any::Any + Send + 'static), cache_name: Option<
         .map(|name| format!("[{name}] "))
         .unwrap_or_default();
 
+    let action = if disabled {
+        "Disabled the eviction listener because it panicked"
+    } else {
+        "The eviction listener panicked"
+    };
+
     if let Some(m) = message {
-        log::error!("{cn}Disabled the eviction listener because it panicked at '{m}'");
+        log::error!("{cn}{action} at '{m}'");
     } else {
-        log::error!("{cn}Disabled the eviction listener because it panicked");
+        log::error!("{cn}{action}");
     }
 }
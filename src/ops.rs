@@ -2,18 +2,28 @@
 
 /// Operations used by the `and_compute_with` and similar methods.
 pub mod compute {
-    use std::sync::Arc;
+    use std::{sync::Arc, time::Duration};
 
     use crate::Entry;
 
     /// Instructs the `and_compute_with` and similar methods how to modify the cached
     /// entry.
+    ///
+    /// More operations may be added in the future, so a `match` on `Op` outside of
+    /// this crate needs a wildcard arm.
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[non_exhaustive]
     pub enum Op<V> {
         /// No-operation. Do not modify the cached entry.
         Nop,
         /// Insert or update the value of the cached entry.
         Put(V),
+        /// Insert or update the value of the cached entry, and set the time-to-live
+        /// of the entry to the given duration.
+        ///
+        /// See `insert_with_ttl` method of the cache for how the time-to-live is
+        /// applied to the entry.
+        PutWithTtl(V, Duration),
         /// Remove the cached entry.
         Remove,
     }
@@ -5,27 +5,70 @@ use std::{
     time::{Duration, Instant},
 };
 
+#[cfg(feature = "future")]
+use std::{future::Future, pin::Pin};
+
+use crate::common::HousekeeperConfig;
+
 #[derive(Clone, Debug)]
 /// The policy of a cache.
 pub struct Policy {
     max_capacity: Option<u64>,
+    initial_capacity: Option<usize>,
     num_segments: usize,
+    eviction_policy: EvictionPolicy,
     time_to_live: Option<Duration>,
     time_to_idle: Option<Duration>,
+    timer_wheel_precision: Duration,
+    has_weigher: bool,
+    has_expiry: bool,
+    has_eviction_listener: bool,
+    supports_invalidation_closures: bool,
+    maintenance_task_timeout: Option<Duration>,
+    max_log_sync_repeats: u32,
+    eviction_batch_size: u32,
+    proactive_expiration: Option<Duration>,
+    pinned_weighted_size: u64,
 }
 
 impl Policy {
+    // https://rust-lang.github.io/rust-clippy/master/index.html#too_many_arguments
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         max_capacity: Option<u64>,
+        initial_capacity: Option<usize>,
         num_segments: usize,
+        eviction_policy: EvictionPolicy,
         time_to_live: Option<Duration>,
         time_to_idle: Option<Duration>,
+        timer_wheel_precision: Duration,
+        has_weigher: bool,
+        has_expiry: bool,
+        has_eviction_listener: bool,
+        supports_invalidation_closures: bool,
+        housekeeper_config: &HousekeeperConfig,
     ) -> Self {
+        // The maintenance task timeout is used only when an eviction listener is set.
+        let maintenance_task_timeout =
+            has_eviction_listener.then_some(housekeeper_config.maintenance_task_timeout);
+
         Self {
             max_capacity,
+            initial_capacity,
             num_segments,
+            eviction_policy,
             time_to_live,
             time_to_idle,
+            timer_wheel_precision,
+            has_weigher,
+            has_expiry,
+            has_eviction_listener,
+            supports_invalidation_closures,
+            maintenance_task_timeout,
+            max_log_sync_repeats: housekeeper_config.max_log_sync_repeats,
+            eviction_batch_size: housekeeper_config.eviction_batch_size,
+            proactive_expiration: housekeeper_config.proactive_expiration,
+            pinned_weighted_size: 0,
         }
     }
 
@@ -49,6 +92,21 @@ impl Policy {
         self.num_segments = num;
     }
 
+    /// Returns the `initial_capacity` of the cache.
+    pub fn initial_capacity(&self) -> Option<usize> {
+        self.initial_capacity
+    }
+
+    #[cfg(feature = "sync")]
+    pub(crate) fn set_initial_capacity(&mut self, capacity: Option<usize>) {
+        self.initial_capacity = capacity;
+    }
+
+    /// Returns the [`EvictionPolicy`] of the cache.
+    pub fn eviction_policy(&self) -> &EvictionPolicy {
+        &self.eviction_policy
+    }
+
     /// Returns the `time_to_live` of the cache.
     pub fn time_to_live(&self) -> Option<Duration> {
         self.time_to_live
@@ -58,6 +116,78 @@ impl Policy {
     pub fn time_to_idle(&self) -> Option<Duration> {
         self.time_to_idle
     }
+
+    /// Returns the precision of the per-entry expiration, which is the effective
+    /// tick of the [`TimerWheelConfig`] of the cache.
+    pub fn timer_wheel_precision(&self) -> Duration {
+        self.timer_wheel_precision
+    }
+
+    /// Returns `true` if a weigher closure is set to the cache.
+    pub fn has_weigher(&self) -> bool {
+        self.has_weigher
+    }
+
+    /// Returns `true` if an [`Expiry`] (or an `AsyncExpiry` for the
+    /// `future::Cache`) is set to the cache.
+    pub fn has_expiry(&self) -> bool {
+        self.has_expiry
+    }
+
+    /// Returns `true` if an eviction listener closure is set to the cache.
+    pub fn has_eviction_listener(&self) -> bool {
+        self.has_eviction_listener
+    }
+
+    /// Returns `true` if the cache supports `invalidate_entries_if` method.
+    pub fn supports_invalidation_closures(&self) -> bool {
+        self.supports_invalidation_closures
+    }
+
+    /// Returns the timeout of a single run of the pending maintenance tasks, or
+    /// `None` if no eviction listener is set to the cache.
+    ///
+    /// The timeout prevents the cache operations from being blocked for a long
+    /// time by a slow eviction listener.
+    pub fn maintenance_task_timeout(&self) -> Option<Duration> {
+        self.maintenance_task_timeout
+    }
+
+    /// Returns the maximum number of times the maintenance tasks repeat receiving
+    /// the pending read and write operation logs in a single run.
+    pub fn max_log_sync_repeats(&self) -> u32 {
+        self.max_log_sync_repeats
+    }
+
+    /// Returns the maximum number of entries processed by each eviction step of the
+    /// maintenance tasks.
+    pub fn eviction_batch_size(&self) -> u32 {
+        self.eviction_batch_size
+    }
+
+    /// Returns the tolerance of the proactive expiration, or `None` if it is
+    /// disabled.
+    ///
+    /// The proactive expiration is only available for the `sync` caches, so this
+    /// always returns `None` for `future::Cache`. See
+    /// `sync::CacheBuilder::proactive_expiration` for details.
+    pub fn proactive_expiration(&self) -> Option<Duration> {
+        self.proactive_expiration
+    }
+
+    /// Returns the total weighted size of the pinned entries in the cache when
+    /// this `Policy` was taken. The pinned entries are never evicted for size, so
+    /// the max capacity available to the other entries is reduced by this amount.
+    ///
+    /// Like `weighted_size` of the cache, the value is _an estimate_ that is
+    /// updated by the pending maintenance tasks.
+    pub fn pinned_weighted_size(&self) -> u64 {
+        self.pinned_weighted_size
+    }
+
+    pub(crate) fn set_pinned_weighted_size(&mut self, size: u64) {
+        self.pinned_weighted_size = size;
+    }
 }
 
 /// The eviction (and admission) policy of a cache.
@@ -85,7 +215,7 @@ impl Policy {
 ///
 /// Use associate function [`EvictionPolicy::tiny_lfu`](#method.tiny_lfu) or
 /// [`EvictionPolicy::lru`](#method.lru) to obtain an instance of `EvictionPolicy`.
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct EvictionPolicy {
     pub(crate) config: EvictionPolicyConfig,
 }
@@ -126,13 +256,144 @@ impl fmt::Debug for EvictionPolicy {
     }
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub(crate) enum EvictionPolicyConfig {
     #[default]
     TinyLfu,
     Lru,
 }
 
+/// The layout of the hierarchical timer wheel, which a cache uses to expire the
+/// entries having per-entry expiration times (e.g. set by an [`Expiry`]).
+///
+/// The timer wheel has some levels of buckets. Each bucket at level 0 spans a
+/// _tick_, and each bucket at the next level spans all buckets of the previous
+/// level. The entries expiring beyond the last level are kept in an overflow
+/// queue, and they are moved to the levels as the time passes.
+///
+/// The tick is the precision of the per-entry expiration. An expired entry is
+/// removed by the housekeeping task at most one tick after it expired. A shorter
+/// tick gives better precision, and more levels or buckets make the timer wheel
+/// span a longer time before the overflow queue, at the cost of more buckets to
+/// scan.
+///
+/// The default layout has a tick of ~1.07 seconds and four levels of 64, 64, 32
+/// and 4 buckets, which span ~6.5 days.
+///
+/// # Example
+///
+/// ```rust
+/// use moka::policy::TimerWheelConfig;
+/// use std::time::Duration;
+///
+/// // A tick of ~67 milliseconds, and five levels spanning ~52 days.
+/// let config = TimerWheelConfig::new(Duration::from_millis(50), &[64, 64, 64, 32, 8]);
+/// assert_eq!(config.tick(), Duration::from_nanos(1 << 26));
+/// assert!(config.span() > Duration::from_secs(50 * 24 * 60 * 60));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimerWheelConfig {
+    tick_nanos: u64,
+    bucket_counts: Vec<u32>,
+}
+
+impl Default for TimerWheelConfig {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), &[64, 64, 32, 4])
+    }
+}
+
+impl TimerWheelConfig {
+    /// The maximum number of levels.
+    pub const MAX_LEVELS: usize = 16;
+
+    /// The maximum number of buckets in a level.
+    pub const MAX_BUCKETS_PER_LEVEL: u32 = 256;
+
+    /// Creates a timer wheel layout with the given `tick` and the numbers of
+    /// buckets in each level.
+    ///
+    /// The `tick` (in nanoseconds) and the bucket counts are rounded up to the next
+    /// power of two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_counts` is empty or has more than
+    /// [`MAX_LEVELS`](#associatedconstant.MAX_LEVELS) levels, if any bucket count is
+    /// zero or greater than
+    /// [`MAX_BUCKETS_PER_LEVEL`](#associatedconstant.MAX_BUCKETS_PER_LEVEL), or if
+    /// the timer wheel spans longer than 2^62 nanoseconds (~146 years).
+    pub fn new(tick: Duration, bucket_counts: &[u32]) -> Self {
+        const MAX_SPAN_NANOS: u64 = 1 << 62;
+
+        assert!(
+            (1..=Self::MAX_LEVELS).contains(&bucket_counts.len()),
+            "bucket_counts must have between 1 and {} levels",
+            Self::MAX_LEVELS
+        );
+        assert!(
+            bucket_counts
+                .iter()
+                .all(|c| (1..=Self::MAX_BUCKETS_PER_LEVEL).contains(c)),
+            "bucket count must be between 1 and {}",
+            Self::MAX_BUCKETS_PER_LEVEL
+        );
+
+        let tick_nanos = u64::try_from(tick.as_nanos())
+            .ok()
+            .filter(|nanos| *nanos <= MAX_SPAN_NANOS)
+            .expect("tick is too long")
+            .max(1)
+            .next_power_of_two();
+        let bucket_counts: Vec<_> = bucket_counts
+            .iter()
+            .map(|c| c.next_power_of_two())
+            .collect();
+
+        let span = bucket_counts
+            .iter()
+            .try_fold(tick_nanos, |span, c| span.checked_mul(*c as u64));
+        assert!(
+            span.is_some_and(|span| span <= MAX_SPAN_NANOS),
+            "timer wheel spans too long"
+        );
+
+        Self {
+            tick_nanos,
+            bucket_counts,
+        }
+    }
+
+    /// Returns the effective tick, which is the precision of the per-entry
+    /// expiration.
+    pub fn tick(&self) -> Duration {
+        Duration::from_nanos(self.tick_nanos)
+    }
+
+    /// Returns the effective numbers of buckets in each level.
+    pub fn bucket_counts(&self) -> &[u32] {
+        &self.bucket_counts
+    }
+
+    /// Returns the time spanned by all levels. Entries expiring beyond this are
+    /// kept in the overflow queue.
+    pub fn span(&self) -> Duration {
+        Duration::from_nanos(self.level_spans().last().copied().unwrap_or_default())
+    }
+
+    /// Returns the span of a bucket in each level in nanoseconds, followed by the
+    /// span of the whole timer wheel.
+    pub(crate) fn level_spans(&self) -> Vec<u64> {
+        let mut spans = Vec::with_capacity(self.bucket_counts.len() + 1);
+        spans.push(self.tick_nanos);
+        for count in &self.bucket_counts {
+            let last = *spans.last().unwrap();
+            spans.push(last * *count as u64);
+        }
+        spans
+    }
+}
+
 /// Calculates when cache entries expire. A single expiration time is retained on
 /// each entry so that the lifetime of an entry may be extended or reduced by
 /// subsequent evaluations.
@@ -306,10 +567,142 @@ where
     }
 }
 
+/// A future returned by the methods of an [`AsyncExpiry`].
+///
+/// You can use the [`boxed` method][boxed-method] of `FutureExt` trait to convert a
+/// regular `Future` object into `ExpiryFuture`.
+///
+/// [boxed-method]: ../future/trait.FutureExt.html#method.boxed
+#[cfg(feature = "future")]
+#[cfg_attr(docsrs, doc(cfg(feature = "future")))]
+pub type ExpiryFuture<'a> = Pin<Box<dyn Future<Output = Option<Duration>> + Send + 'a>>;
+
+/// The async version of [`Expiry`] for `future::Cache`. Set it to the cache by
+/// [`future::CacheBuilder::async_expire_after`][async-expire-after].
+///
+/// Use this trait instead of `Expiry` when you need to `.await` something (e.g.
+/// consulting a remote configuration) to calculate the expiration time of an
+/// entry. The methods have the same meaning as the ones of `Expiry`, but return
+/// an [`ExpiryFuture`].
+///
+/// # When the futures are awaited
+///
+/// - [`expire_after_create`](#method.expire_after_create) and
+///   [`expire_after_update`](#method.expire_after_update) are awaited by the
+///   cache write methods such as `insert` and `get_with`, before they return.
+/// - [`expire_after_read`](#method.expire_after_read) is _not_ awaited by the
+///   cache read methods such as `get`. Instead, it is awaited later by the
+///   housekeeping task of the cache (e.g. `run_pending_tasks`), which applies
+///   the recorded reads to the cache. So the expiration time returned by it will
+///   be applied lazily, and it may not be called for every read. (e.g. when the
+///   read record was discarded as the channel was full)
+///
+/// [async-expire-after]: ../future/struct.CacheBuilder.html#method.async_expire_after
+///
+/// # Example
+///
+/// ```rust
+/// // Cargo.toml
+/// //
+/// // [dependencies]
+/// // moka = { version = "0.12", features = ["future"] }
+/// // tokio = { version = "1", features = ["rt-multi-thread", "macros" ] }
+/// use moka::{
+///     future::{Cache, FutureExt},
+///     policy::{AsyncExpiry, ExpiryFuture},
+/// };
+/// use std::time::{Duration, Instant};
+///
+/// struct MyExpiry;
+///
+/// impl MyExpiry {
+///     // e.g. Fetch the time-to-live from a remote configuration service.
+///     async fn fetch_ttl(&self, _key: &str) -> Duration {
+///         Duration::from_secs(60)
+///     }
+/// }
+///
+/// impl AsyncExpiry<String, u32> for MyExpiry {
+///     fn expire_after_create<'a>(
+///         &'a self,
+///         key: &'a String,
+///         _value: &'a u32,
+///         _created_at: Instant,
+///     ) -> ExpiryFuture<'a> {
+///         async move { Some(self.fetch_ttl(key).await) }.boxed()
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache = Cache::builder()
+///         .max_capacity(100)
+///         .async_expire_after(MyExpiry)
+///         .build();
+///
+///     cache.insert("a".to_string(), 1).await;
+///     let metadata = cache.peek_metadata("a").await.unwrap();
+///     assert!(metadata.time_to_expiration().unwrap() <= Duration::from_secs(60));
+/// }
+/// ```
+#[cfg(feature = "future")]
+#[cfg_attr(docsrs, doc(cfg(feature = "future")))]
+pub trait AsyncExpiry<K, V> {
+    /// The async version of [`Expiry::expire_after_create`].
+    ///
+    /// The default implementation returns `None` (no expiration).
+    #[allow(unused_variables)]
+    fn expire_after_create<'a>(
+        &'a self,
+        key: &'a K,
+        value: &'a V,
+        created_at: Instant,
+    ) -> ExpiryFuture<'a> {
+        Box::pin(std::future::ready(None))
+    }
+
+    /// The async version of [`Expiry::expire_after_read`].
+    ///
+    /// Note that this method is called by the housekeeping task of the cache, not
+    /// by the read methods. `read_at` is the time when the entry was last read.
+    ///
+    /// The default implementation returns `duration_until_expiry` (not modify the
+    /// expiration time).
+    #[allow(unused_variables)]
+    fn expire_after_read<'a>(
+        &'a self,
+        key: &'a K,
+        value: &'a V,
+        read_at: Instant,
+        duration_until_expiry: Option<Duration>,
+        last_modified_at: Instant,
+    ) -> ExpiryFuture<'a> {
+        Box::pin(std::future::ready(duration_until_expiry))
+    }
+
+    /// The async version of [`Expiry::expire_after_update`].
+    ///
+    /// The default implementation returns `duration_until_expiry` (not modify the
+    /// expiration time).
+    #[allow(unused_variables)]
+    fn expire_after_update<'a>(
+        &'a self,
+        key: &'a K,
+        value: &'a V,
+        updated_at: Instant,
+        duration_until_expiry: Option<Duration>,
+    ) -> ExpiryFuture<'a> {
+        Box::pin(std::future::ready(duration_until_expiry))
+    }
+}
+
 pub(crate) struct ExpirationPolicy<K, V> {
     time_to_live: Option<Duration>,
     time_to_idle: Option<Duration>,
     expiry: Option<Arc<dyn Expiry<K, V> + Send + Sync + 'static>>,
+    #[cfg(feature = "future")]
+    async_expiry: Option<Arc<dyn AsyncExpiry<K, V> + Send + Sync + 'static>>,
+    timer_wheel: TimerWheelConfig,
 }
 
 impl<K, V> Default for ExpirationPolicy<K, V> {
@@ -318,6 +711,9 @@ impl<K, V> Default for ExpirationPolicy<K, V> {
             time_to_live: None,
             time_to_idle: None,
             expiry: None,
+            #[cfg(feature = "future")]
+            async_expiry: None,
+            timer_wheel: TimerWheelConfig::default(),
         }
     }
 }
@@ -328,6 +724,9 @@ impl<K, V> Clone for ExpirationPolicy<K, V> {
             time_to_live: self.time_to_live,
             time_to_idle: self.time_to_idle,
             expiry: self.expiry.clone(),
+            #[cfg(feature = "future")]
+            async_expiry: self.async_expiry.clone(),
+            timer_wheel: self.timer_wheel.clone(),
         }
     }
 }
@@ -343,6 +742,9 @@ impl<K, V> ExpirationPolicy<K, V> {
             time_to_live,
             time_to_idle,
             expiry,
+            #[cfg(feature = "future")]
+            async_expiry: None,
+            timer_wheel: TimerWheelConfig::default(),
         }
     }
 
@@ -368,8 +770,38 @@ impl<K, V> ExpirationPolicy<K, V> {
         self.expiry.clone()
     }
 
+    /// Sets the `Expiry`. This will unset the `AsyncExpiry` if any.
     pub(crate) fn set_expiry(&mut self, expiry: Arc<dyn Expiry<K, V> + Send + Sync + 'static>) {
         self.expiry = Some(expiry);
+        #[cfg(feature = "future")]
+        {
+            self.async_expiry = None;
+        }
+    }
+
+    #[cfg(feature = "future")]
+    pub(crate) fn async_expiry(
+        &self,
+    ) -> Option<Arc<dyn AsyncExpiry<K, V> + Send + Sync + 'static>> {
+        self.async_expiry.clone()
+    }
+
+    /// Sets the `AsyncExpiry`. This will unset the `Expiry` if any.
+    #[cfg(feature = "future")]
+    pub(crate) fn set_async_expiry(
+        &mut self,
+        expiry: Arc<dyn AsyncExpiry<K, V> + Send + Sync + 'static>,
+    ) {
+        self.async_expiry = Some(expiry);
+        self.expiry = None;
+    }
+
+    pub(crate) fn timer_wheel(&self) -> &TimerWheelConfig {
+        &self.timer_wheel
+    }
+
+    pub(crate) fn set_timer_wheel(&mut self, config: TimerWheelConfig) {
+        self.timer_wheel = config;
     }
 }
 
@@ -9,17 +9,8 @@ mod key_lock;
 mod segment;
 mod value_initializer;
 
-/// The type of the unique ID to identify a predicate used by
-/// [`Cache::invalidate_entries_if`][invalidate-if] method.
-///
-/// A `PredicateId` is a `String` of UUID (version 4).
-///
-/// [invalidate-if]: ./struct.Cache.html#method.invalidate_entries_if
-pub type PredicateId = String;
-
-pub(crate) type PredicateIdStr<'a> = &'a str;
-
 pub use crate::common::iter::Iter;
+pub use crate::common::predicate_id::PredicateId;
 pub use {
     builder::CacheBuilder,
     cache::Cache,
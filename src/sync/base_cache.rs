@@ -15,20 +15,24 @@ use crate::{
             deques::Deques,
             entry_info::EntryInfo,
             housekeeper::{Housekeeper, InnerSync},
-            AccessTime, KeyHash, KeyHashDate, KvEntry, OldEntryInfo, ReadOp, ValueEntry, Weigher,
-            WriteOp,
+            AccessTime, EvictionVeto, InsertOptions, KeyHash, KeyHashDate, KvEntry, OldEntryInfo,
+            ReadOp, ValueEntry, Weigher, WriteOp, Writer,
         },
         deque::{DeqNode, Deque},
-        error::CapacityError,
+        error::{CapacityError, InsertError, WriteError},
         frequency_sketch::FrequencySketch,
         iter::ScanningGet,
         time::{AtomicInstant, Clock, Instant},
         timer_wheel::{ReschedulingResult, TimerWheel},
+        write_behind::{PendingFlush, WriteBehind, WriteBehindConfig},
         CacheRegion, HousekeeperConfig,
     },
-    notification::{notifier::RemovalNotifier, EvictionListener, RemovalCause},
+    notification::{
+        notifier::RemovalNotifier, CacheEvent, EventPublisher, EvictionListener, ListenerConfig,
+        RemovalCause, RemovedEntries, Subscriber,
+    },
     policy::{EvictionPolicy, EvictionPolicyConfig, ExpirationPolicy},
-    Entry, Expiry, Policy, PredicateError,
+    Entry, EntryMetadata, Expiry, Policy, PredicateError,
 };
 
 use crossbeam_channel::{Receiver, Sender, TrySendError};
@@ -43,7 +47,7 @@ use std::{
     rc::Rc,
     sync::{
         atomic::{AtomicBool, AtomicU8, Ordering},
-        Arc,
+        Arc, OnceLock,
     },
     time::{Duration, Instant as StdInstant},
 };
@@ -96,6 +100,17 @@ impl<K, V, S> BaseCache<K, V, S> {
         self.inner.weighted_size()
     }
 
+    pub(crate) fn pinned_weighted_size(&self) -> u64 {
+        self.inner.pinned_weighted_size()
+    }
+
+    pub(crate) fn eviction_listener_panic_count(&self) -> u64 {
+        self.inner
+            .removal_notifier
+            .as_ref()
+            .map_or(0, |notifier| notifier.panic_count())
+    }
+
     pub(crate) fn is_map_disabled(&self) -> bool {
         *self.inner.max_capacity.read() == Some(0)
     }
@@ -105,19 +120,65 @@ impl<K, V, S> BaseCache<K, V, S> {
         self.inner.is_removal_notifier_enabled()
     }
 
+    #[inline]
+    pub(crate) fn is_writer_enabled(&self) -> bool {
+        self.inner.writer.is_some()
+    }
+
+    #[inline]
+    pub(crate) fn has_max_entry_weight(&self) -> bool {
+        self.inner.max_entry_weight.is_some()
+    }
+
+    /// Writes the entry through the writer (if any). The caller must hold the key
+    /// lock.
+    pub(crate) fn write_through(&self, key: &K, value: &V) -> Result<(), WriteError> {
+        match &self.inner.writer {
+            Some(writer) => writer.write(key, value).map_err(WriteError::new),
+            None => Ok(()),
+        }
+    }
+
+    /// Deletes the entry through the writer (if any). The caller must hold the key
+    /// lock.
+    pub(crate) fn delete_through(&self, key: &K, cause: RemovalCause) -> Result<(), WriteError> {
+        match &self.inner.writer {
+            Some(writer) => writer.delete(key, cause).map_err(WriteError::new),
+            None => Ok(()),
+        }
+    }
+
     #[inline]
     pub(crate) fn current_time(&self) -> Instant {
         self.inner.current_time()
     }
 
+    pub(crate) fn next_expiration_time(&self) -> Option<StdInstant> {
+        let time = self.inner.next_expiration_time()?;
+        Some(self.inner.clock().to_std_instant(time))
+    }
+
     pub(crate) fn notify_invalidate(&self, key: &Arc<K>, entry: &MiniArc<ValueEntry<K, V>>)
     where
-        K: Send + Sync + 'static,
+        K: Hash + Eq + Send + Sync + 'static,
         V: Clone + Send + Sync + 'static,
     {
         self.inner.notify_invalidate(key, entry);
     }
 
+    pub(crate) fn notify_replaced(&self, key: &Arc<K>, entry: &MiniArc<ValueEntry<K, V>>)
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        self.inner.notify_upsert(
+            Arc::clone(key),
+            entry,
+            entry.last_accessed(),
+            entry.last_modified(),
+        );
+    }
+
     /// Sets the max capacity for this cache.
     /// 
     /// # Behavior
@@ -202,8 +263,13 @@ where
         initial_capacity: Option<usize>,
         build_hasher: S,
         weigher: Option<Weigher<K, V>>,
+        max_entry_weight: Option<u64>,
+        can_evict: Option<EvictionVeto<K, V>>,
+        writer: Option<Writer<K, V>>,
+        write_behind: Option<WriteBehindConfig<K, V>>,
         eviction_policy: EvictionPolicy,
         eviction_listener: Option<EvictionListener<K, V>>,
+        listener_config: ListenerConfig<K>,
         expiration_policy: ExpirationPolicy<K, V>,
         housekeeper_config: HousekeeperConfig,
         invalidator_enabled: bool,
@@ -226,24 +292,49 @@ where
             initial_capacity,
             build_hasher,
             weigher,
+            max_entry_weight,
+            can_evict,
+            writer,
+            write_behind,
             eviction_policy,
             eviction_listener,
+            listener_config,
             r_rcv,
             w_rcv,
             expiration_policy,
+            housekeeper_config.clone(),
             invalidator_enabled,
             clock,
         ));
 
+        let housekeeper = Arc::new(Housekeeper::new(
+            is_eviction_listener_enabled,
+            housekeeper_config,
+            fast_now,
+        ));
+        housekeeper.start_expiration_scheduler(&inner);
+
         Self {
             inner,
             read_op_ch: r_snd,
             write_op_ch: w_snd,
-            housekeeper: Some(Arc::new(Housekeeper::new(
-                is_eviction_listener_enabled,
-                housekeeper_config,
-                fast_now,
-            ))),
+            housekeeper: Some(housekeeper),
+        }
+    }
+
+    pub(crate) fn set_time_to_live(&self, duration: Option<Duration>) {
+        self.inner.set_time_to_live(duration);
+        // Let the proactive expiration (if enabled) recompute the next expiration
+        // time.
+        if let Some(hk) = &self.housekeeper {
+            hk.notify_write();
+        }
+    }
+
+    pub(crate) fn set_time_to_idle(&self, duration: Option<Duration>) {
+        self.inner.set_time_to_idle(duration);
+        if let Some(hk) = &self.housekeeper {
+            hk.notify_write();
         }
     }
 
@@ -262,14 +353,8 @@ where
         // TODO: Maybe we can just call ScanningGet::scanning_get.
         self.inner
             .get_key_value_and(key, hash, |k, entry| {
-                let i = &self.inner;
-                let (ttl, tti, va) = (&i.time_to_live(), &i.time_to_idle(), &i.valid_after());
                 let now = self.current_time();
-
-                !is_expired_by_per_entry_ttl(entry.entry_info(), now)
-                    && !is_expired_entry_wo(ttl, va, entry, now)
-                    && !is_expired_entry_ao(tti, va, entry, now)
-                    && !i.is_invalidated_entry(k, entry)
+                self.inner.is_live_entry(k, entry, now)
             })
             .unwrap_or_default() // `false` is the default for `bool` type.
     }
@@ -351,14 +436,7 @@ where
                     }
                 }
 
-                let i = &self.inner;
-                let (ttl, tti, va) = (&i.time_to_live(), &i.time_to_idle(), &i.valid_after());
-
-                if is_expired_by_per_entry_ttl(entry.entry_info(), now)
-                    || is_expired_entry_wo(ttl, va, entry, now)
-                    || is_expired_entry_ao(tti, va, entry, now)
-                    || i.is_invalidated_entry(k, entry)
-                {
+                if !self.inner.is_live_entry(k, entry, now) {
                     // Expired or invalidated entry.
                     None
                 } else {
@@ -407,8 +485,8 @@ where
                     |k, v, t, d| expiry.expire_after_read(k, v, t, d, lm),
                     &entry.entry_info().key_hash().key,
                     &entry,
-                    self.inner.expiration_policy.time_to_live(),
-                    self.inner.expiration_policy.time_to_idle(),
+                    self.inner.time_to_live(),
+                    self.inner.time_to_idle(),
                     now,
                     self.inner.clock(),
                 );
@@ -429,6 +507,188 @@ where
         }
     }
 
+    /// Sets the pinned flag of the entry for the key. Returns a `WriteOp` to update
+    /// the pinned weight of the cache, or `None` if the key does not exist or the
+    /// entry has been expired or invalidated.
+    pub(crate) fn set_pinned_with_hash<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        pinned: bool,
+    ) -> Option<(WriteOp<K, V>, Instant)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let now = self.current_time();
+        self.inner.get_key_value_and_then(key, hash, |k, entry| {
+            if !self.inner.is_live_entry(k, entry, now) {
+                None
+            } else {
+                entry.entry_info().set_pinned(pinned);
+                let op = WriteOp::UpdatePinned {
+                    value_entry: MiniArc::clone(entry),
+                };
+                Some((op, now))
+            }
+        })
+    }
+
+    /// Sets the per-entry expiration time of the entry for the key to
+    /// `per_entry_ttl` from now, or unsets it when `per_entry_ttl` is `None`.
+    /// Returns a `WriteOp` to reschedule the entry on the timer wheel, or `None` if
+    /// the key does not exist or the entry has been expired or invalidated.
+    pub(crate) fn set_expiration_with_hash<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        per_entry_ttl: Option<Duration>,
+    ) -> Option<(WriteOp<K, V>, Instant)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let now = self.current_time();
+        self.inner.get_key_value_and_then(key, hash, |k, entry| {
+            if !self.inner.is_live_entry(k, entry, now) {
+                None
+            } else {
+                entry
+                    .entry_info()
+                    .set_expiration_time(per_entry_ttl.map(|d| now.saturating_add(d)));
+                let op = WriteOp::UpdateExpiration {
+                    value_entry: MiniArc::clone(entry),
+                };
+                Some((op, now))
+            }
+        })
+    }
+
+    /// Marks the entry for the key as accessed without cloning its value. Like
+    /// `get_with_hash`, this updates the last accessed time of the entry, calls the
+    /// `expire_after_read` method of the `Expiry`, and records a read. Returns
+    /// `false` if the key does not exist or the entry has been expired or
+    /// invalidated.
+    pub(crate) fn touch_with_hash<Q>(&self, key: &Q, hash: u64) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        if self.is_map_disabled() {
+            return false;
+        }
+
+        let mut now = self.current_time();
+
+        let maybe_entry = self.inner.get_key_value_and_then(key, hash, |k, entry| {
+            self.inner
+                .is_live_entry(k, entry, now)
+                .then(|| MiniArc::clone(entry))
+        });
+
+        let Some(entry) = maybe_entry else {
+            return false;
+        };
+
+        let mut is_expiry_modified = false;
+
+        // Call the user supplied `expire_after_read` method if any. See
+        // `get_with_hash` for the details.
+        if let Some(expiry) = &self.inner.expiration_policy.expiry() {
+            let lm = entry.last_modified().expect("Last modified is not set");
+            now = now.max(lm);
+            let lm = self.inner.clock().to_std_instant(lm);
+            is_expiry_modified = Self::expire_after_read_or_update(
+                |k, v, t, d| expiry.expire_after_read(k, v, t, d, lm),
+                &entry.entry_info().key_hash().key,
+                &entry,
+                self.inner.time_to_live(),
+                self.inner.time_to_idle(),
+                now,
+                self.inner.clock(),
+            );
+        }
+
+        entry.set_last_accessed(now);
+
+        let op = ReadOp::Hit {
+            value_entry: entry,
+            is_expiry_modified,
+        };
+        self.record_read_op(op, now)
+            .expect("Failed to record a touch op");
+        true
+    }
+
+    /// Returns a clone of the value for the key, or `None` if the key does not exist
+    /// or the entry has been expired or invalidated.
+    ///
+    /// Unlike `get_with_hash_without_recording`, this method neither updates the
+    /// last accessed time of the entry nor calls the `expire_after_read` method of
+    /// the `Expiry`. So it does not reset the idle timer of the entry.
+    pub(crate) fn peek_with_hash<Q>(&self, key: &Q, hash: u64) -> Option<V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        if self.is_map_disabled() {
+            return None;
+        }
+
+        let now = self.current_time();
+        self.inner.get_key_value_and_then(key, hash, |k, entry| {
+            self.inner
+                .is_live_entry(k, entry, now)
+                .then(|| entry.value.clone())
+        })
+    }
+
+    /// Returns the metadata of the entry for the key, or `None` if the key does not
+    /// exist or the entry has been expired or invalidated. Unlike `get_with_hash`,
+    /// this method does not record a read.
+    pub(crate) fn peek_metadata_with_hash<Q>(&self, key: &Q, hash: u64) -> Option<EntryMetadata>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        if self.is_map_disabled() {
+            return None;
+        }
+
+        let now = self.current_time();
+        let entry = self.inner.get_key_value_and_then(key, hash, |k, entry| {
+            self.inner
+                .is_live_entry(k, entry, now)
+                .then(|| MiniArc::clone(entry))
+        })?;
+
+        let i = &self.inner;
+        let info = entry.entry_info();
+        let la = entry.last_accessed()?;
+        let lm = entry.last_modified()?;
+
+        // The entry expires at the earliest of the per-entry expiration time, and
+        // the times derived from the cache-wide TTL and TTI.
+        let expiration_time = [
+            info.expiration_time(),
+            i.time_to_live().map(|ttl| lm.saturating_add(ttl)),
+            i.time_to_idle().map(|tti| la.saturating_add(tti)),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+
+        // Only read the frequency sketch. Do not increment it.
+        let frequency = i.frequency_sketch.read().frequency(hash);
+
+        let clock = i.clock();
+        Some(EntryMetadata {
+            last_accessed: clock.to_std_instant(la),
+            last_modified: clock.to_std_instant(lm),
+            expiration_time: expiration_time.map(|ts| clock.to_std_instant(ts)),
+            time_to_expiration: expiration_time.map(|ts| ts.saturating_duration_since(now)),
+            policy_weight: info.policy_weight(),
+            frequency,
+            is_admitted: info.is_admitted(),
+            is_pinned: info.is_pinned(),
+        })
+    }
+
     pub(crate) fn get_key_with_hash<Q>(&self, key: &Q, hash: u64) -> Option<Arc<K>>
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -466,6 +726,15 @@ where
         self.inner.set_valid_after(now);
     }
 
+    /// Invalidates all the entries including the ones written at the current time.
+    /// `invalidate_all` leaves them valid because an entry is invalid only when it
+    /// was written strictly before the `valid_after` time.
+    pub(crate) fn invalidate_all_until_now(&self) {
+        let now = self.current_time();
+        self.inner
+            .set_valid_after(now.saturating_add(Duration::from_nanos(1)));
+    }
+
     pub(crate) fn invalidate_entries_if(
         &self,
         predicate: PredicateFun<K, V>,
@@ -491,21 +760,10 @@ where
     fn scanning_get(&self, key: &Arc<K>) -> Option<V> {
         let hash = self.hash(&**key);
         self.inner.get_key_value_and_then(&**key, hash, |k, entry| {
-            let i = &self.inner;
-            let (ttl, tti, va) = (&i.time_to_live(), &i.time_to_idle(), &i.valid_after());
             let now = self.current_time();
-
-            if is_expired_by_per_entry_ttl(entry.entry_info(), now)
-                || is_expired_entry_wo(ttl, va, entry, now)
-                || is_expired_entry_ao(tti, va, entry, now)
-                || i.is_invalidated_entry(k, entry)
-            {
-                // Expired or invalidated entry.
-                None
-            } else {
-                // Valid entry.
-                Some(entry.value.clone())
-            }
+            self.inner
+                .is_live_entry(k, entry, now)
+                .then(|| entry.value.clone())
         })
     }
 
@@ -538,23 +796,95 @@ where
         }
     }
 
+    /// Weighs the given entry and returns the weight if it does not exceed the
+    /// max entry weight of the cache.
     #[inline]
+    pub(crate) fn check_entry_weight(&self, key: &K, value: &V) -> Result<u64, InsertError> {
+        let weight = self.inner.weigh(key, value);
+        match self.inner.max_entry_weight {
+            Some(max_entry_weight) if weight > max_entry_weight => {
+                Err(InsertError::EntryTooHeavy {
+                    weight,
+                    max_entry_weight,
+                })
+            }
+            _ => Ok(weight),
+        }
+    }
+
+    /// Notifies the eviction listener and the event subscribers (if any) that the
+    /// given entry was rejected and not stored in the cache. The caller should hold
+    /// the key lock (if any).
+    pub(crate) fn notify_rejected(&self, key: Arc<K>, value: V)
+    where
+        V: Clone,
+    {
+        self.inner.events.publish(|| CacheEvent::Removed {
+            key: Arc::clone(&key),
+            value: value.clone(),
+            cause: RemovalCause::Rejected,
+        });
+        if let Some(notifier) = &self.inner.removal_notifier {
+            notifier.notify(key, value, RemovalCause::Rejected);
+        }
+    }
+
+    /// Registers the subscriber to receive the events of this cache.
+    pub(crate) fn subscribe(&self, subscriber: Subscriber<K, V>) {
+        // Create the key locks before registering the subscriber, so that the
+        // events of the same key are published in order.
+        self.inner.enable_key_locks();
+        self.inner.events.subscribe(subscriber);
+    }
+
+    /// Flushes all the dirty entries in the write-behind buffer (if any).
+    pub(crate) fn flush_write_behind(&self) {
+        if let Some(wb) = &self.inner.write_behind {
+            wb.flush();
+        }
+    }
+
+    /// Waits for the write-behind batches handed to the flush thread so far.
+    #[cfg(test)]
+    pub(crate) fn wait_for_write_behind_flushes(&self) {
+        if let Some(wb) = &self.inner.write_behind {
+            wb.pending().wait();
+        }
+    }
+
+    /// Blocks until the eviction listener has been called for all the notifications
+    /// queued so far.
+    pub(crate) fn wait_for_notifications(&self) {
+        if let Some(notifier) = &self.inner.removal_notifier {
+            notifier.wait_for_notifications();
+        }
+    }
+
     pub(crate) fn do_insert_with_hash(
         &self,
         key: Arc<K>,
         hash: u64,
         value: V,
-    ) -> (WriteOp<K, V>, Instant) {
-        let weight = self.inner.weigh(&key, &value);
+        weight: u64,
+        opts: InsertOptions,
+    ) -> Result<(WriteOp<K, V>, Instant), WriteError> {
         let op_cnt1 = Rc::new(AtomicU8::new(0));
         let op_cnt2 = Rc::clone(&op_cnt1);
         let mut op1 = None;
         let mut op2 = None;
 
-        // Lock the key for update if blocking removal notification is enabled.
+        // Lock the key for update if blocking removal notification or the writer
+        // is enabled.
         let kl = self.maybe_key_lock(&key);
         let _klg = &kl.as_ref().map(|kl| kl.lock());
 
+        // Write the entry through the writer (if any) before modifying the cache,
+        // so that the cache will not be modified if the writer fails. The loaded
+        // values are not written as they came from the external storage.
+        if !opts.loaded {
+            self.write_through(&key, &value)?;
+        }
+
         let ts = self.current_time();
 
         // TODO: Instead using Arc<AtomicU8> to check if the actual operation was
@@ -575,6 +905,7 @@ where
             // on_insert
             || {
                 let (entry, gen) = self.new_value_entry(&key, hash, value.clone(), ts, weight);
+                opts.apply(entry.entry_info(), ts);
                 let ins_op = WriteOp::new_upsert(&key, hash, &entry, gen, 0, weight);
                 let cnt = op_cnt1.fetch_add(1, Ordering::Relaxed);
                 op1 = Some((cnt, ins_op));
@@ -589,6 +920,7 @@ where
                 // last_accessed and last_modified timestamps.
                 let old_info = OldEntryInfo::new(old_entry);
                 let (entry, gen) = self.new_value_entry_from(value.clone(), ts, weight, old_entry);
+                opts.apply(entry.entry_info(), ts);
                 let upd_op = WriteOp::new_upsert(&key, hash, &entry, gen, old_weight, weight);
                 let cnt = op_cnt2.fetch_add(1, Ordering::Relaxed);
                 op2 = Some((cnt, old_info, upd_op));
@@ -596,16 +928,23 @@ where
             },
         );
 
-        match (op1, op2) {
-            (Some((_cnt, ins_op)), None) => self.do_post_insert_steps(ts, &key, ins_op),
+        // Mark the entry dirty in the write-behind buffer (if any) while holding the
+        // key lock, so that the buffer will have the latest value of the key.
+        if let (Some(wb), false) = (&self.inner.write_behind, opts.loaded) {
+            wb.mark_dirty(Arc::clone(&key), value, ts);
+        }
+
+        let result = match (op1, op2) {
+            (Some((_cnt, ins_op)), None) => self.do_post_insert_steps(ts, &key, ins_op, opts),
             (Some((cnt1, ins_op)), Some((cnt2, ..))) if cnt1 > cnt2 => {
-                self.do_post_insert_steps(ts, &key, ins_op)
+                self.do_post_insert_steps(ts, &key, ins_op, opts)
             }
             (_, Some((_cnt, old_info, upd_op))) => {
-                self.do_post_update_steps(ts, key, old_info, upd_op)
+                self.do_post_update_steps(ts, key, old_info, upd_op, opts)
             }
             (None, None) => unreachable!(),
-        }
+        };
+        Ok(result)
     }
 
     fn do_post_insert_steps(
@@ -613,12 +952,18 @@ where
         ts: Instant,
         key: &Arc<K>,
         ins_op: WriteOp<K, V>,
+        opts: InsertOptions,
     ) -> (WriteOp<K, V>, Instant) {
-        if let (Some(expiry), WriteOp::Upsert { value_entry, .. }) =
+        if opts.ttl.is_some() {
+            // The per-entry time-to-live has been already set.
+        } else if let (Some(expiry), WriteOp::Upsert { value_entry, .. }) =
             (&self.inner.expiration_policy.expiry(), &ins_op)
         {
             Self::expire_after_create(expiry, key, value_entry, ts, self.inner.clock());
         }
+        if let WriteOp::Upsert { value_entry, .. } = &ins_op {
+            self.publish_upsert(key, &value_entry.value, false, opts.loaded);
+        }
         (ins_op, ts)
     }
 
@@ -628,33 +973,57 @@ where
         key: Arc<K>,
         old_info: OldEntryInfo<K, V>,
         upd_op: WriteOp<K, V>,
+        opts: InsertOptions,
     ) -> (WriteOp<K, V>, Instant) {
-        if let (Some(expiry), WriteOp::Upsert { value_entry, .. }) =
+        if opts.ttl.is_some() {
+            // The per-entry time-to-live has been already set.
+        } else if let (Some(expiry), WriteOp::Upsert { value_entry, .. }) =
             (&self.inner.expiration_policy.expiry(), &upd_op)
         {
             Self::expire_after_read_or_update(
                 |k, v, t, d| expiry.expire_after_update(k, v, t, d),
                 &key,
                 value_entry,
-                self.inner.expiration_policy.time_to_live(),
-                self.inner.expiration_policy.time_to_idle(),
+                self.inner.time_to_live(),
+                self.inner.time_to_idle(),
                 ts,
                 self.inner.clock(),
             );
+        } else if let WriteOp::Upsert { value_entry, .. } = &upd_op {
+            // Without an `Expiry`, the per-entry expiration time can only be set by
+            // the insert methods taking a time-to-live. Clear it so that the updated
+            // entry follows the cache-wide expiration policies.
+            value_entry.entry_info().set_expiration_time(None);
         }
 
         if self.is_removal_notifier_enabled() {
             self.inner.notify_upsert(
-                key,
+                Arc::clone(&key),
                 &old_info.entry,
                 old_info.last_accessed,
                 old_info.last_modified,
             );
         }
+        // Publish the new value after the removal of the old value, so that the
+        // subscribers can apply the events in order.
+        if let WriteOp::Upsert { value_entry, .. } = &upd_op {
+            self.publish_upsert(&key, &value_entry.value, true, opts.loaded);
+        }
         crossbeam_epoch::pin().flush();
         (upd_op, ts)
     }
 
+    fn publish_upsert(&self, key: &Arc<K>, value: &V, is_update: bool, loaded: bool) {
+        self.inner.events.publish(|| {
+            let (key, value) = (Arc::clone(key), value.clone());
+            match (loaded, is_update) {
+                (true, _) => CacheEvent::Loaded { key, value },
+                (false, false) => CacheEvent::Inserted { key, value },
+                (false, true) => CacheEvent::Updated { key, value },
+            }
+        });
+    }
+
     #[inline]
     fn apply_reads_if_needed(&self, inner: &Inner<K, V, S>, now: Instant) {
         let len = self.read_op_ch.len();
@@ -685,7 +1054,7 @@ impl<K, V, S> BaseCache<K, V, S> {
         hash: u64,
         value: V,
         timestamp: Instant,
-        policy_weight: u32,
+        policy_weight: u64,
     ) -> (MiniArc<ValueEntry<K, V>>, u16) {
         let key_hash = KeyHash::new(Arc::clone(key), hash);
         let info = MiniArc::new(EntryInfo::new(key_hash, timestamp, policy_weight));
@@ -698,7 +1067,7 @@ impl<K, V, S> BaseCache<K, V, S> {
         &self,
         value: V,
         timestamp: Instant,
-        policy_weight: u32,
+        policy_weight: u64,
         other: &ValueEntry<K, V>,
     ) -> (MiniArc<ValueEntry<K, V>>, u16) {
         let info = MiniArc::clone(other.entry_info());
@@ -792,11 +1161,22 @@ where
     pub(crate) fn key_locks_map_is_empty(&self) -> bool {
         self.inner.key_locks_map_is_empty()
     }
+
+    pub(crate) fn event_subscriber_count(&self) -> usize {
+        self.inner.events.subscriber_count()
+    }
 }
 
 struct EvictionState<'a, K, V> {
     counters: EvictionCounters,
     notifier: Option<&'a RemovalNotifier<K, V>>,
+    events: &'a EventPublisher<K, V>,
+    write_behind: Option<&'a WriteBehind<K, V>>,
+    /// The notifications collected for the batch eviction listener, or deferred
+    /// until the write-behind flush is done.
+    batch: RemovedEntries<K, V>,
+    /// The write-behind flush to wait for before calling the eviction listener.
+    pending_flush: Option<PendingFlush>,
     more_entries_to_evict: bool,
 }
 
@@ -804,17 +1184,24 @@ impl<'a, K, V> EvictionState<'a, K, V> {
     fn new(
         entry_count: u64,
         weighted_size: u64,
+        pinned_weight: u64,
         notifier: Option<&'a RemovalNotifier<K, V>>,
+        events: &'a EventPublisher<K, V>,
+        write_behind: Option<&'a WriteBehind<K, V>>,
     ) -> Self {
         Self {
-            counters: EvictionCounters::new(entry_count, weighted_size),
+            counters: EvictionCounters::new(entry_count, weighted_size, pinned_weight),
             notifier,
+            events,
+            write_behind,
+            batch: Vec::default(),
+            pending_flush: None,
             more_entries_to_evict: false,
         }
     }
 
     fn is_notifier_enabled(&self) -> bool {
-        self.notifier.is_some()
+        self.notifier.is_some() || self.events.has_subscribers()
     }
 
     fn notify_entry_removal(
@@ -823,13 +1210,43 @@ impl<'a, K, V> EvictionState<'a, K, V> {
         entry: &MiniArc<ValueEntry<K, V>>,
         cause: RemovalCause,
     ) where
-        K: Send + Sync + 'static,
+        K: Hash + Eq + Send + Sync + 'static,
         V: Clone + Send + Sync + 'static,
     {
+        // Do not wait for the write-behind flush here as the deques lock is held.
+        // Instead, defer the notification until the locks are released.
+        if let Some(wb) = self.write_behind {
+            if let Some(pending) = wb.prepare_removal(&key, cause) {
+                // A later flush includes the earlier ones.
+                self.pending_flush = Some(pending);
+            }
+        }
+        self.events.publish(|| CacheEvent::Removed {
+            key: Arc::clone(&key),
+            value: entry.value.clone(),
+            cause,
+        });
+        match self.notifier {
+            Some(notifier) if notifier.is_batch() || self.pending_flush.is_some() => {
+                self.batch.push((key, entry.value.clone(), cause));
+            }
+            Some(notifier) => notifier.notify(key, entry.value.clone(), cause),
+            None => (),
+        }
+    }
+
+    /// Notifies the batch eviction listener of the entries removed so far, and the
+    /// notifications deferred for the write-behind flush after waiting for it.
+    fn notify_batched_removals(&mut self)
+    where
+        K: Send + Sync + 'static,
+        V: Send + 'static,
+    {
+        if let Some(pending) = self.pending_flush.take() {
+            pending.wait();
+        }
         if let Some(notifier) = self.notifier {
-            notifier.notify(key, entry.value.clone(), cause);
-        } else {
-            panic!("notify_entry_removal is called when the notification is disabled");
+            notifier.notify_batch(std::mem::take(&mut self.batch));
         }
     }
 }
@@ -837,31 +1254,35 @@ impl<'a, K, V> EvictionState<'a, K, V> {
 struct EvictionCounters {
     entry_count: u64,
     weighted_size: u64,
+    /// The total weight of the admitted entries that are pinned. It is included in
+    /// the `weighted_size`.
+    pinned_weight: u64,
     eviction_count: u64,
 }
 
 impl EvictionCounters {
     #[inline]
-    fn new(entry_count: u64, weighted_size: u64) -> Self {
+    fn new(entry_count: u64, weighted_size: u64, pinned_weight: u64) -> Self {
         Self {
             entry_count,
             weighted_size,
+            pinned_weight,
             eviction_count: 0,
         }
     }
 
     #[inline]
-    fn saturating_add(&mut self, entry_count: u64, weight: u32) {
+    fn saturating_add(&mut self, entry_count: u64, weight: u64) {
         self.entry_count += entry_count;
         let total = &mut self.weighted_size;
-        *total = total.saturating_add(weight as u64);
+        *total = total.saturating_add(weight);
     }
 
     #[inline]
-    fn saturating_sub(&mut self, entry_count: u64, weight: u32) {
+    fn saturating_sub(&mut self, entry_count: u64, weight: u64) {
         self.entry_count -= entry_count;
         let total = &mut self.weighted_size;
-        *total = total.saturating_sub(weight as u64);
+        *total = total.saturating_sub(weight);
     }
 
     #[inline]
@@ -869,6 +1290,39 @@ impl EvictionCounters {
         let count = &mut self.eviction_count;
         *count = count.saturating_add(1);
     }
+
+    /// Updates the pinned weight for an admitted entry whose policy weight is
+    /// changing from `old_weight` to `new_weight`. The entry is counted as pinned
+    /// when its `is_pinned` flag is set.
+    #[inline]
+    fn update_pinned<K>(&mut self, info: &EntryInfo<K>, old_weight: u64, new_weight: u64) {
+        let total = &mut self.pinned_weight;
+        if info.is_policy_pinned() {
+            *total = total.saturating_sub(old_weight);
+        }
+        let is_pinned = info.is_pinned();
+        if is_pinned {
+            *total = total.saturating_add(new_weight);
+        }
+        info.set_policy_pinned(is_pinned);
+    }
+
+    /// Removes the weight of an entry being removed from the pinned weight, if it
+    /// has been counted.
+    #[inline]
+    fn remove_pinned<K>(&mut self, info: &EntryInfo<K>, weight: u64) {
+        if info.is_policy_pinned() {
+            let total = &mut self.pinned_weight;
+            *total = total.saturating_sub(weight);
+            info.set_policy_pinned(false);
+        }
+    }
+
+    /// Returns the total weight of the admitted entries that are not pinned.
+    #[inline]
+    fn unpinned_weight(&self) -> u64 {
+        self.weighted_size.saturating_sub(self.pinned_weight)
+    }
 }
 
 #[derive(Default)]
@@ -878,15 +1332,15 @@ struct EntrySizeAndFrequency {
 }
 
 impl EntrySizeAndFrequency {
-    fn new(policy_weight: u32) -> Self {
+    fn new(policy_weight: u64) -> Self {
         Self {
-            policy_weight: policy_weight as u64,
+            policy_weight,
             ..Default::default()
         }
     }
 
-    fn add_policy_weight(&mut self, weight: u32) {
-        self.policy_weight += weight as u64;
+    fn add_policy_weight(&mut self, weight: u64) {
+        self.policy_weight += weight;
     }
 
     fn add_frequency(&mut self, freq: &FrequencySketch, hash: u64) {
@@ -917,6 +1371,7 @@ pub(crate) struct Inner<K, V, S> {
     pub(crate) max_capacity: RwLock<Option<u64>>,
     entry_count: AtomicCell<u64>,
     weighted_size: AtomicCell<u64>,
+    pinned_weighted_size: AtomicCell<u64>,
     pub(crate) cache: CacheStore<K, V, S>,
     build_hasher: S,
     deques: Mutex<Deques<K>>,
@@ -925,12 +1380,25 @@ pub(crate) struct Inner<K, V, S> {
     frequency_sketch_enabled: AtomicBool,
     read_op_ch: Receiver<ReadOp<K, V>>,
     write_op_ch: Receiver<WriteOp<K, V>>,
-    eviction_policy: EvictionPolicyConfig,
+    eviction_policy: AtomicCell<EvictionPolicyConfig>,
     expiration_policy: ExpirationPolicy<K, V>,
+    time_to_live: AtomicCell<Option<Duration>>,
+    time_to_idle: AtomicCell<Option<Duration>>,
+    /// Whether the write order queue is maintained. Once set, it will never be
+    /// unset, even if the time-to-live is disabled later.
+    write_order_enabled: AtomicBool,
+    initial_capacity: Option<usize>,
+    housekeeper_config: HousekeeperConfig,
     valid_after: AtomicInstant,
     weigher: Option<Weigher<K, V>>,
+    max_entry_weight: Option<u64>,
+    can_evict: Option<EvictionVeto<K, V>>,
+    writer: Option<Writer<K, V>>,
+    write_behind: Option<WriteBehind<K, V>>,
     removal_notifier: Option<RemovalNotifier<K, V>>,
-    key_locks: Option<KeyLockMap<K, S>>,
+    events: EventPublisher<K, V>,
+    // Created on demand when the first event subscriber is registered.
+    key_locks: OnceLock<KeyLockMap<K, S>>,
     invalidator: Option<Invalidator<K, V, S>>,
     clock: Clock,
 }
@@ -961,7 +1429,27 @@ impl<K, V, S> Inner<K, V, S> {
 
     fn policy(&self) -> Policy {
         let exp = &self.expiration_policy;
-        Policy::new(self.max_capacity.read().clone(), 1, exp.time_to_live(), exp.time_to_idle())
+        let (ttl, tti) = (self.time_to_live(), self.time_to_idle());
+        let precision = exp.timer_wheel().tick();
+        let has_expiry = exp.expiry().is_some();
+        let mut policy = Policy::new(
+            *self.max_capacity.read(),
+            self.initial_capacity,
+            1,
+            EvictionPolicy {
+                config: self.eviction_policy.load(),
+            },
+            ttl,
+            tti,
+            precision,
+            self.weigher.is_some(),
+            has_expiry,
+            self.removal_notifier.is_some(),
+            self.invalidator.is_some(),
+            &self.housekeeper_config,
+        );
+        policy.set_pinned_weighted_size(self.pinned_weighted_size());
+        policy
     }
 
     #[inline]
@@ -974,9 +1462,23 @@ impl<K, V, S> Inner<K, V, S> {
         self.weighted_size.load()
     }
 
+    #[inline]
+    fn pinned_weighted_size(&self) -> u64 {
+        self.pinned_weighted_size.load()
+    }
+
     #[inline]
     pub(crate) fn is_removal_notifier_enabled(&self) -> bool {
-        self.removal_notifier.is_some()
+        self.removal_notifier.is_some() || self.events.has_subscribers()
+    }
+
+    fn enable_key_locks(&self)
+    where
+        K: Hash + Eq,
+        S: BuildHasher + Clone,
+    {
+        self.key_locks
+            .get_or_init(|| KeyLockMap::with_hasher(self.build_hasher.clone()));
     }
 
     pub(crate) fn maybe_key_lock(&self, key: &Arc<K>) -> Option<KeyLock<'_, K, S>>
@@ -984,7 +1486,7 @@ impl<K, V, S> Inner<K, V, S> {
         K: Hash + Eq,
         S: BuildHasher,
     {
-        self.key_locks.as_ref().map(|kls| kls.key_lock(key))
+        self.key_locks.get().map(|kls| kls.key_lock(key))
     }
 
     #[inline]
@@ -1002,23 +1504,22 @@ impl<K, V, S> Inner<K, V, S> {
 
     #[inline]
     fn time_to_live(&self) -> Option<Duration> {
-        self.expiration_policy.time_to_live()
+        self.time_to_live.load()
     }
 
     #[inline]
     fn time_to_idle(&self) -> Option<Duration> {
-        self.expiration_policy.time_to_idle()
+        self.time_to_idle.load()
     }
 
     #[inline]
     fn has_expiry(&self) -> bool {
-        let exp = &self.expiration_policy;
-        exp.time_to_live().is_some() || exp.time_to_idle().is_some()
+        self.time_to_live().is_some() || self.time_to_idle().is_some()
     }
 
     #[inline]
     fn is_write_order_queue_enabled(&self) -> bool {
-        self.expiration_policy.time_to_live().is_some() || self.invalidator.is_some()
+        self.write_order_enabled.load(Ordering::Acquire)
     }
 
     #[inline]
@@ -1035,6 +1536,13 @@ impl<K, V, S> Inner<K, V, S> {
     fn has_valid_after(&self) -> bool {
         self.valid_after.is_set()
     }
+
+    fn next_expiration_time(&self) -> Option<Instant> {
+        let (ttl, tti) = (self.time_to_live(), self.time_to_idle());
+        let by_policy = self.deques.lock().next_expiration_time(ttl, tti);
+        let per_entry = self.timer_wheel.lock().next_expiration_time();
+        by_policy.into_iter().chain(per_entry).min()
+    }
 }
 
 impl<K, V, S> Inner<K, V, S>
@@ -1052,14 +1560,21 @@ where
         initial_capacity: Option<usize>,
         build_hasher: S,
         weigher: Option<Weigher<K, V>>,
+        max_entry_weight: Option<u64>,
+        can_evict: Option<EvictionVeto<K, V>>,
+        writer: Option<Writer<K, V>>,
+        write_behind: Option<WriteBehindConfig<K, V>>,
         eviction_policy: EvictionPolicy,
         eviction_listener: Option<EvictionListener<K, V>>,
+        listener_config: ListenerConfig<K>,
         read_op_ch: Receiver<ReadOp<K, V>>,
         write_op_ch: Receiver<WriteOp<K, V>>,
         expiration_policy: ExpirationPolicy<K, V>,
+        housekeeper_config: HousekeeperConfig,
         invalidator_enabled: bool,
         clock: Clock,
     ) -> Self {
+        let requested_initial_capacity = initial_capacity;
         // TODO: Calculate the number of segments based on the max capacity and the
         // number of CPUs.
         let (num_segments, initial_capacity) = if max_capacity == Some(0) {
@@ -1077,15 +1592,20 @@ where
         );
 
         let now = clock.now();
-        let timer_wheel = Mutex::new(TimerWheel::new(now));
-
-        let (removal_notifier, key_locks) = if let Some(listener) = eviction_listener {
-            let rn = RemovalNotifier::new(listener, name.clone());
-            let kl = KeyLockMap::with_hasher(build_hasher.clone());
-            (Some(rn), Some(kl))
-        } else {
-            (None, None)
-        };
+        let timer_wheel = Mutex::new(TimerWheel::new(now, expiration_policy.timer_wheel()));
+
+        let removal_notifier = eviction_listener
+            .map(|listener| RemovalNotifier::new(listener, listener_config, name.clone()));
+        let write_behind =
+            write_behind.map(|config| WriteBehind::new(config, name.as_deref(), clock.clone()));
+        // The key locks are used to serialize the removal notifications, the events
+        // and the writes (including the write-behind buffer updates) for the same
+        // key. If none of them is enabled yet, the key locks will be created when an
+        // event subscriber is registered.
+        let key_locks = OnceLock::new();
+        if removal_notifier.is_some() || writer.is_some() || write_behind.is_some() {
+            key_locks.get_or_init(|| KeyLockMap::with_hasher(build_hasher.clone()));
+        }
 
         let invalidator = if invalidator_enabled {
             Some(Invalidator::new(build_hasher.clone()))
@@ -1093,11 +1613,16 @@ where
             None
         };
 
+        let time_to_live = expiration_policy.time_to_live();
+        let time_to_idle = expiration_policy.time_to_idle();
+        let write_order_enabled = time_to_live.is_some() || invalidator.is_some();
+
         Self {
             name,
             max_capacity: RwLock::new(max_capacity),
             entry_count: AtomicCell::default(),
             weighted_size: AtomicCell::default(),
+            pinned_weighted_size: AtomicCell::default(),
             cache,
             build_hasher,
             deques: Mutex::default(),
@@ -1106,17 +1631,68 @@ where
             frequency_sketch_enabled: AtomicBool::default(),
             read_op_ch,
             write_op_ch,
-            eviction_policy: eviction_policy.config,
+            eviction_policy: AtomicCell::new(eviction_policy.config),
             expiration_policy,
+            time_to_live: AtomicCell::new(time_to_live),
+            time_to_idle: AtomicCell::new(time_to_idle),
+            write_order_enabled: AtomicBool::new(write_order_enabled),
+            initial_capacity: requested_initial_capacity,
+            housekeeper_config,
             valid_after: AtomicInstant::default(),
             weigher,
+            max_entry_weight,
+            can_evict,
+            writer,
+            write_behind,
             removal_notifier,
+            events: EventPublisher::default(),
             key_locks,
             invalidator,
             clock,
         }
     }
 
+    /// Sets the `time_to_live` of the cache. The write order queue is populated
+    /// with the existing entries if it was not enabled yet.
+    fn set_time_to_live(&self, duration: Option<Duration>) {
+        // Hold the lock of the deques so that no entry is admitted while the write
+        // order queue is being populated.
+        let mut deqs = self.deques.lock();
+        if duration.is_some() && !self.is_write_order_queue_enabled() {
+            self.populate_write_order_queue(&mut deqs);
+            self.write_order_enabled.store(true, Ordering::Release);
+        }
+        self.time_to_live.store(duration);
+    }
+
+    fn set_time_to_idle(&self, duration: Option<Duration>) {
+        self.time_to_idle.store(duration);
+    }
+
+    /// Pushes the admitted entries to the write order queue in the order of their
+    /// last modified times.
+    fn populate_write_order_queue(&self, deqs: &mut Deques<K>) {
+        let mut entries = Vec::new();
+        for deq in [&deqs.window, &deqs.probation, &deqs.protected] {
+            let mut next = deq.peek_front_ptr();
+            while let Some(node) = next {
+                next = DeqNode::next_node_ptr(node);
+                let elem = &unsafe { node.as_ref() }.element;
+                let (key, hash) = (elem.key(), elem.hash());
+                if let Some(entry) = self.cache.get(hash, |k| k == key) {
+                    if entry.is_admitted() && entry.write_order_q_node().is_none() {
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.last_modified());
+        for entry in entries {
+            deqs.push_back_wo(KeyHashDate::new(entry.entry_info()), &entry);
+        }
+    }
+
     #[inline]
     fn hash<Q>(&self, key: &Q) -> u64
     where
@@ -1179,6 +1755,20 @@ where
         }
     }
 
+    /// Returns `true` if the entry has neither expired (by its per-entry
+    /// expiration time or the cache-wide TTL and TTI) nor been invalidated.
+    #[inline]
+    fn is_live_entry(&self, key: &Arc<K>, entry: &MiniArc<ValueEntry<K, V>>, now: Instant) -> bool
+    where
+        V: Clone,
+    {
+        let va = &self.valid_after();
+        !is_expired_by_per_entry_ttl(entry.entry_info(), now)
+            && !is_expired_entry_wo(&self.time_to_live(), va, entry, now)
+            && !is_expired_entry_ao(&self.time_to_idle(), va, entry, now)
+            && !self.is_invalidated_entry(key, entry)
+    }
+
     /// Returns `true` if the entry is invalidated by `invalidate_entries_if` method.
     #[inline]
     fn is_invalidated_entry(&self, key: &Arc<K>, entry: &MiniArc<ValueEntry<K, V>>) -> bool
@@ -1192,9 +1782,15 @@ where
     }
 
     #[inline]
-    fn weigh(&self, key: &K, value: &V) -> u32 {
+    fn weigh(&self, key: &K, value: &V) -> u64 {
         self.weigher.as_ref().map_or(1, |w| w(key, value))
     }
+
+    /// Returns `true` if the `can_evict` closure vetoes the eviction of the entry.
+    #[inline]
+    fn is_eviction_vetoed(&self, key: &K, value: &V) -> bool {
+        self.can_evict.as_ref().is_some_and(|f| !f(key, value))
+    }
 }
 
 impl<K, V, S> InnerSync for Inner<K, V, S>
@@ -1215,6 +1811,10 @@ where
     fn now(&self) -> Instant {
         self.current_time()
     }
+
+    fn next_expiration_time(&self) -> Option<Instant> {
+        Inner::next_expiration_time(self)
+    }
 }
 
 impl<K, V, S> Inner<K, V, S>
@@ -1246,8 +1846,15 @@ where
         let mut calls = 0u32;
         let current_ec = self.entry_count.load();
         let current_ws = self.weighted_size.load();
-        let mut eviction_state =
-            EvictionState::new(current_ec, current_ws, self.removal_notifier.as_ref());
+        let current_pw = self.pinned_weighted_size.load();
+        let mut eviction_state = EvictionState::new(
+            current_ec,
+            current_ws,
+            current_pw,
+            self.removal_notifier.as_ref(),
+            &self.events,
+            self.write_behind.as_ref(),
+        );
 
         loop {
             if should_process_logs {
@@ -1258,13 +1865,29 @@ where
 
                 let w_len = self.write_op_ch.len();
                 if w_len > 0 {
-                    self.apply_writes(&mut deqs, &mut timer_wheel, w_len, &mut eviction_state);
+                    self.apply_writes(
+                        &mut deqs,
+                        &mut timer_wheel,
+                        w_len,
+                        eviction_batch_size,
+                        &mut eviction_state,
+                    );
                 }
 
-                if self.eviction_policy == EvictionPolicyConfig::TinyLfu
-                    && self.should_enable_frequency_sketch(&eviction_state.counters)
-                {
-                    self.enable_frequency_sketch(&eviction_state.counters);
+                match self.eviction_policy.load() {
+                    EvictionPolicyConfig::TinyLfu
+                        if self.should_enable_frequency_sketch(&eviction_state.counters) =>
+                    {
+                        self.enable_frequency_sketch(&eviction_state.counters);
+                    }
+                    // The eviction policy has been switched to LRU, which does not use
+                    // the frequency sketch.
+                    EvictionPolicyConfig::Lru
+                        if self.frequency_sketch_enabled.load(Ordering::Acquire) =>
+                    {
+                        self.disable_frequency_sketch();
+                    }
+                    _ => (),
                 }
 
                 calls += 1;
@@ -1319,6 +1942,7 @@ where
                     &mut timer_wheel,
                     eviction_batch_size,
                     weights_to_evict,
+                    RemovalCause::Size,
                     &mut eviction_state,
                 );
             }
@@ -1353,11 +1977,22 @@ where
         self.entry_count.store(eviction_state.counters.entry_count);
         self.weighted_size
             .store(eviction_state.counters.weighted_size);
+        self.pinned_weighted_size
+            .store(eviction_state.counters.pinned_weight);
 
         crossbeam_epoch::pin().flush();
 
         // Ensure the deqs lock is held until here.
         drop(deqs);
+        drop(timer_wheel);
+
+        // Call the batch eviction listener and deliver the deferred notifications
+        // after releasing the locks.
+        eviction_state.notify_batched_removals();
+
+        if let Some(wb) = &self.write_behind {
+            wb.flush_if_due(self.current_time());
+        }
 
         eviction_state.more_entries_to_evict
     }
@@ -1372,16 +2007,21 @@ where
     V: Send + Sync + 'static,
     S: BuildHasher + Clone + Send + Sync + 'static,
 {
-    fn has_enough_capacity(&self, candidate_weight: u32, counters: &EvictionCounters) -> bool {
+    fn has_enough_capacity(&self, candidate_weight: u64, counters: &EvictionCounters) -> bool {
         self.max_capacity.read().map_or(true, |limit| {
-            counters.weighted_size + candidate_weight as u64 <= limit
+            counters.weighted_size + candidate_weight <= limit
         })
     }
 
+    /// Returns the weights to evict to meet the max capacity. Pinned entries cannot
+    /// be evicted, so the returned value never exceeds the total weight of the
+    /// unpinned entries.
     fn weights_to_evict(&self, counters: &EvictionCounters) -> u64 {
-        self.max_capacity.read()
+        self.max_capacity
+            .read()
             .map(|limit| counters.weighted_size.saturating_sub(limit))
             .unwrap_or_default()
+            .min(counters.unpinned_weight())
     }
 
     #[inline]
@@ -1411,6 +2051,12 @@ where
         }
     }
 
+    fn disable_frequency_sketch(&self) {
+        *self.frequency_sketch.write() = FrequencySketch::default();
+        self.frequency_sketch_enabled
+            .store(false, Ordering::Release);
+    }
+
     #[cfg(test)]
     fn enable_frequency_sketch_for_testing(&self) {
         if let Some(max_cap) = *self.max_capacity.read() {
@@ -1488,11 +2134,14 @@ where
         deqs: &mut Deques<K>,
         timer_wheel: &mut TimerWheel<K>,
         count: usize,
+        eviction_batch_size: u32,
         eviction_state: &mut EvictionState<'_, K, V>,
     ) where
         V: Clone,
     {
-        use WriteOp::{Remove, SetCapacity, Upsert};
+        use WriteOp::{
+            Remove, SetCapacity, SetEvictionPolicy, UpdateExpiration, UpdatePinned, Upsert,
+        };
         let ch = &self.write_op_ch;
 
         for _ in 0..count {
@@ -1533,11 +2182,42 @@ where
                     // Update the capacity and check if eviction is needed
                     let needs_eviction = self.update_max_capacity(new_capacity);
                     
-                    // If eviction is needed, set the flag to trigger eviction
+                    // If eviction is needed, evict the entries over the new capacity
+                    // now, so that they are reported as `CapacityReduced`, and set the
+                    // flag to trigger eviction of the rest (if any).
                     if needs_eviction {
+                        self.evict_over_reduced_capacity(
+                            deqs,
+                            timer_wheel,
+                            eviction_batch_size,
+                            eviction_state,
+                        );
                         eviction_state.more_entries_to_evict = true;
                     }
                 }
+                Ok(SetEvictionPolicy { policy }) => {
+                    // Both policies keep the admitted entries in the probation deque,
+                    // so no entries need to be moved between the deque regions. The
+                    // frequency sketch is enabled or disabled by the caller.
+                    self.eviction_policy.store(policy);
+                }
+                Ok(UpdatePinned { value_entry: entry }) => {
+                    // If the entry has not been admitted yet, `handle_admit` will
+                    // take care of it.
+                    if entry.is_admitted() {
+                        let weight = entry.policy_weight();
+                        eviction_state
+                            .counters
+                            .update_pinned(entry.entry_info(), weight, weight);
+                    }
+                }
+                Ok(UpdateExpiration { value_entry: entry }) => {
+                    // If the entry has not been admitted yet, `handle_admit` will
+                    // take care of it.
+                    if entry.is_admitted() {
+                        self.update_timer_wheel(&entry, timer_wheel);
+                    }
+                }
                 Err(_) => break,
             };
         }
@@ -1549,8 +2229,8 @@ where
         kh: KeyHash<K>,
         entry: MiniArc<ValueEntry<K, V>>,
         gen: u16,
-        old_weight: u32,
-        new_weight: u32,
+        old_weight: u64,
+        new_weight: u64,
         deqs: &mut Deques<K>,
         timer_wheel: &mut TimerWheel<K>,
         freq: &FrequencySketch,
@@ -1565,6 +2245,7 @@ where
                 // The entry has been already admitted, so treat this as an update.
                 counters.saturating_sub(0, old_weight);
                 counters.saturating_add(0, new_weight);
+                counters.update_pinned(entry.entry_info(), old_weight, new_weight);
                 self.update_timer_wheel(&entry, timer_wheel);
                 deqs.move_to_back_ao(&entry);
                 deqs.move_to_back_wo(&entry);
@@ -1572,7 +2253,9 @@ where
                 return;
             }
 
-            if self.has_enough_capacity(new_weight, counters) {
+            // A pinned entry is always admitted. If the cache is full, unpinned
+            // entries will be evicted by `evict_lru_entries` to make room.
+            if entry.entry_info().is_pinned() || self.has_enough_capacity(new_weight, counters) {
                 // There are enough room in the cache (or the cache is unbounded).
                 // Add the candidate to the deques.
                 self.handle_admit(&entry, new_weight, deqs, timer_wheel, counters);
@@ -1582,7 +2265,7 @@ where
         }
 
         if let Some(max) = *self.max_capacity.read() {
-            if new_weight as u64 > max {
+            if new_weight > max {
                 // The candidate is too big to fit in the cache. Reject it.
 
                 // Lock the key for removal if blocking removal notification is enabled.
@@ -1613,11 +2296,11 @@ where
         // https://github.com/moka-rs/moka/issues/389
 
         // Try to admit the candidate.
-        let admission_result = match &self.eviction_policy {
+        let admission_result = match self.eviction_policy.load() {
             EvictionPolicyConfig::TinyLfu => {
                 let mut candidate = EntrySizeAndFrequency::new(new_weight);
                 candidate.add_frequency(freq, kh.hash);
-                Self::admit(&candidate, &self.cache, deqs, freq)
+                Self::admit(&candidate, &self.cache, self.can_evict.as_ref(), deqs, freq)
             }
             EvictionPolicyConfig::Lru => AdmissionResult::Admitted {
                 victim_keys: SmallVec::default(),
@@ -1698,7 +2381,7 @@ where
                 if let Some(entry) = removed {
                     entry.entry_info().set_policy_gen(gen);
                     if eviction_state.is_notifier_enabled() {
-                        eviction_state.notify_entry_removal(key, &entry, RemovalCause::Size);
+                        eviction_state.notify_entry_removal(key, &entry, RemovalCause::Rejected);
                     }
                     eviction_state.counters.incr_eviction_count();
                 }
@@ -1727,6 +2410,7 @@ where
     fn admit(
         candidate: &EntrySizeAndFrequency,
         cache: &CacheStore<K, V, S>,
+        can_evict: Option<&EvictionVeto<K, V>>,
         deqs: &mut Deques<K>,
         freq: &FrequencySketch,
     ) -> AdmissionResult<K> {
@@ -1760,11 +2444,24 @@ where
                 continue;
             }
 
+            if vic_elem.entry_info().is_pinned() {
+                // Skip this node as a pinned entry cannot be a victim.
+                unsafe { deq.move_to_back(victim) };
+                retries += 1;
+                continue;
+            }
+
             let key = vic_elem.key();
             let hash = vic_elem.hash();
             let last_accessed = vic_elem.entry_info().last_accessed();
 
             if let Some(vic_entry) = cache.get(hash, |k| k == key) {
+                if can_evict.is_some_and(|f| !f(key, &vic_entry.value)) {
+                    // Skip this node as the `can_evict` closure vetoed its eviction.
+                    unsafe { deq.move_to_back(victim) };
+                    retries += 1;
+                    continue;
+                }
                 victims.add_policy_weight(vic_entry.policy_weight());
                 victims.add_frequency(freq, hash);
                 victim_keys.push((KeyHash::new(Arc::clone(key), hash), last_accessed));
@@ -1793,12 +2490,13 @@ where
     fn handle_admit(
         &self,
         entry: &MiniArc<ValueEntry<K, V>>,
-        policy_weight: u32,
+        policy_weight: u64,
         deqs: &mut Deques<K>,
         timer_wheel: &mut TimerWheel<K>,
         counters: &mut EvictionCounters,
     ) {
         counters.saturating_add(1, policy_weight);
+        counters.update_pinned(entry.entry_info(), 0, policy_weight);
 
         self.update_timer_wheel(entry, timer_wheel);
 
@@ -1885,6 +2583,7 @@ where
         if entry.is_admitted() {
             entry.set_admitted(false);
             counters.saturating_sub(1, entry.policy_weight());
+            counters.remove_pinned(entry.entry_info(), entry.policy_weight());
             // The following two unlink_* functions will unset the deq nodes.
             deqs.unlink_ao(&entry);
             Deques::unlink_wo(&mut deqs.write_order, &entry);
@@ -1910,6 +2609,7 @@ where
         if entry.is_admitted() {
             entry.set_admitted(false);
             counters.saturating_sub(1, entry.policy_weight());
+            counters.remove_pinned(entry.entry_info(), entry.policy_weight());
             // The following two unlink_* functions will unset the deq nodes.
             Deques::unlink_ao_from_deque(ao_deq_name, ao_deq, &entry);
             Deques::unlink_wo(wo_deq, &entry);
@@ -2006,7 +2706,7 @@ where
             self.remove_expired_wo(deqs, timer_wheel, batch_size, now, state);
         }
 
-        if self.expiration_policy.time_to_idle().is_some() || self.has_valid_after() {
+        if self.time_to_idle().is_some() || self.has_valid_after() {
             self.remove_expired_ao(Window, deqs, timer_wheel, batch_size, now, state);
             self.remove_expired_ao(Probation, deqs, timer_wheel, batch_size, now, state);
             self.remove_expired_ao(Protected, deqs, timer_wheel, batch_size, now, state);
@@ -2026,7 +2726,7 @@ where
     ) where
         V: Clone,
     {
-        let tti = &self.expiration_policy.time_to_idle();
+        let tti = &self.time_to_idle();
         let va = &self.valid_after();
         let deq_name = cache_region.name();
         let (ao_deq, wo_deq) = deqs.select_mut(cache_region);
@@ -2047,7 +2747,7 @@ where
                 Some((key, hash, false, Some(ts))) => {
                     let cause = match is_entry_expired_ao_or_invalid(tti, va, ts, now) {
                         (true, _) => RemovalCause::Expired,
-                        (false, true) => RemovalCause::Explicit,
+                        (false, true) => RemovalCause::InvalidatedAll,
                         (false, false) => {
                             more_to_evict = false;
                             break;
@@ -2165,7 +2865,7 @@ where
     ) where
         V: Clone,
     {
-        let ttl = &self.expiration_policy.time_to_live();
+        let ttl = &self.time_to_live();
         let va = &self.valid_after();
         let mut more_to_evict = true;
 
@@ -2184,7 +2884,7 @@ where
                 Some((key, hash, false, Some(ts))) => {
                     let cause = match is_entry_expired_wo_or_invalid(ttl, va, ts, now) {
                         (true, _) => RemovalCause::Expired,
-                        (false, true) => RemovalCause::Explicit,
+                        (false, true) => RemovalCause::InvalidatedAll,
                         (false, false) => {
                             more_to_evict = false;
                             break;
@@ -2295,12 +2995,45 @@ where
         }
     }
 
+    /// Evicts the LRU entries until the cache fits in its reduced max capacity, or
+    /// until no more entries can be evicted (e.g. they are pinned). The evicted
+    /// entries are reported as `CapacityReduced`.
+    fn evict_over_reduced_capacity(
+        &self,
+        deqs: &mut Deques<K>,
+        timer_wheel: &mut TimerWheel<K>,
+        batch_size: u32,
+        eviction_state: &mut EvictionState<'_, K, V>,
+    ) where
+        V: Clone,
+    {
+        loop {
+            let weights_to_evict = self.weights_to_evict(&eviction_state.counters);
+            if weights_to_evict == 0 {
+                break;
+            }
+            let last_eviction_count = eviction_state.counters.eviction_count;
+            self.evict_lru_entries(
+                deqs,
+                timer_wheel,
+                batch_size,
+                weights_to_evict,
+                RemovalCause::CapacityReduced,
+                eviction_state,
+            );
+            if eviction_state.counters.eviction_count == last_eviction_count {
+                break;
+            }
+        }
+    }
+
     fn evict_lru_entries(
         &self,
         deqs: &mut Deques<K>,
         timer_wheel: &mut TimerWheel<K>,
         batch_size: u32,
         weights_to_evict: u64,
+        cause: RemovalCause,
         eviction_state: &mut EvictionState<'_, K, V>,
     ) where
         V: Clone,
@@ -2324,10 +3057,18 @@ where
                     node.element.hash(),
                     entry_info.is_dirty(),
                     entry_info.last_accessed(),
+                    entry_info.is_pinned(),
                 )
             });
 
-            let (key, hash, ts) = match maybe_key_hash_ts {
+            if let Some((.., false, _, true)) = maybe_key_hash_ts {
+                // Skip this entry as it is pinned. Move it to the back of the deque
+                // so that the unpinned entries behind it can be evicted.
+                ao_deq.move_front_to_back();
+                continue;
+            }
+
+            let (key, hash, ts) = match maybe_key_hash_ts.map(|(k, h, d, ts, _)| (k, h, d, ts)) {
                 Some((key, hash, false, Some(ts))) => (key, hash, ts),
                 // TODO: Remove the second pattern `Some((_key, false, None))` once we change
                 // `last_modified` and `last_accessed` in `EntryInfo` from `Option<Instant>` to
@@ -2353,24 +3094,31 @@ where
             let kl = self.maybe_key_lock(&key);
             let _klg = &kl.as_ref().map(|kl| kl.lock());
 
+            let mut vetoed = false;
             let maybe_entry = self.cache.remove_if(
                 hash,
                 |k| k == &key,
                 |_, v| {
-                    if let Some(la) = v.last_accessed() {
-                        la == ts
-                    } else {
+                    if v.entry_info().is_pinned() {
+                        // The entry has been pinned after we checked above.
+                        false
+                    } else if v.last_accessed() != Some(ts) {
+                        false
+                    } else if self.is_eviction_vetoed(&key, &v.value) {
+                        vetoed = true;
                         false
+                    } else {
+                        true
                     }
                 },
             );
 
             if let Some(entry) = maybe_entry {
+                let weight = entry.policy_weight();
                 if eviction_state.is_notifier_enabled() {
-                    eviction_state.notify_entry_removal(key, &entry, RemovalCause::Size);
+                    eviction_state.notify_entry_removal(key, &entry, cause);
                 }
                 eviction_state.counters.incr_eviction_count();
-                let weight = entry.policy_weight();
                 Self::handle_remove_with_deques(
                     deq_name,
                     ao_deq,
@@ -2379,7 +3127,12 @@ where
                     entry,
                     &mut eviction_state.counters,
                 );
-                evicted = evicted.saturating_add(weight as u64);
+                evicted = evicted.saturating_add(weight);
+            } else if vetoed {
+                // Skip this entry as the `can_evict` closure vetoed its eviction.
+                // Move it to the back of the deque so that the entries behind it
+                // can be evicted.
+                ao_deq.move_front_to_back();
             } else {
                 self.skip_updated_entry_ao(&key, hash, deq_name, ao_deq, wo_deq);
                 more_to_evict = false;
@@ -2394,7 +3147,7 @@ where
 
 impl<K, V, S> Inner<K, V, S>
 where
-    K: Send + Sync + 'static,
+    K: Hash + Eq + Send + Sync + 'static,
     V: Clone + Send + Sync + 'static,
 {
     pub(crate) fn notify_single_removal(
@@ -2403,6 +3156,18 @@ where
         entry: &MiniArc<ValueEntry<K, V>>,
         cause: RemovalCause,
     ) {
+        // Flush or discard the pending write of the removed key in the write-behind
+        // buffer (if any).
+        if let Some(wb) = &self.write_behind {
+            if let Some(pending) = wb.prepare_removal(&key, cause) {
+                pending.wait();
+            }
+        }
+        self.events.publish(|| CacheEvent::Removed {
+            key: Arc::clone(&key),
+            value: entry.value.clone(),
+            cause,
+        });
         if let Some(notifier) = &self.removal_notifier {
             notifier.notify(key, entry.value.clone(), cause);
         }
@@ -2417,21 +3182,20 @@ where
         last_modified: Option<Instant>,
     ) {
         let now = self.current_time();
-        let exp = &self.expiration_policy;
 
         let mut cause = RemovalCause::Replaced;
 
         if let Some(last_accessed) = last_accessed {
-            if is_expired_by_tti(&exp.time_to_idle(), last_accessed, now) {
+            if is_expired_by_tti(&self.time_to_idle(), last_accessed, now) {
                 cause = RemovalCause::Expired;
             }
         }
 
         if let Some(last_modified) = last_modified {
-            if is_expired_by_ttl(&exp.time_to_live(), last_modified, now) {
+            if is_expired_by_ttl(&self.time_to_live(), last_modified, now) {
                 cause = RemovalCause::Expired;
             } else if is_invalid_entry(&self.valid_after(), last_modified) {
-                cause = RemovalCause::Explicit;
+                cause = RemovalCause::InvalidatedAll;
             }
         }
 
@@ -2441,18 +3205,17 @@ where
     #[inline]
     fn notify_invalidate(&self, key: &Arc<K>, entry: &MiniArc<ValueEntry<K, V>>) {
         let now = self.current_time();
-        let exp = &self.expiration_policy;
 
         let mut cause = RemovalCause::Explicit;
 
         if let Some(last_accessed) = entry.last_accessed() {
-            if is_expired_by_tti(&exp.time_to_idle(), last_accessed, now) {
+            if is_expired_by_tti(&self.time_to_idle(), last_accessed, now) {
                 cause = RemovalCause::Expired;
             }
         }
 
         if let Some(last_modified) = entry.last_modified() {
-            if is_expired_by_ttl(&exp.time_to_live(), last_modified, now) {
+            if is_expired_by_ttl(&self.time_to_live(), last_modified, now) {
                 cause = RemovalCause::Expired;
             }
         }
@@ -2480,9 +3243,9 @@ where
 
     fn key_locks_map_is_empty(&self) -> bool {
         self.key_locks
-            .as_ref()
+            .get()
             .map(|m| m.is_empty())
-            // If key_locks is None, consider it is empty.
+            // If key_locks is not created, consider it is empty.
             .unwrap_or(true)
     }
 }
@@ -2604,6 +3367,7 @@ fn is_expired_by_ttl(
 mod tests {
     use crate::{
         common::{time::Clock, HousekeeperConfig},
+        notification::ListenerConfig,
         policy::{EvictionPolicy, ExpirationPolicy},
     };
 
@@ -2624,8 +3388,13 @@ mod tests {
                 None,
                 RandomState::default(),
                 None,
+                None,
+                None,
+                None,
+                None,
                 EvictionPolicy::default(),
                 None,
+                ListenerConfig::default(),
                 ExpirationPolicy::default(),
                 HousekeeperConfig::default(),
                 false,
@@ -2691,7 +3460,10 @@ mod tests {
         }
 
         fn insert(cache: &BaseCache<Key, Value>, key: Key, hash: u64, value: Value) {
-            let (op, _now) = cache.do_insert_with_hash(Arc::new(key), hash, value);
+            let weight = cache.check_entry_weight(&key, &value).unwrap();
+            let (op, _now) = cache
+                .do_insert_with_hash(Arc::new(key), hash, value, weight, Default::default())
+                .unwrap();
             cache.write_op_ch.send(op).expect("Failed to send");
         }
 
@@ -2970,8 +3742,13 @@ mod tests {
             None,
             RandomState::default(),
             None,
+            None,
+            None,
+            None,
+            None,
             EvictionPolicy::default(),
             None,
+            ListenerConfig::default(),
             ExpirationPolicy::new(
                 Some(Duration::from_secs(TTL)),
                 Some(Duration::from_secs(TTI)),
@@ -1,22 +1,27 @@
 use super::{
     base_cache::{BaseCache, HouseKeeperArc},
-    value_initializer::{InitResult, ValueInitializer},
+    value_initializer::{FailedOp, InitResult, ValueInitializer},
     CacheBuilder, OwnedKeyEntrySelector, RefKeyEntrySelector,
 };
 use crate::{
     common::{
+        builder_utils,
         concurrent::{
-            constants::WRITE_RETRY_INTERVAL_MICROS, housekeeper::InnerSync, Weigher, WriteOp,
+            constants::WRITE_RETRY_INTERVAL_MICROS, housekeeper::InnerSync, EvictionVeto,
+            InsertOptions, Weigher, WriteOp, Writer,
         },
         iter::ScanningGet,
-        time::{Clock, Instant},
+        time::{self, Clock, Instant},
+        write_behind::WriteBehindConfig,
         HousekeeperConfig,
     },
-    notification::EvictionListener,
+    notification::{
+        Backpressure, EventReceiver, EvictionListener, ListenerConfig, RemovalCause, Subscriber,
+    },
     ops::compute::{self, CompResult},
     policy::{EvictionPolicy, ExpirationPolicy},
     sync::{Iter, PredicateId},
-    Entry, Policy, PredicateError,
+    Entry, EntryMetadata, InsertError, Policy, PredicateError, WriteError,
 };
 
 use crossbeam_channel::{Sender, TrySendError};
@@ -26,7 +31,7 @@ use std::{
     fmt,
     hash::{BuildHasher, Hash},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant as StdInstant, SystemTime},
 };
 
 /// A thread-safe concurrent synchronous in-memory cache.
@@ -627,10 +632,24 @@ impl<K, V, S> Cache<K, V, S> {
         self.base.name()
     }
 
+    /// Logs the error from the [`CacheWriter`][crate::CacheWriter] that is not
+    /// returned to the caller, e.g. by `insert` and `invalidate`.
+    pub(crate) fn log_writer_error(&self, _error: &WriteError) {
+        #[cfg(feature = "logging")]
+        {
+            let cn = self
+                .name()
+                .map(|name| format!("[{name}] "))
+                .unwrap_or_default();
+            log::error!("{cn}{_error}");
+        }
+    }
+
     /// Returns a read-only cache policy of this cache.
     ///
-    /// At this time, cache policy cannot be modified after cache creation.
-    /// A future version may support to modify it.
+    /// Some of the policy can be modified after cache creation by methods such as
+    /// `set_max_capacity_block`, `set_time_to_live` and
+    /// `set_time_to_idle`.
     pub fn policy(&self) -> Policy {
         self.base.policy()
     }
@@ -682,6 +701,25 @@ impl<K, V, S> Cache<K, V, S> {
     pub fn weighted_size(&self) -> u64 {
         self.base.weighted_size()
     }
+
+    /// Returns an approximate total weighted size of the pinned entries in this
+    /// cache. The pinned entries are also counted in the
+    /// [`weighted_size`](#method.weighted_size).
+    ///
+    /// Like `weighted_size`, the value returned is _an estimate_. See
+    /// [`pin`](#method.pin) for more details about pinned entries.
+    pub fn pinned_weighted_size(&self) -> u64 {
+        self.base.pinned_weighted_size()
+    }
+
+    /// Returns the number of times the eviction listener of this cache panicked.
+    ///
+    /// The panics are caught by the cache. What happens to the listener after a
+    /// panic is configured by the `eviction_listener_panic_policy` method of the
+    /// cache builder.
+    pub fn eviction_listener_panic_count(&self) -> u64 {
+        self.base.eviction_listener_panic_count()
+    }
 }
 
 impl<K, V> Cache<K, V, RandomState>
@@ -703,8 +741,13 @@ where
             None,
             build_hasher,
             None,
+            None,
+            None,
+            None,
+            None,
             EvictionPolicy::default(),
             None,
+            ListenerConfig::default(),
             ExpirationPolicy::default(),
             HousekeeperConfig::default(),
             false,
@@ -735,8 +778,13 @@ where
         initial_capacity: Option<usize>,
         build_hasher: S,
         weigher: Option<Weigher<K, V>>,
+        max_entry_weight: Option<u64>,
+        can_evict: Option<EvictionVeto<K, V>>,
+        writer: Option<Writer<K, V>>,
+        write_behind: Option<WriteBehindConfig<K, V>>,
         eviction_policy: EvictionPolicy,
         eviction_listener: Option<EvictionListener<K, V>>,
+        listener_config: ListenerConfig<K>,
         expiration_policy: ExpirationPolicy<K, V>,
         housekeeper_config: HousekeeperConfig,
         invalidator_enabled: bool,
@@ -749,8 +797,13 @@ where
                 initial_capacity,
                 build_hasher.clone(),
                 weigher,
+                max_entry_weight,
+                can_evict,
+                writer,
+                write_behind,
                 eviction_policy,
                 eviction_listener,
+                listener_config,
                 expiration_policy,
                 housekeeper_config,
                 invalidator_enabled,
@@ -811,6 +864,258 @@ where
         self.base.set_max_capacity_async(new_capacity)
     }
 
+    /// Sets the `time_to_live` of this cache. `None` disables the expiration by
+    /// the time-to-live.
+    ///
+    /// The new value also applies to the existing entries. They will not be
+    /// returned by the read methods once they are expired by the new value, and
+    /// they will be removed from the cache at the next run of the pending
+    /// maintenance tasks.
+    ///
+    /// Enabling the time-to-live on a cache that was built without it (and without
+    /// [`support_invalidation_closures`][support-invalidation-closures]) takes a
+    /// time proportional to the number of the entries in the cache.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is longer than 1000 years.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Cache::new(100);
+    /// cache.insert(1, "one");
+    ///
+    /// cache.set_time_to_live(Some(Duration::from_millis(100)));
+    /// assert_eq!(cache.policy().time_to_live(), Some(Duration::from_millis(100)));
+    ///
+    /// std::thread::sleep(Duration::from_millis(150));
+    /// assert_eq!(cache.get(&1), None);
+    /// ```
+    ///
+    /// [support-invalidation-closures]:
+    ///     ./struct.CacheBuilder.html#method.support_invalidation_closures
+    pub fn set_time_to_live(&self, duration: Option<Duration>) {
+        builder_utils::ensure_expirations_or_panic(duration, None);
+        self.base.set_time_to_live(duration);
+    }
+
+    /// Sets the `time_to_idle` of this cache. `None` disables the expiration by
+    /// the time-to-idle.
+    ///
+    /// The new value also applies to the existing entries. They will not be
+    /// returned by the read methods once they are expired by the new value, and
+    /// they will be removed from the cache at the next run of the pending
+    /// maintenance tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is longer than 1000 years.
+    pub fn set_time_to_idle(&self, duration: Option<Duration>) {
+        builder_utils::ensure_expirations_or_panic(None, duration);
+        self.base.set_time_to_idle(duration);
+    }
+
+    /// Switches the [`EvictionPolicy`] of this cache without losing the cached
+    /// entries.
+    ///
+    /// The new policy is sent to the cache as a write operation, so it takes effect
+    /// (and is reported by [`policy`](#method.policy)) once the pending tasks are
+    /// processed, e.g. by calling [`run_pending_tasks`](#method.run_pending_tasks).
+    ///
+    /// Switching to the LRU policy releases the frequency sketch used by TinyLFU.
+    /// Switching back to TinyLFU allocates a new one, so the historical popularity
+    /// of the keys is rebuilt from scratch.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::{policy::EvictionPolicy, sync::Cache};
+    ///
+    /// let cache = Cache::new(100);
+    /// cache.insert(1, "one");
+    ///
+    /// cache.set_eviction_policy(EvictionPolicy::lru());
+    /// cache.run_pending_tasks();
+    ///
+    /// assert_eq!(cache.policy().eviction_policy(), &EvictionPolicy::lru());
+    /// assert_eq!(cache.get(&1), Some("one"));
+    /// ```
+    pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        let op = WriteOp::SetEvictionPolicy {
+            policy: policy.config,
+        };
+        let now = self.base.current_time();
+        let hk = self.base.housekeeper.as_ref();
+        Self::schedule_write_op(
+            self.base.inner.as_ref(),
+            &self.base.write_op_ch,
+            op,
+            now,
+            hk,
+        )
+        .expect("Failed to set the eviction policy");
+    }
+
+    /// Subscribes to the mutations of this cache, and returns an
+    /// [`EventReceiver`][event-receiver] of the [`CacheEvent`][cache-event]s.
+    ///
+    /// The events are sent to a bounded channel with the given `capacity`
+    /// (at least one). When the channel is full, the given
+    /// [`Backpressure`][backpressure] determines whether the new event waits for
+    /// room, or an event is discarded and counted.
+    ///
+    /// Unlike the eviction listener, the subscriber also receives the inserts,
+    /// updates and loads, so it can be used to replicate the contents of this cache
+    /// to a secondary store. When an existing value is replaced, the `Removed`
+    /// event of the old value is sent before the `Updated` event of the new value.
+    ///
+    /// The events of the same key are sent in the order the mutations were applied,
+    /// even when the key is updated by multiple threads concurrently. (The first
+    /// subscription makes the cache serialize the mutations of the same key by a
+    /// per-key lock.) Only the mutations made after subscribing are sent, and
+    /// those in progress while subscribing may be sent out of order. Dropping the
+    /// receiver unsubscribes it.
+    ///
+    /// [event-receiver]: ../notification/struct.EventReceiver.html
+    /// [cache-event]: ../notification/enum.CacheEvent.html
+    /// [backpressure]: ../notification/enum.Backpressure.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::{
+    ///     notification::{Backpressure, CacheEvent},
+    ///     sync::Cache,
+    /// };
+    ///
+    /// let cache = Cache::new(100);
+    /// let events = cache.subscribe(16, Backpressure::Block);
+    ///
+    /// cache.insert(1, "one");
+    /// cache.insert(1, "uno");
+    /// cache.invalidate(&1);
+    ///
+    /// assert!(matches!(events.recv(), Some(CacheEvent::Inserted { .. })));
+    /// assert!(matches!(events.recv(), Some(CacheEvent::Removed { .. })));
+    /// assert!(matches!(events.recv(), Some(CacheEvent::Updated { .. })));
+    /// assert!(matches!(events.recv(), Some(CacheEvent::Removed { .. })));
+    /// ```
+    pub fn subscribe(&self, capacity: usize, backpressure: Backpressure) -> EventReceiver<K, V> {
+        let subscriber = Subscriber::new(capacity, backpressure);
+        let receiver = EventReceiver::new(&subscriber);
+        self.base.subscribe(subscriber);
+        receiver
+    }
+
+    /// Blocks the current thread until the eviction listener has been called for
+    /// all the notifications queued so far.
+    ///
+    /// This method is useful only when the cache was built with
+    /// [`DeliveryMode::Queued`][queued]. With the default `DeliveryMode::Immediate`,
+    /// the listener has already been called when a cache operation returns, so this
+    /// method returns immediately.
+    ///
+    /// Note that this method does not run the pending tasks. Call
+    /// [`run_pending_tasks`](#method.run_pending_tasks) first if you want the
+    /// notifications for the evicted or expired entries to be queued.
+    ///
+    /// When called from the eviction listener itself (which runs on the delivery
+    /// thread), this method returns immediately instead of waiting for the
+    /// listener to return.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::{notification::DeliveryMode, sync::Cache};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let removed = Arc::new(Mutex::new(Vec::new()));
+    /// let removed2 = Arc::clone(&removed);
+    ///
+    /// let cache = Cache::builder()
+    ///     .eviction_listener(move |k, v, _cause| removed2.lock().unwrap().push((*k, v)))
+    ///     .delivery_mode(DeliveryMode::Queued)
+    ///     .build();
+    ///
+    /// cache.insert(1, "one");
+    /// cache.invalidate(&1);
+    ///
+    /// cache.wait_for_notifications();
+    /// assert_eq!(*removed.lock().unwrap(), vec![(1, "one")]);
+    /// ```
+    ///
+    /// [queued]: ../notification/enum.DeliveryMode.html#variant.Queued
+    pub fn wait_for_notifications(&self) {
+        self.base.wait_for_notifications();
+    }
+
+    /// Closes the cache for a shutdown. Invalidates all entries, runs the pending
+    /// tasks until the invalidated entries are removed, flushes the write-behind
+    /// buffer (if any) and waits for the flush, and then waits until the eviction
+    /// listener has been called for all of them.
+    ///
+    /// The eviction listener will be notified with
+    /// [`RemovalCause::InvalidatedAll`][invalidated-all] for the removed entries.
+    /// Note that dropping a cache drops its entries without notifying the listener,
+    /// so call this method before dropping the cache if the listener needs to see
+    /// every entry (e.g. to flush them to a backing store).
+    ///
+    /// This method does not prevent other threads from using the cache. Entries
+    /// inserted while this method is running may be left in the cache, so stop the
+    /// writers before calling it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::{notification::RemovalCause, sync::Cache};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let flushed = Arc::new(Mutex::new(Vec::new()));
+    /// let flushed2 = Arc::clone(&flushed);
+    ///
+    /// let cache = Cache::builder()
+    ///     .eviction_listener(move |k, v, cause| {
+    ///         assert_eq!(cause, RemovalCause::InvalidatedAll);
+    ///         flushed2.lock().unwrap().push((*k, v));
+    ///     })
+    ///     .build();
+    ///
+    /// cache.insert(1, "one");
+    /// cache.insert(2, "two");
+    ///
+    /// cache.close();
+    /// assert_eq!(cache.entry_count(), 0);
+    ///
+    /// let mut flushed = flushed.lock().unwrap();
+    /// flushed.sort_unstable();
+    /// assert_eq!(*flushed, vec![(1, "one"), (2, "two")]);
+    /// ```
+    ///
+    /// [invalidated-all]: ../notification/enum.RemovalCause.html#variant.InvalidatedAll
+    pub fn close(&self) {
+        self.base.invalidate_all_until_now();
+
+        // `run_pending_tasks` may not remove all the invalidated entries at once
+        // (e.g. when it timed out because of a slow eviction listener), so repeat it
+        // while it makes progress.
+        let mut last_count = u64::MAX;
+        loop {
+            self.run_pending_tasks();
+            let count = self.entry_count();
+            if count == 0 || count >= last_count {
+                break;
+            }
+            last_count = count;
+        }
+
+        self.base.flush_write_behind();
+        self.wait_for_notifications();
+    }
+
     /// Returns `true` if the cache contains a value for the key.
     ///
     /// Unlike the `get` method, this method is not considered a cache read operation,
@@ -859,6 +1164,93 @@ where
         self.base.get_with_hash(key, hash, need_key)
     }
 
+    /// Returns a _clone_ of the value corresponding to the key, without any side
+    /// effects on the cache.
+    ///
+    /// Unlike the `get` method, this method is not considered a cache read
+    /// operation. So it does not update the historic popularity estimator, the
+    /// access order (LRU) of the entry, or reset the idle timer for the key. It is
+    /// useful for debugging or scanning the cache in background.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Cache::builder()
+    ///     .time_to_idle(Duration::from_secs(60))
+    ///     .build();
+    /// cache.insert("key1", "value1");
+    ///
+    /// // `peek` does not reset the idle timer of "key1".
+    /// assert_eq!(cache.peek(&"key1"), Some("value1"));
+    /// assert_eq!(cache.peek(&"key2"), None);
+    /// ```
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.peek_with_hash(key, self.base.hash(key))
+    }
+
+    pub(crate) fn peek_with_hash<Q>(&self, key: &Q, hash: u64) -> Option<V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.peek_with_hash(key, hash)
+    }
+
+    /// Returns the [`EntryMetadata`] of the entry for the key, such as its last
+    /// accessed and modified times, its expiration time, its weight and its
+    /// estimated access frequency.
+    ///
+    /// Like the `contains_key` method, this method is not considered a cache read
+    /// operation, so it does not update the historic popularity estimator or reset
+    /// the idle timer for the key.
+    ///
+    /// Returns `None` if the cache does not contain a value for the key.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [`EntryMetadata`]: ../struct.EntryMetadata.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Cache::builder()
+    ///     .time_to_live(Duration::from_secs(60))
+    ///     .build();
+    /// cache.insert("key1", "value1");
+    ///
+    /// let metadata = cache.peek_metadata(&"key1").unwrap();
+    /// assert!(metadata.last_modified() <= metadata.last_accessed());
+    /// assert!(metadata.time_to_expiration().unwrap() <= Duration::from_secs(60));
+    /// assert_eq!(metadata.policy_weight(), 1);
+    ///
+    /// assert!(cache.peek_metadata(&"key2").is_none());
+    /// ```
+    pub fn peek_metadata<Q>(&self, key: &Q) -> Option<EntryMetadata>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.peek_metadata_with_hash(key, self.base.hash(key))
+    }
+
+    pub(crate) fn peek_metadata_with_hash<Q>(&self, key: &Q, hash: u64) -> Option<EntryMetadata>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.peek_metadata_with_hash(key, hash)
+    }
+
     /// Takes a key `K` and returns an [`OwnedKeyEntrySelector`] that can be used to
     /// select or insert an entry.
     ///
@@ -1081,7 +1473,7 @@ where
             self.base
                 .get_with_hash_without_recording(&*key, hash, replace_if.as_mut())
         };
-        let insert = |v| self.insert_with_hash(key.clone(), hash, v);
+        let insert = |v| self.load_with_hash_and_ttl(key.clone(), hash, v, None);
 
         let k = if need_key {
             Some(Arc::clone(&key))
@@ -1110,12 +1502,13 @@ where
         key: Arc<K>,
         hash: u64,
         init: impl FnOnce() -> V,
+        ttl: Option<Duration>,
     ) -> Entry<K, V> {
         match self.base.get_with_hash(&*key, hash, true) {
             Some(entry) => entry,
             None => {
                 let value = init();
-                self.insert_with_hash(Arc::clone(&key), hash, value.clone());
+                self.load_with_hash_and_ttl(Arc::clone(&key), hash, value.clone(), ttl);
                 Entry::new(Some(key), value, true, false)
             }
         }
@@ -1126,6 +1519,7 @@ where
         key: &Q,
         hash: u64,
         init: impl FnOnce() -> V,
+        ttl: Option<Duration>,
     ) -> Entry<K, V>
     where
         Q: Equivalent<K> + ToOwned<Owned = K> + Hash + ?Sized,
@@ -1135,7 +1529,7 @@ where
             None => {
                 let key = Arc::new(key.to_owned());
                 let value = init();
-                self.insert_with_hash(Arc::clone(&key), hash, value.clone());
+                self.load_with_hash_and_ttl(Arc::clone(&key), hash, value.clone(), ttl);
                 Entry::new(Some(key), value, true, false)
             }
         }
@@ -1302,7 +1696,7 @@ where
             self.base
                 .get_with_hash_without_recording(&*key, hash, ignore_if)
         };
-        let insert = |v| self.insert_with_hash(key.clone(), hash, v);
+        let insert = |v| self.load_with_hash_and_ttl(key.clone(), hash, v, None);
 
         let k = if need_key {
             Some(Arc::clone(&key))
@@ -1494,7 +1888,7 @@ where
             self.base
                 .get_with_hash_without_recording(&*key, hash, ignore_if)
         };
-        let insert = |v| self.insert_with_hash(key.clone(), hash, v);
+        let insert = |v| self.load_with_hash_and_ttl(key.clone(), hash, v, None);
 
         let k = if need_key {
             Some(Arc::clone(&key))
@@ -1524,6 +1918,12 @@ where
     /// Inserts a key-value pair into the cache.
     ///
     /// If the cache has this key present, the value is updated.
+    ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to write the entry,
+    /// the cache is not modified and the error is only logged (when the `logging`
+    /// feature is enabled). Use [`try_insert`](#method.try_insert) to get the error.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub fn insert(&self, key: K, value: V) {
         let hash = self.base.hash(&key);
         let key = Arc::new(key);
@@ -1531,11 +1931,219 @@ where
     }
 
     pub(crate) fn insert_with_hash(&self, key: Arc<K>, hash: u64, value: V) {
+        // The rejection has been already notified to the eviction listener. The
+        // error from the writer (if any) is returned only by `try_insert`.
+        let result = self.do_insert_with_hash(key, hash, value, InsertOptions::default());
+        if let Err(InsertError::WriterFailed(e)) = result {
+            self.log_writer_error(&e);
+        }
+    }
+
+    /// Inserts a key-value pair into the cache with the time-to-live for this
+    /// entry.
+    ///
+    /// The entry will expire after `ttl` elapsed from this insert, regardless of
+    /// the [`Expiry`][expiry-trait] set to the cache; the `Expiry` is not called
+    /// for this insert. The cache-wide
+    /// `time_to_live` and `time_to_idle` still apply, so the entry will expire at
+    /// the earliest of them.
+    ///
+    /// If the cache has this key present, the value is updated and the
+    /// time-to-live of the entry is replaced with `ttl`. Note that updating the
+    /// entry by [`insert`](#method.insert) will clear the time-to-live of the
+    /// entry set by this method, unless an `Expiry` is set to the cache.
+    ///
+    /// [expiry-trait]: ../policy/trait.Expiry.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Cache::new(100);
+    ///
+    /// cache.insert_with_ttl("a", "alice", Duration::from_millis(50));
+    /// cache.insert("b", "bob");
+    /// assert_eq!(cache.get(&"a"), Some("alice"));
+    ///
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert_eq!(cache.get(&"a"), None);
+    /// assert_eq!(cache.get(&"b"), Some("bob"));
+    /// ```
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        let hash = self.base.hash(&key);
+        let key = Arc::new(key);
+        // The rejection has been already notified to the eviction listener.
+        let result = self.insert_with_hash_and_ttl(key, hash, value, Some(ttl));
+        if let Err(InsertError::WriterFailed(e)) = result {
+            self.log_writer_error(&e);
+        }
+    }
+
+    /// Inserts a key-value pair into the cache with the wall-clock time when this
+    /// entry expires.
+    ///
+    /// This is useful when the upstream gives the expiration as an absolute time,
+    /// such as the `Expires` header of HTTP or the `exp` claim of JWT. If
+    /// `expire_at` has already passed, the entry is inserted as expired, so it will
+    /// never be returned and will be removed by a housekeeping task later.
+    ///
+    /// Other than that, this method works in the same way as
+    /// [`insert_with_ttl`](#method.insert_with_ttl).
+    ///
+    /// # Clock adjustments
+    ///
+    /// The cache keeps time with a monotonic clock, which is not affected by the
+    /// adjustments to the system clock. So `expire_at` is converted to the
+    /// time-to-live of the entry (`expire_at` minus `SystemTime::now()`) when this
+    /// method is called, and the adjustments to the system clock made _after_ the
+    /// insert (e.g. by NTP) will not change when the entry expires.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let cache = Cache::new(100);
+    ///
+    /// // e.g. The expiration time of a token given by an authorization server.
+    /// let expire_at = SystemTime::now() + Duration::from_millis(50);
+    /// cache.insert_with_expire_at("token", "secret", expire_at);
+    /// assert_eq!(cache.get(&"token"), Some("secret"));
+    ///
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert_eq!(cache.get(&"token"), None);
+    /// ```
+    pub fn insert_with_expire_at(&self, key: K, value: V, expire_at: SystemTime) {
+        self.insert_with_ttl(key, value, time::duration_until(expire_at));
+    }
+
+    /// Inserts the entry with the given per-entry time-to-live. If `ttl` is
+    /// `None`, this is the same as `try_insert_with_hash`.
+    pub(crate) fn insert_with_hash_and_ttl(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<(), InsertError> {
+        let opts = InsertOptions {
+            ttl,
+            ..Default::default()
+        };
+        self.do_insert_with_hash(key, hash, value, opts)
+    }
+
+    /// Inserts the value loaded by `get_with` or a similar method. The subscribers
+    /// will receive it as a `Loaded` event.
+    fn load_with_hash_and_ttl(&self, key: Arc<K>, hash: u64, value: V, ttl: Option<Duration>) {
+        // The rejection has been already notified to the eviction listener.
+        let opts = InsertOptions {
+            loaded: true,
+            ttl,
+            ..Default::default()
+        };
+        let _ = self.do_insert_with_hash(key, hash, value, opts);
+    }
+
+    /// Inserts a key-value pair into the cache, and returns an error if the entry
+    /// was rejected by the cache.
+    ///
+    /// This method works like [`insert`](#method.insert) but returns
+    /// [`InsertError::EntryTooHeavy`][entry-too-heavy] when the weight of the entry
+    /// exceeds the max entry weight configured by
+    /// [`CacheBuilder::max_entry_weight`][max-entry-weight]. In that case, the
+    /// entry is not stored, and the existing entry for the key (if any) is
+    /// removed.
+    ///
+    /// It also returns [`InsertError::WriterFailed`][writer-failed] when the
+    /// [`CacheWriter`][cache-writer] of the cache failed to write the entry. In that
+    /// case, the cache is not modified.
+    ///
+    /// [entry-too-heavy]: ../enum.InsertError.html#variant.EntryTooHeavy
+    /// [max-entry-weight]: ./struct.CacheBuilder.html#method.max_entry_weight
+    /// [writer-failed]: ../enum.InsertError.html#variant.WriterFailed
+    /// [cache-writer]: ../trait.CacheWriter.html
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::{sync::Cache, InsertError};
+    ///
+    /// let cache = Cache::builder()
+    ///     .max_capacity(100)
+    ///     .weigher(|_k, v: &String| v.len() as u32)
+    ///     .max_entry_weight(10)
+    ///     .build();
+    ///
+    /// assert!(cache.try_insert(1, "short".to_string()).is_ok());
+    /// assert_eq!(
+    ///     cache.try_insert(2, "a little too long".to_string()),
+    ///     Err(InsertError::EntryTooHeavy { weight: 17, max_entry_weight: 10 })
+    /// );
+    /// assert!(!cache.contains_key(&2));
+    /// ```
+    pub fn try_insert(&self, key: K, value: V) -> Result<(), InsertError> {
+        let hash = self.base.hash(&key);
+        let key = Arc::new(key);
+        self.try_insert_with_hash(key, hash, value)
+    }
+
+    pub(crate) fn try_insert_with_hash(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        value: V,
+    ) -> Result<(), InsertError> {
+        self.do_insert_with_hash(key, hash, value, InsertOptions::default())
+    }
+
+    /// Inserts a key-value pair into the cache and pins the entry.
+    ///
+    /// If the cache has this key present, the value is updated and the entry is
+    /// pinned. See [`pin`](#method.pin) for more details about pinned entries.
+    pub fn insert_pinned(&self, key: K, value: V) {
+        let hash = self.base.hash(&key);
+        let key = Arc::new(key);
+        self.insert_pinned_with_hash(key, hash, value);
+    }
+
+    pub(crate) fn insert_pinned_with_hash(&self, key: Arc<K>, hash: u64, value: V) {
+        let opts = InsertOptions {
+            pin: true,
+            ..Default::default()
+        };
+        let result = self.do_insert_with_hash(key, hash, value, opts);
+        if let Err(InsertError::WriterFailed(e)) = result {
+            self.log_writer_error(&e);
+        }
+    }
+
+    fn do_insert_with_hash(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        value: V,
+        opts: InsertOptions,
+    ) -> Result<(), InsertError> {
         if self.base.is_map_disabled() {
-            return;
+            return Ok(());
         }
 
-        let (op, now) = self.base.do_insert_with_hash(key, hash, value);
+        let weight = match self.base.check_entry_weight(&key, &value) {
+            Ok(weight) => weight,
+            Err(e) => {
+                self.reject_with_hash(key, hash, value);
+                return Err(e);
+            }
+        };
+
+        let (op, now) = self
+            .base
+            .do_insert_with_hash(key, hash, value, weight, opts)
+            .map_err(InsertError::WriterFailed)?;
         let hk = self.base.housekeeper.as_ref();
         Self::schedule_write_op(
             self.base.inner.as_ref(),
@@ -1545,6 +2153,48 @@ where
             hk,
         )
         .expect("Failed to insert");
+        Ok(())
+    }
+
+    /// Rejects the value that exceeds the max entry weight. The existing entry (if
+    /// any) is removed so that its stale value will not be returned after this
+    /// insert, and it is notified as replaced by the rejected value. Both
+    /// notifications are made under the same key lock. The old value is not
+    /// deleted through the writer as the rejected value was not written either.
+    fn reject_with_hash(&self, key: Arc<K>, hash: u64, value: V) {
+        let kl = self.base.maybe_key_lock(&key);
+        let klg = kl.as_ref().map(|kl| kl.lock());
+
+        let removed = self.base.remove_entry(&*key, hash).map(|kv| {
+            let entry_gen = kv.entry.entry_info().incr_entry_gen();
+            if self.base.is_removal_notifier_enabled() {
+                self.base.notify_replaced(&kv.key, &kv.entry);
+            }
+            (kv, entry_gen)
+        });
+        self.base.notify_rejected(key, value);
+
+        // Drop the locks before scheduling write op to avoid a potential dead lock.
+        std::mem::drop(klg);
+        std::mem::drop(kl);
+
+        if let Some((kv, entry_gen)) = removed {
+            let now = self.base.current_time();
+            let op = WriteOp::Remove {
+                kv_entry: kv,
+                entry_gen,
+            };
+            let hk = self.base.housekeeper.as_ref();
+            Self::schedule_write_op(
+                self.base.inner.as_ref(),
+                &self.base.write_op_ch,
+                op,
+                now,
+                hk,
+            )
+            .expect("Failed to remove");
+            crossbeam_epoch::pin().flush();
+        }
     }
 
     pub(crate) fn compute_with_hash_and_fun<F>(
@@ -1561,24 +2211,45 @@ where
             .value_initializer
             .try_compute(key, hash, self, f, post_init, true)
         {
-            Ok(result) => result,
+            Ok((result, _)) => result,
             Err(_) => unreachable!(),
         }
     }
 
-    pub(crate) fn try_compute_with_hash_and_fun<F, E>(
+    pub(crate) fn checked_compute_with_hash_and_fun<F>(
         &self,
         key: Arc<K>,
         hash: u64,
         f: F,
-    ) -> Result<compute::CompResult<K, V>, E>
+    ) -> Result<compute::CompResult<K, V>, WriteError>
     where
-        F: FnOnce(Option<Entry<K, V>>) -> Result<compute::Op<V>, E>,
-        E: Send + Sync + 'static,
+        F: FnOnce(Option<Entry<K, V>>) -> compute::Op<V>,
     {
-        let post_init = ValueInitializer::<K, V, S>::post_init_for_try_compute_with;
+        let post_init = ValueInitializer::<K, V, S>::post_init_for_compute_with;
+        match self
+            .value_initializer
+            .try_compute(key, hash, self, f, post_init, true)
+        {
+            Ok((_, Some(FailedOp { error: Some(e), .. }))) => Err(e),
+            Ok((result, _)) => Ok(result),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    pub(crate) fn try_compute_with_hash_and_fun<F, E>(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        f: F,
+    ) -> Result<compute::CompResult<K, V>, E>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> Result<compute::Op<V>, E>,
+        E: Send + Sync + 'static,
+    {
+        let post_init = ValueInitializer::<K, V, S>::post_init_for_try_compute_with;
         self.value_initializer
             .try_compute(key, hash, self, f, post_init, true)
+            .map(|(result, _)| result)
     }
 
     pub(crate) fn upsert_with_hash_and_fun<F>(&self, key: Arc<K>, hash: u64, f: F) -> Entry<K, V>
@@ -1588,20 +2259,223 @@ where
         let post_init = ValueInitializer::<K, V, S>::post_init_for_upsert_with;
         match self
             .value_initializer
-            .try_compute(key, hash, self, f, post_init, false)
+            .try_compute(Arc::clone(&key), hash, self, f, post_init, false)
+        {
+            Ok((CompResult::Inserted(entry) | CompResult::ReplacedWith(entry), None)) => entry,
+            // The value was not stored in the cache.
+            Ok((_, Some(FailedOp { value: Some(v), .. }))) => {
+                Entry::new(Some(key), v, false, false)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) fn checked_upsert_with_hash_and_fun<F>(
+        &self,
+        key: Arc<K>,
+        hash: u64,
+        f: F,
+    ) -> Result<Entry<K, V>, WriteError>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> V,
+    {
+        let post_init = ValueInitializer::<K, V, S>::post_init_for_upsert_with;
+        match self
+            .value_initializer
+            .try_compute(Arc::clone(&key), hash, self, f, post_init, false)
         {
-            Ok(CompResult::Inserted(entry) | CompResult::ReplacedWith(entry)) => entry,
+            Ok((CompResult::Inserted(entry) | CompResult::ReplacedWith(entry), None)) => Ok(entry),
+            Ok((_, Some(FailedOp { error: Some(e), .. }))) => Err(e),
+            // The value was rejected for its weight.
+            Ok((_, Some(FailedOp { value: Some(v), .. }))) => {
+                Ok(Entry::new(Some(key), v, false, false))
+            }
             _ => unreachable!(),
         }
     }
 
+    /// Pins the cached entry for the key so that it will never be evicted by the
+    /// max capacity of the cache. Returns `true` if the entry exists.
+    ///
+    /// A pinned entry still counts toward the max capacity, so the cache will evict
+    /// the unpinned entries to make room for it. A pinned entry can still be
+    /// removed by [`invalidate`](#method.invalidate) and other invalidation
+    /// methods, or expire by the expiration policies. Updating the value of a
+    /// pinned entry does not unpin it.
+    ///
+    /// If the total weight of the pinned entries exceeds the max capacity, the cache
+    /// evicts all unpinned entries and keeps the pinned ones, so its
+    /// [`weighted_size`](#method.weighted_size) will exceed the max capacity until
+    /// some of the pinned entries are unpinned or removed. Check
+    /// [`pinned_weighted_size`](#method.pinned_weighted_size) to monitor it.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    ///
+    /// let cache = Cache::new(2);
+    /// cache.insert_pinned("config", 0);
+    /// cache.insert("a", 1);
+    /// assert!(cache.pin(&"a"));
+    /// assert!(!cache.pin(&"b"));
+    ///
+    /// for i in 0..10 {
+    ///     cache.insert("other", i);
+    ///     cache.run_pending_tasks();
+    /// }
+    ///
+    /// assert!(cache.contains_key(&"config"));
+    /// assert!(cache.contains_key(&"a"));
+    /// assert_eq!(cache.pinned_weighted_size(), 2);
+    /// ```
+    pub fn pin<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        self.set_pinned_with_hash(key, hash, true)
+    }
+
+    /// Unpins the cached entry for the key so that it can be evicted again. Returns
+    /// `true` if the entry exists.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn unpin<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        self.set_pinned_with_hash(key, hash, false)
+    }
+
+    pub(crate) fn set_pinned_with_hash<Q>(&self, key: &Q, hash: u64, pinned: bool) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        if let Some((op, now)) = self.base.set_pinned_with_hash(key, hash, pinned) {
+            let hk = self.base.housekeeper.as_ref();
+            Self::schedule_write_op(
+                self.base.inner.as_ref(),
+                &self.base.write_op_ch,
+                op,
+                now,
+                hk,
+            )
+            .expect("Failed to pin");
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sets the expiration of the cached entry for the key to `ttl` from now, without
+    /// changing its value. Returns `true` if the entry exists.
+    ///
+    /// When `ttl` is `None`, the per-entry expiration time of the entry is unset, so
+    /// the entry will only be expired by the cache-wide `time_to_live` and
+    /// `time_to_idle`. Like the per-entry TTL given by `insert_with_ttl`, the
+    /// expiration time set by this method can be overridden by the
+    /// [`Expiry`][crate::Expiry] of the cache on the next read or update of the
+    /// entry.
+    ///
+    /// This method does not trigger a `Replaced` notification to the eviction
+    /// listener as the value is not changed.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Cache::builder()
+    ///     .time_to_live(Duration::from_secs(60))
+    ///     .build();
+    /// cache.insert("a", 1);
+    ///
+    /// // Expire "a" earlier than the cache-wide TTL.
+    /// assert!(cache.set_expiration(&"a", Some(Duration::from_millis(50))));
+    /// assert!(!cache.set_expiration(&"b", None));
+    ///
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert_eq!(cache.get(&"a"), None);
+    /// ```
+    pub fn set_expiration<Q>(&self, key: &Q, ttl: Option<Duration>) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.set_expiration_with_hash(key, self.base.hash(key), ttl)
+    }
+
+    pub(crate) fn set_expiration_with_hash<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        ttl: Option<Duration>,
+    ) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        if let Some((op, now)) = self.base.set_expiration_with_hash(key, hash, ttl) {
+            let hk = self.base.housekeeper.as_ref();
+            Self::schedule_write_op(
+                self.base.inner.as_ref(),
+                &self.base.write_op_ch,
+                op,
+                now,
+                hk,
+            )
+            .expect("Failed to set expiration");
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Marks the cached entry for the key as accessed, without cloning its value.
+    /// Returns `true` if the entry exists.
+    ///
+    /// Like the `get` method, this method resets the idle timer for the key, calls
+    /// the `expire_after_read` method of the [`Expiry`][crate::Expiry] if any, and
+    /// updates the historic popularity estimator.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn touch<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.touch_with_hash(key, self.base.hash(key))
+    }
+
+    pub(crate) fn touch_with_hash<Q>(&self, key: &Q, hash: u64) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.base.touch_with_hash(key, hash)
+    }
+
     /// Discards any cached value for the key.
     ///
     /// If you need to get a the value that has been discarded, use the
     /// [`remove`](#method.remove) method instead.
     ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to delete the entry,
+    /// the cached value is not discarded and the error is only logged (when the
+    /// `logging` feature is enabled). Use [`try_invalidate`](#method.try_invalidate)
+    /// to get the error.
+    ///
     /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
     /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub fn invalidate<Q>(&self, key: &Q)
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -1615,8 +2489,15 @@ where
     /// If you do not need to get the value that has been discarded, use the
     /// [`invalidate`](#method.invalidate) method instead.
     ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to delete the entry,
+    /// the cached value is not discarded, `None` is returned and the error is only
+    /// logged (when the `logging` feature is enabled). Use
+    /// [`try_remove`](#method.try_remove) to get the error.
+    ///
     /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
     /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub fn remove<Q>(&self, key: &Q) -> Option<V>
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -1625,14 +2506,69 @@ where
         self.invalidate_with_hash(key, hash, true)
     }
 
+    /// Discards any cached value for the key, and returns an error if the
+    /// [`CacheWriter`][cache-writer] of the cache failed to delete it.
+    ///
+    /// This method works like [`invalidate`](#method.invalidate). If the writer
+    /// fails, the cached value is not discarded and the error is returned.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    pub fn try_invalidate<Q>(&self, key: &Q) -> Result<(), WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        self.try_invalidate_with_hash(key, hash, false, true)
+            .map(|_| ())
+    }
+
+    /// Discards any cached value for the key and returns a _clone_ of the value,
+    /// or returns an error if the [`CacheWriter`][cache-writer] of the cache failed
+    /// to delete it.
+    ///
+    /// This method works like [`remove`](#method.remove). If the writer fails, the
+    /// cached value is not discarded and the error is returned.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    pub fn try_remove<Q>(&self, key: &Q) -> Result<Option<V>, WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.base.hash(key);
+        self.try_invalidate_with_hash(key, hash, true, true)
+    }
+
     pub(crate) fn invalidate_with_hash<Q>(&self, key: &Q, hash: u64, need_value: bool) -> Option<V>
     where
         Q: Equivalent<K> + Hash + ?Sized,
     {
-        // Lock the key for removal if blocking removal notification is enabled.
+        // The error from the writer (if any) is returned only by `try_invalidate`
+        // and `try_remove`.
+        self.try_invalidate_with_hash(key, hash, need_value, true)
+            .unwrap_or_else(|e| {
+                self.log_writer_error(&e);
+                None
+            })
+    }
+
+    /// Removes the entry for the key. If `write_through` is `true`, deletes it
+    /// through the writer (if any) first, and returns an error without removing
+    /// the entry if the writer fails.
+    pub(crate) fn try_invalidate_with_hash<Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        need_value: bool,
+        write_through: bool,
+    ) -> Result<Option<V>, WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // Lock the key for removal if blocking removal notification or the writer
+        // is enabled.
         let mut kl = None;
         let mut klg = None;
-        if self.base.is_removal_notifier_enabled() {
+        if self.base.is_removal_notifier_enabled() || self.base.is_writer_enabled() {
             // To lock the key, we have to get Arc<K> for key (&Q).
             //
             // TODO: Enhance this if possible. This is rather hack now because
@@ -1646,10 +2582,16 @@ where
             if let Some(arc_key) = self.base.get_key_with_hash(key, hash) {
                 kl = self.base.maybe_key_lock(&arc_key);
                 klg = kl.as_ref().map(|kl| kl.lock());
+
+                // Delete the entry through the writer before removing it from the
+                // cache, so that the cache will not be modified if the writer fails.
+                if write_through {
+                    self.base.delete_through(&arc_key, RemovalCause::Explicit)?;
+                }
             }
         }
 
-        match self.base.remove_entry(key, hash) {
+        let removed = match self.base.remove_entry(key, hash) {
             None => None,
             Some(kv) => {
                 let now = self.base.current_time();
@@ -1689,7 +2631,8 @@ where
                 crossbeam_epoch::pin().flush();
                 maybe_v
             }
-        }
+        };
+        Ok(removed)
     }
 
     /// Discards all cached values.
@@ -1817,6 +2760,43 @@ where
             hk.run_pending_tasks(&*self.base.inner);
         }
     }
+
+    /// Returns the earliest time when a cached entry will expire, or `None` if no
+    /// entry will expire.
+    ///
+    /// The expired entries are removed by [`run_pending_tasks`][run-pending-tasks],
+    /// so you can call it at the returned time to get the expired entries removed
+    /// (and the eviction listener notified) promptly. Alternatively, you can let the
+    /// cache do it by enabling
+    /// [`CacheBuilder::proactive_expiration`][proactive-expiration].
+    ///
+    /// The returned time reflects only the writes that have been applied by the
+    /// pending tasks, and it can be earlier than the actual expiration time, e.g.
+    /// when the entry has been read or updated since then.
+    ///
+    /// [run-pending-tasks]: #method.run_pending_tasks
+    /// [proactive-expiration]: ./struct.CacheBuilder.html#method.proactive_expiration
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let cache = Cache::builder()
+    ///     .time_to_live(Duration::from_secs(60))
+    ///     .build();
+    /// assert!(cache.next_expiration_time().is_none());
+    ///
+    /// cache.insert(1, "one");
+    /// cache.run_pending_tasks();
+    ///
+    /// let next = cache.next_expiration_time().unwrap();
+    /// assert!(next <= Instant::now() + Duration::from_secs(60));
+    /// ```
+    pub fn next_expiration_time(&self) -> Option<StdInstant> {
+        self.base.next_expiration_time()
+    }
 }
 
 impl<'a, K, V, S> IntoIterator for &'a Cache<K, V, S>
@@ -1883,7 +2863,12 @@ where
         loop {
             BaseCache::<K, V, S>::apply_reads_writes_if_needed(inner, ch, now, housekeeper);
             match ch.try_send(op) {
-                Ok(()) => break,
+                Ok(()) => {
+                    if let Some(hk) = housekeeper {
+                        hk.notify_write();
+                    }
+                    break;
+                }
                 Err(TrySendError::Full(op1)) => {
                     op = op1;
                     std::thread::sleep(Duration::from_micros(WRITE_RETRY_INTERVAL_MICROS));
@@ -1934,6 +2919,7 @@ mod tests {
     use crate::{
         common::{time::Clock, HousekeeperConfig},
         notification::RemovalCause,
+        ops::compute,
         policy::{test_utils::ExpiryCallCounters, EvictionPolicy},
         Expiry,
     };
@@ -1945,7 +2931,7 @@ mod tests {
             atomic::{AtomicU8, Ordering},
             Arc,
         },
-        time::{Duration, Instant as StdInstant},
+        time::{Duration, Instant as StdInstant, SystemTime},
     };
 
     #[test]
@@ -2010,13 +2996,13 @@ mod tests {
 
         // "d" should not be admitted because its frequency is too low.
         cache.insert("d", "david"); //   count: d -> 0
-        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert_eq!(cache.get(&"d"), None); //   d -> 1
         assert!(!cache.contains_key(&"d"));
 
         cache.insert("d", "david");
-        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert!(!cache.contains_key(&"d"));
         assert_eq!(cache.get(&"d"), None); //   d -> 2
@@ -2148,16 +3134,7 @@ mod tests {
     }
 
     #[test]
-    fn size_aware_eviction() {
-        let weigher = |_k: &&str, v: &(&str, u32)| v.1;
-
-        let alice = ("alice", 10);
-        let bob = ("bob", 15);
-        let bill = ("bill", 20);
-        let cindy = ("cindy", 5);
-        let david = ("david", 15);
-        let dennis = ("dennis", 15);
-
+    fn switch_eviction_policy() {
         // The following `Vec`s will hold actual and expected notifications.
         let actual = Arc::new(Mutex::new(Vec::new()));
         let mut expected = Vec::new();
@@ -2166,10 +3143,9 @@ mod tests {
         let a1 = Arc::clone(&actual);
         let listener = move |k, v, cause| a1.lock().push((k, v, cause));
 
-        // Create a cache with the eviction listener.
+        // Create a cache with the TinyLFU policy and the eviction listener.
         let mut cache = Cache::builder()
-            .max_capacity(31)
-            .weigher(weigher)
+            .max_capacity(3)
             .eviction_listener(listener)
             .build();
         cache.reconfigure_for_testing();
@@ -2177,97 +3153,903 @@ mod tests {
         // Make the cache exterior immutable.
         let cache = cache;
 
-        cache.insert("a", alice);
-        cache.insert("b", bob);
-        assert_eq!(cache.get(&"a"), Some(alice));
-        assert!(cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert_eq!(cache.get(&"b"), Some(bob));
-        cache.run_pending_tasks();
-        // order (LRU -> MRU) and counts: a -> 1, b -> 1
-
-        cache.insert("c", cindy);
-        assert_eq!(cache.get(&"c"), Some(cindy));
-        assert!(cache.contains_key(&"c"));
-        // order and counts: a -> 1, b -> 1, c -> 1
+        cache.insert("a", "alice");
+        cache.insert("b", "bob");
+        cache.insert("c", "cindy");
         cache.run_pending_tasks();
-
-        assert!(cache.contains_key(&"a"));
-        assert_eq!(cache.get(&"a"), Some(alice));
-        assert_eq!(cache.get(&"b"), Some(bob));
-        assert!(cache.contains_key(&"b"));
+        assert_eq!(cache.get(&"a"), Some("alice"));
+        assert_eq!(cache.get(&"b"), Some("bob"));
+        assert_eq!(cache.get(&"c"), Some("cindy"));
         cache.run_pending_tasks();
-        // order and counts: c -> 1, a -> 2, b -> 2
+        // counts: a -> 1, b -> 1, c -> 1
 
-        // To enter "d" (weight: 15), it needs to evict "c" (w: 5) and "a" (w: 10).
-        // "d" must have higher count than 3, which is the aggregated count
-        // of "a" and "c".
-        cache.insert("d", david); //   count: d -> 0
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // "d" should not be admitted because its frequency is too low.
+        cache.insert("d", "david");
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks();
-        assert_eq!(cache.get(&"d"), None); //   d -> 1
         assert!(!cache.contains_key(&"d"));
 
-        cache.insert("d", david);
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // Switch to LRU. The cached entries should be kept.
+        cache.set_eviction_policy(EvictionPolicy::lru());
         cache.run_pending_tasks();
-        assert!(!cache.contains_key(&"d"));
-        assert_eq!(cache.get(&"d"), None); //   d -> 2
+        assert_eq!(cache.policy().eviction_policy(), &EvictionPolicy::lru());
+        assert_eq!(cache.entry_count(), 3);
 
-        cache.insert("d", david);
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // "e" should be admitted because the cache uses the LRU strategy now.
+        // "a" is the LRU and should be evicted.
+        cache.insert("e", "emily");
+        expected.push((Arc::new("a"), "alice", RemovalCause::Size));
         cache.run_pending_tasks();
-        assert_eq!(cache.get(&"d"), None); //   d -> 3
-        assert!(!cache.contains_key(&"d"));
+        assert!(!cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"e"));
 
-        cache.insert("d", david);
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        // Switch back to TinyLFU.
+        cache.set_eviction_policy(EvictionPolicy::tiny_lfu());
         cache.run_pending_tasks();
-        assert!(!cache.contains_key(&"d"));
-        assert_eq!(cache.get(&"d"), None); //   d -> 4
+        assert_eq!(
+            cache.policy().eviction_policy(),
+            &EvictionPolicy::tiny_lfu()
+        );
+        assert_eq!(cache.entry_count(), 3);
 
-        // Finally "d" should be admitted by evicting "c" and "a".
-        cache.insert("d", dennis);
-        expected.push((Arc::new("c"), cindy, RemovalCause::Size));
-        expected.push((Arc::new("a"), alice, RemovalCause::Size));
+        for _ in 0..2 {
+            assert_eq!(cache.get(&"b"), Some("bob"));
+            assert_eq!(cache.get(&"c"), Some("cindy"));
+            assert_eq!(cache.get(&"e"), Some("emily"));
+        }
         cache.run_pending_tasks();
-        assert_eq!(cache.get(&"a"), None);
-        assert_eq!(cache.get(&"b"), Some(bob));
-        assert_eq!(cache.get(&"c"), None);
-        assert_eq!(cache.get(&"d"), Some(dennis));
-        assert!(!cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"c"));
-        assert!(cache.contains_key(&"d"));
 
-        // Update "b" with "bill" (w: 15 -> 20). This should evict "d" (w: 15).
-        cache.insert("b", bill);
-        expected.push((Arc::new("b"), bob, RemovalCause::Replaced));
-        expected.push((Arc::new("d"), dennis, RemovalCause::Size));
+        // "f" should not be admitted because its frequency is too low.
+        cache.insert("f", "frank");
+        expected.push((Arc::new("f"), "frank", RemovalCause::Rejected));
         cache.run_pending_tasks();
-        assert_eq!(cache.get(&"b"), Some(bill));
-        assert_eq!(cache.get(&"d"), None);
-        assert!(cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"d"));
+        assert!(!cache.contains_key(&"f"));
 
-        // Re-add "a" (w: 10) and update "b" with "bob" (w: 20 -> 15).
+        verify_notification_vec(&cache, actual, &expected);
+    }
+
+    #[test]
+    fn reduce_max_capacity() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        // Create a cache with the LRU policy and the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(5)
+            .eviction_policy(EvictionPolicy::lru())
+            .eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice");
+        cache.insert("b", "bob");
+        cache.insert("c", "cindy");
+        cache.insert("d", "david");
+        cache.insert("e", "emily");
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 5);
+
+        // Reduce the max capacity. The three LRU entries should be evicted with
+        // `CapacityReduced`.
+        cache.set_max_capacity_async(2).unwrap();
+        expected.push((Arc::new("a"), "alice", RemovalCause::CapacityReduced));
+        expected.push((Arc::new("b"), "bob", RemovalCause::CapacityReduced));
+        expected.push((Arc::new("c"), "cindy", RemovalCause::CapacityReduced));
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 2);
+        assert!(cache.contains_key(&"d"));
+        assert!(cache.contains_key(&"e"));
+
+        // Later evictions should be reported as `Size` again.
+        cache.insert("f", "frank");
+        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"d"));
+        assert!(cache.contains_key(&"f"));
+
+        verify_notification_vec(&cache, actual, &expected);
+    }
+
+    #[test]
+    fn subscribe_to_events() {
+        use crate::notification::{Backpressure, CacheEvent};
+
+        // Create a cache with the LRU policy so that the eviction is deterministic.
+        let mut cache = Cache::builder()
+            .max_capacity(2)
+            .eviction_policy(EvictionPolicy::lru())
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let events = cache.subscribe(16, Backpressure::Block);
+
+        cache.insert("a", "alice");
+        cache.insert("a", "alex");
+        assert_eq!(cache.get_with("b", || "bob"), "bob");
+        cache.invalidate(&"a");
+        cache.insert("c", "cindy");
+        cache.insert("d", "david");
+        // "b" is the LRU and should be evicted.
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"b"));
+
+        let actual = events
+            .try_iter()
+            .map(|event| match event {
+                CacheEvent::Inserted { key, value } => ("inserted", *key, value, None),
+                CacheEvent::Updated { key, value } => ("updated", *key, value, None),
+                CacheEvent::Removed { key, value, cause } => ("removed", *key, value, Some(cause)),
+                CacheEvent::Loaded { key, value } => ("loaded", *key, value, None),
+            })
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            ("inserted", "a", "alice", None),
+            ("removed", "a", "alice", Some(RemovalCause::Replaced)),
+            ("updated", "a", "alex", None),
+            ("loaded", "b", "bob", None),
+            ("removed", "a", "alex", Some(RemovalCause::Explicit)),
+            ("inserted", "c", "cindy", None),
+            ("inserted", "d", "david", None),
+            ("removed", "b", "bob", Some(RemovalCause::Size)),
+        ];
+        assert_eq!(actual, expected);
+        assert_eq!(events.dropped_count(), 0);
+
+        // The receiver should be disconnected after the cache is dropped.
+        drop(cache);
+        assert!(events.recv().is_none());
+    }
+
+    #[test]
+    fn event_backpressure() {
+        use crate::notification::{Backpressure, CacheEvent};
+
+        let mut cache = Cache::new(100);
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let drop_oldest = cache.subscribe(2, Backpressure::DropOldest);
+        let count_drops = cache.subscribe(2, Backpressure::CountDrops);
+
+        for i in 0..5 {
+            cache.insert(i, i);
+        }
+
+        let keys = |events: Vec<CacheEvent<i32, i32>>| {
+            events.iter().map(|ev| **ev.key()).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(drop_oldest.try_iter().collect()), vec![3, 4]);
+        assert_eq!(drop_oldest.dropped_count(), 3);
+        assert_eq!(keys(count_drops.try_iter().collect()), vec![0, 1]);
+        assert_eq!(count_drops.dropped_count(), 3);
+
+        // Dropping a receiver unsubscribes it.
+        drop(count_drops);
+        cache.insert(5, 5);
+        assert_eq!(keys(drop_oldest.try_iter().collect()), vec![5]);
+        assert_eq!(cache.base.event_subscriber_count(), 1);
+    }
+
+    #[test]
+    fn pinned_entries() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert_pinned("a", "alice");
+        cache.insert("b", "bob");
+        assert!(cache.pin(&"b"));
+        assert!(!cache.pin(&"z"));
+        cache.insert("c", "cindy");
+        cache.run_pending_tasks();
+        // a (pinned) -> b (pinned) -> c
+        assert_eq!(cache.entry_count(), 3);
+        assert_eq!(cache.pinned_weighted_size(), 2);
+
+        // "c" should be evicted because "a" and "b" are pinned.
+        cache.insert("d", "david");
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"c"));
+        assert!(cache.contains_key(&"d"));
+
+        // Updating a pinned entry does not unpin it.
+        cache.insert("a", "anne");
+        expected.push((Arc::new("a"), "alice", RemovalCause::Replaced));
+        cache.run_pending_tasks();
+        assert_eq!(cache.pinned_weighted_size(), 2);
+
+        // The pinned weight exceeds the max capacity. All unpinned entries are
+        // evicted and the pinned entries are kept.
+        cache.insert_pinned("e", "emily");
+        cache.insert_pinned("f", "frank");
+        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 4);
+        assert_eq!(cache.weighted_size(), 4);
+        assert_eq!(cache.pinned_weighted_size(), 4);
+        assert_eq!(cache.policy().pinned_weighted_size(), 4);
+        assert_eq!(cache.get(&"a"), Some("anne"));
+        assert_eq!(cache.get(&"b"), Some("bob"));
+        assert_eq!(cache.get(&"e"), Some("emily"));
+        assert_eq!(cache.get(&"f"), Some("frank"));
+
+        // Unpinning an entry makes it evictable again.
+        assert!(cache.unpin(&"a"));
+        expected.push((Arc::new("a"), "anne", RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"a"));
+        assert_eq!(cache.entry_count(), 3);
+        assert_eq!(cache.pinned_weighted_size(), 3);
+
+        // A pinned entry can be invalidated.
+        cache.invalidate(&"b");
+        expected.push((Arc::new("b"), "bob", RemovalCause::Explicit));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"b"));
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.pinned_weighted_size(), 2);
+
+        verify_notification_vec(&cache, actual, &expected);
+        assert!(cache.key_locks_map_is_empty());
+    }
+
+    #[test]
+    fn can_evict() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        // Create a `can_evict` closure that vetoes the eviction of the keys in
+        // `in_use`.
+        let in_use = Arc::new(Mutex::new(vec!["a"]));
+        let in_use1 = Arc::clone(&in_use);
+        let can_evict = move |k: &&str, _v: &&str| !in_use1.lock().contains(k);
+
+        // Create a cache with the eviction listener and the `can_evict` closure.
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .eviction_listener(listener)
+            .can_evict(can_evict)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice");
+        cache.insert("b", "bob");
+        cache.insert("c", "cindy");
+        cache.run_pending_tasks();
+        // a -> b -> c
+
+        // "a" is the LRU but in use, so "b" should be evicted instead.
+        cache.insert("d", "david");
+        expected.push((Arc::new("b"), "bob", RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert!(cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"b"));
+        assert_eq!(cache.entry_count(), 3);
+
+        // When all entries are in use, nothing can be evicted and the cache
+        // temporarily exceeds its max capacity.
+        in_use.lock().extend(["c", "d", "e"]);
+        cache.insert("e", "emily");
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 4);
+
+        // Once "c" is no longer in use, it can be evicted.
+        in_use.lock().retain(|k| k != &"c");
+        cache.run_pending_tasks();
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Size));
+        assert!(!cache.contains_key(&"c"));
+        assert_eq!(cache.entry_count(), 3);
+
+        verify_notification_vec(&cache, actual, &expected);
+    }
+
+    #[test]
+    fn writer() {
+        use crate::{
+            ops::compute::{CompResult, Op},
+            CacheWriter, InsertError,
+        };
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        type BoxError = Box<dyn std::error::Error + Send + Sync>;
+        type Log = Vec<(&'static str, Option<&'static str>, Option<RemovalCause>)>;
+
+        #[derive(Default)]
+        struct TestWriter {
+            // The writes and deletes the writer has received.
+            log: Mutex<Log>,
+            fail: AtomicBool,
+        }
+
+        impl TestWriter {
+            fn check_failure(&self) -> Result<(), BoxError> {
+                if self.fail.load(Ordering::Relaxed) {
+                    Err("storage unavailable".into())
+                } else {
+                    Ok(())
+                }
+            }
+
+            fn take_log(&self) -> Log {
+                std::mem::take(&mut *self.log.lock())
+            }
+        }
+
+        impl CacheWriter<&'static str, &'static str> for Arc<TestWriter> {
+            fn write(&self, key: &&'static str, value: &&'static str) -> Result<(), BoxError> {
+                self.check_failure()?;
+                self.log.lock().push((key, Some(value), None));
+                Ok(())
+            }
+
+            fn delete(&self, key: &&'static str, cause: RemovalCause) -> Result<(), BoxError> {
+                self.check_failure()?;
+                self.log.lock().push((key, None, Some(cause)));
+                Ok(())
+            }
+        }
+
+        let writer = Arc::new(TestWriter::default());
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .writer(Arc::clone(&writer))
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        // Inserts and updates are written through.
+        cache.insert("a", "alice");
+        assert_eq!(cache.try_insert("b", "bob"), Ok(()));
+        cache.entry("b").and_compute_with(|_| Op::Put("bill"));
+        // Loaded values are not written.
+        cache.get_with("c", || "cindy");
+        cache.run_pending_tasks();
+        assert_eq!(
+            writer.take_log(),
+            vec![
+                ("a", Some("alice"), None),
+                ("b", Some("bob"), None),
+                ("b", Some("bill"), None),
+            ]
+        );
+
+        // Removals of existing entries are deleted through.
+        cache.invalidate(&"a");
+        assert_eq!(cache.try_remove(&"c"), Ok(Some("cindy")));
+        assert_eq!(cache.try_remove(&"z"), Ok(None));
+        assert_eq!(
+            writer.take_log(),
+            vec![
+                ("a", None, Some(RemovalCause::Explicit)),
+                ("c", None, Some(RemovalCause::Explicit)),
+            ]
+        );
+
+        // When the writer fails, the cache is not modified.
+        writer.fail.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            cache.try_insert("b", "bob"),
+            Err(InsertError::WriterFailed(_))
+        ));
+        assert!(cache.try_insert("d", "david").is_err());
+        assert!(cache.try_invalidate(&"b").is_err());
+        assert!(cache.try_remove(&"b").is_err());
+        let result = cache.entry("b").and_compute_with(|_| Op::Put("bob"));
+        assert!(matches!(result, CompResult::Unchanged(_)));
+        let result = cache.entry("b").and_compute_with(|_| Op::Remove);
+        assert!(matches!(result, CompResult::Unchanged(_)));
+        let result = cache.entry("d").and_compute_with(|_| Op::Put("david"));
+        assert!(matches!(result, CompResult::StillNone(_)));
+        // The upsert returns the value that was not stored.
+        let entry = cache.entry("b").and_upsert_with(|_| "bob");
+        assert_eq!(entry.into_value(), "bob");
+        // The checked methods return the error from the writer.
+        let result = cache
+            .entry("b")
+            .and_compute_with_checked(|_| Op::Put("bob"));
+        assert!(result.is_err());
+        let result = cache
+            .entry_by_ref(&"b")
+            .and_compute_with_checked(|_| Op::Remove);
+        assert!(result.is_err());
+        assert!(cache
+            .entry("d")
+            .and_upsert_with_checked(|_| "david")
+            .is_err());
+        assert!(cache
+            .entry_by_ref(&"d")
+            .and_upsert_with_checked(|_| "david")
+            .is_err());
+        // A `Nop` does not call the writer.
+        let result = cache.entry("b").and_compute_with_checked(|_| Op::Nop);
+        assert!(matches!(result, Ok(CompResult::Unchanged(_))));
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"b"), Some("bill"));
+        assert!(!cache.contains_key(&"d"));
+        assert!(writer.take_log().is_empty());
+
+        // The housekeeping tasks do not call the writer.
+        writer.fail.store(false, Ordering::Relaxed);
+        cache.invalidate_all();
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 0);
+        assert!(writer.take_log().is_empty());
+    }
+
+    #[test]
+    fn write_behind() {
+        // The flushed entries and the removal notifications in the order they
+        // happened.
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        // The names of the threads calling the flush closure.
+        let flush_threads = Arc::new(Mutex::new(Vec::new()));
+
+        // Create a flush closure and an eviction listener recording to the log.
+        let log1 = Arc::clone(&log);
+        let ft1 = Arc::clone(&flush_threads);
+        let flush = move |mut entries: Vec<(Arc<&'static str>, u32)>| {
+            ft1.lock()
+                .push(std::thread::current().name().map(ToString::to_string));
+            entries.sort_unstable();
+            let mut log = log1.lock();
+            log.extend(entries.into_iter().map(|(k, v)| ("flushed", *k, v)));
+        };
+        let log2 = Arc::clone(&log);
+        let listener = move |k: Arc<&'static str>, v, _cause| log2.lock().push(("removed", *k, v));
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the write-behind buffer and the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .write_behind(3, Duration::from_secs(1), flush)
+            .eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let take_log = || std::mem::take(&mut *log.lock());
+
+        // Repeated writes to the same key are coalesced.
+        cache.insert("a", 1);
+        cache.insert("a", 2);
+        cache.insert("b", 1);
+        // Loaded values are not flushed.
+        cache.get_with("c", || 1);
+        cache.run_pending_tasks();
+        // The replaced value is not flushed.
+        assert_eq!(take_log(), vec![("removed", "a", 1)]);
+
+        // The buffer is handed to the flush thread when it reaches the max batch
+        // size.
+        cache.insert("d", 1);
+        cache.base.wait_for_write_behind_flushes();
+        assert_eq!(
+            take_log(),
+            vec![
+                ("flushed", "a", 2),
+                ("flushed", "b", 1),
+                ("flushed", "d", 1)
+            ]
+        );
+
+        // The buffer is flushed when the oldest entry has waited for the max delay.
+        cache.insert("e", 1);
+        mock.increment(Duration::from_millis(500));
+        cache.run_pending_tasks();
+        cache.base.wait_for_write_behind_flushes();
+        assert!(take_log().is_empty());
+        mock.increment(Duration::from_millis(500));
+        cache.run_pending_tasks();
+        cache.base.wait_for_write_behind_flushes();
+        assert_eq!(take_log(), vec![("flushed", "e", 1)]);
+
+        // The pending write of an invalidated entry is discarded, so the deleted
+        // value will not be written back.
+        cache.insert("f", 1);
+        cache.invalidate(&"f");
+        assert_eq!(take_log(), vec![("removed", "f", 1)]);
+
+        // The remaining dirty entries are flushed by `close`.
+        cache.insert("g", 1);
+        mock.increment(Duration::from_millis(100));
+        cache.close();
+        let log = take_log();
+        // "g" is flushed right before its removal is notified, and the other
+        // entries are not flushed again.
+        let pos = log.iter().position(|e| e == &("removed", "g", 1)).unwrap();
+        assert_eq!(log[pos - 1], ("flushed", "g", 1));
+        assert_eq!(log.len(), 7);
+
+        // The flush closure was never called by the threads writing to the cache.
+        let flush_threads = flush_threads.lock();
+        assert_eq!(flush_threads.len(), 3);
+        assert!(flush_threads
+            .iter()
+            .all(|name| name.as_deref() == Some("moka-write-behind")));
+    }
+
+    #[test]
+    fn write_behind_without_cache_operations() {
+        let store = Arc::new(Mutex::new(Vec::new()));
+        let wait_for_store = |len: usize| {
+            for _ in 0..500 {
+                if store.lock().len() >= len {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            false
+        };
+
+        // The flush thread flushes the buffer after the max delay, even if the cache
+        // is not used anymore.
+        let s1 = Arc::clone(&store);
+        let cache = Cache::builder()
+            .write_behind(100, Duration::from_millis(50), move |entries| {
+                s1.lock().extend(entries.into_iter().map(|(k, v)| (*k, v)));
+            })
+            .build();
+        cache.insert(1, 1);
+        assert!(wait_for_store(1));
+
+        // The remaining dirty entries are flushed after the cache is dropped.
+        let s2 = Arc::clone(&store);
+        let cache = Cache::builder()
+            .write_behind(100, Duration::from_secs(3600), move |entries| {
+                s2.lock().extend(entries.into_iter().map(|(k, v)| (*k, v)));
+            })
+            .build();
+        cache.insert(2, 2);
+        drop(cache);
+        assert!(wait_for_store(2));
+
+        assert_eq!(*store.lock(), vec![(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn max_entry_weight() {
+        use crate::InsertError;
+
+        let weigher = |_k: &&str, v: &(&str, u32)| v.1;
+
+        let alice = ("alice", 10);
+        let bob = ("bob", 15);
+        let bill = ("bill", 20);
+        let cindy = ("cindy", 5);
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(31)
+            .weigher(weigher)
+            .max_entry_weight(15)
+            .eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        assert_eq!(cache.try_insert("a", alice), Ok(()));
+        cache.insert("b", bob);
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"a"), Some(alice));
+        assert_eq!(cache.get(&"b"), Some(bob));
+        assert_eq!(cache.weighted_size(), 25);
+
+        // "c" is heavier than the max entry weight, so it is rejected.
+        assert_eq!(
+            cache.try_insert("c", bill),
+            Err(InsertError::EntryTooHeavy {
+                weight: 20,
+                max_entry_weight: 15
+            })
+        );
+        expected.push((Arc::new("c"), bill, RemovalCause::Rejected));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"c"));
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.weighted_size(), 25);
+
+        // Updating "b" with a heavier value is rejected, and the stale value of "b"
+        // is removed as replaced.
+        cache.insert("b", bill);
+        expected.push((Arc::new("b"), bob, RemovalCause::Replaced));
+        expected.push((Arc::new("b"), bill, RemovalCause::Rejected));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"b"));
+        assert_eq!(cache.entry_count(), 1);
+        assert_eq!(cache.weighted_size(), 10);
+
+        // A light entry can be inserted as usual.
+        assert_eq!(cache.try_insert("c", cindy), Ok(()));
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"c"), Some(cindy));
+        assert_eq!(cache.weighted_size(), 15);
+
+        // Computing a heavier value for "a" is rejected too, and "a" is reported as
+        // removed.
+        let result = cache
+            .entry("a")
+            .and_compute_with(|_| compute::Op::Put(bill));
+        assert!(matches!(result, compute::CompResult::Removed(e) if e.value() == &alice));
+        expected.push((Arc::new("a"), alice, RemovalCause::Replaced));
+        expected.push((Arc::new("a"), bill, RemovalCause::Rejected));
+
+        // Upserting a heavier value returns the value that was not stored.
+        let entry = cache.entry("d").and_upsert_with(|_| bill);
+        assert!(!entry.is_fresh());
+        assert_eq!(entry.into_value(), bill);
+        expected.push((Arc::new("d"), bill, RemovalCause::Rejected));
+
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.weighted_size(), 5);
+
+        verify_notification_vec(&cache, actual, &expected);
+        assert!(cache.key_locks_map_is_empty());
+    }
+
+    #[test]
+    fn size_aware_eviction() {
+        let weigher = |_k: &&str, v: &(&str, u32)| v.1;
+
+        let alice = ("alice", 10);
+        let bob = ("bob", 15);
+        let bill = ("bill", 20);
+        let cindy = ("cindy", 5);
+        let david = ("david", 15);
+        let dennis = ("dennis", 15);
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(31)
+            .weigher(weigher)
+            .eviction_listener(listener)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", alice);
+        cache.insert("b", bob);
+        assert_eq!(cache.get(&"a"), Some(alice));
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert_eq!(cache.get(&"b"), Some(bob));
+        cache.run_pending_tasks();
+        // order (LRU -> MRU) and counts: a -> 1, b -> 1
+
+        cache.insert("c", cindy);
+        assert_eq!(cache.get(&"c"), Some(cindy));
+        assert!(cache.contains_key(&"c"));
+        // order and counts: a -> 1, b -> 1, c -> 1
+        cache.run_pending_tasks();
+
+        assert!(cache.contains_key(&"a"));
+        assert_eq!(cache.get(&"a"), Some(alice));
+        assert_eq!(cache.get(&"b"), Some(bob));
+        assert!(cache.contains_key(&"b"));
+        cache.run_pending_tasks();
+        // order and counts: c -> 1, a -> 2, b -> 2
+
+        // To enter "d" (weight: 15), it needs to evict "c" (w: 5) and "a" (w: 10).
+        // "d" must have higher count than 3, which is the aggregated count
+        // of "a" and "c".
+        cache.insert("d", david); //   count: d -> 0
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"d"), None); //   d -> 1
+        assert!(!cache.contains_key(&"d"));
+
+        cache.insert("d", david);
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.get(&"d"), None); //   d -> 2
+
+        cache.insert("d", david);
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"d"), None); //   d -> 3
+        assert!(!cache.contains_key(&"d"));
+
+        cache.insert("d", david);
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.get(&"d"), None); //   d -> 4
+
+        // Finally "d" should be admitted by evicting "c" and "a".
+        cache.insert("d", dennis);
+        expected.push((Arc::new("c"), cindy, RemovalCause::Size));
+        expected.push((Arc::new("a"), alice, RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(bob));
+        assert_eq!(cache.get(&"c"), None);
+        assert_eq!(cache.get(&"d"), Some(dennis));
+        assert!(!cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"c"));
+        assert!(cache.contains_key(&"d"));
+
+        // Update "b" with "bill" (w: 15 -> 20). This should evict "d" (w: 15).
+        cache.insert("b", bill);
+        expected.push((Arc::new("b"), bob, RemovalCause::Replaced));
+        expected.push((Arc::new("d"), dennis, RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"b"), Some(bill));
+        assert_eq!(cache.get(&"d"), None);
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"d"));
+
+        // Re-add "a" (w: 10) and update "b" with "bob" (w: 20 -> 15).
         cache.insert("a", alice);
         cache.insert("b", bob);
         expected.push((Arc::new("b"), bill, RemovalCause::Replaced));
         cache.run_pending_tasks();
-        assert_eq!(cache.get(&"a"), Some(alice));
-        assert_eq!(cache.get(&"b"), Some(bob));
-        assert_eq!(cache.get(&"d"), None);
-        assert!(cache.contains_key(&"a"));
-        assert!(cache.contains_key(&"b"));
-        assert!(!cache.contains_key(&"d"));
+        assert_eq!(cache.get(&"a"), Some(alice));
+        assert_eq!(cache.get(&"b"), Some(bob));
+        assert_eq!(cache.get(&"d"), None);
+        assert!(cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(!cache.contains_key(&"d"));
+
+        // Verify the sizes.
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.weighted_size(), 25);
+
+        verify_notification_vec(&cache, actual, &expected);
+        assert!(cache.key_locks_map_is_empty());
+    }
+
+    #[test]
+    fn u64_weigher() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+
+        // The weights of the values are in GiB, which exceed `u32::MAX` bytes.
+        let weigher = |_k: &&str, v: &(&str, u64)| v.1 * GIB;
+
+        let alice = ("alice", 5);
+        let bob = ("bob", 6);
+        let cindy = ("cindy", 3);
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        // Create a cache with the eviction listener. Do not call
+        // `reconfigure_for_testing` here, as it would size the frequency sketch by
+        // the max capacity (12 GiB) rather than by the number of entries.
+        let cache = Cache::builder()
+            .max_capacity(12 * GIB)
+            .eviction_policy(EvictionPolicy::lru())
+            .weigher_u64(weigher)
+            .eviction_listener(listener)
+            .build();
+
+        cache.insert("a", alice);
+        cache.insert("b", bob);
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.weighted_size(), 11 * GIB);
+
+        // "a" should be evicted to make room for "c".
+        cache.insert("c", cindy);
+        expected.push((Arc::new("a"), alice, RemovalCause::Size));
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"a"));
+        assert!(cache.contains_key(&"b"));
+        assert!(cache.contains_key(&"c"));
+        assert_eq!(cache.weighted_size(), 9 * GIB);
+
+        verify_notification_vec(&cache, actual, &expected);
+    }
+
+    #[test]
+    fn max_memory_bytes() {
+        use crate::common::concurrent::entry_overhead_bytes;
+
+        let entry_bytes = (entry_overhead_bytes::<u32, String>() + 100) as u64;
+
+        let mut cache = Cache::builder()
+            .max_memory_bytes(3 * entry_bytes)
+            .eviction_policy(EvictionPolicy::lru())
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let value = || String::with_capacity(100);
+
+        cache.insert(1, value());
+        cache.run_pending_tasks();
+        assert_eq!(cache.weighted_size(), entry_bytes);
 
-        // Verify the sizes.
-        assert_eq!(cache.entry_count(), 2);
-        assert_eq!(cache.weighted_size(), 25);
+        cache.insert(2, value());
+        cache.insert(3, value());
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 3);
+        assert_eq!(cache.weighted_size(), 3 * entry_bytes);
 
-        verify_notification_vec(&cache, actual, &expected);
-        assert!(cache.key_locks_map_is_empty());
+        // The memory limit is reached, so the least recently used entry is
+        // evicted.
+        cache.insert(4, value());
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 3);
+        assert!(!cache.contains_key(&1));
+        assert!(cache.contains_key(&4));
     }
 
     #[test]
@@ -2332,9 +4114,9 @@ mod tests {
         // https://github.com/moka-rs/moka/issues/155
 
         cache.invalidate_all();
-        expected.push((Arc::new("a"), "alice", RemovalCause::Explicit));
-        expected.push((Arc::new("b"), "bob", RemovalCause::Explicit));
-        expected.push((Arc::new("c"), "cindy", RemovalCause::Explicit));
+        expected.push((Arc::new("a"), "alice", RemovalCause::InvalidatedAll));
+        expected.push((Arc::new("b"), "bob", RemovalCause::InvalidatedAll));
+        expected.push((Arc::new("c"), "cindy", RemovalCause::InvalidatedAll));
         cache.run_pending_tasks();
 
         cache.insert("d", "david");
@@ -2393,11 +4175,13 @@ mod tests {
         assert!(cache.contains_key(&1));
         assert!(cache.contains_key(&2));
 
+        let by_predicate = RemovalCause::InvalidatedByPredicate;
+
         let names = ["alice", "alex"].iter().cloned().collect::<HashSet<_>>();
-        cache.invalidate_entries_if(move |_k, &v| names.contains(v))?;
+        let id = cache.invalidate_entries_if(move |_k, &v| names.contains(v))?;
         assert_eq!(cache.base.invalidation_predicate_count(), 1);
-        expected.push((Arc::new(0), "alice", RemovalCause::Explicit));
-        expected.push((Arc::new(2), "alex", RemovalCause::Explicit));
+        expected.push((Arc::new(0), "alice", by_predicate(id)));
+        expected.push((Arc::new(2), "alex", by_predicate(id)));
 
         mock.increment(Duration::from_secs(5)); // 10 secs from the start.
 
@@ -2420,36 +4204,405 @@ mod tests {
         assert!(!cache.contains_key(&2));
         assert!(cache.contains_key(&3));
 
-        assert_eq!(cache.entry_count(), 2);
-        assert_eq!(cache.invalidation_predicate_count(), 0);
+        assert_eq!(cache.entry_count(), 2);
+        assert_eq!(cache.invalidation_predicate_count(), 0);
+
+        mock.increment(Duration::from_secs(5)); // 15 secs from the start.
+
+        let alice_id = cache.invalidate_entries_if(|_k, &v| v == "alice")?;
+        let bob_id = cache.invalidate_entries_if(|_k, &v| v == "bob")?;
+        assert_eq!(cache.invalidation_predicate_count(), 2);
+        // key 1 was inserted before key 3.
+        expected.push((Arc::new(1), "bob", by_predicate(bob_id)));
+        expected.push((Arc::new(3), "alice", by_predicate(alice_id)));
+
+        // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
+        cache.run_pending_tasks(); // To submit the invalidation task.
+        std::thread::sleep(Duration::from_millis(200));
+        cache.run_pending_tasks(); // To process the task result.
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert!(cache.get(&1).is_none());
+        assert!(cache.get(&3).is_none());
+
+        assert!(!cache.contains_key(&1));
+        assert!(!cache.contains_key(&3));
+
+        assert_eq!(cache.entry_count(), 0);
+        assert_eq!(cache.invalidation_predicate_count(), 0);
+
+        verify_notification_vec(&cache, actual, &expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_with_ttl() {
+        use crate::ops::compute::Op;
+
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(30))
+            .eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert_with_ttl("a", "alice", Duration::from_secs(5));
+        cache.insert("b", "bob");
+        cache
+            .entry("c")
+            .or_insert_with_ttl("cindy", Duration::from_secs(10));
+        cache
+            .entry("d")
+            .and_compute_with(|_| Op::PutWithTtl("david", Duration::from_secs(15)));
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 4);
+
+        mock.increment(Duration::from_secs(6)); // 6 secs from the start.
+        assert_eq!(cache.get(&"a"), None);
+        expected.push((Arc::new("a"), "alice", RemovalCause::Expired));
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 3);
+
+        // Replace the TTL of "c" with a new one. ("c" will expire at 26 secs)
+        cache.insert_with_ttl("c", "carl", Duration::from_secs(20));
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Replaced));
+        // Updating "d" without a TTL clears its TTL, so only the cache-wide TTL
+        // applies to it. ("d" will expire at 36 secs)
+        cache.insert("d", "dennis");
+        expected.push((Arc::new("d"), "david", RemovalCause::Replaced));
+        cache.run_pending_tasks();
+
+        mock.increment(Duration::from_secs(10)); // 16 secs.
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"b"), Some("bob"));
+        assert_eq!(cache.get(&"c"), Some("carl"));
+        assert_eq!(cache.get(&"d"), Some("dennis"));
+        assert_eq!(cache.entry_count(), 3);
+
+        mock.increment(Duration::from_secs(15)); // 31 secs.
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"c"), None);
+        assert_eq!(cache.get(&"d"), Some("dennis"));
+        expected.push((Arc::new("c"), "carl", RemovalCause::Expired));
+        expected.push((Arc::new("b"), "bob", RemovalCause::Expired));
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 1);
+
+        verify_notification_vec(&cache, actual, &expected);
+    }
+
+    #[test]
+    fn peek() {
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_idle(Duration::from_secs(10))
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice");
+        cache.insert("b", "bob");
+        cache.run_pending_tasks();
+        let md = cache.peek_metadata(&"a").expect("Entry not found");
+        let freq = md.frequency();
+
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        assert_eq!(cache.peek(&"a"), Some("alice"));
+        assert_eq!(cache.peek(&"b"), Some("bob"));
+        assert_eq!(cache.peek(&"c"), None);
+        assert_eq!(cache.get(&"b"), Some("bob"));
+        cache.run_pending_tasks();
+
+        // `peek` did not update the frequency of "a".
+        let md = cache.peek_metadata(&"a").expect("Entry not found");
+        assert_eq!(md.frequency(), freq);
+
+        // `peek` did not reset the idle timer of "a", while `get` did for "b".
+        mock.increment(Duration::from_secs(5)); // 10 secs.
+        assert_eq!(cache.peek(&"a"), None);
+        assert_eq!(cache.peek(&"b"), Some("bob"));
+    }
+
+    #[test]
+    fn peek_metadata() {
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .weigher(|_k, v: &&str| v.len() as u32)
+            .time_to_live(Duration::from_secs(30))
+            .time_to_idle(Duration::from_secs(10))
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice");
+        cache.run_pending_tasks();
+
+        let md = cache.peek_metadata(&"a").expect("Entry not found");
+        let start = md.last_modified();
+        assert_eq!(md.last_accessed(), start);
+        // The TTI comes first.
+        assert_eq!(md.expiration_time(), Some(start + Duration::from_secs(10)));
+        assert_eq!(md.time_to_expiration(), Some(Duration::from_secs(10)));
+        assert_eq!(md.policy_weight(), 5);
+        assert!(md.is_admitted());
+        assert!(!md.is_pinned());
+        assert!(cache.peek_metadata(&"b").is_none());
+
+        // Read "a" a few times to increase its estimated frequency.
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        for _ in 0..3 {
+            assert_eq!(cache.get(&"a"), Some("alice"));
+        }
+        cache.run_pending_tasks();
+
+        let md = cache.peek_metadata(&"a").expect("Entry not found");
+        let freq = md.frequency();
+        assert!(freq > 0);
+        assert_eq!(md.last_accessed(), start + Duration::from_secs(5));
+        assert_eq!(md.last_modified(), start);
+        assert_eq!(md.time_to_expiration(), Some(Duration::from_secs(10)));
+
+        // Peeking the metadata does not count as a read. It does neither reset the
+        // idle timer nor update the frequency.
+        mock.increment(Duration::from_secs(9)); // 14 secs.
+        let md = cache.peek_metadata(&"a").expect("Entry not found");
+        assert_eq!(md.last_accessed(), start + Duration::from_secs(5));
+        assert_eq!(md.time_to_expiration(), Some(Duration::from_secs(1)));
+        cache.run_pending_tasks();
+        let md = cache.peek_metadata(&"a").expect("Entry not found");
+        assert_eq!(md.frequency(), freq);
+
+        // A per-entry TTL shorter than the TTI comes first.
+        cache.insert_with_ttl("c", "cindy", Duration::from_secs(3));
+        let md = cache.peek_metadata(&"c").expect("Entry not found");
+        assert_eq!(md.time_to_expiration(), Some(Duration::from_secs(3)));
+        assert_eq!(md.expiration_time(), Some(start + Duration::from_secs(17)));
+
+        mock.increment(Duration::from_secs(1)); // 15 secs.
+        assert!(cache.peek_metadata(&"a").is_none());
+        assert!(cache.peek_metadata(&"c").is_some());
+
+        mock.increment(Duration::from_secs(2)); // 17 secs.
+        assert!(cache.peek_metadata(&"c").is_none());
+    }
+
+    #[test]
+    fn insert_with_expire_at() {
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder().max_capacity(100).clock(clock).build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let now = SystemTime::now();
+        cache.insert_with_expire_at("a", "alice", now + Duration::from_secs(5));
+        // "b" has already expired.
+        cache.insert_with_expire_at("b", "bob", now - Duration::from_secs(1));
+        assert_eq!(cache.get(&"a"), Some("alice"));
+        assert_eq!(cache.get(&"b"), None);
+
+        mock.increment(Duration::from_secs(4)); // 4 secs from the start.
+        assert_eq!(cache.get(&"a"), Some("alice"));
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 1);
+
+        mock.increment(Duration::from_secs(2)); // 6 secs.
+        assert_eq!(cache.get(&"a"), None);
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[test]
+    fn next_expiration_time() {
+        let (clock, mock) = Clock::mock();
+
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(60))
+            .time_to_idle(Duration::from_secs(30))
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        let exp_time = |key| cache.peek_metadata(key).and_then(|m| m.expiration_time());
+
+        assert_eq!(cache.next_expiration_time(), None);
+
+        cache.insert("a", "alice");
+        mock.increment(Duration::from_secs(1)); // 1 sec from the start.
+        cache.insert("b", "bob");
+        cache.run_pending_tasks();
+        assert_eq!(cache.next_expiration_time(), exp_time(&"a"));
+
+        // The per-entry expiration of "c" is earlier than the others.
+        cache.insert_with_ttl("c", "cindy", Duration::from_secs(5));
+        cache.run_pending_tasks();
+        assert_eq!(cache.next_expiration_time(), exp_time(&"c"));
+
+        mock.increment(Duration::from_secs(5)); // 6 secs.
+        cache.run_pending_tasks();
+        assert!(!cache.contains_key(&"c"));
+        assert_eq!(cache.next_expiration_time(), exp_time(&"a"));
+
+        // Reading "a" extends its time-to-idle, so "b" will expire first.
+        assert_eq!(cache.get(&"a"), Some("alice"));
+        cache.run_pending_tasks();
+        assert_eq!(cache.next_expiration_time(), exp_time(&"b"));
+
+        cache.invalidate_all();
+        cache.run_pending_tasks();
+        assert_eq!(cache.next_expiration_time(), None);
+    }
+
+    #[test]
+    fn proactive_expiration() {
+        // The following `Vec` will hold actual notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the proactive expiration.
+        let cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(10))
+            .eviction_listener(listener)
+            .proactive_expiration(Duration::from_millis(1))
+            .clock(clock)
+            .build();
+
+        // Wakes up the background thread, which would otherwise wait (in real time)
+        // for the time-to-live, and waits until it removes `n` entries.
+        let wait_for_removals = |n: usize| {
+            cache.base.housekeeper.as_ref().unwrap().notify_write();
+            for _ in 0..500 {
+                if actual.lock().len() >= n {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            panic!("the expired entries were not removed");
+        };
+
+        cache.insert("a", "alice");
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        cache.insert("b", "bob");
+
+        // Do not access the cache. The background thread will remove the entries
+        // when they expire.
+        mock.increment(Duration::from_secs(6)); // 11 secs.
+        wait_for_removals(1);
+        assert_eq!(
+            *actual.lock(),
+            vec![(Arc::new("a"), "alice", RemovalCause::Expired)]
+        );
+
+        mock.increment(Duration::from_secs(5)); // 16 secs.
+        wait_for_removals(2);
+        assert_eq!(
+            actual.lock()[1],
+            (Arc::new("b"), "bob", RemovalCause::Expired)
+        );
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[test]
+    fn set_expiration_and_touch() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache with the eviction listener.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .time_to_idle(Duration::from_secs(10))
+            .eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
 
-        mock.increment(Duration::from_secs(5)); // 15 secs from the start.
+        cache.insert("a", "alice");
+        cache.insert("b", "bob");
+        cache.insert("c", "cindy");
+        cache.run_pending_tasks();
 
-        cache.invalidate_entries_if(|_k, &v| v == "alice")?;
-        cache.invalidate_entries_if(|_k, &v| v == "bob")?;
-        assert_eq!(cache.invalidation_predicate_count(), 2);
-        // key 1 was inserted before key 3.
-        expected.push((Arc::new(1), "bob", RemovalCause::Explicit));
-        expected.push((Arc::new(3), "alice", RemovalCause::Explicit));
+        // Shorten the expiration of "a".
+        assert!(cache.set_expiration(&"a", Some(Duration::from_secs(5))));
+        assert!(!cache.set_expiration(&"x", Some(Duration::from_secs(5))));
+        cache.run_pending_tasks();
 
-        // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
-        cache.run_pending_tasks(); // To submit the invalidation task.
-        std::thread::sleep(Duration::from_millis(200));
-        cache.run_pending_tasks(); // To process the task result.
-        std::thread::sleep(Duration::from_millis(200));
+        mock.increment(Duration::from_secs(6)); // 6 secs from the start.
 
-        assert!(cache.get(&1).is_none());
-        assert!(cache.get(&3).is_none());
+        // The timer wheel should expire "a" without reading it.
+        cache.run_pending_tasks();
+        expected.push((Arc::new("a"), "alice", RemovalCause::Expired));
+        assert_eq!(cache.entry_count(), 2);
 
-        assert!(!cache.contains_key(&1));
-        assert!(!cache.contains_key(&3));
+        // Touching "b" resets its idle timer. ("b" will expire at 16 secs)
+        assert!(cache.touch(&"b"));
+        assert!(!cache.touch(&"a"));
+        // Set a per-entry expiration on "c" and then unset it.
+        assert!(cache.set_expiration(&"c", Some(Duration::from_secs(1))));
+        assert!(cache.set_expiration(&"c", None));
+        cache.run_pending_tasks();
 
-        assert_eq!(cache.entry_count(), 0);
-        assert_eq!(cache.invalidation_predicate_count(), 0);
+        mock.increment(Duration::from_secs(3)); // 9 secs.
+        cache.run_pending_tasks();
+        assert_eq!(cache.peek(&"c"), Some("cindy"));
 
-        verify_notification_vec(&cache, actual, &expected);
+        mock.increment(Duration::from_secs(3)); // 12 secs.
+        cache.run_pending_tasks();
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Expired));
+        assert_eq!(cache.get(&"b"), Some("bob"));
+        assert_eq!(cache.entry_count(), 1);
 
-        Ok(())
+        // Neither `set_expiration` nor `touch` should trigger a `Replaced`
+        // notification.
+        verify_notification_vec(&cache, actual, &expected);
     }
 
     #[test]
@@ -2534,6 +4687,78 @@ mod tests {
         verify_notification_vec(&cache, actual, &expected);
     }
 
+    #[test]
+    fn set_time_to_live_and_time_to_idle() {
+        // The following `Vec`s will hold actual and expected notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        let (clock, mock) = Clock::mock();
+
+        // Create a cache without the time-to-live and time-to-idle.
+        let mut cache = Cache::builder()
+            .max_capacity(100)
+            .eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice");
+        cache.run_pending_tasks();
+
+        mock.increment(Duration::from_secs(5)); // 5 secs from the start.
+        cache.insert("b", "bob");
+        cache.run_pending_tasks();
+
+        // Enable the time-to-live. It applies to the existing entries.
+        cache.set_time_to_live(Some(Duration::from_secs(8)));
+        assert_eq!(cache.policy().time_to_live(), Some(Duration::from_secs(8)));
+
+        mock.increment(Duration::from_secs(4)); // 9 secs.
+        expected.push((Arc::new("a"), "alice", RemovalCause::Expired));
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some("bob"));
+
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 1);
+
+        mock.increment(Duration::from_secs(4)); // 13 secs.
+        expected.push((Arc::new("b"), "bob", RemovalCause::Expired));
+        cache.run_pending_tasks();
+        assert!(cache.is_table_empty());
+
+        // Disable the time-to-live.
+        cache.set_time_to_live(None);
+        assert_eq!(cache.policy().time_to_live(), None);
+
+        cache.insert("c", "cindy");
+        cache.run_pending_tasks();
+
+        mock.increment(Duration::from_secs(100)); // 113 secs.
+        cache.run_pending_tasks();
+        assert_eq!(cache.get(&"c"), Some("cindy"));
+
+        // Enable the time-to-idle.
+        cache.set_time_to_idle(Some(Duration::from_secs(5)));
+        assert_eq!(cache.policy().time_to_idle(), Some(Duration::from_secs(5)));
+
+        mock.increment(Duration::from_secs(6)); // 119 secs.
+        expected.push((Arc::new("c"), "cindy", RemovalCause::Expired));
+        assert_eq!(cache.get(&"c"), None);
+
+        cache.run_pending_tasks();
+        assert!(cache.is_table_empty());
+
+        verify_notification_vec(&cache, actual, &expected);
+    }
+
     #[test]
     fn time_to_idle() {
         // The following `Vec`s will hold actual and expected notifications.
@@ -4548,7 +6773,7 @@ mod tests {
 
         // This will be rejected due to the size constraint.
         cache.insert('e', "emily");
-        expected.push((Arc::new('e'), "emily", RemovalCause::Size));
+        expected.push((Arc::new('e'), "emily", RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert_eq!(cache.entry_count(), 3);
 
@@ -4719,7 +6944,7 @@ mod tests {
         // Create an eviction listener.
         // Note that this listener is slow and will take 300 ms to complete.
         let a0 = Arc::clone(&actual);
-        let listener = move |_k, v, cause| {
+        let listener = move |_k, v, cause: RemovalCause| {
             a0.lock().push(Event::BeginNotify(v, cause));
             sleep(Duration::from_millis(300));
             a0.lock().push(Event::EndNotify(v, cause));
@@ -4971,6 +7196,154 @@ mod tests {
         assert_eq!(cache.entry_count(), MAX_CAPACITY);
     }
 
+    #[test]
+    fn close() {
+        const NUM_ENTRIES: u64 = 20;
+
+        // Let `run_pending_tasks` time out after removing a few entries, so that
+        // `close` has to call it repeatedly.
+        const EVICTION_TIMEOUT: Duration = Duration::from_millis(30);
+        const LISTENER_DELAY: Duration = Duration::from_millis(11);
+
+        let hk_conf = HousekeeperConfig::new(Some(EVICTION_TIMEOUT), Some(1), Some(1));
+        let (clock, mock) = Clock::mock();
+
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let a1 = Arc::clone(&actual);
+        let mock1 = Arc::clone(&mock);
+        let listener = move |k: Arc<u64>, _v, cause| {
+            mock1.increment(LISTENER_DELAY);
+            a1.lock().push((*k, cause));
+        };
+
+        let mut cache = Cache::builder()
+            .eviction_listener(listener)
+            .housekeeper_config(hk_conf)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for i in 0..NUM_ENTRIES {
+            cache.insert(i, format!("v{i}"));
+        }
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), NUM_ENTRIES);
+
+        // Insert one more entry without running the pending tasks. Do not advance
+        // the clock, so the entry is written at the time of `close`.
+        cache.insert(NUM_ENTRIES, format!("v{NUM_ENTRIES}"));
+
+        cache.close();
+        assert_eq!(cache.entry_count(), 0);
+        assert!(cache.iter().next().is_none());
+
+        let mut actual = actual.lock();
+        actual.sort_unstable_by_key(|(k, _)| *k);
+        let expected = (0..=NUM_ENTRIES)
+            .map(|i| (i, RemovalCause::InvalidatedAll))
+            .collect::<Vec<_>>();
+        assert_eq!(*actual, expected);
+    }
+
+    #[test]
+    fn wait_for_notifications_in_queued_listener() {
+        use crate::notification::DeliveryMode;
+
+        // The listener calls `wait_for_notifications` of its own cache, which
+        // would deadlock if it waited for the delivery thread (itself).
+        let cache_slot: Arc<Mutex<Option<Cache<u32, u32>>>> = Arc::default();
+        let actual = Arc::new(Mutex::new(Vec::new()));
+
+        let (slot1, a1) = (Arc::clone(&cache_slot), Arc::clone(&actual));
+        let listener = move |k: Arc<u32>, v, cause| {
+            let cache = slot1.lock().clone();
+            if let Some(cache) = cache {
+                cache.wait_for_notifications();
+            }
+            a1.lock().push((k, v, cause));
+        };
+
+        let cache = Cache::builder()
+            .eviction_listener(listener)
+            .delivery_mode(DeliveryMode::Queued)
+            .build();
+        *cache_slot.lock() = Some(cache.clone());
+
+        cache.insert(1, 10);
+        cache.invalidate(&1);
+        cache.wait_for_notifications();
+        assert_eq!(
+            *actual.lock(),
+            vec![(Arc::new(1), 10, RemovalCause::Explicit)]
+        );
+
+        // Break the reference cycle between the cache and the listener.
+        cache_slot.lock().take();
+    }
+
+    #[test]
+    fn queued_eviction_listener() {
+        use crate::notification::DeliveryMode;
+
+        const NUM_KEYS: u32 = 10;
+        const NUM_ROUNDS: u32 = 300; // More than the queue size in total.
+
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let a1 = Arc::clone(&actual);
+        let listener = move |k: Arc<u32>, v, cause| {
+            let thread_name = std::thread::current().name().map(ToString::to_string);
+            a1.lock().push((*k, v, cause, thread_name));
+        };
+
+        let mut cache = Cache::builder()
+            .name("queued")
+            .eviction_listener(listener)
+            .delivery_mode(DeliveryMode::Queued)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for round in 0..NUM_ROUNDS {
+            for key in 0..NUM_KEYS {
+                cache.insert(key, round);
+            }
+            cache.run_pending_tasks();
+        }
+        for key in 0..NUM_KEYS {
+            cache.invalidate(&key);
+        }
+
+        cache.wait_for_notifications();
+
+        let actual = actual.lock();
+        assert_eq!(actual.len() as u32, NUM_KEYS * NUM_ROUNDS);
+
+        // All notifications are delivered by the notifier thread, and the
+        // notifications for the same key are delivered in order.
+        for key in 0..NUM_KEYS {
+            let values = actual
+                .iter()
+                .filter(|(k, ..)| *k == key)
+                .map(|(_, v, cause, thread_name)| {
+                    assert_eq!(thread_name.as_deref(), Some("moka-notifier-queued"));
+                    let expected_cause = if *v == NUM_ROUNDS - 1 {
+                        RemovalCause::Explicit
+                    } else {
+                        RemovalCause::Replaced
+                    };
+                    assert_eq!(cause, &expected_cause);
+                    *v
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(values, (0..NUM_ROUNDS).collect::<Vec<_>>());
+        }
+    }
+
     // NOTE: To enable the panic logging, run the following command:
     //
     // RUST_LOG=moka=info cargo test --features 'logging' -- \
@@ -5025,6 +7398,177 @@ mod tests {
         cache.run_pending_tasks();
 
         verify_notification_vec(&cache, actual, &expected);
+        assert_eq!(cache.eviction_listener_panic_count(), 1);
+    }
+
+    #[test]
+    fn batch_eviction_listener() {
+        let (clock, mock) = Clock::mock();
+
+        // Record each batch as a sorted vec of keys and causes.
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let b1 = Arc::clone(&batches);
+        let listener = move |entries: Vec<(Arc<u64>, String, RemovalCause)>| {
+            let mut batch = entries
+                .into_iter()
+                .map(|(k, _v, cause)| (*k, cause))
+                .collect::<Vec<_>>();
+            batch.sort_unstable_by_key(|(k, _)| *k);
+            b1.lock().push(batch);
+        };
+
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .batch_eviction_listener(listener)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for i in 0..3 {
+            cache.insert(i, format!("v{i}"));
+        }
+        cache.run_pending_tasks();
+        // Nothing has been removed yet, so the listener should not be called.
+        assert!(batches.lock().is_empty());
+
+        // The two least recently used entries will be evicted by one maintenance
+        // pass.
+        cache.insert(3, "v3".into());
+        cache.insert(4, "v4".into());
+        cache.run_pending_tasks();
+        assert_eq!(
+            batches.lock().pop(),
+            Some(vec![(0, RemovalCause::Size), (1, RemovalCause::Size)])
+        );
+
+        // An explicit invalidation is notified by itself.
+        cache.invalidate(&2);
+        assert_eq!(
+            batches.lock().pop(),
+            Some(vec![(2, RemovalCause::Explicit)])
+        );
+
+        mock.increment(Duration::from_secs(1));
+        cache.invalidate_all();
+        cache.run_pending_tasks();
+        assert_eq!(
+            *batches.lock(),
+            vec![vec![
+                (3, RemovalCause::InvalidatedAll),
+                (4, RemovalCause::InvalidatedAll)
+            ]]
+        );
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[test]
+    fn panicking_batch_eviction_listener() {
+        // Create a batch eviction listener that panics on every batch.
+        let listener = |_entries: Vec<(Arc<u64>, String, RemovalCause)>| {
+            panic!("Panic now!");
+        };
+
+        // Create a panic hook that records the keys and causes of each batch.
+        let panics = Arc::new(Mutex::new(Vec::new()));
+        let p1 = Arc::clone(&panics);
+        let hook = move |_payload: Box<dyn std::any::Any + Send>,
+                         entries: &[(Arc<u64>, RemovalCause)]| {
+            let mut batch: Vec<_> = entries.iter().map(|(k, cause)| (**k, *cause)).collect();
+            batch.sort_unstable_by_key(|(k, _)| *k);
+            p1.lock().push(batch);
+        };
+
+        let mut cache = Cache::builder()
+            .max_capacity(3)
+            .eviction_policy(EvictionPolicy::lru())
+            .batch_eviction_listener(listener)
+            .eviction_listener_panic_hook(hook)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for i in 0..5 {
+            cache.insert(i, format!("v{i}"));
+        }
+        cache.run_pending_tasks();
+
+        // The hook received all the entries of the batch, not only the first one.
+        assert_eq!(
+            *panics.lock(),
+            vec![vec![(0, RemovalCause::Size), (1, RemovalCause::Size)]]
+        );
+        assert_eq!(cache.eviction_listener_panic_count(), 1);
+    }
+
+    #[test]
+    fn continue_after_panicking_eviction_listener() {
+        use crate::notification::ListenerPanicPolicy;
+
+        let actual = Arc::new(Mutex::new(Vec::new()));
+        let mut expected = Vec::new();
+        let panics = Arc::new(Mutex::new(Vec::new()));
+
+        // Create an eviction listener that panics when it see
+        // a value "panic now!".
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| {
+            if v == "panic now!" {
+                panic!("Panic now!");
+            }
+            a1.lock().push((k, v, cause))
+        };
+
+        // Create a panic hook that records the payload, keys and causes.
+        let p1 = Arc::clone(&panics);
+        let hook = move |payload: Box<dyn std::any::Any + Send>,
+                         entries: &[(Arc<&'static str>, RemovalCause)]| {
+            let message = payload.downcast_ref::<&str>().map(ToString::to_string);
+            let entries: Vec<_> = entries.iter().map(|(k, cause)| (**k, *cause)).collect();
+            p1.lock().push((message, entries));
+        };
+
+        // Create a cache with the eviction listener that stays enabled after a
+        // panic.
+        let mut cache = Cache::builder()
+            .eviction_listener(listener)
+            .eviction_listener_panic_policy(ListenerPanicPolicy::Continue)
+            .eviction_listener_panic_hook(hook)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("alice", "panic now!");
+        cache.insert("alice", "a1");
+        cache.insert("bob", "panic now!");
+        cache.invalidate(&"bob");
+        cache.invalidate(&"alice");
+        expected.push((Arc::new("alice"), "a1", RemovalCause::Explicit));
+        cache.run_pending_tasks();
+
+        // The listener was still called after the panics.
+        verify_notification_vec(&cache, actual, &expected);
+        assert_eq!(cache.eviction_listener_panic_count(), 2);
+        assert_eq!(
+            *panics.lock(),
+            vec![
+                (
+                    Some("Panic now!".into()),
+                    vec![("alice", RemovalCause::Replaced)]
+                ),
+                (
+                    Some("Panic now!".into()),
+                    vec![("bob", RemovalCause::Explicit)]
+                ),
+            ]
+        );
     }
 
     // This test ensures that the `contains_key`, `get` and `invalidate` can use
@@ -5066,7 +7610,7 @@ mod tests {
         let counters1 = Arc::clone(&counters);
 
         let listener = move |_k, _v, cause| match cause {
-            RemovalCause::Size => counters1.incl_evicted(),
+            RemovalCause::Size | RemovalCause::Rejected => counters1.incl_evicted(),
             RemovalCause::Explicit => counters1.incl_invalidated(),
             _ => (),
         };
@@ -1,12 +1,13 @@
 use equivalent::Equivalent;
 
-use crate::{ops::compute, Entry};
+use crate::{ops::compute, Entry, WriteError};
 
 use super::Cache;
 
 use std::{
     hash::{BuildHasher, Hash},
     sync::Arc,
+    time::Duration,
 };
 
 /// Provides advanced methods to select or insert an entry of the cache.
@@ -51,6 +52,9 @@ where
     ///    `ops::compute::Op<V>`.
     /// 2. Execute the op on the cache:
     ///    - `Op::Put(V)`: Put the new value `V` to the cache.
+    ///    - `Op::PutWithTtl(V, Duration)`: Put the new value `V` to the cache with
+    ///      the time-to-live for the entry. It works as `Op::Put(V)` in the table
+    ///      below.
     ///    - `Op::Remove`: Remove the current cached entry.
     ///    - `Op::Nop`: Do nothing.
     /// 3. Return an `ops::compute::CompResult<K, V>` as the followings:
@@ -171,6 +175,9 @@ where
     /// 2. If resolved to `Err(E)`, return it.
     /// 3. Else, execute the op on the cache:
     ///    - `Ok(Op::Put(V))`: Put the new value `V` to the cache.
+    ///    - `Ok(Op::PutWithTtl(V, Duration))`: Put the new value `V` to the cache
+    ///      with the time-to-live for the entry. It works as `Ok(Op::Put(V))` in the
+    ///      table below.
     ///    - `Ok(Op::Remove)`: Remove the current cached entry.
     ///    - `Ok(Op::Nop)`: Do nothing.
     /// 4. Return an `Ok(ops::compute::CompResult<K, V>)` as the followings:
@@ -294,6 +301,46 @@ where
         self.cache.upsert_with_hash_and_fun(key, self.hash, f)
     }
 
+    /// Performs a compute operation on a cached entry by using the given closure
+    /// `f`, and returns the error from the [`CacheWriter`][cache-writer] of the
+    /// cache if the writer failed.
+    ///
+    /// This method works like [`and_compute_with`][compute-with] method, except that
+    /// it returns an `Err(WriteError)` instead of `Unchanged` or `StillNone` when
+    /// the writer failed to write `Op::Put` or `Op::PutWithTtl`, or to delete the
+    /// entry for `Op::Remove`. In that case, the cache is not modified.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    /// [compute-with]: #method.and_compute_with
+    pub fn and_compute_with_checked<F>(self, f: F) -> Result<compute::CompResult<K, V>, WriteError>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> compute::Op<V>,
+    {
+        let key = Arc::new(self.owned_key);
+        self.cache
+            .checked_compute_with_hash_and_fun(key, self.hash, f)
+    }
+
+    /// Performs an upsert of an [`Entry`] by using the given closure `f`, and
+    /// returns the error from the [`CacheWriter`][cache-writer] of the cache if the
+    /// writer failed.
+    ///
+    /// This method works like [`and_upsert_with`][upsert-with] method, except that
+    /// it returns an `Err(WriteError)` when the writer failed to write the new
+    /// value. In that case, the cache is not modified.
+    ///
+    /// [`Entry`]: ../struct.Entry.html
+    /// [cache-writer]: ../trait.CacheWriter.html
+    /// [upsert-with]: #method.and_upsert_with
+    pub fn and_upsert_with_checked<F>(self, f: F) -> Result<Entry<K, V>, WriteError>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> V,
+    {
+        let key = Arc::new(self.owned_key);
+        self.cache
+            .checked_upsert_with_hash_and_fun(key, self.hash, f)
+    }
+
     /// Returns the corresponding [`Entry`] for the key given when this entry
     /// selector was constructed. If the entry does not exist, inserts one by calling
     /// the [`default`][std-default-function] function of the value type `V`.
@@ -324,7 +371,7 @@ where
     {
         let key = Arc::new(self.owned_key);
         self.cache
-            .get_or_insert_with_hash(key, self.hash, Default::default)
+            .get_or_insert_with_hash(key, self.hash, Default::default, None)
     }
 
     /// Returns the corresponding [`Entry`] for the key given when this entry
@@ -354,7 +401,44 @@ where
     pub fn or_insert(self, default: V) -> Entry<K, V> {
         let key = Arc::new(self.owned_key);
         let init = || default;
-        self.cache.get_or_insert_with_hash(key, self.hash, init)
+        self.cache
+            .get_or_insert_with_hash(key, self.hash, init, None)
+    }
+
+    /// Returns the corresponding [`Entry`] for the key given when this entry
+    /// selector was constructed. If the entry does not exist, inserts one by using
+    /// the the given `default` value for `V`, with the time-to-live `ttl` for the
+    /// entry.
+    ///
+    /// See [`Cache::insert_with_ttl`][insert-with-ttl] for how the time-to-live is
+    /// applied to the entry.
+    ///
+    /// [`Entry`]: ../struct.Entry.html
+    /// [insert-with-ttl]: ./struct.Cache.html#method.insert_with_ttl
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache: Cache<String, u32> = Cache::new(100);
+    /// let key = "key1".to_string();
+    ///
+    /// let entry = cache
+    ///     .entry(key.clone())
+    ///     .or_insert_with_ttl(3, Duration::from_millis(50));
+    /// assert!(entry.is_fresh());
+    /// assert_eq!(entry.into_value(), 3);
+    ///
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert!(!cache.contains_key(&key));
+    /// ```
+    pub fn or_insert_with_ttl(self, default: V, ttl: Duration) -> Entry<K, V> {
+        let key = Arc::new(self.owned_key);
+        let init = || default;
+        self.cache
+            .get_or_insert_with_hash(key, self.hash, init, Some(ttl))
     }
 
     /// Returns the corresponding [`Entry`] for the key given when this entry
@@ -593,6 +677,9 @@ where
     ///    `ops::compute::Op<V>`.
     /// 2. Execute the op on the cache:
     ///    - `Op::Put(V)`: Put the new value `V` to the cache.
+    ///    - `Op::PutWithTtl(V, Duration)`: Put the new value `V` to the cache with
+    ///      the time-to-live for the entry. It works as `Op::Put(V)` in the table
+    ///      below.
     ///    - `Op::Remove`: Remove the current cached entry.
     ///    - `Op::Nop`: Do nothing.
     /// 3. Return an `ops::compute::CompResult<K, V>` as the followings:
@@ -713,6 +800,9 @@ where
     /// 2. If resolved to `Err(E)`, return it.
     /// 3. Else, execute the op on the cache:
     ///    - `Ok(Op::Put(V))`: Put the new value `V` to the cache.
+    ///    - `Ok(Op::PutWithTtl(V, Duration))`: Put the new value `V` to the cache
+    ///      with the time-to-live for the entry. It works as `Ok(Op::Put(V))` in the
+    ///      table below.
     ///    - `Ok(Op::Remove)`: Remove the current cached entry.
     ///    - `Ok(Op::Nop)`: Do nothing.
     /// 4. Return an `Ok(ops::compute::CompResult<K, V>)` as the followings:
@@ -836,6 +926,46 @@ where
         self.cache.upsert_with_hash_and_fun(key, self.hash, f)
     }
 
+    /// Performs a compute operation on a cached entry by using the given closure
+    /// `f`, and returns the error from the [`CacheWriter`][cache-writer] of the
+    /// cache if the writer failed.
+    ///
+    /// This method works like [`and_compute_with`][compute-with] method, except that
+    /// it returns an `Err(WriteError)` instead of `Unchanged` or `StillNone` when
+    /// the writer failed to write `Op::Put` or `Op::PutWithTtl`, or to delete the
+    /// entry for `Op::Remove`. In that case, the cache is not modified.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    /// [compute-with]: #method.and_compute_with
+    pub fn and_compute_with_checked<F>(self, f: F) -> Result<compute::CompResult<K, V>, WriteError>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> compute::Op<V>,
+    {
+        let key = Arc::new(self.ref_key.to_owned());
+        self.cache
+            .checked_compute_with_hash_and_fun(key, self.hash, f)
+    }
+
+    /// Performs an upsert of an [`Entry`] by using the given closure `f`, and
+    /// returns the error from the [`CacheWriter`][cache-writer] of the cache if the
+    /// writer failed.
+    ///
+    /// This method works like [`and_upsert_with`][upsert-with] method, except that
+    /// it returns an `Err(WriteError)` when the writer failed to write the new
+    /// value. In that case, the cache is not modified.
+    ///
+    /// [`Entry`]: ../struct.Entry.html
+    /// [cache-writer]: ../trait.CacheWriter.html
+    /// [upsert-with]: #method.and_upsert_with
+    pub fn and_upsert_with_checked<F>(self, f: F) -> Result<Entry<K, V>, WriteError>
+    where
+        F: FnOnce(Option<Entry<K, V>>) -> V,
+    {
+        let key = Arc::new(self.ref_key.to_owned());
+        self.cache
+            .checked_upsert_with_hash_and_fun(key, self.hash, f)
+    }
+
     /// Returns the corresponding [`Entry`] for the reference of the key given when
     /// this entry selector was constructed. If the entry does not exist, inserts one
     /// by cloning the key and calling the [`default`][std-default-function] function
@@ -866,7 +996,7 @@ where
         V: Default,
     {
         self.cache
-            .get_or_insert_with_hash_by_ref(self.ref_key, self.hash, Default::default)
+            .get_or_insert_with_hash_by_ref(self.ref_key, self.hash, Default::default, None)
     }
 
     /// Returns the corresponding [`Entry`] for the reference of the key given when
@@ -896,7 +1026,42 @@ where
     pub fn or_insert(self, default: V) -> Entry<K, V> {
         let init = || default;
         self.cache
-            .get_or_insert_with_hash_by_ref(self.ref_key, self.hash, init)
+            .get_or_insert_with_hash_by_ref(self.ref_key, self.hash, init, None)
+    }
+
+    /// Returns the corresponding [`Entry`] for the reference of the key given when
+    /// this entry selector was constructed. If the entry does not exist, inserts one
+    /// by cloning the key and using the given `default` value for `V`, with the
+    /// time-to-live `ttl` for the entry.
+    ///
+    /// See [`Cache::insert_with_ttl`][insert-with-ttl] for how the time-to-live is
+    /// applied to the entry.
+    ///
+    /// [`Entry`]: ../struct.Entry.html
+    /// [insert-with-ttl]: ./struct.Cache.html#method.insert_with_ttl
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use moka::sync::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let cache: Cache<String, u32> = Cache::new(100);
+    /// let key = "key1".to_string();
+    ///
+    /// let entry = cache
+    ///     .entry_by_ref(&key)
+    ///     .or_insert_with_ttl(3, Duration::from_millis(50));
+    /// assert!(entry.is_fresh());
+    /// assert_eq!(entry.into_value(), 3);
+    ///
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert!(!cache.contains_key(&key));
+    /// ```
+    pub fn or_insert_with_ttl(self, default: V, ttl: Duration) -> Entry<K, V> {
+        let init = || default;
+        self.cache
+            .get_or_insert_with_hash_by_ref(self.ref_key, self.hash, init, Some(ttl))
     }
 
     /// Returns the corresponding [`Entry`] for the reference of the key given when
@@ -1,4 +1,4 @@
-use super::{base_cache::Inner, PredicateId, PredicateIdStr};
+use super::{base_cache::Inner, PredicateId};
 use crate::{
     common::{
         concurrent::{arc::MiniArc, AccessTime, KvEntry, ValueEntry},
@@ -16,7 +16,6 @@ use std::{
         Arc,
     },
 };
-use uuid::Uuid;
 
 pub(crate) type PredicateFun<K, V> = Arc<dyn Fn(&K, &V) -> bool + Send + Sync + 'static>;
 
@@ -117,7 +116,7 @@ impl<K, V, S> Invalidator<K, V, S> {
         let preds = &self.predicates;
 
         while tries < MAX_RETRY {
-            let id = Uuid::new_v4().as_hyphenated().to_string();
+            let id = PredicateId::new_v4();
 
             let hash = preds.hash(&id);
             if preds.contains_key(hash, |k| k == &id) {
@@ -125,8 +124,8 @@ impl<K, V, S> Invalidator<K, V, S> {
 
                 continue; // Retry
             }
-            let pred = Predicate::new(&id, predicate, registered_at);
-            preds.insert_entry_and(id.clone(), hash, pred, |_, _| ());
+            let pred = Predicate::new(id, predicate, registered_at);
+            preds.insert_entry_and(id, hash, pred, |_, _| ());
             self.is_empty.store(false, Ordering::Release);
 
             return Ok(id);
@@ -155,6 +154,7 @@ impl<K, V, S> Invalidator<K, V, S> {
                 &entry.value,
                 ts,
             )
+            .is_some()
         } else {
             false
         }
@@ -183,8 +183,8 @@ impl<K, V, S> Invalidator<K, V, S> {
             let key = &candidate.key;
             let hash = candidate.hash;
             let ts = candidate.timestamp;
-            if self.apply(&predicates, cache, key, hash, ts) {
-                if let Some(entry) = Self::invalidate(cache, key, hash, ts) {
+            if let Some(id) = self.apply(&predicates, cache, key, hash, ts) {
+                if let Some(entry) = Self::invalidate(cache, key, hash, ts, id) {
                     invalidated.push(KvEntry {
                         key: Arc::clone(key),
                         entry,
@@ -208,17 +208,18 @@ where
     K: Hash + Eq,
     S: BuildHasher,
 {
+    /// Returns the ID of the first predicate that matches the given entry, if any.
     #[inline]
-    fn do_apply_predicates<I>(predicates: I, key: &K, value: &V, ts: Instant) -> bool
+    fn do_apply_predicates<I>(predicates: I, key: &K, value: &V, ts: Instant) -> Option<PredicateId>
     where
         I: Iterator<Item = Predicate<K, V>>,
     {
         for predicate in predicates {
             if predicate.is_applicable(ts) && predicate.apply(key, value) {
-                return true;
+                return Some(predicate.id);
             }
         }
-        false
+        None
     }
 
     fn remove_finished_predicates(
@@ -257,8 +258,8 @@ where
     {
         let pred_map = &self.predicates;
         for p in predicates.iter() {
-            let hash = pred_map.hash(p.id());
-            pred_map.remove(hash, |k| k == p.id());
+            let hash = pred_map.hash(&p.id());
+            pred_map.remove(hash, |k| *k == p.id());
         }
 
         if pred_map.is_empty() {
@@ -273,7 +274,7 @@ where
         key: &Arc<K>,
         hash: u64,
         ts: Instant,
-    ) -> bool {
+    ) -> Option<PredicateId> {
         if let Some(entry) = cache.cache.get(hash, |k| k == key) {
             if let Some(lm) = entry.last_modified() {
                 if lm == ts {
@@ -287,7 +288,7 @@ where
             }
         }
 
-        false
+        None
     }
 
     fn invalidate(
@@ -295,6 +296,7 @@ where
         key: &Arc<K>,
         hash: u64,
         ts: Instant,
+        predicate_id: PredicateId,
     ) -> Option<MiniArc<ValueEntry<K, V>>>
     where
         K: Send + Sync + 'static,
@@ -317,7 +319,11 @@ where
         );
         if let Some(entry) = &maybe_entry {
             if cache.is_removal_notifier_enabled() {
-                cache.notify_single_removal(Arc::clone(key), entry, RemovalCause::Explicit);
+                cache.notify_single_removal(
+                    Arc::clone(key),
+                    entry,
+                    RemovalCause::InvalidatedByPredicate(predicate_id),
+                );
             }
         }
         maybe_entry
@@ -355,7 +361,7 @@ struct Predicate<K, V> {
 impl<K, V> Clone for Predicate<K, V> {
     fn clone(&self) -> Self {
         Self {
-            id: self.id.clone(),
+            id: self.id,
             f: Arc::clone(&self.f),
             registered_at: self.registered_at,
         }
@@ -363,16 +369,16 @@ impl<K, V> Clone for Predicate<K, V> {
 }
 
 impl<K, V> Predicate<K, V> {
-    fn new(id: PredicateIdStr<'_>, f: PredicateFun<K, V>, registered_at: Instant) -> Self {
+    fn new(id: PredicateId, f: PredicateFun<K, V>, registered_at: Instant) -> Self {
         Self {
-            id: id.to_string(),
+            id,
             f,
             registered_at,
         }
     }
 
-    fn id(&self) -> PredicateIdStr<'_> {
-        &self.id
+    fn id(&self) -> PredicateId {
+        self.id
     }
 
     fn is_applicable(&self, last_modified: Instant) -> bool {
@@ -2,17 +2,19 @@ use crossbeam_utils::atomic::AtomicCell;
 use equivalent::Equivalent;
 
 use super::{cache::Cache, CacheBuilder, OwnedKeyEntrySelector, RefKeyEntrySelector};
-use crate::common::concurrent::Weigher;
-use crate::common::time::Clock;
+use crate::common::concurrent::{EvictionVeto, Weigher, Writer};
+use crate::common::time::{self, Clock};
+use crate::common::write_behind::WriteBehindConfig;
 use crate::CapacityError;
 use crate::{
     common::{
+        builder_utils,
         iter::{Iter, ScanningGet},
         HousekeeperConfig,
     },
-    notification::EvictionListener,
+    notification::{Backpressure, EventReceiver, EvictionListener, ListenerConfig, Subscriber},
     policy::{EvictionPolicy, ExpirationPolicy},
-    Entry, Policy, PredicateError,
+    Entry, EntryMetadata, InsertError, Policy, PredicateError, WriteError,
 };
 
 use std::{
@@ -20,6 +22,7 @@ use std::{
     fmt,
     hash::{BuildHasher, Hash, Hasher},
     sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
 /// A thread-safe concurrent in-memory cache, with multiple internal segments.
@@ -107,8 +110,13 @@ where
             num_segments,
             build_hasher,
             None,
+            None,
+            None,
+            None,
+            None,
             EvictionPolicy::default(),
             None,
+            ListenerConfig::default(),
             ExpirationPolicy::default(),
             HousekeeperConfig::default(),
             false,
@@ -154,6 +162,84 @@ where
         Ok(())
     }
 
+    /// Sets the `time_to_live` of this cache. `None` disables the expiration by
+    /// the time-to-live.
+    ///
+    /// See [`Cache::set_time_to_live`](./struct.Cache.html#method.set_time_to_live)
+    /// for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is longer than 1000 years.
+    pub fn set_time_to_live(&self, duration: Option<Duration>) {
+        builder_utils::ensure_expirations_or_panic(duration, None);
+        for segment in self.inner.segments.iter() {
+            segment.base.set_time_to_live(duration);
+        }
+    }
+
+    /// Sets the `time_to_idle` of this cache. `None` disables the expiration by
+    /// the time-to-idle.
+    ///
+    /// See [`Cache::set_time_to_idle`](./struct.Cache.html#method.set_time_to_idle)
+    /// for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is longer than 1000 years.
+    pub fn set_time_to_idle(&self, duration: Option<Duration>) {
+        builder_utils::ensure_expirations_or_panic(None, duration);
+        for segment in self.inner.segments.iter() {
+            segment.base.set_time_to_idle(duration);
+        }
+    }
+
+    /// Switches the [`EvictionPolicy`] of this cache without losing the cached
+    /// entries.
+    ///
+    /// See [`Cache::set_eviction_policy`](./struct.Cache.html#method.set_eviction_policy)
+    /// for details.
+    pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        for segment in self.inner.segments.iter() {
+            segment.set_eviction_policy(policy.clone());
+        }
+    }
+
+    /// Subscribes to the mutations of this cache, and returns an
+    /// [`EventReceiver`][event-receiver] of the events of all segments.
+    ///
+    /// See [`Cache::subscribe`](./struct.Cache.html#method.subscribe) for details.
+    ///
+    /// [event-receiver]: ../notification/struct.EventReceiver.html
+    pub fn subscribe(&self, capacity: usize, backpressure: Backpressure) -> EventReceiver<K, V> {
+        let subscriber = Subscriber::new(capacity, backpressure);
+        let receiver = EventReceiver::new(&subscriber);
+        for segment in self.inner.segments.iter() {
+            segment.base.subscribe(subscriber.clone());
+        }
+        receiver
+    }
+
+    /// Blocks the current thread until the eviction listener has been called for
+    /// all the notifications queued so far in all segments.
+    ///
+    /// See [`Cache::wait_for_notifications`](./struct.Cache.html#method.wait_for_notifications)
+    /// for details.
+    pub fn wait_for_notifications(&self) {
+        for segment in self.inner.segments.iter() {
+            segment.wait_for_notifications();
+        }
+    }
+
+    /// Closes the cache for a shutdown. Invalidates all entries in all segments,
+    /// and waits until the eviction listener has been called for all of them.
+    ///
+    /// See [`Cache::close`](./struct.Cache.html#method.close) for details.
+    pub fn close(&self) {
+        for segment in self.inner.segments.iter() {
+            segment.close();
+        }
+    }
 }
 
 impl<K, V, S> SegmentedCache<K, V, S> {
@@ -164,12 +250,15 @@ impl<K, V, S> SegmentedCache<K, V, S> {
 
     /// Returns a read-only cache policy of this cache.
     ///
-    /// At this time, cache policy cannot be modified after cache creation.
-    /// A future version may support to modify it.
+    /// Some of the policy can be modified after cache creation by methods such as
+    /// `set_max_capacity_block`, `set_time_to_live` and
+    /// `set_time_to_idle`.
     pub fn policy(&self) -> Policy {
         let mut policy = self.inner.segments[0].policy();
         policy.set_max_capacity(self.inner.desired_capacity.load());
         policy.set_num_segments(self.inner.segments.len());
+        policy.set_initial_capacity(self.inner.initial_capacity);
+        policy.set_pinned_weighted_size(self.pinned_weighted_size());
         policy
     }
 
@@ -228,6 +317,34 @@ impl<K, V, S> SegmentedCache<K, V, S> {
             .map(|seg| seg.weighted_size())
             .sum()
     }
+
+    /// Returns an approximate total weighted size of the pinned entries in this
+    /// cache. The pinned entries are also counted in the
+    /// [`weighted_size`](#method.weighted_size).
+    ///
+    /// Like `weighted_size`, the value returned is _an estimate_. See
+    /// [`pin`](#method.pin) for more details about pinned entries.
+    pub fn pinned_weighted_size(&self) -> u64 {
+        self.inner
+            .segments
+            .iter()
+            .map(|seg| seg.pinned_weighted_size())
+            .sum()
+    }
+
+    /// Returns the number of times the eviction listener of this cache panicked,
+    /// summed over all segments.
+    ///
+    /// See [`Cache::eviction_listener_panic_count`][cache-method] for details.
+    ///
+    /// [cache-method]: ./struct.Cache.html#method.eviction_listener_panic_count
+    pub fn eviction_listener_panic_count(&self) -> u64 {
+        self.inner
+            .segments
+            .iter()
+            .map(|seg| seg.eviction_listener_panic_count())
+            .sum()
+    }
 }
 
 impl<K, V, S> SegmentedCache<K, V, S>
@@ -247,8 +364,13 @@ where
         num_segments: usize,
         build_hasher: S,
         weigher: Option<Weigher<K, V>>,
+        max_entry_weight: Option<u64>,
+        can_evict: Option<EvictionVeto<K, V>>,
+        writer: Option<Writer<K, V>>,
+        write_behind: Option<WriteBehindConfig<K, V>>,
         eviction_policy: EvictionPolicy,
         eviction_listener: Option<EvictionListener<K, V>>,
+        listener_config: ListenerConfig<K>,
         expiration_policy: ExpirationPolicy<K, V>,
         housekeeper_config: HousekeeperConfig,
         invalidator_enabled: bool,
@@ -262,8 +384,13 @@ where
                 num_segments,
                 build_hasher,
                 weigher,
+                max_entry_weight,
+                can_evict,
+                writer,
+                write_behind,
                 eviction_policy,
                 eviction_listener,
+                listener_config,
                 expiration_policy,
                 housekeeper_config,
                 invalidator_enabled,
@@ -309,6 +436,44 @@ where
             .map(Entry::into_value)
     }
 
+    /// Returns a _clone_ of the value corresponding to the key, without any side
+    /// effects on the cache.
+    ///
+    /// Unlike the `get` method, this method is not considered a cache read
+    /// operation. So it does not update the historic popularity estimator, the
+    /// access order (LRU) of the entry, or reset the idle timer for the key. It is
+    /// useful for debugging or scanning the cache in background.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner.select(hash).peek_with_hash(key, hash)
+    }
+
+    /// Returns the [`EntryMetadata`] of the entry for the key, such as its last
+    /// accessed and modified times, its expiration time, its weight and its
+    /// estimated access frequency.
+    ///
+    /// Like the `contains_key` method, this method is not considered a cache read
+    /// operation, so it does not update the historic popularity estimator or reset
+    /// the idle timer for the key.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [`EntryMetadata`]: ../struct.EntryMetadata.html
+    pub fn peek_metadata<Q>(&self, key: &Q) -> Option<EntryMetadata>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner.select(hash).peek_metadata_with_hash(key, hash)
+    }
+
     pub fn entry(&self, key: K) -> OwnedKeyEntrySelector<'_, K, V, S>
     where
         K: Hash + Eq,
@@ -492,19 +657,163 @@ where
     /// Inserts a key-value pair into the cache.
     ///
     /// If the cache has this key present, the value is updated.
+    ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to write the entry,
+    /// the cache is not modified and the error is only logged (when the `logging`
+    /// feature is enabled). Use [`try_insert`](#method.try_insert) to get the error.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub fn insert(&self, key: K, value: V) {
         let hash = self.inner.hash(&key);
         let key = Arc::new(key);
         self.inner.select(hash).insert_with_hash(key, hash, value);
     }
 
+    /// Inserts a key-value pair into the cache with the time-to-live for this
+    /// entry.
+    ///
+    /// See [`Cache::insert_with_ttl`][cache-insert-with-ttl] for more details.
+    ///
+    /// [cache-insert-with-ttl]: ./struct.Cache.html#method.insert_with_ttl
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        let hash = self.inner.hash(&key);
+        let key = Arc::new(key);
+        // The rejection has been already notified to the eviction listener.
+        let cache = self.inner.select(hash);
+        let result = cache.insert_with_hash_and_ttl(key, hash, value, Some(ttl));
+        if let Err(InsertError::WriterFailed(e)) = result {
+            cache.log_writer_error(&e);
+        }
+    }
+
+    /// Inserts a key-value pair into the cache with the wall-clock time when this
+    /// entry expires.
+    ///
+    /// See [`Cache::insert_with_expire_at`][cache-insert-with-expire-at] for more
+    /// details.
+    ///
+    /// [cache-insert-with-expire-at]: ./struct.Cache.html#method.insert_with_expire_at
+    pub fn insert_with_expire_at(&self, key: K, value: V, expire_at: SystemTime) {
+        self.insert_with_ttl(key, value, time::duration_until(expire_at));
+    }
+
+    /// Inserts a key-value pair into the cache, and returns an error if the entry
+    /// was rejected by the cache.
+    ///
+    /// See [`Cache::try_insert`][cache-try-insert] for more details.
+    ///
+    /// [cache-try-insert]: ./struct.Cache.html#method.try_insert
+    pub fn try_insert(&self, key: K, value: V) -> Result<(), InsertError> {
+        let hash = self.inner.hash(&key);
+        let key = Arc::new(key);
+        self.inner
+            .select(hash)
+            .try_insert_with_hash(key, hash, value)
+    }
+
+    /// Inserts a key-value pair into the cache and pins the entry.
+    ///
+    /// If the cache has this key present, the value is updated and the entry is
+    /// pinned. See [`pin`](#method.pin) for more details about pinned entries.
+    pub fn insert_pinned(&self, key: K, value: V) {
+        let hash = self.inner.hash(&key);
+        let key = Arc::new(key);
+        self.inner
+            .select(hash)
+            .insert_pinned_with_hash(key, hash, value);
+    }
+
+    /// Pins the cached entry for the key so that it will never be evicted by the
+    /// max capacity of the cache. Returns `true` if the entry exists.
+    ///
+    /// A pinned entry still counts toward the max capacity of its segment. If the
+    /// total weight of the pinned entries in a segment exceeds the capacity of the
+    /// segment, the segment evicts all of its unpinned entries and keeps the pinned
+    /// ones. See [`Cache::pin`](./struct.Cache.html#method.pin) for more details.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn pin<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner
+            .select(hash)
+            .set_pinned_with_hash(key, hash, true)
+    }
+
+    /// Unpins the cached entry for the key so that it can be evicted again. Returns
+    /// `true` if the entry exists.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn unpin<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner
+            .select(hash)
+            .set_pinned_with_hash(key, hash, false)
+    }
+
+    /// Sets the expiration of the cached entry for the key to `ttl` from now, without
+    /// changing its value. Returns `true` if the entry exists.
+    ///
+    /// When `ttl` is `None`, the per-entry expiration time of the entry is unset, so
+    /// the entry will only be expired by the cache-wide `time_to_live` and
+    /// `time_to_idle`. Like the per-entry TTL given by `insert_with_ttl`, the
+    /// expiration time set by this method can be overridden by the
+    /// [`Expiry`][crate::Expiry] of the cache on the next read or update of the
+    /// entry.
+    ///
+    /// This method does not trigger a `Replaced` notification to the eviction
+    /// listener as the value is not changed.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn set_expiration<Q>(&self, key: &Q, ttl: Option<Duration>) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner
+            .select(hash)
+            .set_expiration_with_hash(key, hash, ttl)
+    }
+
+    /// Marks the cached entry for the key as accessed, without cloning its value.
+    /// Returns `true` if the entry exists.
+    ///
+    /// Like the `get` method, this method resets the idle timer for the key, calls
+    /// the `expire_after_read` method of the [`Expiry`][crate::Expiry] if any, and
+    /// updates the historic popularity estimator.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
+    /// on the borrowed form _must_ match those for the key type.
+    pub fn touch<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner.select(hash).touch_with_hash(key, hash)
+    }
+
     /// Discards any cached value for the key.
     ///
     /// If you need to get a the value that has been discarded, use the
     /// [`remove`](#method.remove) method instead.
     ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to delete the entry,
+    /// the cached value is not discarded and the error is only logged (when the
+    /// `logging` feature is enabled). Use [`try_invalidate`](#method.try_invalidate)
+    /// to get the error.
+    ///
     /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
     /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub fn invalidate<Q>(&self, key: &Q)
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -520,8 +829,15 @@ where
     /// If you do not need to get the value that has been discarded, use the
     /// [`invalidate`](#method.invalidate) method instead.
     ///
+    /// If the [`CacheWriter`][cache-writer] of the cache fails to delete the entry,
+    /// the cached value is not discarded, `None` is returned and the error is only
+    /// logged (when the `logging` feature is enabled). Use
+    /// [`try_remove`](#method.try_remove) to get the error.
+    ///
     /// The key may be any borrowed form of the cache's key type, but `Hash` and `Eq`
     /// on the borrowed form _must_ match those for the key type.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
     pub fn remove<Q>(&self, key: &Q) -> Option<V>
     where
         Q: Equivalent<K> + Hash + ?Sized,
@@ -532,6 +848,42 @@ where
             .invalidate_with_hash(key, hash, true)
     }
 
+    /// Discards any cached value for the key, and returns an error if the
+    /// [`CacheWriter`][cache-writer] of the cache failed to delete it.
+    ///
+    /// See [`Cache::try_invalidate`][cache-try-invalidate] for more details.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    /// [cache-try-invalidate]: ./struct.Cache.html#method.try_invalidate
+    pub fn try_invalidate<Q>(&self, key: &Q) -> Result<(), WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner
+            .select(hash)
+            .try_invalidate_with_hash(key, hash, false, true)
+            .map(|_| ())
+    }
+
+    /// Discards any cached value for the key and returns a clone of the value, or
+    /// returns an error if the [`CacheWriter`][cache-writer] of the cache failed to
+    /// delete it.
+    ///
+    /// See [`Cache::try_remove`][cache-try-remove] for more details.
+    ///
+    /// [cache-writer]: ../trait.CacheWriter.html
+    /// [cache-try-remove]: ./struct.Cache.html#method.try_remove
+    pub fn try_remove<Q>(&self, key: &Q) -> Result<Option<V>, WriteError>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let hash = self.inner.hash(key);
+        self.inner
+            .select(hash)
+            .try_invalidate_with_hash(key, hash, true, true)
+    }
+
     /// Discards all cached values.
     ///
     /// This method returns immediately by just setting the current time as the
@@ -661,6 +1013,20 @@ where
             segment.run_pending_tasks();
         }
     }
+
+    /// Returns the earliest time when a cached entry in any segment will expire, or
+    /// `None` if no entry will expire.
+    ///
+    /// See [`Cache::next_expiration_time`][cache-next-expiration-time] for details.
+    ///
+    /// [cache-next-expiration-time]: ./struct.Cache.html#method.next_expiration_time
+    pub fn next_expiration_time(&self) -> Option<Instant> {
+        self.inner
+            .segments
+            .iter()
+            .filter_map(Cache::next_expiration_time)
+            .min()
+    }
 }
 
 impl<'a, K, V, S> IntoIterator for &'a SegmentedCache<K, V, S>
@@ -720,6 +1086,7 @@ where
 
 struct Inner<K, V, S> {
     desired_capacity: AtomicCell<Option<u64>>,
+    initial_capacity: Option<usize>,
     segments: Box<[Cache<K, V, S>]>,
     build_hasher: S,
     segment_shift: u32,
@@ -742,8 +1109,13 @@ where
         num_segments: usize,
         build_hasher: S,
         weigher: Option<Weigher<K, V>>,
+        max_entry_weight: Option<u64>,
+        can_evict: Option<EvictionVeto<K, V>>,
+        writer: Option<Writer<K, V>>,
+        write_behind: Option<WriteBehindConfig<K, V>>,
         eviction_policy: EvictionPolicy,
         eviction_listener: Option<EvictionListener<K, V>>,
+        listener_config: ListenerConfig<K>,
         expiration_policy: ExpirationPolicy<K, V>,
         housekeeper_config: HousekeeperConfig,
         invalidator_enabled: bool,
@@ -751,6 +1123,20 @@ where
     ) -> Self {
         assert!(num_segments > 0);
 
+        // Let all the segments share one thread of the proactive expiration, one
+        // thread delivering the queued notifications, and one thread flushing the
+        // write-behind buffers.
+        let mut housekeeper_config = housekeeper_config;
+        housekeeper_config.share_expiration_scheduler();
+        let mut listener_config = listener_config;
+        if eviction_listener.is_some() {
+            listener_config.share_delivery_thread(name.as_deref());
+        }
+        let write_behind = write_behind.map(|mut config| {
+            config.share_flush_thread(name.as_deref());
+            config
+        });
+
         let actual_num_segments = num_segments.next_power_of_two();
         let segment_shift = 64 - actual_num_segments.trailing_zeros();
         let seg_max_capacity =
@@ -767,8 +1153,13 @@ where
                     seg_init_capacity,
                     build_hasher.clone(),
                     weigher.clone(),
+                    max_entry_weight,
+                    can_evict.clone(),
+                    writer.clone(),
+                    write_behind.clone(),
                     eviction_policy.clone(),
                     eviction_listener.clone(),
+                    listener_config.clone(),
                     expiration_policy.clone(),
                     housekeeper_config.clone(),
                     invalidator_enabled,
@@ -779,6 +1170,7 @@ where
 
         Self {
             desired_capacity: AtomicCell::new(max_capacity),
+            initial_capacity,
             segments: segments.into_boxed_slice(),
             build_hasher,
             segment_shift,
@@ -814,7 +1206,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::SegmentedCache;
-    use crate::notification::RemovalCause;
+    use crate::{notification::RemovalCause, policy::EvictionPolicy};
     use parking_lot::Mutex;
     use std::{error::Error, fmt::Display, sync::Arc, time::Duration};
 
@@ -880,13 +1272,13 @@ mod tests {
 
         // "d" should not be admitted because its frequency is too low.
         cache.insert("d", "david"); //   count: d -> 0
-        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert_eq!(cache.get(&"d"), None); //   d -> 1
         assert!(!cache.contains_key(&"d"));
 
         cache.insert("d", "david");
-        expected.push((Arc::new("d"), "david", RemovalCause::Size));
+        expected.push((Arc::new("d"), "david", RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert!(!cache.contains_key(&"d"));
         assert_eq!(cache.get(&"d"), None); //   d -> 2
@@ -941,6 +1333,99 @@ mod tests {
         assert_eq!(cache.iter().count(), 3);
     }
 
+    #[test]
+    fn set_time_to_live_and_time_to_idle() {
+        let (clock, mock) = crate::common::time::Clock::mock();
+
+        let mut cache = SegmentedCache::builder(4)
+            .max_capacity(100)
+            .clock(clock)
+            .build();
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        for i in 0..10 {
+            cache.insert(i, i);
+        }
+        cache.run_pending_tasks();
+
+        cache.set_time_to_live(Some(Duration::from_secs(10)));
+        cache.set_time_to_idle(Some(Duration::from_secs(5)));
+        let policy = cache.policy();
+        assert_eq!(policy.time_to_live(), Some(Duration::from_secs(10)));
+        assert_eq!(policy.time_to_idle(), Some(Duration::from_secs(5)));
+
+        mock.increment(Duration::from_secs(3)); // 3 secs from the start.
+        assert_eq!(cache.get(&0), Some(0));
+
+        mock.increment(Duration::from_secs(3)); // 6 secs.
+        assert_eq!(cache.get(&0), Some(0));
+        assert_eq!(cache.get(&1), None);
+
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 1);
+
+        mock.increment(Duration::from_secs(4)); // 10 secs.
+        assert_eq!(cache.get(&0), None);
+
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[test]
+    fn switch_eviction_policy() {
+        let mut cache = SegmentedCache::new(100, 4);
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        cache.insert("a", "alice");
+        cache.set_eviction_policy(EvictionPolicy::lru());
+        cache.run_pending_tasks();
+
+        assert_eq!(cache.policy().eviction_policy(), &EvictionPolicy::lru());
+        assert_eq!(cache.get(&"a"), Some("alice"));
+    }
+
+    #[test]
+    fn subscribe_to_events() {
+        use crate::notification::{Backpressure, CacheEvent};
+        use std::collections::HashSet;
+
+        let mut cache = SegmentedCache::new(100, 4);
+        cache.reconfigure_for_testing();
+
+        // Make the cache exterior immutable.
+        let cache = cache;
+
+        // A single receiver gets the events of all segments.
+        let events = cache.subscribe(16, Backpressure::Block);
+
+        for i in 0..8 {
+            cache.insert(i, i * 10);
+        }
+        cache.invalidate(&3);
+
+        let mut inserted = HashSet::new();
+        let mut removed = Vec::new();
+        for event in events.try_iter() {
+            match event {
+                CacheEvent::Inserted { key, .. } => assert!(inserted.insert(*key)),
+                CacheEvent::Removed { key, value, cause } => removed.push((*key, value, cause)),
+                event => panic!("Unexpected event: {event:?}"),
+            }
+        }
+        assert_eq!(inserted, (0..8).collect());
+        assert_eq!(removed, vec![(3, 30, RemovalCause::Explicit)]);
+
+        // The receiver should be disconnected after the cache is dropped.
+        drop(cache);
+        assert!(events.recv().is_none());
+    }
+
     #[test]
     fn size_aware_eviction() {
         let weigher = |_k: &&str, v: &(&str, u32)| v.1;
@@ -997,25 +1482,25 @@ mod tests {
         // "d" must have higher count than 3, which is the aggregated count
         // of "a" and "c".
         cache.insert("d", david); //   count: d -> 0
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert_eq!(cache.get(&"d"), None); //   d -> 1
         assert!(!cache.contains_key(&"d"));
 
         cache.insert("d", david);
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert!(!cache.contains_key(&"d"));
         assert_eq!(cache.get(&"d"), None); //   d -> 2
 
         cache.insert("d", david);
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert_eq!(cache.get(&"d"), None); //   d -> 3
         assert!(!cache.contains_key(&"d"));
 
         cache.insert("d", david);
-        expected.push((Arc::new("d"), david, RemovalCause::Size));
+        expected.push((Arc::new("d"), david, RemovalCause::Rejected));
         cache.run_pending_tasks();
         assert!(!cache.contains_key(&"d"));
         assert_eq!(cache.get(&"d"), None); //   d -> 4
@@ -1139,9 +1624,9 @@ mod tests {
         // https://github.com/moka-rs/moka/issues/155
 
         cache.invalidate_all();
-        expected.insert(Arc::new("a"), ("alice", RemovalCause::Explicit));
-        expected.insert(Arc::new("b"), ("bob", RemovalCause::Explicit));
-        expected.insert(Arc::new("c"), ("cindy", RemovalCause::Explicit));
+        expected.insert(Arc::new("a"), ("alice", RemovalCause::InvalidatedAll));
+        expected.insert(Arc::new("b"), ("bob", RemovalCause::InvalidatedAll));
+        expected.insert(Arc::new("c"), ("cindy", RemovalCause::InvalidatedAll));
         cache.run_pending_tasks();
 
         cache.insert("d", "david");
@@ -1172,7 +1657,15 @@ mod tests {
 
         // Create an eviction listener.
         let a1 = Arc::clone(&actual);
+        // Each segment registers its own predicate, so the predicate IDs are
+        // replaced with a placeholder and not checked here.
+        let placeholder = crate::sync::PredicateId::new_v4();
+        let by_predicate = move || RemovalCause::InvalidatedByPredicate(placeholder);
         let listener = move |k, v, cause| {
+            let cause = match cause {
+                RemovalCause::InvalidatedByPredicate(_) => by_predicate(),
+                cause => cause,
+            };
             a1.lock().insert(k, (v, cause));
         };
 
@@ -1207,8 +1700,8 @@ mod tests {
         let names = ["alice", "alex"].iter().cloned().collect::<HashSet<_>>();
         cache.invalidate_entries_if(move |_k, &v| names.contains(v))?;
         assert_eq!(cache.invalidation_predicate_count(), SEGMENTS);
-        expected.insert(Arc::new(0), ("alice", RemovalCause::Explicit));
-        expected.insert(Arc::new(2), ("alex", RemovalCause::Explicit));
+        expected.insert(Arc::new(0), ("alice", by_predicate()));
+        expected.insert(Arc::new(2), ("alex", by_predicate()));
 
         mock.increment(Duration::from_secs(5)); // 10 secs from the start.
 
@@ -1239,8 +1732,8 @@ mod tests {
         cache.invalidate_entries_if(|_k, &v| v == "alice")?;
         cache.invalidate_entries_if(|_k, &v| v == "bob")?;
         assert_eq!(cache.invalidation_predicate_count(), SEGMENTS * 2);
-        expected.insert(Arc::new(1), ("bob", RemovalCause::Explicit));
-        expected.insert(Arc::new(3), ("alice", RemovalCause::Explicit));
+        expected.insert(Arc::new(1), ("bob", by_predicate()));
+        expected.insert(Arc::new(3), ("alice", by_predicate()));
 
         // Run the invalidation task and wait for it to finish. (TODO: Need a better way than sleeping)
         cache.run_pending_tasks(); // To submit the invalidation task.
@@ -1262,6 +1755,101 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn proactive_expiration() {
+        const SEGMENTS: usize = 4;
+
+        // The following `Vec` will hold actual notifications.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+
+        // Create an eviction listener.
+        let a1 = Arc::clone(&actual);
+        let listener = move |k, v, cause| a1.lock().push((k, v, cause));
+
+        let (clock, mock) = crate::common::time::Clock::mock();
+
+        // Create a cache with the proactive expiration.
+        let cache = SegmentedCache::builder(SEGMENTS)
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(10))
+            .eviction_listener(listener)
+            .proactive_expiration(Duration::from_millis(1))
+            .clock(clock)
+            .build();
+
+        // All the segments share one expiration scheduler, hence one thread.
+        let housekeepers = cache
+            .inner
+            .segments
+            .iter()
+            .map(|seg| seg.base.housekeeper.as_ref().unwrap())
+            .collect::<Vec<_>>();
+        assert!(housekeepers
+            .windows(2)
+            .all(|hks| hks[0].shares_expiration_scheduler_with(hks[1])));
+
+        for i in 0..8 {
+            cache.insert(i, i);
+        }
+        mock.increment(Duration::from_secs(11)); // 11 secs from the start.
+
+        // Wake up the background thread, which would otherwise wait (in real time)
+        // for the time-to-live, and wait until it removes the entries from all the
+        // segments.
+        housekeepers[0].notify_write();
+        for _ in 0..500 {
+            if actual.lock().len() >= 8 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(actual.lock().len(), 8);
+        assert!(actual
+            .lock()
+            .iter()
+            .all(|(_, _, cause)| *cause == RemovalCause::Expired));
+
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[test]
+    fn queued_eviction_listener() {
+        use crate::notification::DeliveryMode;
+
+        const SEGMENTS: usize = 4;
+        const NUM_KEYS: u32 = 32;
+
+        // The following `Vec` will hold the keys and the delivering thread IDs.
+        let actual = Arc::new(Mutex::new(Vec::new()));
+
+        let a1 = Arc::clone(&actual);
+        let listener = move |k: Arc<u32>, _v, _cause| {
+            a1.lock().push((*k, std::thread::current().id()));
+        };
+
+        let cache = SegmentedCache::builder(SEGMENTS)
+            .max_capacity(100)
+            .eviction_listener(listener)
+            .delivery_mode(DeliveryMode::Queued)
+            .build();
+
+        for key in 0..NUM_KEYS {
+            cache.insert(key, key);
+        }
+        for key in 0..NUM_KEYS {
+            cache.invalidate(&key);
+        }
+        cache.wait_for_notifications();
+
+        // All the segments share one delivery thread.
+        let actual = actual.lock();
+        assert_eq!(actual.len() as u32, NUM_KEYS);
+        let thread_id = actual[0].1;
+        assert_ne!(thread_id, std::thread::current().id());
+        assert!(actual.iter().all(|(_, id)| *id == thread_id));
+    }
+
     #[test]
     fn test_iter() {
         const NUM_KEYS: usize = 50;
@@ -1898,7 +2486,7 @@ mod tests {
         let counters1 = Arc::clone(&counters);
 
         let listener = move |_k, _v, cause| match cause {
-            RemovalCause::Size => counters1.incl_evicted(),
+            RemovalCause::Size | RemovalCause::Rejected => counters1.incl_evicted(),
             RemovalCause::Explicit => counters1.incl_invalidated(),
             _ => (),
         };
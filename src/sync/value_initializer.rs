@@ -9,7 +9,7 @@ use std::{
 use crate::{
     common::concurrent::arc::MiniArc,
     ops::compute::{CompResult, Op},
-    Entry,
+    Entry, InsertError, WriteError,
 };
 
 use super::{Cache, ComputeNone, OptionallyNone};
@@ -40,6 +40,19 @@ impl<V> fmt::Debug for WaiterValue<V> {
 
 type Waiter<V> = MiniArc<RwLock<WaiterValue<V>>>;
 
+/// The `Op` returned by a compute closure that could not be applied to the cache.
+/// The `CompResult` paired with it reports the actual state of the entry.
+pub(crate) struct FailedOp<V> {
+    /// The value of `Op::Put`, or `None` for `Op::Remove`.
+    pub(crate) value: Option<V>,
+    /// The error from the cache writer, or `None` if the op was rejected for
+    /// another reason, e.g. the weight of the value.
+    pub(crate) error: Option<WriteError>,
+}
+
+/// The result of `try_compute`.
+pub(crate) type Computed<K, V> = (CompResult<K, V>, Option<FailedOp<V>>);
+
 pub(crate) enum InitResult<V, E> {
     Initialized(V),
     ReadExisting(V),
@@ -184,7 +197,7 @@ where
         f: F,
         post_init: fn(O) -> Result<Op<V>, E>,
         allow_nop: bool,
-    ) -> Result<CompResult<K, V>, E>
+    ) -> Result<Computed<K, V>, E>
     where
         V: 'static,
         F: FnOnce(Option<Entry<K, V>>) -> O,
@@ -229,11 +242,15 @@ where
         let maybe_entry = cache
             .base
             .get_with_hash_and_ignore_if(&*c_key, c_hash, ignore_if, true);
-        let maybe_value = if allow_nop {
-            maybe_entry.as_ref().map(|ent| ent.value().clone())
-        } else {
-            None
-        };
+        // Keep the current value for `Op::Nop`, for the case that the writer fails
+        // and the entry is left unchanged, and for the case that the new value is
+        // rejected for its weight and the entry is invalidated.
+        let maybe_value =
+            if allow_nop || cache.base.is_writer_enabled() || cache.base.has_max_entry_weight() {
+                maybe_entry.as_ref().map(|ent| ent.value().clone())
+            } else {
+                None
+            };
         let entry_existed = maybe_entry.is_some();
 
         // Evaluate the `f` closure. Catching panic is safe here as we will not
@@ -261,43 +278,78 @@ where
             }
         };
 
-        let result = match op {
-            Op::Nop => {
-                if let Some(value) = maybe_value {
-                    Ok(CompResult::Unchanged(Entry::new(
-                        Some(c_key),
-                        value,
-                        false,
-                        false,
-                    )))
-                } else {
-                    Ok(CompResult::StillNone(c_key))
-                }
+        let ttl = match &op {
+            Op::PutWithTtl(_, ttl) => Some(*ttl),
+            _ => None,
+        };
+
+        let unchanged = |c_key, maybe_value| {
+            if let Some(value) = maybe_value {
+                CompResult::Unchanged(Entry::new(Some(c_key), value, false, false))
+            } else {
+                CompResult::StillNone(c_key)
             }
-            Op::Put(value) => {
-                cache.insert_with_hash(Arc::clone(&c_key), c_hash, value.clone());
-                if entry_existed {
-                    crossbeam_epoch::pin().flush();
-                    let entry = Entry::new(Some(c_key), value, true, true);
-                    Ok(CompResult::ReplacedWith(entry))
-                } else {
-                    let entry = Entry::new(Some(c_key), value, true, false);
-                    Ok(CompResult::Inserted(entry))
+        };
+
+        let result = match op {
+            Op::Nop => (unchanged(c_key, maybe_value), None),
+            Op::Put(value) | Op::PutWithTtl(value, _) => {
+                let r =
+                    cache.insert_with_hash_and_ttl(Arc::clone(&c_key), c_hash, value.clone(), ttl);
+                match r {
+                    Ok(()) if entry_existed => {
+                        crossbeam_epoch::pin().flush();
+                        let entry = Entry::new(Some(c_key), value, true, true);
+                        (CompResult::ReplacedWith(entry), None)
+                    }
+                    Ok(()) => {
+                        let entry = Entry::new(Some(c_key), value, true, false);
+                        (CompResult::Inserted(entry), None)
+                    }
+                    // The writer failed, so the entry was not modified.
+                    Err(InsertError::WriterFailed(e)) => {
+                        let failed = FailedOp {
+                            value: Some(value),
+                            error: Some(e),
+                        };
+                        (unchanged(c_key, maybe_value), Some(failed))
+                    }
+                    // The value was rejected for its weight, and the existing entry (if
+                    // any) was invalidated.
+                    Err(InsertError::EntryTooHeavy { .. }) => {
+                        let result = match maybe_value {
+                            Some(prev_v) => {
+                                CompResult::Removed(Entry::new(Some(c_key), prev_v, false, false))
+                            }
+                            None => CompResult::StillNone(c_key),
+                        };
+                        let failed = FailedOp {
+                            value: Some(value),
+                            error: None,
+                        };
+                        (result, Some(failed))
+                    }
                 }
             }
-            Op::Remove => {
-                let maybe_prev_v = cache.invalidate_with_hash(&*c_key, c_hash, true);
-                if let Some(prev_v) = maybe_prev_v {
+            Op::Remove => match cache.try_invalidate_with_hash(&*c_key, c_hash, true, true) {
+                Ok(Some(prev_v)) => {
                     crossbeam_epoch::pin().flush();
                     let entry = Entry::new(Some(c_key), prev_v, false, false);
-                    Ok(CompResult::Removed(entry))
-                } else {
-                    Ok(CompResult::StillNone(c_key))
+                    (CompResult::Removed(entry), None)
                 }
-            }
+                Ok(None) => (CompResult::StillNone(c_key), None),
+                // The writer failed, so the entry was not removed.
+                Err(e) => {
+                    let failed = FailedOp {
+                        value: None,
+                        error: Some(e),
+                    };
+                    (unchanged(c_key, maybe_value), Some(failed))
+                }
+            },
         };
         self.remove_waiter(w_key, w_hash);
-        result
+        Ok(result)
 
         // The lock will be unlocked here.
     }